@@ -0,0 +1,108 @@
+//! Mempool benchmark: admission, eviction and block template selection at
+//! 1,000 and 10,000 entries.
+//!
+//! Run with `cargo bench --bench mempool`. With the fee-rate index every
+//! operation is logarithmic in the pool size, so the per-operation times at
+//! 10,000 entries should stay close to those at 1,000 rather than grow tenfold.
+//! The run fails if they grow by more than `MAX_SLOWDOWN`.
+
+use std::time::{Duration, Instant};
+
+use trinitychain::blockchain::Mempool;
+use trinitychain::crypto::KeyPair;
+use trinitychain::transaction::{Transaction, TransferTx};
+
+/// Signed transfers of distinct triangles from distinct senders, paying
+/// fees that do not follow their order
+fn signed_transfers(count: usize, offset: usize) -> Vec<Transaction> {
+    (offset..offset + count)
+        .map(|i| {
+            let sender = KeyPair::generate().expect("key generation");
+            let mut input_hash = [0u8; 32];
+            input_hash[..8].copy_from_slice(&(i as u64).to_le_bytes());
+            let fee = (i as u64).wrapping_mul(7919) % 1_000 + 1;
+            let mut tx = TransferTx::new(input_hash, "ab".repeat(32), sender.address(), fee, i as u64);
+            let signature = sender.sign(&tx.signable_message()).expect("signing");
+            tx.sign(signature, sender.public_key_bytes());
+            Transaction::Transfer(tx)
+        })
+        .collect()
+}
+
+fn per_op(elapsed: Duration, ops: usize) -> Duration {
+    elapsed / ops.max(1) as u32
+}
+
+/// Per-operation times measured on one pool
+struct Timings {
+    insert: Duration,
+    select: Duration,
+}
+
+/// Fill a pool with `size` transactions, timing admission and top-N selection
+fn bench(size: usize) -> (Mempool, Timings) {
+    let transactions = signed_transfers(size, 0);
+    warm_signature_cache(&transactions);
+
+    let mut mempool = Mempool::new();
+    let start = Instant::now();
+    for tx in transactions {
+        mempool.add_transaction(tx).expect("admission");
+    }
+    let insert = per_op(start.elapsed(), size);
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        std::hint::black_box(mempool.get_transactions_by_fee(TOP_N));
+    }
+    let select = per_op(start.elapsed(), ROUNDS);
+
+    println!("{:>6} entries: insert {:>9.2?}/tx, top-{} {:>9.2?}", size, insert, TOP_N, select);
+    (mempool, Timings { insert, select })
+}
+
+/// Fail if an operation on the large pool is more than `MAX_SLOWDOWN` times
+/// slower than on the small one: a linear scan would be about tenfold
+fn check_scaling(operation: &str, small: Duration, large: Duration) {
+    let ratio = large.as_secs_f64() / small.as_secs_f64().max(f64::EPSILON);
+    println!("{} slowdown from 1,000 entries: {:.2}x", operation, ratio);
+    assert!(
+        ratio <= MAX_SLOWDOWN,
+        "{} grew {:.2}x from 1,000 to {} entries, more than {}x",
+        operation, ratio, Mempool::MAX_TRANSACTIONS, MAX_SLOWDOWN
+    );
+}
+
+/// Signatures are cached after the first check; warm the cache so the
+/// timings measure the pool rather than signature verification
+fn warm_signature_cache(transactions: &[Transaction]) {
+    for tx in transactions {
+        tx.verify_signatures().expect("valid signature");
+    }
+}
+
+const TOP_N: usize = 100;
+const ROUNDS: usize = 1_000;
+const EVICTIONS: usize = 1_000;
+/// Allowed growth in per-operation time between 1,000 and 10,000 entries
+const MAX_SLOWDOWN: f64 = 4.0;
+
+fn main() {
+    let (_, small) = bench(1_000);
+    let (mut full, large) = bench(Mempool::MAX_TRANSACTIONS);
+
+    // Every admission to a full pool first evicts its lowest fee-rate entry
+    let extra = signed_transfers(EVICTIONS, Mempool::MAX_TRANSACTIONS);
+    warm_signature_cache(&extra);
+    let start = Instant::now();
+    for tx in extra {
+        full.add_transaction(tx).expect("admission with eviction");
+    }
+    let evict = per_op(start.elapsed(), EVICTIONS);
+    assert_eq!(full.len(), Mempool::MAX_TRANSACTIONS);
+    println!("{:>6} entries: insert with eviction {:>9.2?}/tx", full.len(), evict);
+
+    check_scaling("insert", small.insert, large.insert);
+    check_scaling("top-N", small.select, large.select);
+    check_scaling("insert with eviction", small.insert, evict);
+}
@@ -0,0 +1,341 @@
+//! Checksummed address encoding
+//!
+//! On chain, owners are stored in canonical form: the lowercase hex SHA-256 of
+//! a public key, or `script:<hex>` for script-locked triangles. Users see and
+//! type a Bech32m encoding of the same 32 bytes (BIP-350 checksum) with a
+//! human-readable part naming the network and a version symbol for the kind
+//! of owner:
+//!
+//! ```text
+//! tri1q<key hash...><checksum>     key address on mainnet
+//! ttri1p<script hash...><checksum> script address on testnet
+//! ```
+//!
+//! A typo changes the checksum, so a mistyped address is rejected instead of
+//! sending triangles to an owner nobody holds. Legacy hex addresses are still
+//! accepted while wallets move over.
+
+use crate::crypto::is_key_address;
+use crate::error::ChainError;
+use crate::script::{is_script_address, SCRIPT_ADDRESS_PREFIX};
+use crate::transaction::Address;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+const BECH32M_CONST: u32 = 0x2bc830a3;
+const CHECKSUM_LENGTH: usize = 6;
+const MAX_LENGTH: usize = 90;
+
+/// Environment variable selecting the network addresses are encoded for
+pub const NETWORK_ENV: &str = "TRINITY_NETWORK";
+
+/// Network an encoded address belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Regtest,
+}
+
+impl Network {
+    pub const ALL: [Network; 3] = [Network::Mainnet, Network::Testnet, Network::Regtest];
+
+    /// Human-readable part of addresses on this network
+    pub fn hrp(self) -> &'static str {
+        match self {
+            Network::Mainnet => "tri",
+            Network::Testnet => "ttri",
+            Network::Regtest => "rtri",
+        }
+    }
+
+    pub fn from_hrp(hrp: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|network| network.hrp() == hrp)
+    }
+
+    /// Network selected by `TRINITY_NETWORK` ("mainnet", "testnet" or
+    /// "regtest"), defaulting to mainnet
+    pub fn current() -> Self {
+        match std::env::var(NETWORK_ENV).as_deref() {
+            Ok("testnet") => Network::Testnet,
+            Ok("regtest") => Network::Regtest,
+            _ => Network::Mainnet,
+        }
+    }
+}
+
+/// What an address locks triangles to; encoded as the address version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressKind {
+    /// SHA-256 of a public key
+    Key = 0,
+    /// Hash of a spending script
+    Script = 1,
+}
+
+impl AddressKind {
+    fn from_version(version: u8) -> Option<Self> {
+        match version {
+            0 => Some(AddressKind::Key),
+            1 => Some(AddressKind::Script),
+            _ => None,
+        }
+    }
+}
+
+/// The parts of an encoded address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedAddress {
+    pub network: Network,
+    pub kind: AddressKind,
+    pub program: [u8; 32],
+}
+
+impl DecodedAddress {
+    /// Split a canonical address into its kind and hash
+    pub fn from_canonical(address: &str, network: Network) -> Option<Self> {
+        let (kind, hex_part) = if is_key_address(address) {
+            (AddressKind::Key, address)
+        } else if is_script_address(address) {
+            (AddressKind::Script, &address[SCRIPT_ADDRESS_PREFIX.len()..])
+        } else {
+            return None;
+        };
+        let mut program = [0u8; 32];
+        hex::decode_to_slice(hex_part.to_lowercase(), &mut program).ok()?;
+        Some(DecodedAddress { network, kind, program })
+    }
+
+    /// The form stored on chain
+    pub fn canonical(&self) -> Address {
+        match self.kind {
+            AddressKind::Key => hex::encode(self.program),
+            AddressKind::Script => format!("{}{}", SCRIPT_ADDRESS_PREFIX, hex::encode(self.program)),
+        }
+    }
+
+    /// The Bech32m form shown to users
+    pub fn encode(&self) -> String {
+        let mut data = vec![self.kind as u8];
+        data.extend(convert_bits(&self.program, 8, 5, true).expect("padding is allowed"));
+        bech32m_encode(self.network.hrp(), &data)
+    }
+
+    /// Parse a Bech32m address, checking its checksum, network and version
+    pub fn decode(address: &str) -> Result<Self, ChainError> {
+        let (hrp, data) = bech32m_decode(address)?;
+        let network = Network::from_hrp(&hrp)
+            .ok_or_else(|| ChainError::InvalidAddress(format!("Unknown address prefix '{}'", hrp)))?;
+        let (&version, program) = data.split_first()
+            .ok_or_else(|| ChainError::InvalidAddress("Address has no version".to_string()))?;
+        let kind = AddressKind::from_version(version)
+            .ok_or_else(|| ChainError::InvalidAddress(format!("Unsupported address version {}", version)))?;
+        let program: [u8; 32] = convert_bits(program, 5, 8, false)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| ChainError::InvalidAddress("Address must hold 32 bytes".to_string()))?;
+        Ok(DecodedAddress { network, kind, program })
+    }
+}
+
+/// Whether `address` is in the canonical on-chain form
+pub fn is_canonical(address: &str) -> bool {
+    is_key_address(address) || is_script_address(address)
+}
+
+/// Whether `input` carries a known address prefix, and so was meant as an
+/// encoded address
+pub fn looks_encoded(input: &str) -> bool {
+    let lower = input.to_lowercase();
+    lower.rsplit_once('1').is_some_and(|(hrp, _)| Network::from_hrp(hrp).is_some())
+}
+
+/// Parse an address typed by a user into canonical form. Accepts the Bech32m
+/// encoding for the current network and, during the transition, legacy hex
+/// and `script:` addresses.
+pub fn parse_address(input: &str) -> Result<Address, ChainError> {
+    parse_address_for(input, Network::current())
+}
+
+/// `parse_address` for an explicit network
+pub fn parse_address_for(input: &str, network: Network) -> Result<Address, ChainError> {
+    let input = input.trim();
+    if is_script_address(input) {
+        return Ok(format!("{}{}", SCRIPT_ADDRESS_PREFIX, input[SCRIPT_ADDRESS_PREFIX.len()..].to_lowercase()));
+    }
+    let lower = input.to_lowercase();
+    if is_key_address(&lower) {
+        return Ok(lower);
+    }
+    if !looks_encoded(input) {
+        return Err(ChainError::InvalidAddress(format!(
+            "'{}' is not an address; expected {}1... or 64 hex characters", input, network.hrp()
+        )));
+    }
+
+    let decoded = DecodedAddress::decode(input)?;
+    if decoded.network != network {
+        return Err(ChainError::InvalidAddress(format!(
+            "{} is a {:?} address, but this node is on {:?}", input, decoded.network, network
+        )));
+    }
+    Ok(decoded.canonical())
+}
+
+/// Encode a canonical address for display on the current network. Anything
+/// that is not a canonical address is returned unchanged.
+pub fn display_address(address: &str) -> String {
+    DecodedAddress::from_canonical(address, Network::current())
+        .map(|decoded| decoded.encode())
+        .unwrap_or_else(|| address.to_string())
+}
+
+fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    let mut checksum: u32 = 1;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let bytes = hrp.as_bytes();
+    bytes.iter().map(|b| b >> 5)
+        .chain(std::iter::once(0))
+        .chain(bytes.iter().map(|b| b & 31))
+        .collect()
+}
+
+fn bech32m_encode(hrp: &str, data: &[u8]) -> String {
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0; CHECKSUM_LENGTH]);
+    let checksum = polymod(values) ^ BECH32M_CONST;
+
+    let mut encoded = format!("{}1", hrp);
+    for &value in data {
+        encoded.push(CHARSET[value as usize] as char);
+    }
+    for i in 0..CHECKSUM_LENGTH {
+        encoded.push(CHARSET[((checksum >> (5 * (5 - i))) & 31) as usize] as char);
+    }
+    encoded
+}
+
+/// Split a Bech32m string into its human-readable part and 5-bit data,
+/// verifying the checksum
+fn bech32m_decode(input: &str) -> Result<(String, Vec<u8>), ChainError> {
+    let invalid = |reason: &str| ChainError::InvalidAddress(format!("{}: {}", input, reason));
+    if input.len() > MAX_LENGTH {
+        return Err(invalid("too long"));
+    }
+    if input.bytes().any(|b| !(33..=126).contains(&b)) {
+        return Err(invalid("invalid character"));
+    }
+    if input.bytes().any(|b| b.is_ascii_lowercase()) && input.bytes().any(|b| b.is_ascii_uppercase()) {
+        return Err(invalid("mixed case"));
+    }
+
+    let lower = input.to_lowercase();
+    let (hrp, data_part) = lower.rsplit_once('1').ok_or_else(|| invalid("missing separator"))?;
+    if hrp.is_empty() || data_part.len() < CHECKSUM_LENGTH {
+        return Err(invalid("too short"));
+    }
+    let data = data_part.bytes()
+        .map(|c| CHARSET.iter().position(|&x| x == c).map(|v| v as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| invalid("invalid character"))?;
+
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    if polymod(values) != BECH32M_CONST {
+        return Err(invalid("checksum mismatch, check for typos"));
+    }
+    Ok((hrp.to_string(), data[..data.len() - CHECKSUM_LENGTH].to_vec()))
+}
+
+/// Regroup bits, e.g. bytes into 5-bit symbols. Without padding, leftover
+/// bits must be zero and fewer than `from`.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max = (1u32 << to) - 1;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        if (value as u32) >> from != 0 {
+            return None;
+        }
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+
+    #[test]
+    fn test_bech32m_reference_checksums() {
+        // Valid Bech32m strings from BIP-350
+        for valid in [
+            "A1LQFN3A",
+            "a1lqfn3a",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+            "?1v759aa",
+        ] {
+            assert!(bech32m_decode(valid).is_ok(), "{}", valid);
+        }
+        // Bech32 (not Bech32m) checksum, bad character and mixed case
+        for invalid in ["a12uel5l", "tri1b4n0q5v", "A1LQfN3A"] {
+            assert!(bech32m_decode(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_address_roundtrip_and_typo_detection() {
+        let key_address = KeyPair::generate().unwrap().address();
+        let decoded = DecodedAddress::from_canonical(&key_address, Network::Mainnet).unwrap();
+        let encoded = decoded.encode();
+        assert!(encoded.starts_with("tri1q"));
+        assert_eq!(parse_address_for(&encoded, Network::Mainnet).unwrap(), key_address);
+        assert_eq!(parse_address_for(&encoded.to_uppercase(), Network::Mainnet).unwrap(), key_address);
+
+        // Legacy hex is still accepted
+        assert_eq!(parse_address_for(&key_address.to_uppercase(), Network::Mainnet).unwrap(), key_address);
+
+        // Any single substituted character is caught
+        let last = encoded.len() - 1;
+        for i in [4, 20, last] {
+            let mut typo = encoded.clone().into_bytes();
+            typo[i] = if typo[i] == b'q' { b'p' } else { b'q' };
+            let typo = String::from_utf8(typo).unwrap();
+            assert!(parse_address_for(&typo, Network::Mainnet).is_err(), "{}", typo);
+        }
+
+        let script_address = format!("{}{}", SCRIPT_ADDRESS_PREFIX, "ab".repeat(32));
+        let testnet = DecodedAddress::from_canonical(&script_address, Network::Testnet).unwrap().encode();
+        assert!(testnet.starts_with("ttri1p"));
+        assert_eq!(parse_address_for(&testnet, Network::Testnet).unwrap(), script_address);
+        assert!(parse_address_for(&testnet, Network::Mainnet).is_err());
+        assert!(parse_address_for("genesis_owner", Network::Mainnet).is_err());
+    }
+}
@@ -1,8 +1,12 @@
 //! Address book for managing labeled addresses
 
+use crate::address::{self, looks_encoded};
+use crate::blockchain::{genesis_triangle, Block, BlockHeight, Sha256Hash, TriangleState};
 use crate::error::ChainError;
+use crate::geometry::Area;
+use crate::transaction::{Address, Transaction};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::path::PathBuf;
 
@@ -13,6 +17,10 @@ pub struct AddressEntry {
     pub address: String,
     pub notes: Option<String>,
     pub added: String,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 /// Address book
@@ -21,6 +29,21 @@ pub struct AddressBook {
     pub entries: HashMap<String, AddressEntry>, // key is the label (lowercase)
 }
 
+/// Outcome of importing entries into an address book
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    /// Labels already present, left alone because overwriting was not requested
+    pub skipped: Vec<String>,
+}
+
+/// Check that `address` is a valid encoded or legacy address, returning it
+/// in canonical form
+pub fn validate_address(address: &str) -> Result<Address, ChainError> {
+    address::parse_address(address)
+}
+
 impl AddressBook {
     /// Create a new empty address book
     pub fn new() -> Self {
@@ -38,12 +61,17 @@ impl AddressBook {
                 format!("Label '{}' already exists", label)
             ));
         }
+        if label.trim().is_empty() {
+            return Err(ChainError::WalletError("Label cannot be empty".to_string()));
+        }
 
         let entry = AddressEntry {
             label: label.clone(),
-            address,
+            address: validate_address(&address)?,
             notes,
             added: chrono::Utc::now().to_rfc3339(),
+            group: None,
+            tags: Vec::new(),
         };
 
         self.entries.insert(key, entry);
@@ -64,6 +92,35 @@ impl AddressBook {
         self.entries.get(&key)
     }
 
+    fn get_mut(&mut self, label: &str) -> Result<&mut AddressEntry, ChainError> {
+        self.entries.get_mut(&label.to_lowercase())
+            .ok_or_else(|| ChainError::WalletError(format!("Label '{}' not found", label)))
+    }
+
+    /// Find the entry for an address
+    pub fn find_by_address(&self, address: &str) -> Option<&AddressEntry> {
+        self.entries.values().find(|entry| entry.address == address)
+    }
+
+    /// Turn a payment recipient into an address: a valid address is used as
+    /// is, anything else is looked up as a label. A mistyped encoded address
+    /// reports why it failed to parse.
+    pub fn resolve(&self, recipient: &str) -> Result<Address, ChainError> {
+        let parse_error = match validate_address(recipient) {
+            Ok(address) => return Ok(address),
+            Err(e) => e,
+        };
+        if let Some(entry) = self.get(recipient) {
+            return Ok(entry.address.clone());
+        }
+        if looks_encoded(recipient) {
+            return Err(parse_error);
+        }
+        Err(ChainError::WalletError(format!(
+            "'{}' is neither a valid address nor a label in the address book", recipient
+        )))
+    }
+
     /// Search for addresses (by label or address)
     pub fn search(&self, query: &str) -> Vec<&AddressEntry> {
         let query_lower = query.to_lowercase();
@@ -84,6 +141,117 @@ impl AddressBook {
         entries
     }
 
+    /// Put an entry in a group, or take it out with `None`
+    pub fn set_group(&mut self, label: &str, group: Option<String>) -> Result<(), ChainError> {
+        self.get_mut(label)?.group = group.filter(|g| !g.trim().is_empty());
+        Ok(())
+    }
+
+    /// Tag an entry; returns false if it already had the tag
+    pub fn add_tag(&mut self, label: &str, tag: &str) -> Result<bool, ChainError> {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || tag.contains(';') {
+            return Err(ChainError::WalletError(format!("Invalid tag '{}'", tag)));
+        }
+        let entry = self.get_mut(label)?;
+        if entry.tags.contains(&tag) {
+            return Ok(false);
+        }
+        entry.tags.push(tag);
+        entry.tags.sort();
+        Ok(true)
+    }
+
+    /// Remove a tag; returns false if the entry did not have it
+    pub fn remove_tag(&mut self, label: &str, tag: &str) -> Result<bool, ChainError> {
+        let tag = tag.trim().to_lowercase();
+        let entry = self.get_mut(label)?;
+        let before = entry.tags.len();
+        entry.tags.retain(|t| *t != tag);
+        Ok(entry.tags.len() != before)
+    }
+
+    /// Entries in a group, sorted by label
+    pub fn in_group(&self, group: &str) -> Vec<&AddressEntry> {
+        self.list().into_iter()
+            .filter(|entry| entry.group.as_deref().is_some_and(|g| g.eq_ignore_ascii_case(group)))
+            .collect()
+    }
+
+    /// Entries carrying a tag, sorted by label
+    pub fn with_tag(&self, tag: &str) -> Vec<&AddressEntry> {
+        let tag = tag.to_lowercase();
+        self.list().into_iter().filter(|entry| entry.tags.contains(&tag)).collect()
+    }
+
+    /// All group names in use
+    pub fn groups(&self) -> Vec<String> {
+        self.entries.values().filter_map(|e| e.group.clone()).collect::<BTreeSet<_>>().into_iter().collect()
+    }
+
+    /// Add imported entries. Every address is validated before anything is
+    /// changed; existing labels are replaced only with `overwrite`.
+    pub fn import(&mut self, entries: Vec<AddressEntry>, overwrite: bool) -> Result<ImportSummary, ChainError> {
+        let mut checked = Vec::with_capacity(entries.len());
+        for mut entry in entries {
+            if entry.label.trim().is_empty() {
+                return Err(ChainError::WalletError("Imported entry has an empty label".to_string()));
+            }
+            entry.address = validate_address(&entry.address)
+                .map_err(|e| ChainError::WalletError(format!("Entry '{}': {}", entry.label, e)))?;
+            entry.tags = entry.tags.iter().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()).collect();
+            entry.tags.sort();
+            entry.tags.dedup();
+            if entry.added.is_empty() {
+                entry.added = chrono::Utc::now().to_rfc3339();
+            }
+            checked.push(entry);
+        }
+
+        let mut summary = ImportSummary::default();
+        for entry in checked {
+            let key = entry.label.to_lowercase();
+            let label = entry.label.clone();
+            match self.entries.get(&key) {
+                Some(_) if !overwrite => summary.skipped.push(label),
+                Some(_) => {
+                    self.entries.insert(key, entry);
+                    summary.updated.push(label);
+                }
+                None => {
+                    self.entries.insert(key, entry);
+                    summary.added.push(label);
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Export all entries as a JSON array
+    pub fn export_json(&self) -> Result<String, ChainError> {
+        serde_json::to_string_pretty(&self.list())
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize address book: {}", e)))
+    }
+
+    /// Export all entries as CSV with a header row; tags are separated by `;`
+    pub fn export_csv(&self) -> String {
+        let mut csv = format!("{}\n", CSV_HEADER.join(","));
+        for entry in self.list() {
+            let fields = [
+                entry.label.as_str(),
+                entry.address.as_str(),
+                entry.notes.as_deref().unwrap_or_default(),
+                entry.group.as_deref().unwrap_or_default(),
+                &entry.tags.join(";"),
+                entry.added.as_str(),
+            ];
+            let row: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
     /// Save address book to file
     pub fn save(&self, path: &PathBuf) -> Result<(), ChainError> {
         let json = serde_json::to_string_pretty(self)
@@ -111,6 +279,178 @@ impl AddressBook {
     }
 }
 
+const CSV_HEADER: [&str; 6] = ["label", "address", "notes", "group", "tags", "added"];
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Split CSV text into rows of fields, honouring quoted fields
+fn csv_rows(contents: &str) -> Result<Vec<Vec<String>>, ChainError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(ChainError::WalletError("Unterminated quoted field in CSV".to_string()));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|row| !(row.len() == 1 && row[0].trim().is_empty()));
+    Ok(rows)
+}
+
+/// Parse entries from CSV. The header row names the columns; `label` and
+/// `address` are required, `notes`, `group`, `tags` and `added` optional.
+pub fn parse_csv(contents: &str) -> Result<Vec<AddressEntry>, ChainError> {
+    let mut rows = csv_rows(contents)?.into_iter();
+    let header: Vec<String> = rows.next()
+        .ok_or_else(|| ChainError::WalletError("CSV file is empty".to_string()))?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let (Some(label_col), Some(address_col)) = (column("label"), column("address")) else {
+        return Err(ChainError::WalletError("CSV header needs 'label' and 'address' columns".to_string()));
+    };
+    let [notes_col, group_col, tags_col, added_col] = ["notes", "group", "tags", "added"].map(column);
+
+    rows.enumerate()
+        .map(|(i, row)| {
+            let get = |col: Option<usize>| col.and_then(|c| row.get(c)).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+            let label = get(Some(label_col))
+                .ok_or_else(|| ChainError::WalletError(format!("CSV row {} has no label", i + 2)))?;
+            Ok(AddressEntry {
+                label,
+                address: get(Some(address_col)).unwrap_or_default(),
+                notes: get(notes_col),
+                added: get(added_col).unwrap_or_default(),
+                group: get(group_col),
+                tags: get(tags_col).map(|t| t.split(';').map(str::to_string).collect()).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Parse entries from JSON: an array of entries, or a whole address book file
+pub fn parse_json(contents: &str) -> Result<Vec<AddressEntry>, ChainError> {
+    if let Ok(entries) = serde_json::from_str::<Vec<AddressEntry>>(contents) {
+        return Ok(entries);
+    }
+    serde_json::from_str::<AddressBook>(contents)
+        .map(|book| book.entries.into_values().collect())
+        .map_err(|e| ChainError::WalletError(format!("Failed to parse address book entries: {}", e)))
+}
+
+/// Whether a payment went to or came from a contact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentDirection {
+    ToContact,
+    FromContact,
+}
+
+/// A transfer between a contact and anyone else, found on chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payment {
+    pub tx_hash: String,
+    pub block_height: BlockHeight,
+    pub timestamp: i64,
+    pub direction: PaymentDirection,
+    /// The other side of the transfer
+    pub counterparty: Address,
+    pub triangle_hash: String,
+    /// Area of the transferred triangle, when its creation is on chain
+    pub area: Option<Area>,
+    pub fee: u64,
+    pub memo: Option<String>,
+}
+
+/// Every transfer to or from `address` in `blocks`, oldest first.
+///
+/// The payer is the triangle's owner as replayed from `blocks`, not the
+/// transfer's `sender`, which the transaction only claims. Transfers of
+/// triangles whose creation is not in `blocks` have no known payer and are
+/// skipped.
+pub fn payment_history(address: &str, blocks: &[Block]) -> Vec<Payment> {
+    // Transfers keep the triangle's hash, so areas are known from the
+    // transaction that created each triangle
+    let genesis = genesis_triangle();
+    let mut areas: HashMap<Sha256Hash, Area> = HashMap::from([(genesis.hash(), genesis.area())]);
+    let mut owners: HashMap<Sha256Hash, Address> = HashMap::from([(genesis.hash(), genesis.owner.clone())]);
+    let mut payments = Vec::new();
+
+    for block in blocks {
+        for tx in &block.transactions {
+            match tx {
+                Transaction::Coinbase(cb) => {
+                    if let Ok(triangle) = TriangleState::coinbase_triangle(cb, block.header.height) {
+                        areas.insert(triangle.hash(), triangle.area());
+                        owners.insert(triangle.hash(), triangle.owner);
+                    }
+                }
+                Transaction::Subdivision(sub) => {
+                    owners.remove(&sub.parent_hash);
+                    for child in &sub.children {
+                        areas.insert(child.hash(), child.area());
+                        owners.insert(child.hash(), child.owner.clone());
+                    }
+                }
+                Transaction::Transfer(transfer) => {
+                    let Some(payer) = owners.insert(transfer.input_hash, transfer.new_owner.clone()) else {
+                        continue;
+                    };
+                    let (direction, counterparty) = if transfer.new_owner == address {
+                        (PaymentDirection::ToContact, payer)
+                    } else if payer == address {
+                        (PaymentDirection::FromContact, transfer.new_owner.clone())
+                    } else {
+                        continue;
+                    };
+                    payments.push(Payment {
+                        tx_hash: tx.hash_str(),
+                        block_height: block.header.height,
+                        timestamp: block.header.timestamp,
+                        direction,
+                        counterparty,
+                        triangle_hash: hex::encode(transfer.input_hash),
+                        area: areas.get(&transfer.input_hash).copied(),
+                        fee: transfer.fee,
+                        memo: transfer.memo.clone(),
+                    });
+                }
+            }
+        }
+    }
+    payments
+}
+
 /// Get the default address book path
 pub fn get_addressbook_path() -> PathBuf {
     let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
@@ -122,6 +462,11 @@ pub fn load_default() -> Result<AddressBook, ChainError> {
     AddressBook::load(&get_addressbook_path())
 }
 
+/// Resolve a payment recipient against the default address book
+pub fn resolve_recipient(recipient: &str) -> Result<Address, ChainError> {
+    load_default()?.resolve(recipient)
+}
+
 /// Save the default address book
 pub fn save_default(book: &AddressBook) -> Result<(), ChainError> {
     let path = get_addressbook_path();
@@ -138,6 +483,12 @@ pub fn save_default(book: &AddressBook) -> Result<(), ChainError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::blockchain::Blockchain;
+    use crate::transaction::TransferTx;
+
+    fn address(byte: u8) -> String {
+        hex::encode([byte; 32])
+    }
 
     #[test]
     fn test_addressbook_add_and_get() {
@@ -145,19 +496,19 @@ mod tests {
 
         book.add(
             "Alice".to_string(),
-            "abc123".to_string(),
+            address(0xab),
             Some("Friend".to_string())
         ).unwrap();
 
         let entry = book.get("alice").unwrap();
         assert_eq!(entry.label, "Alice");
-        assert_eq!(entry.address, "abc123");
+        assert_eq!(entry.address, address(0xab));
     }
 
     #[test]
     fn test_addressbook_remove() {
         let mut book = AddressBook::new();
-        book.add("Bob".to_string(), "def456".to_string(), None).unwrap();
+        book.add("Bob".to_string(), address(0xde), None).unwrap();
 
         let removed = book.remove("bob").unwrap();
         assert_eq!(removed.label, "Bob");
@@ -167,11 +518,86 @@ mod tests {
     #[test]
     fn test_addressbook_search() {
         let mut book = AddressBook::new();
-        book.add("Alice".to_string(), "abc123".to_string(), Some("Friend".to_string())).unwrap();
-        book.add("Bob".to_string(), "def456".to_string(), Some("Colleague".to_string())).unwrap();
+        book.add("Alice".to_string(), address(0xab), Some("Friend".to_string())).unwrap();
+        book.add("Bob".to_string(), address(0xde), Some("Colleague".to_string())).unwrap();
 
         let results = book.search("friend");
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].label, "Alice");
     }
+
+    #[test]
+    fn test_addresses_are_validated_and_labels_resolve() {
+        let mut book = AddressBook::new();
+        assert!(book.add("Short".to_string(), "abc123".to_string(), None).is_err());
+        assert!(book.add("Bad".to_string(), "z".repeat(64), None).is_err());
+
+        book.add("Carol".to_string(), address(0xcd).to_uppercase(), None).unwrap();
+        assert_eq!(book.get("carol").unwrap().address, address(0xcd));
+        let script = format!("script:{}", address(0x11));
+        book.add("Vault".to_string(), script.clone(), None).unwrap();
+
+        assert_eq!(book.resolve("CAROL").unwrap(), address(0xcd));
+        assert_eq!(book.resolve(&script).unwrap(), script);
+        assert_eq!(book.resolve(&address(0x99)).unwrap(), address(0x99));
+        assert!(book.resolve("dave").is_err());
+    }
+
+    #[test]
+    fn test_groups_tags_and_csv_roundtrip() {
+        let mut book = AddressBook::new();
+        book.add("Alice".to_string(), address(0xab), Some("Lunch, \"Tuesdays\"".to_string())).unwrap();
+        book.add("Bob".to_string(), address(0xde), None).unwrap();
+        book.set_group("alice", Some("Friends".to_string())).unwrap();
+        assert!(book.add_tag("alice", "Coffee").unwrap());
+        assert!(!book.add_tag("alice", "coffee").unwrap());
+        book.add_tag("bob", "work").unwrap();
+        assert_eq!(book.in_group("friends").len(), 1);
+        assert_eq!(book.with_tag("WORK")[0].label, "Bob");
+        assert_eq!(book.groups(), vec!["Friends".to_string()]);
+
+        let mut imported = AddressBook::new();
+        let summary = imported.import(parse_csv(&book.export_csv()).unwrap(), false).unwrap();
+        assert_eq!(summary.added, vec!["Alice".to_string(), "Bob".to_string()]);
+        let alice = imported.get("alice").unwrap();
+        assert_eq!(alice.notes.as_deref(), Some("Lunch, \"Tuesdays\""));
+        assert_eq!(alice.group.as_deref(), Some("Friends"));
+        assert_eq!(alice.tags, vec!["coffee".to_string()]);
+
+        // Re-importing skips existing labels unless overwriting; one bad
+        // address rejects the whole import
+        let entries = parse_json(&book.export_json().unwrap()).unwrap();
+        assert_eq!(imported.import(entries.clone(), false).unwrap().skipped.len(), 2);
+        assert_eq!(imported.import(entries, true).unwrap().updated.len(), 2);
+        let bad = parse_csv("label,address\nEve,not-an-address\nFrank,").unwrap();
+        assert!(imported.import(bad, false).is_err());
+        assert!(imported.get("eve").is_none());
+    }
+
+    #[test]
+    fn test_payment_history() {
+        let mut chain = Blockchain::new();
+        let genesis = genesis_triangle();
+        let mut transfer = TransferTx::new(genesis.hash(), address(0xab), genesis.owner.clone(), 0, 0);
+        transfer.memo = Some("rent".to_string());
+        chain.blocks[0].transactions.push(Transaction::Transfer(transfer));
+
+        let payments = payment_history(&address(0xab), &chain.blocks);
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0].direction, PaymentDirection::ToContact);
+        assert_eq!(payments[0].counterparty, genesis.owner);
+        assert_eq!(payments[0].area, Some(genesis.area()));
+        assert_eq!(payments[0].memo.as_deref(), Some("rent"));
+        assert_eq!(payment_history(&genesis.owner, &chain.blocks)[0].direction, PaymentDirection::FromContact);
+
+        // A forged sender does not make the payment look like it came from a
+        // contact; the payer is whoever owned the triangle
+        let forged = TransferTx::new(genesis.hash(), address(0xcd), address(0xee), 0, 1);
+        chain.blocks[0].transactions.push(Transaction::Transfer(forged));
+        assert!(payment_history(&address(0xee), &chain.blocks).is_empty());
+        let from_ab = payment_history(&address(0xab), &chain.blocks);
+        assert_eq!(from_ab.len(), 2);
+        assert_eq!(from_ab[1].direction, PaymentDirection::FromContact);
+        assert_eq!(from_ab[1].counterparty, address(0xcd));
+    }
 }
@@ -1,10 +1,11 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     routing::{get, post},
     Json, Router, http::StatusCode, response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::{Duration, Instant};
@@ -12,12 +13,15 @@ use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 use tokio::task::JoinHandle;
 
-use crate::blockchain::{Blockchain, Block};
-use crate::persistence::Database;
+use crate::addressbook::{self, AddressBook, AddressEntry, Payment};
+use crate::blockchain::{Blockchain, Block, Sha256Hash};
+use crate::geometry::{Area, BoundingBox, Point, Triangle};
+use crate::persistence::{Database, LineageEvent, MEMPOOL_SAVE_INTERVAL};
 use crate::transaction::Transaction;
 use crate::crypto::KeyPair;
+use crate::hdwallet;
 use crate::miner;
-use crate::network::Node;
+use crate::network::{self, Node};
 use secp256k1::ecdsa::Signature;
 
 /// Mining state that tracks the current mining operation
@@ -54,6 +58,8 @@ struct AppState {
     db: Arc<Mutex<Database>>,
     mining: MiningState,
     network: NetworkState,
+    /// Address book used to resolve contact labels
+    address_book_path: PathBuf,
 }
 
 pub async fn run_api_server() {
@@ -77,6 +83,7 @@ pub async fn run_api_server() {
         db: Arc::new(Mutex::new(db)),
         mining: MiningState::default(),
         network: NetworkState::default(),
+        address_book_path: addressbook::get_addressbook_path(),
     };
 
     // Initialize network state with default values
@@ -99,6 +106,10 @@ pub async fn run_api_server() {
         *port = 8333;
     }
 
+    // Save the mempool periodically and once more on shutdown
+    let shutdown_state = app_state.clone();
+    tokio::spawn(save_mempool_periodically(app_state.clone()));
+
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
@@ -116,6 +127,11 @@ pub async fn run_api_server() {
         .route("/address/:addr/balance", get(get_address_balance))
         .route("/address/:addr/triangles", get(get_address_triangles))
         .route("/address/:addr/history", get(get_address_history))
+        .route("/addresses/balance", post(get_aggregate_balance))
+        // Spatial queries
+        .route("/triangles/region", get(get_triangles_in_region))
+        .route("/triangles/at", get(get_triangles_at_point))
+        .route("/triangle/:hash/lineage", get(get_triangle_lineage))
         // Transactions
         .route("/transaction", post(submit_transaction))
         .route("/transaction/:hash", get(get_transaction_status))
@@ -125,6 +141,12 @@ pub async fn run_api_server() {
         .route("/wallet/create", post(create_wallet))
         .route("/wallet/send", post(send_transaction))
         .route("/wallet/import", post(import_wallet))
+        .route("/wallet/sign-message", post(sign_message))
+        .route("/message/verify", post(verify_message))
+        // Address book
+        .route("/addressbook", get(list_contacts).post(add_contact))
+        .route("/addressbook/:label", get(get_contact))
+        .route("/addressbook/resolve/:recipient", get(resolve_recipient))
         // Mining
         .route("/mining/status", get(get_mining_status))
         .route("/mining/start", post(start_mining))
@@ -159,9 +181,49 @@ pub async fn run_api_server() {
         }
     };
     println!("API server listening on http://{}", addr);
-    if let Err(e) = axum::serve(listener, app).await {
+    if let Err(e) = axum::serve(listener, app).with_graceful_shutdown(network::shutdown_signal()).await {
         eprintln!("API server encountered a fatal error: {}", e);
     }
+    save_mempool_off_thread(shutdown_state).await;
+}
+
+/// Write the current mempool to the database
+fn save_mempool(state: &AppState) {
+    let mempool = match state.blockchain.lock() {
+        Ok(blockchain) => blockchain.mempool.clone(),
+        Err(_) => {
+            eprintln!("Failed to save mempool: blockchain lock is poisoned");
+            return;
+        }
+    };
+    let db = match state.db.lock() {
+        Ok(lock) => lock,
+        Err(_) => {
+            eprintln!("Failed to save mempool: database lock is poisoned");
+            return;
+        }
+    };
+    if let Err(e) = db.save_mempool(&mempool) {
+        eprintln!("Failed to save mempool: {}", e);
+    }
+}
+
+async fn save_mempool_periodically(state: AppState) {
+    let mut interval = tokio::time::interval(MEMPOOL_SAVE_INTERVAL);
+    // The first tick completes immediately
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        save_mempool_off_thread(state.clone()).await;
+    }
+}
+
+/// Write the current mempool on a blocking thread; SQLite writes block, so
+/// they are kept off the async workers
+async fn save_mempool_off_thread(state: AppState) {
+    if tokio::task::spawn_blocking(move || save_mempool(&state)).await.is_err() {
+        eprintln!("Failed to save mempool: save task panicked");
+    }
 }
 
 async fn get_blockchain_height(State(state): State<AppState>) -> impl IntoResponse {
@@ -190,10 +252,45 @@ async fn get_block_by_hash(State(state): State<AppState>, Path(hash): Path<Strin
     Ok(Json(block))
 }
 
+/// Balance of one address. `total_area` is an approximate JSON number kept
+/// for existing clients; `total_area_exact` is the exact decimal string.
 #[derive(Serialize, Deserialize)]
 pub struct BalanceResponse {
     pub triangles: Vec<String>,
     pub total_area: f64,
+    pub total_area_exact: Area,
+}
+
+/// Maximum number of addresses one aggregate balance request may cover
+const MAX_AGGREGATE_ADDRESSES: usize = 1000;
+
+/// Addresses to aggregate: an explicit list, an account xpub, or both
+#[derive(Serialize, Deserialize, Default)]
+pub struct AggregateBalanceRequest {
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub xpub: Option<String>,
+    #[serde(default)]
+    pub gap_limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AddressBalance {
+    pub address: String,
+    pub triangles: Vec<String>,
+    pub area: f64,
+    pub area_exact: Area,
+}
+
+/// Combined balance; `addresses` lists only the addresses holding triangles
+#[derive(Serialize, Deserialize)]
+pub struct AggregateBalanceResponse {
+    pub total_area: f64,
+    pub total_area_exact: Area,
+    pub triangle_count: usize,
+    pub addresses_checked: usize,
+    pub addresses: Vec<AddressBalance>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -224,21 +321,25 @@ async fn get_blockchain_stats(State(state): State<AppState>) -> impl IntoRespons
     Json(StatsResponse {
         height: blockchain.blocks.len() as u64,
         difficulty: blockchain.difficulty,
-        utxo_count: blockchain.state.utxo_set.len(),
+        utxo_count: blockchain.state.count(),
         mempool_size: blockchain.mempool.len(),
         recent_blocks,
     }).into_response()
 }
 
 async fn get_address_balance(State(state): State<AppState>, Path(addr): Path<String>) -> impl IntoResponse {
+    let addr = match resolve_label(&state, addr) {
+        Ok(addr) => addr,
+        Err(rejection) => return rejection.into_response(),
+    };
     let blockchain = match state.blockchain.lock() {
         Ok(lock) => lock,
         Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get blockchain lock").into_response(),
     };
     let mut triangles = Vec::new();
-    let mut total_area = 0.0;
+    let mut total_area = Area::ZERO;
 
-    for (hash, triangle) in &blockchain.state.utxo_set {
+    for (hash, triangle) in blockchain.state.utxo_set() {
         if triangle.owner == addr {
             triangles.push(hex::encode(hash));
             total_area += triangle.area();
@@ -247,7 +348,74 @@ async fn get_address_balance(State(state): State<AppState>, Path(addr): Path<Str
 
     Json(BalanceResponse {
         triangles,
-        total_area,
+        total_area: total_area.to_f64(),
+        total_area_exact: total_area,
+    }).into_response()
+}
+
+async fn get_aggregate_balance(
+    State(state): State<AppState>,
+    Json(request): Json<AggregateBalanceRequest>,
+) -> impl IntoResponse {
+    let blockchain = match state.blockchain.lock() {
+        Ok(lock) => lock,
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get blockchain lock").into_response(),
+    };
+
+    let mut addresses: Vec<String> = Vec::new();
+    for address in request.addresses {
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+
+    if let Some(xpub) = &request.xpub {
+        let gap_limit = request.gap_limit.unwrap_or(hdwallet::DEFAULT_GAP_LIMIT);
+        if gap_limit == 0 || gap_limit as usize > MAX_AGGREGATE_ADDRESSES {
+            return (StatusCode::BAD_REQUEST, "Invalid gap limit").into_response();
+        }
+        let on_chain = hdwallet::addresses_on_chain(&blockchain);
+        let discovery = match hdwallet::HDWallet::discover_xpub(xpub, gap_limit, |a| on_chain.contains(a)) {
+            Ok(discovery) => discovery,
+            Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+        };
+        for derived in discovery.used {
+            if !addresses.contains(&derived.address) {
+                addresses.push(derived.address);
+            }
+        }
+    }
+
+    if addresses.len() > MAX_AGGREGATE_ADDRESSES {
+        return (StatusCode::BAD_REQUEST, format!("At most {} addresses per request", MAX_AGGREGATE_ADDRESSES)).into_response();
+    }
+
+    let mut balances: Vec<AddressBalance> = addresses.iter()
+        .map(|address| AddressBalance { address: address.clone(), triangles: Vec::new(), area: 0.0, area_exact: Area::ZERO })
+        .collect();
+    let positions: std::collections::HashMap<&str, usize> = addresses.iter()
+        .enumerate()
+        .map(|(i, a)| (a.as_str(), i))
+        .collect();
+
+    for (hash, triangle) in blockchain.state.utxo_set() {
+        if let Some(&i) = positions.get(triangle.owner.as_str()) {
+            balances[i].triangles.push(hex::encode(hash));
+            balances[i].area_exact += triangle.area();
+        }
+    }
+    balances.retain(|b| !b.triangles.is_empty());
+    for balance in &mut balances {
+        balance.area = balance.area_exact.to_f64();
+    }
+    let total_area: Area = balances.iter().map(|b| b.area_exact).sum();
+
+    Json(AggregateBalanceResponse {
+        total_area: total_area.to_f64(),
+        total_area_exact: total_area,
+        triangle_count: balances.iter().map(|b| b.triangles.len()).sum(),
+        addresses_checked: addresses.len(),
+        addresses: balances,
     }).into_response()
 }
 
@@ -257,7 +425,8 @@ async fn submit_transaction(State(state): State<AppState>, Json(tx): Json<Transa
         Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get blockchain lock").into_response(),
     };
     let tx_hash = tx.hash_str();
-    match blockchain.mempool.add_transaction(tx) {
+    let chain = &mut *blockchain;
+    match chain.mempool.add_transaction_with_state(tx, &chain.state) {
         Ok(_) => Json(tx_hash).into_response(),
         Err(e) => (StatusCode::BAD_REQUEST, format!("Failed to add transaction: {}", e)).into_response(),
     }
@@ -313,33 +482,199 @@ async fn get_block_by_height(State(state): State<AppState>, Path(height): Path<u
     Ok(Json(block))
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct MetadataInfo {
+    pub name: Option<String>,
+    pub content_hash: Option<String>,
+    pub uri: Option<String>,
+    pub commitment: String,
+}
+
+/// A live triangle. `area` is an approximate JSON number kept for existing
+/// clients; `area_exact` is the exact decimal string.
 #[derive(Serialize, Deserialize)]
 pub struct TriangleInfo {
     pub hash: String,
+    pub owner: String,
     pub area: f64,
+    pub area_exact: Area,
     pub vertices: Vec<(f64, f64)>,
+    pub metadata: Option<MetadataInfo>,
+}
+
+impl TriangleInfo {
+    fn new(hash: &Sha256Hash, triangle: &Triangle) -> Self {
+        TriangleInfo {
+            hash: hex::encode(hash),
+            owner: triangle.owner.clone(),
+            area: triangle.area().to_f64(),
+            area_exact: triangle.area(),
+            vertices: vec![
+                (triangle.a.x.to_f64(), triangle.a.y.to_f64()),
+                (triangle.b.x.to_f64(), triangle.b.y.to_f64()),
+                (triangle.c.x.to_f64(), triangle.c.y.to_f64()),
+            ],
+            metadata: triangle.metadata.as_ref().map(|metadata| MetadataInfo {
+                name: metadata.name.clone(),
+                content_hash: metadata.content_hash.map(hex::encode),
+                uri: metadata.uri.clone(),
+                commitment: hex::encode(metadata.commitment()),
+            }),
+        }
+    }
 }
 
 async fn get_address_triangles(State(state): State<AppState>, Path(addr): Path<String>) -> impl IntoResponse {
+    let addr = match resolve_label(&state, addr) {
+        Ok(addr) => addr,
+        Err(rejection) => return rejection.into_response(),
+    };
     let blockchain = match state.blockchain.lock() {
         Ok(lock) => lock,
         Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get blockchain lock").into_response(),
     };
-    let triangles: Vec<TriangleInfo> = blockchain.state.utxo_set.iter()
+    let triangles: Vec<TriangleInfo> = blockchain.state.utxo_set().iter()
         .filter(|(_, triangle)| triangle.owner == addr)
-        .map(|(hash, triangle)| TriangleInfo {
-            hash: hex::encode(hash),
-            area: triangle.area(),
-            vertices: vec![
-                (triangle.a.x, triangle.a.y),
-                (triangle.b.x, triangle.b.y),
-                (triangle.c.x, triangle.c.y),
-            ],
-        })
+        .map(|(hash, triangle)| TriangleInfo::new(hash, triangle))
+        .collect();
+    Json(triangles).into_response()
+}
+
+/// Maximum number of triangles returned by a region query
+const MAX_REGION_RESULTS: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct RegionQuery {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct PointQuery {
+    pub x: f64,
+    pub y: f64,
+}
+
+async fn get_triangles_in_region(State(state): State<AppState>, Query(query): Query<RegionQuery>) -> impl IntoResponse {
+    if ![query.min_x, query.min_y, query.max_x, query.max_y].iter().all(|v| v.is_finite()) {
+        return (StatusCode::BAD_REQUEST, "Coordinates must be finite numbers").into_response();
+    }
+    let region = BoundingBox::new(
+        Point::from_f64(query.min_x, query.min_y),
+        Point::from_f64(query.max_x, query.max_y),
+    );
+    let limit = query.limit.unwrap_or(MAX_REGION_RESULTS).min(MAX_REGION_RESULTS);
+
+    let blockchain = match state.blockchain.lock() {
+        Ok(lock) => lock,
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get blockchain lock").into_response(),
+    };
+    let triangles: Vec<TriangleInfo> = blockchain.state.triangles_in_region(&region)
+        .into_iter()
+        .take(limit)
+        .map(|(hash, triangle)| TriangleInfo::new(&hash, triangle))
+        .collect();
+    Json(triangles).into_response()
+}
+
+async fn get_triangles_at_point(State(state): State<AppState>, Query(query): Query<PointQuery>) -> impl IntoResponse {
+    if !query.x.is_finite() || !query.y.is_finite() {
+        return (StatusCode::BAD_REQUEST, "Coordinates must be finite numbers").into_response();
+    }
+    let point = Point::from_f64(query.x, query.y);
+
+    let blockchain = match state.blockchain.lock() {
+        Ok(lock) => lock,
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get blockchain lock").into_response(),
+    };
+    let triangles: Vec<TriangleInfo> = blockchain.state.triangles_containing(&point)
+        .into_iter()
+        .map(|(hash, triangle)| TriangleInfo::new(&hash, triangle))
         .collect();
     Json(triangles).into_response()
 }
 
+/// Maximum number of descendants returned by a lineage query
+const MAX_LINEAGE_DESCENDANTS: usize = 1000;
+
+#[derive(Serialize, Deserialize)]
+pub struct LineageEventInfo {
+    pub tx_hash: Option<String>,
+    pub block_hash: String,
+    pub height: u64,
+}
+
+impl From<&LineageEvent> for LineageEventInfo {
+    fn from(event: &LineageEvent) -> Self {
+        LineageEventInfo {
+            tx_hash: event.tx_hash.map(hex::encode),
+            block_hash: hex::encode(event.block_hash),
+            height: event.height,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TransferInfo {
+    pub event: LineageEventInfo,
+    pub new_owner: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LineageResponse {
+    pub hash: String,
+    pub parent: Option<String>,
+    pub created: LineageEventInfo,
+    pub spent: Option<LineageEventInfo>,
+    pub children: Vec<String>,
+    pub transfers: Vec<TransferInfo>,
+    /// Ancestors from the parent up to the root
+    pub ancestors: Vec<String>,
+    /// Descendants in breadth-first order, truncated to MAX_LINEAGE_DESCENDANTS
+    pub descendants: Vec<String>,
+}
+
+async fn get_triangle_lineage(State(state): State<AppState>, Path(hash): Path<String>) -> impl IntoResponse {
+    let hash: Sha256Hash = match hex::decode(&hash).ok().and_then(|bytes| bytes.try_into().ok()) {
+        Some(hash) => hash,
+        None => return (StatusCode::BAD_REQUEST, "Invalid triangle hash").into_response(),
+    };
+
+    let db = match state.db.lock() {
+        Ok(lock) => lock,
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get database lock").into_response(),
+    };
+    let lineage = match db.get_lineage(&hash) {
+        Ok(Some(lineage)) => lineage,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Triangle not found").into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let ancestors = match db.get_ancestors(&hash) {
+        Ok(ancestors) => ancestors,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let descendants = match db.get_descendants(&hash, MAX_LINEAGE_DESCENDANTS) {
+        Ok(descendants) => descendants,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    Json(LineageResponse {
+        hash: hex::encode(lineage.hash),
+        parent: lineage.parent_hash.map(hex::encode),
+        created: (&lineage.created).into(),
+        spent: lineage.spent.as_ref().map(Into::into),
+        children: lineage.children.iter().map(hex::encode).collect(),
+        transfers: lineage.transfers.iter()
+            .map(|t| TransferInfo { event: (&t.event).into(), new_owner: t.new_owner.clone() })
+            .collect(),
+        ancestors: ancestors.iter().map(hex::encode).collect(),
+        descendants: descendants.iter().map(hex::encode).collect(),
+    }).into_response()
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct TransactionHistory {
     pub tx_hash: String,
@@ -349,6 +684,10 @@ pub struct TransactionHistory {
 }
 
 async fn get_address_history(State(state): State<AppState>, Path(addr): Path<String>) -> impl IntoResponse {
+    let addr = match resolve_label(&state, addr) {
+        Ok(addr) => addr,
+        Err(rejection) => return rejection.into_response(),
+    };
     let blockchain = match state.blockchain.lock() {
         Ok(lock) => lock,
         Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get blockchain lock").into_response(),
@@ -447,6 +786,161 @@ async fn import_wallet(Json(req): Json<ImportWalletRequest>) -> Result<Json<Wall
     }
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct SignMessageRequest {
+    pub private_key: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SignedMessageResponse {
+    pub address: String,
+    pub message: String,
+    /// Hex-encoded recoverable signature
+    pub signature: String,
+}
+
+async fn sign_message(Json(req): Json<SignMessageRequest>) -> Result<Json<SignedMessageResponse>, Response> {
+    let private_key_bytes = hex::decode(&req.private_key)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid private key format").into_response())?;
+    let keypair = KeyPair::from_secret_bytes(&private_key_bytes)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid private key: {}", e)).into_response())?;
+    let signature = keypair.sign_message(req.message.as_bytes())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to sign message: {}", e)).into_response())?;
+
+    Ok(Json(SignedMessageResponse {
+        address: keypair.address(),
+        message: req.message,
+        signature: hex::encode(signature),
+    }))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct VerifyMessageRequest {
+    pub address: String,
+    pub message: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct VerifyMessageResponse {
+    pub valid: bool,
+    /// Address recovered from the signature
+    pub signer: String,
+}
+
+async fn verify_message(Json(req): Json<VerifyMessageRequest>) -> Result<Json<VerifyMessageResponse>, Response> {
+    let signature = hex::decode(&req.signature)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid signature format").into_response())?;
+    let signer = crate::crypto::recover_message_signer(req.message.as_bytes(), &signature)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid signature: {}", e)).into_response())?;
+
+    let address = crate::address::parse_address(&req.address).unwrap_or(req.address);
+
+    Ok(Json(VerifyMessageResponse { valid: signer == address, signer }))
+}
+
+fn load_address_book(state: &AppState) -> Result<AddressBook, (StatusCode, String)> {
+    AddressBook::load(&state.address_book_path)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load address book: {}", e)))
+}
+
+/// Let address routes take an encoded address or a contact label in place of
+/// a canonical address. Mistyped encoded addresses are rejected; other
+/// strings are looked up as given.
+fn resolve_label(state: &AppState, addr: String) -> Result<String, (StatusCode, String)> {
+    let parse_error = match crate::address::parse_address(&addr) {
+        Ok(address) => return Ok(address),
+        Err(e) => e,
+    };
+    if let Some(entry) = AddressBook::load(&state.address_book_path).ok().and_then(|book| book.get(&addr).cloned()) {
+        return Ok(entry.address);
+    }
+    if crate::address::looks_encoded(&addr) {
+        return Err((StatusCode::BAD_REQUEST, parse_error.to_string()));
+    }
+    Ok(addr)
+}
+
+#[derive(Deserialize)]
+pub struct ContactFilter {
+    pub group: Option<String>,
+    pub tag: Option<String>,
+}
+
+async fn list_contacts(State(state): State<AppState>, Query(filter): Query<ContactFilter>) -> Result<Json<Vec<AddressEntry>>, Response> {
+    let book = load_address_book(&state).map_err(IntoResponse::into_response)?;
+    let entries = match &filter.group {
+        Some(group) => book.in_group(group),
+        None => book.list(),
+    };
+    let tag = filter.tag.map(|tag| tag.to_lowercase());
+    Ok(Json(entries.into_iter()
+        .filter(|entry| tag.as_ref().is_none_or(|tag| entry.tags.contains(tag)))
+        .cloned()
+        .collect()))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AddContactRequest {
+    pub label: String,
+    pub address: String,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+async fn add_contact(State(state): State<AppState>, Json(req): Json<AddContactRequest>) -> Result<Json<AddressEntry>, Response> {
+    let bad_request = |e: crate::error::ChainError| (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    let mut book = load_address_book(&state).map_err(IntoResponse::into_response)?;
+    book.add(req.label.clone(), req.address, req.notes).map_err(bad_request)?;
+    book.set_group(&req.label, req.group).map_err(bad_request)?;
+    for tag in &req.tags {
+        book.add_tag(&req.label, tag).map_err(bad_request)?;
+    }
+    book.save(&state.address_book_path)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to save address book: {}", e)).into_response())?;
+
+    let entry = book.get(&req.label).cloned().expect("contact was just added");
+    Ok(Json(entry))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ContactResponse {
+    pub entry: AddressEntry,
+    /// Transfers to and from the contact found on chain
+    pub payments: Vec<Payment>,
+}
+
+async fn get_contact(State(state): State<AppState>, Path(label): Path<String>) -> Result<Json<ContactResponse>, Response> {
+    let book = load_address_book(&state).map_err(IntoResponse::into_response)?;
+    let entry = book.get(&label).cloned()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Label '{}' not found", label)).into_response())?;
+    let blockchain = state.blockchain.lock()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get blockchain lock").into_response())?;
+    let payments = addressbook::payment_history(&entry.address, &blockchain.blocks);
+
+    Ok(Json(ContactResponse { entry, payments }))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ResolveResponse {
+    pub address: String,
+    /// Label the recipient matched, if it was not already an address
+    pub label: Option<String>,
+}
+
+async fn resolve_recipient(State(state): State<AppState>, Path(recipient): Path<String>) -> Result<Json<ResolveResponse>, Response> {
+    let book = load_address_book(&state).map_err(IntoResponse::into_response)?;
+    let address = book.resolve(&recipient)
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()).into_response())?;
+    let label = book.get(&recipient).filter(|entry| entry.address == address).map(|entry| entry.label.clone());
+
+    Ok(Json(ResolveResponse { address, label }))
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct SendTransactionRequest {
     pub transaction: Transaction,
@@ -485,7 +979,8 @@ async fn send_transaction(State(state): State<AppState>, Json(req): Json<SendTra
     }
 
     let tx_hash_str = req.transaction.hash_str();
-    match blockchain.mempool.add_transaction(req.transaction) {
+    let chain = &mut *blockchain;
+    match chain.mempool.add_transaction_with_state(req.transaction, &chain.state) {
         Ok(_) => Json(tx_hash_str).into_response(),
         Err(e) => (StatusCode::BAD_REQUEST, format!("Failed to add transaction: {}", e)).into_response(),
     }
@@ -541,7 +1036,10 @@ async fn start_mining(State(state): State<AppState>, Json(req): Json<StartMining
     if state.mining.is_mining.load(Ordering::Relaxed) {
         return (StatusCode::BAD_REQUEST, "Mining already in progress").into_response();
     }
-    let miner_address = req.miner_address;
+    let miner_address = match crate::address::parse_address(&req.miner_address) {
+        Ok(address) => address,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
     state.mining.is_mining.store(true, Ordering::Relaxed);
 
     // Spawn mining task
@@ -570,10 +1068,11 @@ async fn start_mining(State(state): State<AppState>, Json(req): Json<StartMining
                         break;
                     }
                 };
-                let transactions = blockchain.mempool.get_all_transactions();
+                let transactions = blockchain.mempool.get_transactions_by_fee(blockchain.mempool.len());
+                let height = blockchain.blocks.len() as u64;
 
                 // Create coinbase transaction
-                let reward_area = 100u64;
+                let reward_area = Blockchain::expected_coinbase_reward(height, &transactions);
                 let coinbase = Transaction::Coinbase(crate::transaction::CoinbaseTx {
                     reward_area,
                     beneficiary_address: miner_address.clone(),
@@ -582,7 +1081,6 @@ async fn start_mining(State(state): State<AppState>, Json(req): Json<StartMining
                 let mut all_txs = vec![coinbase];
                 all_txs.extend(transactions);
 
-                let height = blockchain.blocks.len() as u64;
                 let previous_hash = blockchain.blocks.last().expect("Blockchain should have at least a genesis block").hash;
                 let difficulty = blockchain.difficulty;
 
@@ -815,17 +1313,22 @@ mod tests {
     use axum_test::TestServer;
 
     fn test_app() -> Router {
-        let blockchain = Blockchain::new();
+        test_app_with(Blockchain::new())
+    }
+
+    fn test_app_with(blockchain: Blockchain) -> Router {
         let db = match Database::open(":memory:") {
             Ok(db) => db,
             Err(e) => panic!("Failed to open in-memory database for test: {}", e),
         };
+        db.save_block(&blockchain.blocks[0]).expect("Saving genesis block should succeed in test");
 
         let app_state = AppState {
             blockchain: Arc::new(Mutex::new(blockchain)),
             db: Arc::new(Mutex::new(db)),
             mining: MiningState::default(),
             network: NetworkState::default(),
+            address_book_path: std::env::temp_dir().join(format!("test_api_addressbook_{}.json", rand::random::<u32>())),
         };
 
         Router::new()
@@ -833,6 +1336,17 @@ mod tests {
             .route("/blockchain/stats", get(get_blockchain_stats)) // Added missing route
             .route("/blockchain/block/:hash", get(get_block_by_hash))
             .route("/address/:addr/balance", get(get_address_balance))
+            .route("/address/:addr/triangles", get(get_address_triangles))
+            .route("/addresses/balance", post(get_aggregate_balance))
+            .route("/wallet/sign-message", post(sign_message))
+            .route("/message/verify", post(verify_message))
+            .route("/addressbook", get(list_contacts).post(add_contact))
+            .route("/addressbook/:label", get(get_contact))
+            .route("/addressbook/resolve/:recipient", get(resolve_recipient))
+            .route("/address/:addr/history", get(get_address_history))
+            .route("/triangles/region", get(get_triangles_in_region))
+            .route("/triangles/at", get(get_triangles_at_point))
+            .route("/triangle/:hash/lineage", get(get_triangle_lineage))
             .route("/transaction", post(submit_transaction))
             .route("/transaction/:hash", get(get_transaction_status))
             .with_state(app_state)
@@ -879,18 +1393,206 @@ mod tests {
         assert_eq!(response.status_code(), StatusCode::OK);
         let balance: BalanceResponse = response.json();
         assert_eq!(balance.triangles.len(), 1);
-        assert!(balance.total_area > 0.0);
+        assert_eq!(balance.total_area_exact, crate::blockchain::genesis_triangle().area());
+        assert_eq!(balance.total_area, balance.total_area_exact.to_f64());
+
+        // Existing clients still read `total_area` as a JSON number
+        let json: serde_json::Value = response.json();
+        assert!(json["total_area"].is_number());
+        assert!(json["total_area_exact"].is_string());
     }
 
     #[tokio::test]
-    async fn test_submit_and_get_transaction() {
+    async fn test_aggregate_balance() {
+        let server = TestServer::new(test_app()).expect("Test server setup failed");
+        let request = AggregateBalanceRequest {
+            addresses: vec!["genesis_owner".to_string(), "nobody".to_string(), "genesis_owner".to_string()],
+            ..Default::default()
+        };
+        let response = server.post("/addresses/balance").json(&request).await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        let balance: AggregateBalanceResponse = response.json();
+        assert_eq!(balance.addresses_checked, 2);
+        assert_eq!(balance.triangle_count, 1);
+        assert_eq!(balance.addresses.len(), 1);
+        assert_eq!(balance.total_area_exact, crate::blockchain::genesis_triangle().area());
+
+        let bad_xpub = AggregateBalanceRequest { xpub: Some("xpub-nope".to_string()), ..Default::default() };
+        let response = server.post("/addresses/balance").json(&bad_xpub).await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_sign_and_verify_message() {
+        let server = TestServer::new(test_app()).expect("Test server setup failed");
+        let keypair = KeyPair::generate().expect("Key generation should succeed");
+        let request = SignMessageRequest {
+            private_key: hex::encode(keypair.secret_key.secret_bytes()),
+            message: "claim triangle 42".to_string(),
+        };
+        let response = server.post("/wallet/sign-message").json(&request).await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        let signed: SignedMessageResponse = response.json();
+        assert_eq!(signed.address, keypair.address());
+
+        let mut verify = VerifyMessageRequest {
+            address: signed.address,
+            message: signed.message,
+            signature: signed.signature,
+        };
+        let verified: VerifyMessageResponse = server.post("/message/verify").json(&verify).await.json();
+        assert!(verified.valid);
+
+        verify.message = "claim triangle 43".to_string();
+        let verified: VerifyMessageResponse = server.post("/message/verify").json(&verify).await.json();
+        assert!(!verified.valid);
+        assert_ne!(verified.signer, keypair.address());
+
+        verify.signature = "zz".to_string();
+        let response = server.post("/message/verify").json(&verify).await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_addressbook_contacts_resolve_labels() {
+        let server = TestServer::new(test_app()).expect("Test server setup failed");
+        let address = KeyPair::generate().expect("Key generation should succeed").address();
+
+        let invalid = AddContactRequest {
+            label: "Mallory".to_string(),
+            address: "not-an-address".to_string(),
+            notes: None,
+            group: None,
+            tags: vec![],
+        };
+        let response = server.post("/addressbook").json(&invalid).await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+        let request = AddContactRequest {
+            label: "Alice".to_string(),
+            address: address.clone(),
+            notes: Some("Friend".to_string()),
+            group: Some("Family".to_string()),
+            tags: vec!["Coffee".to_string()],
+        };
+        let entry: AddressEntry = server.post("/addressbook").json(&request).await.json();
+        assert_eq!(entry.tags, vec!["coffee".to_string()]);
+
+        let listed: Vec<AddressEntry> = server.get("/addressbook")
+            .add_query_params([("group", "family"), ("tag", "coffee")])
+            .await
+            .json();
+        assert_eq!(listed.len(), 1);
+        let listed: Vec<AddressEntry> = server.get("/addressbook").add_query_params([("tag", "tea")]).await.json();
+        assert!(listed.is_empty());
+
+        let resolved: ResolveResponse = server.get("/addressbook/resolve/alice").await.json();
+        assert_eq!(resolved.address, address);
+        assert_eq!(resolved.label.as_deref(), Some("Alice"));
+        let response = server.get("/addressbook/resolve/bob").await;
+        assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+
+        let contact: ContactResponse = server.get("/addressbook/Alice").await.json();
+        assert!(contact.payments.is_empty());
+        let balance = server.get("/address/alice/triangles").await;
+        assert_eq!(balance.status_code(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_encoded_addresses_in_paths() {
         let server = TestServer::new(test_app()).expect("Test server setup failed");
-        let blockchain = Blockchain::new();
-        let _genesis = blockchain.blocks[0].clone();
+        let address = KeyPair::generate().expect("Key generation should succeed").address();
+        let encoded = crate::address::display_address(&address);
+        assert_ne!(encoded, address);
+
+        let response = server.get(&format!("/address/{}/balance", encoded)).await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+
+        let mut typo = encoded.into_bytes();
+        let last = typo.len() - 1;
+        typo[last] = if typo[last] == b'q' { b'p' } else { b'q' };
+        let response = server.get(&format!("/address/{}/balance", String::from_utf8(typo).unwrap())).await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_address_triangles_show_metadata() {
+        let server = TestServer::new(test_app()).expect("Test server setup failed");
+        let response = server.get("/address/genesis_owner/triangles").await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        let triangles: Vec<TriangleInfo> = response.json();
+        assert_eq!(triangles.len(), 1);
+        assert!(triangles[0].metadata.is_none());
+
+        let mut triangle = crate::blockchain::genesis_triangle();
+        triangle.metadata = Some(
+            crate::metadata::TriangleMetadata::new()
+                .with_name("Genesis")
+                .with_content_hash([9u8; 32]),
+        );
+        let info = TriangleInfo::new(&triangle.hash(), &triangle);
+        let metadata = info.metadata.expect("Metadata should be shown");
+        assert_eq!(metadata.name.as_deref(), Some("Genesis"));
+        assert_eq!(metadata.content_hash, Some(hex::encode([9u8; 32])));
+    }
+
+    #[tokio::test]
+    async fn test_spatial_triangle_queries() {
+        let server = TestServer::new(test_app()).expect("Test server setup failed");
+
+        let response = server.get("/triangles/region")
+            .add_query_params([("min_x", 0.2), ("min_y", 0.1), ("max_x", 0.3), ("max_y", 0.2)])
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        let triangles: Vec<TriangleInfo> = response.json();
+        assert_eq!(triangles.len(), 1);
+        assert_eq!(triangles[0].owner, "genesis_owner");
+
+        let response = server.get("/triangles/at").add_query_params([("x", 0.5), ("y", 0.2)]).await;
+        assert_eq!(response.json::<Vec<TriangleInfo>>().len(), 1);
+
+        // Inside the genesis bounding box but outside the triangle itself
+        let response = server.get("/triangles/at").add_query_params([("x", 0.05), ("y", 0.8)]).await;
+        assert!(response.json::<Vec<TriangleInfo>>().is_empty());
+
+        let response = server.get("/triangles/at").add_query_params([("x", "NaN"), ("y", "0")]).await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_triangle_lineage() {
+        let server = TestServer::new(test_app()).expect("Test server setup failed");
+        let genesis_hash = hex::encode(crate::blockchain::genesis_triangle().hash());
+
+        let response = server.get(&format!("/triangle/{}/lineage", genesis_hash)).await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        let lineage: LineageResponse = response.json();
+        assert_eq!(lineage.hash, genesis_hash);
+        assert!(lineage.parent.is_none());
+        assert!(lineage.created.tx_hash.is_none());
+        assert_eq!(lineage.created.height, 0);
+        assert!(lineage.spent.is_none());
+        assert!(lineage.ancestors.is_empty());
+
+        let response = server.get(&format!("/triangle/{}/lineage", hex::encode([7u8; 32]))).await;
+        assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+
+        let response = server.get("/triangle/not-a-hash/lineage").await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_submit_and_get_transaction() {
+        let mut blockchain = Blockchain::new();
         let keypair = KeyPair::generate().expect("Keypair generation should succeed in test");
         let address = keypair.address();
-        let parent_hash = *blockchain.state.utxo_set.keys().next().expect("UTXO set should not be empty in test");
-        let children = blockchain.state.utxo_set.values().next().expect("UTXO set should not be empty in test").subdivide();
+        let parent_hash = *blockchain.state.utxo_set().keys().next().expect("UTXO set should not be empty in test");
+        // Admission checks the sender owns the triangle it spends
+        let mut parent = blockchain.state.remove_triangle(&parent_hash).expect("UTXO set should not be empty in test");
+        parent.owner = address.clone();
+        blockchain.state.insert_triangle(parent_hash, parent.clone());
+        let children = parent.subdivide();
+        let server = TestServer::new(test_app_with(blockchain)).expect("Test server setup failed");
         let mut tx = SubdivisionTx::new(parent_hash, children.to_vec(), address, 0, 1);
         let message = tx.signable_message();
         let signature = keypair.sign(&message).expect("Signing message should succeed in test");
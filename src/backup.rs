@@ -0,0 +1,457 @@
+//! Encrypted backup bundles of all wallet data
+//!
+//! A bundle is one versioned file holding every wallet file of the wallet
+//! directory, the address book, and a manifest describing each wallet (its
+//! kind, public addresses and a SHA-256 checksum of the file). The contents
+//! are sealed with AES-256-GCM under a key derived from the backup password
+//! with Argon2id; the format, version, creation time and KDF parameters are
+//! bound to the ciphertext as associated data.
+//!
+//! Restores can be limited to selected wallets and planned without writing
+//! anything, so a dry run shows what would be created, overwritten or merged.
+
+use std::fs;
+use std::path::Path;
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::addressbook::AddressBook;
+use crate::error::ChainError;
+use crate::keystore::{self, KdfParams, CIPHER_ALGORITHM};
+use crate::transaction::Address;
+use crate::watchonly::WatchOnlyWallet;
+
+/// Identifies backup bundle files
+pub const BACKUP_FORMAT: &str = "trinitychain-backup";
+
+/// Current backup bundle format version
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Address book file name inside the wallet directory
+pub const ADDRESSBOOK_FILE: &str = "addressbook.json";
+
+/// How a wallet file stores its keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletKind {
+    /// Password-encrypted keystore
+    Encrypted,
+    /// Plaintext keystore or single-key wallet
+    Plaintext,
+    WatchOnly,
+    /// Legacy `wallet_backup.json` file holding one encrypted key
+    LegacyBackup,
+}
+
+impl WalletKind {
+    fn of(value: &serde_json::Value) -> Self {
+        if value.get("ciphertext").is_some() {
+            WalletKind::Encrypted
+        } else if WatchOnlyWallet::is_watch_only(value) {
+            WalletKind::WatchOnly
+        } else if value.get("encrypted_secret_key").is_some() {
+            WalletKind::LegacyBackup
+        } else {
+            WalletKind::Plaintext
+        }
+    }
+}
+
+/// One wallet file in a bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletRecord {
+    pub file_name: String,
+    pub kind: WalletKind,
+    /// Addresses readable without unlocking the wallet
+    pub addresses: Vec<Address>,
+    /// Hex SHA-256 of `contents`
+    pub sha256: String,
+    pub contents: String,
+}
+
+impl WalletRecord {
+    /// Name used to select the wallet: "default" for `wallet.json`, `<name>`
+    /// for `wallet_<name>.json`, otherwise the file stem
+    pub fn wallet_name(&self) -> &str {
+        let stem = self.file_name.strip_suffix(".json").unwrap_or(&self.file_name);
+        match stem {
+            "wallet" => "default",
+            _ => stem.strip_prefix("wallet_").unwrap_or(stem),
+        }
+    }
+}
+
+/// The decrypted contents of a bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupContents {
+    pub created: String,
+    pub wallets: Vec<WalletRecord>,
+    #[serde(default)]
+    pub address_book: Option<AddressBook>,
+}
+
+/// Which parts of a bundle to restore
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoreSelection {
+    /// Wallet names or file names; `None` restores every wallet
+    pub wallets: Option<Vec<String>>,
+    pub address_book: bool,
+    /// Replace wallet files that differ from the backup
+    pub overwrite: bool,
+}
+
+impl Default for RestoreSelection {
+    fn default() -> Self {
+        RestoreSelection { wallets: None, address_book: true, overwrite: false }
+    }
+}
+
+/// A change a restore makes, or would make during a dry run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestoreAction {
+    Create { file_name: String },
+    Overwrite { file_name: String },
+    Unchanged { file_name: String },
+    /// The existing file differs and overwriting was not requested
+    Skip { file_name: String },
+    /// Address book entries added, and labels kept because they point elsewhere
+    MergeAddressBook { added: Vec<String>, conflicting: Vec<String> },
+}
+
+impl BackupContents {
+    /// Gather every wallet file and the address book from `dir`. Hidden files
+    /// such as unlock sessions and other backup bundles are left out.
+    pub fn collect(dir: &Path) -> Result<Self, ChainError> {
+        let mut wallets = Vec::new();
+        let mut address_book = None;
+
+        let entries = fs::read_dir(dir)
+            .map_err(|e| ChainError::WalletError(format!("Failed to read wallet directory: {}", e)))?;
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("json"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let Some(file_name) = path.file_name().and_then(|s| s.to_str()).filter(|f| !f.starts_with('.')) else {
+                continue;
+            };
+            if file_name == ADDRESSBOOK_FILE {
+                address_book = Some(AddressBook::load(&path)?);
+                continue;
+            }
+
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| ChainError::WalletError(format!("Failed to read {}: {}", path.display(), e)))?;
+            let value: serde_json::Value = serde_json::from_str(&contents)
+                .map_err(|e| ChainError::WalletError(format!("Failed to parse {}: {}", path.display(), e)))?;
+            if BackupBundle::is_bundle(&value) {
+                continue;
+            }
+
+            let kind = WalletKind::of(&value);
+            let addresses = match kind {
+                WalletKind::LegacyBackup => value.get("address").and_then(|a| a.as_str())
+                    .map(|a| vec![a.to_string()])
+                    .unwrap_or_default(),
+                _ => keystore::wallet_addresses(&path)?,
+            };
+            wallets.push(WalletRecord {
+                file_name: file_name.to_string(),
+                kind,
+                addresses,
+                sha256: sha256_hex(&contents),
+                contents,
+            });
+        }
+
+        Ok(BackupContents { created: chrono::Utc::now().to_rfc3339(), wallets, address_book })
+    }
+
+    /// Check every wallet against its recorded checksum
+    pub fn verify(&self) -> Result<(), ChainError> {
+        for wallet in &self.wallets {
+            if sha256_hex(&wallet.contents) != wallet.sha256 {
+                return Err(ChainError::WalletError(format!(
+                    "Checksum mismatch for {} in backup", wallet.file_name
+                )));
+            }
+            if wallet.file_name.contains(['/', '\\']) || wallet.file_name.starts_with('.') {
+                return Err(ChainError::WalletError(format!("Invalid file name {} in backup", wallet.file_name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Wallets picked by `selection`, failing on names not in the backup
+    fn selected_wallets(&self, selection: &RestoreSelection) -> Result<Vec<&WalletRecord>, ChainError> {
+        let Some(names) = &selection.wallets else {
+            return Ok(self.wallets.iter().collect());
+        };
+        names.iter()
+            .map(|name| {
+                self.wallets.iter()
+                    .find(|w| w.wallet_name() == name || w.file_name == *name)
+                    .ok_or_else(|| ChainError::WalletError(format!("Wallet '{}' is not in the backup", name)))
+            })
+            .collect()
+    }
+
+    /// Work out what restoring into `dir` would change, without writing
+    pub fn plan_restore(&self, dir: &Path, selection: &RestoreSelection) -> Result<Vec<RestoreAction>, ChainError> {
+        let mut actions = Vec::new();
+        for wallet in self.selected_wallets(selection)? {
+            let file_name = wallet.file_name.clone();
+            let path = dir.join(&wallet.file_name);
+            let action = if !path.exists() {
+                RestoreAction::Create { file_name }
+            } else {
+                let existing = fs::read_to_string(&path)
+                    .map_err(|e| ChainError::WalletError(format!("Failed to read {}: {}", path.display(), e)))?;
+                if sha256_hex(&existing) == wallet.sha256 {
+                    RestoreAction::Unchanged { file_name }
+                } else if selection.overwrite {
+                    RestoreAction::Overwrite { file_name }
+                } else {
+                    RestoreAction::Skip { file_name }
+                }
+            };
+            actions.push(action);
+        }
+
+        if let (true, Some(book)) = (selection.address_book, &self.address_book) {
+            let current = AddressBook::load(&dir.join(ADDRESSBOOK_FILE))?;
+            let (added, conflicting) = merge_plan(&current, book);
+            actions.push(RestoreAction::MergeAddressBook { added, conflicting });
+        }
+        Ok(actions)
+    }
+
+    /// Restore the selected parts into `dir`, returning what was done
+    pub fn restore(&self, dir: &Path, selection: &RestoreSelection) -> Result<Vec<RestoreAction>, ChainError> {
+        self.verify()?;
+        let actions = self.plan_restore(dir, selection)?;
+        fs::create_dir_all(dir)
+            .map_err(|e| ChainError::WalletError(format!("Failed to create wallet directory: {}", e)))?;
+
+        for action in &actions {
+            match action {
+                RestoreAction::Create { file_name } | RestoreAction::Overwrite { file_name } => {
+                    let wallet = self.wallets.iter()
+                        .find(|w| &w.file_name == file_name)
+                        .expect("planned wallets come from the backup");
+                    keystore::write_private(&dir.join(file_name), wallet.contents.as_bytes())?;
+                }
+                RestoreAction::MergeAddressBook { added, .. } if !added.is_empty() => {
+                    let path = dir.join(ADDRESSBOOK_FILE);
+                    let mut current = AddressBook::load(&path)?;
+                    let book = self.address_book.as_ref().expect("merge is planned only with an address book");
+                    for key in added {
+                        current.entries.insert(key.to_lowercase(), book.entries[&key.to_lowercase()].clone());
+                    }
+                    current.save(&path)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(actions)
+    }
+}
+
+/// Labels of `backup` entries missing from `current`, and labels present in
+/// both with different addresses
+fn merge_plan(current: &AddressBook, backup: &AddressBook) -> (Vec<String>, Vec<String>) {
+    let mut added = Vec::new();
+    let mut conflicting = Vec::new();
+    for entry in backup.list() {
+        match current.get(&entry.label) {
+            None => added.push(entry.label.clone()),
+            Some(existing) if existing.address != entry.address => conflicting.push(entry.label.clone()),
+            Some(_) => {}
+        }
+    }
+    (added, conflicting)
+}
+
+fn sha256_hex(contents: &str) -> String {
+    hex::encode(Sha256::digest(contents.as_bytes()))
+}
+
+/// An encrypted backup bundle file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupBundle {
+    pub format: String,
+    pub version: u32,
+    pub created: String,
+    pub kdf: KdfParams,
+    pub cipher: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl BackupBundle {
+    /// Encrypt `contents` under `password`
+    pub fn seal(contents: &BackupContents, password: &str, kdf: KdfParams) -> Result<Self, ChainError> {
+        keystore::check_password(password)?;
+        let mut bundle = BackupBundle {
+            format: BACKUP_FORMAT.to_string(),
+            version: BACKUP_VERSION,
+            created: contents.created.clone(),
+            kdf,
+            cipher: CIPHER_ALGORITHM.to_string(),
+            nonce: String::new(),
+            ciphertext: String::new(),
+        };
+
+        let plaintext = Zeroizing::new(serde_json::to_vec(contents)
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize backup: {}", e)))?);
+        let key = bundle.kdf.derive_key(password)?;
+        let (nonce, ciphertext) = keystore::seal(&key, &plaintext, &bundle.associated_data()?)?;
+        bundle.nonce = general_purpose::STANDARD.encode(nonce);
+        bundle.ciphertext = general_purpose::STANDARD.encode(ciphertext);
+        Ok(bundle)
+    }
+
+    /// Decrypt the bundle and check its checksums
+    pub fn open(&self, password: &str) -> Result<BackupContents, ChainError> {
+        if self.format != BACKUP_FORMAT || self.version > BACKUP_VERSION {
+            return Err(ChainError::WalletError(format!(
+                "Unsupported backup format {} version {}", self.format, self.version
+            )));
+        }
+        if self.cipher != CIPHER_ALGORITHM {
+            return Err(ChainError::CryptoError(format!("Unsupported cipher: {}", self.cipher)));
+        }
+
+        let key = self.kdf.derive_key(password)?;
+        let plaintext = keystore::open(&key, &self.nonce, &self.ciphertext, &self.associated_data()?)?;
+        let contents: BackupContents = serde_json::from_slice(&plaintext)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse backup contents: {}", e)))?;
+        contents.verify()?;
+        Ok(contents)
+    }
+
+    /// Header fields the ciphertext is bound to
+    fn associated_data(&self) -> Result<Vec<u8>, ChainError> {
+        serde_json::to_vec(&(&self.format, self.version, &self.created, &self.kdf, &self.cipher))
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize backup header: {}", e)))
+    }
+
+    /// Whether a parsed JSON file is a backup bundle
+    pub fn is_bundle(value: &serde_json::Value) -> bool {
+        value.get("format").and_then(|f| f.as_str()) == Some(BACKUP_FORMAT)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ChainError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to read backup: {}", e)))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse backup: {}", e)))
+    }
+
+    /// Whether the file at `path` is a backup bundle
+    pub fn is_bundle_file(path: &Path) -> bool {
+        fs::read_to_string(path).ok()
+            .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).ok())
+            .is_some_and(|value| Self::is_bundle(&value))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ChainError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize backup: {}", e)))?;
+        keystore::write_private(path, json.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keystore::{Keystore, UnlockedKeystore};
+
+    fn test_kdf() -> KdfParams {
+        KdfParams::new(8, 1, 1).unwrap()
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_bundle_roundtrip_and_selective_restore() {
+        let source = temp_dir("test_backup_source");
+        let mut keystore = Keystore::new(Some("alice".to_string()));
+        keystore.new_receive_address(Some("savings".to_string())).unwrap();
+        UnlockedKeystore::create(&source.join("wallet_alice.json"), keystore, "password123", test_kdf()).unwrap();
+        let watch_only = WatchOnlyWallet::from_addresses(None, ["ee".repeat(32)]).unwrap();
+        watch_only.save(&source.join("wallet.json")).unwrap();
+        fs::write(source.join(".session_wallet_alice.json"), "{}").unwrap();
+        let mut book = AddressBook::new();
+        book.add("Bob".to_string(), "b0".repeat(32), None).unwrap();
+        book.save(&source.join(ADDRESSBOOK_FILE)).unwrap();
+
+        let contents = BackupContents::collect(&source).unwrap();
+        assert_eq!(contents.wallets.len(), 2);
+        assert_eq!(contents.wallets[0].kind, WalletKind::WatchOnly);
+        assert_eq!(contents.wallets[1].kind, WalletKind::Encrypted);
+        assert_eq!(contents.wallets[1].wallet_name(), "alice");
+
+        let bundle = BackupBundle::seal(&contents, "backup-password", test_kdf()).unwrap();
+        assert!(bundle.open("wrong-password").is_err());
+        let opened = bundle.open("backup-password").unwrap();
+
+        // Dry run of a selective restore writes nothing
+        let target = temp_dir("test_backup_target");
+        let selection = RestoreSelection { wallets: Some(vec!["alice".to_string()]), ..Default::default() };
+        let plan = opened.plan_restore(&target, &selection).unwrap();
+        assert_eq!(plan, vec![
+            RestoreAction::Create { file_name: "wallet_alice.json".to_string() },
+            RestoreAction::MergeAddressBook { added: vec!["Bob".to_string()], conflicting: vec![] },
+        ]);
+        assert!(!target.join("wallet_alice.json").exists());
+
+        opened.restore(&target, &selection).unwrap();
+        assert!(!target.join("wallet.json").exists());
+        let restored = UnlockedKeystore::unlock(&target.join("wallet_alice.json"), "password123").unwrap();
+        assert_eq!(restored.keystore.keys[0].label.as_deref(), Some("savings"));
+        assert_eq!(AddressBook::load(&target.join(ADDRESSBOOK_FILE)).unwrap().get("bob").unwrap().address, "b0".repeat(32));
+
+        // A changed wallet is skipped unless overwriting
+        fs::write(target.join("wallet_alice.json"), "{}").unwrap();
+        let plan = opened.plan_restore(&target, &selection).unwrap();
+        assert_eq!(plan[0], RestoreAction::Skip { file_name: "wallet_alice.json".to_string() });
+        assert!(opened.plan_restore(&target, &RestoreSelection {
+            wallets: Some(vec!["carol".to_string()]), ..Default::default()
+        }).is_err());
+
+        fs::remove_dir_all(&source).unwrap();
+        fs::remove_dir_all(&target).unwrap();
+    }
+
+    #[test]
+    fn test_tampered_bundle_is_rejected() {
+        let contents = BackupContents { created: "now".to_string(), wallets: vec![], address_book: None };
+        let mut bundle = BackupBundle::seal(&contents, "backup-password", test_kdf()).unwrap();
+        bundle.created = "later".to_string();
+        assert!(bundle.open("backup-password").is_err());
+
+        let mut record = WalletRecord {
+            file_name: "wallet.json".to_string(),
+            kind: WalletKind::Plaintext,
+            addresses: vec![],
+            sha256: sha256_hex("{}"),
+            contents: "{}".to_string(),
+        };
+        let mut contents = BackupContents { created: "now".to_string(), wallets: vec![record.clone()], address_book: None };
+        assert!(contents.verify().is_ok());
+        record.contents = "{ }".to_string();
+        contents.wallets = vec![record];
+        assert!(contents.verify().is_err());
+    }
+}
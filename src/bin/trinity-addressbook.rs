@@ -1,6 +1,8 @@
 //! Address book management tool
 
-use trinitychain::addressbook::{self};
+use trinitychain::addressbook::{self, AddressEntry, PaymentDirection};
+use trinitychain::persistence::Database;
+use std::path::Path;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = std::env::args().collect();
@@ -15,9 +17,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     match command.as_str() {
         "add" => add_address(&args[2..])?,
         "remove" | "rm" => remove_address(&args[2..])?,
-        "list" | "ls" => list_addresses()?,
+        "list" | "ls" => list_addresses(&args[2..])?,
         "search" => search_addresses(&args[2..])?,
         "get" => get_address(&args[2..])?,
+        "group" => set_group(&args[2..])?,
+        "groups" => list_groups()?,
+        "tag" => tag_address(&args[2..], true)?,
+        "untag" => tag_address(&args[2..], false)?,
+        "import" => import_addresses(&args[2..])?,
+        "export" => export_addresses(&args[2..])?,
+        "history" => show_history(&args[2..])?,
         _ => {
             eprintln!("Unknown command: {}", command);
             print_usage();
@@ -33,14 +42,24 @@ fn print_usage() {
     println!("Usage: trinity-addressbook <command> [arguments]\n");
     println!("Commands:");
     println!("  add <label> <address> [notes]   Add a new address");
+    println!("      [--group G] [--tag T]...");
     println!("  remove <label>                   Remove an address");
-    println!("  list                             List all addresses");
+    println!("  list [--group G] [--tag T]       List all addresses");
     println!("  search <query>                   Search addresses");
     println!("  get <label>                      Get specific address");
+    println!("  group <label> [group]            Set or clear an address's group");
+    println!("  groups                           List groups");
+    println!("  tag <label> <tag>...             Tag an address");
+    println!("  untag <label> <tag>...           Remove tags");
+    println!("  import <file> [--overwrite]      Import from a .csv or .json file");
+    println!("  export <file>                    Export to a .csv or .json file");
+    println!("  history <label>                  Show payments to and from an address");
+    println!("\nLabels can be used in place of addresses with trinity-send.");
     println!("\nExamples:");
-    println!("  trinity-addressbook add Alice abc123... \"My friend\"");
-    println!("  trinity-addressbook list");
+    println!("  trinity-addressbook add Alice abc123... \"My friend\" --group Friends");
+    println!("  trinity-addressbook list --tag coffee");
     println!("  trinity-addressbook search Alice");
+    println!("  trinity-addressbook export contacts.csv");
 }
 
 fn add_address(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
@@ -51,22 +70,29 @@ fn add_address(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
 
     let label = &args[0];
     let address = &args[1];
-    let notes = if args.len() > 2 {
-        Some(args[2..].join(" "))
+    let (rest, group, tags) = split_flags(&args[2..]);
+    let notes = if !rest.is_empty() {
+        Some(rest.join(" "))
     } else {
         None
     };
 
     let mut book = addressbook::load_default()?;
     book.add(label.clone(), address.clone(), notes.clone())?;
+    book.set_group(label, group)?;
+    for tag in &tags {
+        book.add_tag(label, tag)?;
+    }
     addressbook::save_default(&book)?;
 
+    let entry = book.get(label).ok_or("Address was not added")?;
     println!("✅ Address added successfully!");
     println!("📌 Label: {}", label);
-    println!("📍 Address: {}...", &address[..42.min(address.len())]);
+    println!("📍 Address: {}...", &entry.address[..42.min(entry.address.len())]);
     if let Some(n) = notes {
         println!("📝 Notes: {}", n);
     }
+    print_group_and_tags(entry);
 
     Ok(())
 }
@@ -90,9 +116,21 @@ fn remove_address(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn list_addresses() -> Result<(), Box<dyn std::error::Error>> {
+fn list_addresses(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (_, group, tags) = split_flags(args);
     let book = addressbook::load_default()?;
-    let entries = book.list();
+    let entries: Vec<_> = match &group {
+        Some(group) => book.in_group(group),
+        None => book.list(),
+    };
+    let entries: Vec<_> = entries.into_iter()
+        .filter(|entry| tags.iter().all(|tag| entry.tags.contains(&tag.to_lowercase())))
+        .collect();
+
+    if entries.is_empty() && (group.is_some() || !tags.is_empty()) {
+        println!("❌ No addresses match the filter");
+        return Ok(());
+    }
 
     if entries.is_empty() {
         println!("📒 Address book is empty");
@@ -109,6 +147,7 @@ fn list_addresses() -> Result<(), Box<dyn std::error::Error>> {
         if let Some(notes) = &entry.notes {
             println!("📝 Notes: {}", notes);
         }
+        print_group_and_tags(entry);
         println!("📅 Added: {}", entry.added);
     }
 
@@ -166,6 +205,7 @@ fn get_address(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
             if let Some(notes) = &entry.notes {
                 println!("📝 Notes: {}", notes);
             }
+            print_group_and_tags(entry);
             println!("📅 Added: {}", entry.added);
         }
         None => {
@@ -176,3 +216,181 @@ fn get_address(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Split `--group G` and `--tag T` flags from the other arguments
+fn split_flags(args: &[String]) -> (Vec<String>, Option<String>, Vec<String>) {
+    let mut rest = Vec::new();
+    let mut group = None;
+    let mut tags = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--group" => group = iter.next().cloned(),
+            "--tag" => tags.extend(iter.next().cloned()),
+            _ => rest.push(arg.clone()),
+        }
+    }
+    (rest, group, tags)
+}
+
+fn print_group_and_tags(entry: &AddressEntry) {
+    if let Some(group) = &entry.group {
+        println!("👥 Group: {}", group);
+    }
+    if !entry.tags.is_empty() {
+        println!("🏷️  Tags: {}", entry.tags.join(", "));
+    }
+}
+
+fn set_group(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.is_empty() {
+        eprintln!("Usage: trinity-addressbook group <label> [group]");
+        std::process::exit(1);
+    }
+
+    let group = (args.len() > 1).then(|| args[1..].join(" "));
+    let mut book = addressbook::load_default()?;
+    book.set_group(&args[0], group.clone())?;
+    addressbook::save_default(&book)?;
+
+    match group {
+        Some(group) => println!("✅ {} is now in group {}", args[0], group),
+        None => println!("✅ {} removed from its group", args[0]),
+    }
+
+    Ok(())
+}
+
+fn list_groups() -> Result<(), Box<dyn std::error::Error>> {
+    let book = addressbook::load_default()?;
+    let groups = book.groups();
+
+    if groups.is_empty() {
+        println!("👥 No groups yet");
+        println!("\nAdd an address to a group with: trinity-addressbook group <label> <group>");
+        return Ok(());
+    }
+
+    println!("👥 Groups\n");
+    for group in groups {
+        let members: Vec<_> = book.in_group(&group).iter().map(|e| e.label.clone()).collect();
+        println!("  {} ({}): {}", group, members.len(), members.join(", "));
+    }
+
+    Ok(())
+}
+
+fn tag_address(args: &[String], add: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 2 {
+        let command = if add { "tag" } else { "untag" };
+        eprintln!("Usage: trinity-addressbook {} <label> <tag>...", command);
+        std::process::exit(1);
+    }
+
+    let label = &args[0];
+    let mut book = addressbook::load_default()?;
+    for tag in &args[1..] {
+        let changed = if add { book.add_tag(label, tag)? } else { book.remove_tag(label, tag)? };
+        if !changed {
+            println!("⚠️  {} {} tag '{}'", label, if add { "already has" } else { "does not have" }, tag);
+        }
+    }
+    addressbook::save_default(&book)?;
+
+    let entry = book.get(label).ok_or("Address not found")?;
+    println!("✅ Tags for {}: {}", entry.label, entry.tags.join(", "));
+
+    Ok(())
+}
+
+/// Whether a file should be read or written as CSV, judged by its extension
+fn is_csv(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+fn import_addresses(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.is_empty() {
+        eprintln!("Usage: trinity-addressbook import <file.csv|file.json> [--overwrite]");
+        std::process::exit(1);
+    }
+
+    let path = &args[0];
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let entries = if is_csv(path) {
+        addressbook::parse_csv(&contents)?
+    } else {
+        addressbook::parse_json(&contents)?
+    };
+
+    let mut book = addressbook::load_default()?;
+    let summary = book.import(entries, args.iter().any(|a| a == "--overwrite"))?;
+    addressbook::save_default(&book)?;
+
+    println!("✅ Imported {} address(es) from {}", summary.added.len() + summary.updated.len(), path);
+    if !summary.updated.is_empty() {
+        println!("✏️  Updated: {}", summary.updated.join(", "));
+    }
+    if !summary.skipped.is_empty() {
+        println!("⚠️  Skipped existing labels: {}", summary.skipped.join(", "));
+        println!("   Use --overwrite to replace them");
+    }
+
+    Ok(())
+}
+
+fn export_addresses(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.is_empty() {
+        eprintln!("Usage: trinity-addressbook export <file.csv|file.json>");
+        std::process::exit(1);
+    }
+
+    let path = &args[0];
+    let book = addressbook::load_default()?;
+    let contents = if is_csv(path) { book.export_csv() } else { book.export_json()? };
+    std::fs::write(path, contents)
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    println!("✅ Exported {} address(es) to {}", book.entries.len(), path);
+
+    Ok(())
+}
+
+fn show_history(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.is_empty() {
+        eprintln!("Usage: trinity-addressbook history <label>");
+        std::process::exit(1);
+    }
+
+    let book = addressbook::load_default()?;
+    let entry = book.get(&args[0]).ok_or_else(|| format!("Address not found: {}", args[0]))?;
+
+    let db = Database::open("trinitychain.db")
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let chain = db.load_blockchain()
+        .map_err(|e| format!("Failed to load blockchain: {}", e))?;
+    let payments = addressbook::payment_history(&entry.address, &chain.blocks);
+
+    println!("📜 Payments with {} ({} found)\n", entry.label, payments.len());
+
+    for payment in payments {
+        let counterparty = book.find_by_address(&payment.counterparty)
+            .map(|e| e.label.clone())
+            .unwrap_or_else(|| format!("{}...", &payment.counterparty[..16.min(payment.counterparty.len())]));
+        let direction = match payment.direction {
+            PaymentDirection::ToContact => format!("⬅️  from {}", counterparty),
+            PaymentDirection::FromContact => format!("➡️  to {}", counterparty),
+        };
+        let area = payment.area.map(|a| a.to_string()).unwrap_or_else(|| "?".to_string());
+        println!("─────────────────────────────────────────");
+        println!("🧱 Block {}  {}", payment.block_height, direction);
+        println!("🔺 Triangle {}... (area {})", &payment.triangle_hash[..16], area);
+        if let Some(memo) = &payment.memo {
+            println!("📝 Memo: {}", memo);
+        }
+    }
+
+    println!("─────────────────────────────────────────");
+
+    Ok(())
+}
@@ -1,6 +1,12 @@
 //! Check wallet balance - Beautiful edition!
 
+use trinitychain::address;
+use trinitychain::geometry::Area;
+use trinitychain::hdwallet;
+use trinitychain::keystore::{self, UnlockedKeystore};
 use trinitychain::persistence::Database;
+use trinitychain::wallet;
+use std::collections::HashSet;
 use colored::*;
 use comfy_table::{Table, Cell, ContentArrangement, Attribute};
 use comfy_table::presets::UTF8_FULL;
@@ -22,55 +28,47 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("{}", LOGO.bright_cyan());
 
     let args: Vec<String> = std::env::args().collect();
-    let home = std::env::var("HOME")?;
+    let db = Database::open("trinitychain.db")
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let chain = db.load_blockchain()
+        .map_err(|e| format!("Failed to load blockchain: {}", e))?;
 
-    // Check if address was provided as argument
-    let my_address = if args.len() > 1 {
-        args[1].clone()
+    // Check if address was provided as argument, otherwise use every address
+    // of the wallet (support WALLET_NAME env var)
+    let my_addresses: HashSet<String> = if args.len() > 1 {
+        [address::parse_address(&args[1])?].into_iter().collect()
     } else {
-        // Otherwise, load from wallet file (support WALLET_NAME env var)
-        let wallet_name = std::env::var("WALLET_NAME").unwrap_or_else(|_| String::new());
-        let wallet_file = if wallet_name.is_empty() {
-            format!("{}/.trinitychain/wallet.json", home)
-        } else {
-            format!("{}/.trinitychain/wallet_{}.json", home, wallet_name)
-        };
-
-        let wallet_content = std::fs::read_to_string(&wallet_file)
+        let wallet_file = wallet::get_active_wallet_path();
+        let addresses = keystore::wallet_addresses(&wallet_file)
             .map_err(|e| {
                 eprintln!("{}", "╔══════════════════════════════════════════╗".red());
                 eprintln!("{}", "║         ❌ Wallet Not Found!            ║".red().bold());
                 eprintln!("{}", "╚══════════════════════════════════════════╝".red());
                 eprintln!();
                 eprintln!("{}", "💡 Run 'trinity-wallet-new <name>' to create a wallet".yellow());
-                format!("No wallet found at {}: {}", wallet_file, e)
+                format!("No wallet found at {}: {}", wallet_file.display(), e)
             })?;
 
-        let wallet_data: serde_json::Value = serde_json::from_str(&wallet_content)
-            .map_err(|e| format!("Failed to parse wallet: {}", e))?;
-
-        wallet_data["address"].as_str()
-            .ok_or("Wallet address not found in wallet file")?
-            .to_string()
+        // Keep the used/unused state of the wallet's addresses current while it is unlocked
+        if let Some(mut unlocked) = UnlockedKeystore::from_env_session(&wallet_file)? {
+            if unlocked.keystore.refresh_used(&hdwallet::addresses_on_chain(&chain)) > 0 {
+                unlocked.save()?;
+            }
+        }
+        addresses.into_iter().collect()
     };
 
-    let db = Database::open("trinitychain.db")
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    let chain = db.load_blockchain()
-        .map_err(|e| format!("Failed to load blockchain: {}", e))?;
-
     println!("{}", "┌─────────────────────────────────────────────────────────────┐".bright_green());
     println!("{}", "│                    💰 WALLET BALANCE                        │".bright_green().bold());
     println!("{}", "└─────────────────────────────────────────────────────────────┘".bright_green());
     println!();
 
-    let addr_display = if my_address.len() > 50 {
-        format!("{}...{}", &my_address[..24], &my_address[my_address.len()-24..])
+    if my_addresses.len() == 1 {
+        let my_address = my_addresses.iter().next().expect("one address");
+        println!("{}", format!("📍 Address: {}", short_address(my_address, 24)).cyan());
     } else {
-        my_address.to_string()
-    };
-
-    println!("{}", format!("📍 Address: {}", addr_display).cyan());
+        println!("{}", format!("📍 Addresses: {}", my_addresses.len()).cyan());
+    }
 
     let height = chain.blocks.last()
         .map(|b| b.header.height)
@@ -80,16 +78,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!();
 
     let mut my_triangles = 0;
-    let mut total_area = 0.0;
+    let mut total_area = Area::ZERO;
     let mut triangle_list = Vec::new();
 
     // Filter triangles owned by this address
-    for (hash, triangle) in &chain.state.utxo_set {
-        if triangle.owner == my_address {
+    for (hash, triangle) in chain.state.utxo_set() {
+        if my_addresses.contains(&triangle.owner) {
             my_triangles += 1;
             total_area += triangle.area();
             let hash_hex = hex::encode(hash);
-            triangle_list.push((hash_hex, triangle.area()));
+            triangle_list.push((hash_hex, &triangle.owner, triangle.area()));
         }
     }
 
@@ -117,12 +115,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .set_header(vec![
             Cell::new("#").fg(TableColor::Cyan).add_attribute(Attribute::Bold),
             Cell::new("Triangle Hash").fg(TableColor::Cyan).add_attribute(Attribute::Bold),
+            Cell::new("Owner").fg(TableColor::Cyan).add_attribute(Attribute::Bold),
             Cell::new("Area").fg(TableColor::Cyan).add_attribute(Attribute::Bold),
         ]);
 
-    triangle_list.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    triangle_list.sort_by_key(|(_, _, area)| std::cmp::Reverse(*area));
 
-    for (idx, (hash, area)) in triangle_list.iter().enumerate() {
+    for (idx, (hash, owner, area)) in triangle_list.iter().enumerate() {
         let hash_short = if hash.len() > 20 {
             format!("{}...{}", &hash[..10], &hash[hash.len()-10..])
         } else {
@@ -132,7 +131,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         table.add_row(vec![
             Cell::new(format!("{}", idx + 1)).fg(TableColor::Yellow),
             Cell::new(&hash_short).fg(TableColor::Green),
-            Cell::new(format!("{:.6}", area)).fg(TableColor::Magenta),
+            Cell::new(short_address(owner, 8)).fg(TableColor::Blue),
+            Cell::new(area.to_string()).fg(TableColor::Magenta),
         ]);
     }
 
@@ -143,9 +143,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("{}", "║                    💎 TOTAL BALANCE                      ║".bright_green().bold());
     println!("{}", "╠══════════════════════════════════════════════════════════╣".bright_green());
     println!("{}", format!("║  🔺 Triangles: {:<42} ║", my_triangles).green());
-    println!("{}", format!("║  📐 Total Area: {:<39.6} ║", total_area).green());
+    println!("{}", format!("║  📐 Total Area: {:<39} ║", total_area.to_string()).green());
 
-    let avg_area = total_area / my_triangles as f64;
+    let avg_area = total_area.to_f64() / my_triangles as f64;
     println!("{}", format!("║  📊 Average Area: {:<37.6} ║", avg_area).green());
     println!("{}", "╚══════════════════════════════════════════════════════════╝".bright_green());
     println!();
@@ -155,3 +155,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+fn short_address(address: &str, keep: usize) -> String {
+    if address.len() > keep * 2 + 2 {
+        format!("{}...{}", &address[..keep], &address[address.len()-keep..])
+    } else {
+        address.to_string()
+    }
+}
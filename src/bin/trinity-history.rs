@@ -1,7 +1,11 @@
 //! View transaction history for your wallet - Beautiful edition!
 
-use trinitychain::persistence::Database;
+use trinitychain::blockchain::Sha256Hash;
+use trinitychain::persistence::{Database, LineageEvent};
+use trinitychain::keystore;
 use trinitychain::transaction::Transaction;
+use trinitychain::wallet;
+use std::collections::HashSet;
 use colored::*;
 use comfy_table::{Table, Cell, ContentArrangement, Attribute};
 use comfy_table::presets::UTF8_FULL;
@@ -22,41 +26,38 @@ const LOGO: &str = r#"
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("{}", LOGO.bright_magenta());
 
-    let home = std::env::var("HOME")?;
-    let wallet_file = format!("{}/.trinitychain/wallet.json", home);
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 && args[1] == "--triangle" {
+        let hash = args.get(2).ok_or("Usage: trinity-history --triangle <hash>")?;
+        return show_triangle_lineage(hash);
+    }
 
-    let wallet_content = std::fs::read_to_string(&wallet_file)
+    let wallet_file = wallet::get_active_wallet_path();
+    let my_addresses: HashSet<String> = keystore::wallet_addresses(&wallet_file)
         .map_err(|e| {
             eprintln!("{}", "╔══════════════════════════════════════════╗".red());
             eprintln!("{}", "║         ❌ Wallet Not Found!            ║".red().bold());
             eprintln!("{}", "╚══════════════════════════════════════════╝".red());
             eprintln!();
             eprintln!("{}", "💡 Run 'wallet new' to create a wallet".yellow());
-            format!("No wallet found at {}: {}", wallet_file, e)
-        })?;
-
-    let wallet_data: serde_json::Value = serde_json::from_str(&wallet_content)
-        .map_err(|e| format!("Failed to parse wallet: {}", e))?;
-
-    let my_address = wallet_data["address"].as_str()
-        .ok_or("Wallet address not found in wallet file")?;
+            format!("No wallet found at {}: {}", wallet_file.display(), e)
+        })?
+        .into_iter()
+        .collect();
 
     let db = Database::open("trinitychain.db")
         .map_err(|e| format!("Failed to open database: {}", e))?;
     let chain = db.load_blockchain()
         .map_err(|e| format!("Failed to load blockchain: {}", e))?;
 
-    let addr_display = if my_address.len() > 40 {
-        format!("{}...{}", &my_address[..20], &my_address[my_address.len()-16..])
-    } else {
-        my_address.to_string()
-    };
-
     println!("{}", "┌─────────────────────────────────────────────────────────────┐".bright_cyan());
     println!("{}", "│                  📜 TRANSACTION HISTORY                     │".bright_cyan().bold());
     println!("{}", "└─────────────────────────────────────────────────────────────┘".bright_cyan());
     println!();
-    println!("{}", format!("📍 Address: {}", addr_display).cyan());
+    match my_addresses.iter().next() {
+        Some(address) if my_addresses.len() == 1 => println!("{}", format!("📍 Address: {}", short_address(address)).cyan()),
+        _ => println!("{}", format!("📍 Addresses: {}", my_addresses.len()).cyan()),
+    }
     println!();
 
     let mut tx_count = 0;
@@ -80,8 +81,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         for tx in &block.transactions {
             match tx {
                 Transaction::Transfer(transfer_tx) => {
-                    let is_sender = transfer_tx.sender == my_address;
-                    let is_receiver = transfer_tx.new_owner == my_address;
+                    let is_sender = my_addresses.contains(&transfer_tx.sender);
+                    let is_receiver = my_addresses.contains(&transfer_tx.new_owner);
 
                     if is_sender || is_receiver {
                         tx_count += 1;
@@ -140,7 +141,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                 }
                 Transaction::Coinbase(coinbase_tx) => {
-                    if coinbase_tx.beneficiary_address == my_address {
+                    if my_addresses.contains(&coinbase_tx.beneficiary_address) {
                         tx_count += 1;
                         received_count += 1;
                         mining_count += 1;
@@ -156,7 +157,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                 }
                 Transaction::Subdivision(sub_tx) => {
-                    if sub_tx.owner_address == my_address {
+                    if my_addresses.contains(&sub_tx.owner_address) {
                         tx_count += 1;
 
                         let hash_hex = hex::encode(sub_tx.parent_hash);
@@ -231,6 +232,92 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Print where a triangle came from and what became of it
+fn show_triangle_lineage(hash_hex: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let hash: Sha256Hash = hex::decode(hash_hex).ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Triangle hash must be 64 hex characters")?;
+
+    let db = Database::open("trinitychain.db")
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let lineage = db.get_lineage(&hash)
+        .map_err(|e| format!("Failed to load lineage: {}", e))?
+        .ok_or_else(|| format!("Triangle {} not found in the lineage index", hash_hex))?;
+    let ancestors = db.get_ancestors(&hash)
+        .map_err(|e| format!("Failed to load ancestors: {}", e))?;
+
+    println!("{}", "┌─────────────────────────────────────────────────────────────┐".bright_cyan());
+    println!("{}", "│                   🔺 TRIANGLE LINEAGE                       │".bright_cyan().bold());
+    println!("{}", "└─────────────────────────────────────────────────────────────┘".bright_cyan());
+    println!();
+    println!("{}", format!("🔺 Triangle: {}", hex::encode(hash)).cyan());
+    println!();
+
+    let describe = |event: &LineageEvent| match event.tx_hash {
+        Some(tx_hash) => format!("tx {} in block #{}", &hex::encode(tx_hash)[..16], event.height),
+        None => format!("genesis block #{}", event.height),
+    };
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("Event").add_attribute(Attribute::Bold).fg(TableColor::Cyan),
+            Cell::new("Details").add_attribute(Attribute::Bold).fg(TableColor::Cyan),
+        ]);
+
+    table.add_row(vec![
+        Cell::new("Created").fg(TableColor::Green),
+        Cell::new(describe(&lineage.created)),
+    ]);
+    for transfer in &lineage.transfers {
+        table.add_row(vec![
+            Cell::new("Transferred").fg(TableColor::Yellow),
+            Cell::new(format!("to {} by {}", transfer.new_owner, describe(&transfer.event))),
+        ]);
+    }
+    match &lineage.spent {
+        Some(event) => table.add_row(vec![
+            Cell::new("Subdivided").fg(TableColor::Magenta),
+            Cell::new(describe(event)),
+        ]),
+        None => table.add_row(vec![
+            Cell::new("Status").fg(TableColor::Green),
+            Cell::new("Unspent"),
+        ]),
+    };
+    println!("{}", table);
+    println!();
+
+    if ancestors.is_empty() {
+        println!("{}", "🌱 Root triangle (no parent)".bright_green());
+    } else {
+        println!("{}", format!("🌳 Ancestors ({}):", ancestors.len()).bright_yellow().bold());
+        for (depth, ancestor) in ancestors.iter().enumerate() {
+            println!("   {}└─ {}", "  ".repeat(depth), hex::encode(ancestor));
+        }
+    }
+
+    if !lineage.children.is_empty() {
+        println!();
+        println!("{}", format!("🔻 Children ({}):", lineage.children.len()).bright_yellow().bold());
+        for child in &lineage.children {
+            println!("   • {}", hex::encode(child));
+        }
+    }
+
+    Ok(())
+}
+
+fn short_address(address: &str) -> String {
+    if address.len() > 40 {
+        format!("{}...{}", &address[..20], &address[address.len()-16..])
+    } else {
+        address.to_string()
+    }
+}
+
 fn format_timestamp_short(timestamp: i64) -> String {
     use chrono::DateTime;
 
@@ -2,11 +2,10 @@
 
 use trinitychain::persistence::Database;
 use trinitychain::transaction::{Transaction, SubdivisionTx, CoinbaseTx};
-use trinitychain::crypto::KeyPair;
+use trinitychain::keystore;
 use trinitychain::miner::{mine_block, mine_block_parallel};
 use std::env;
 use trinitychain::wallet;
-use secp256k1::SecretKey;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("⛏️  Mining Block...\n");
@@ -39,25 +38,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let wallet_path = wallet::get_default_wallet_path();
     if !wallet_path.exists() {
         println!("👛 No default wallet found. Creating a new one...");
-        wallet::create_default_wallet()?;
+        let password = keystore::prompt_new_password()?;
+        wallet::create_default_wallet(&password)?;
         println!("✅ New wallet created at: {}", wallet_path.display());
     }
 
-    let wallet_content = std::fs::read_to_string(&wallet_path)?;
-    let wallet_data: serde_json::Value = serde_json::from_str(&wallet_content)?;
-
-    let address = wallet_data["address"].as_str()
-        .ok_or("Wallet address not found")?
-        .to_string();
-    let secret_hex = wallet_data["secret_key"].as_str()
-        .ok_or("Secret key not found")?;
-    let secret_bytes = hex::decode(secret_hex)?;
-    let secret_key = SecretKey::from_slice(&secret_bytes)?;
-    let keypair = KeyPair::from_secret_key(secret_key);
+    let unlocked = keystore::unlock_interactive(&wallet_path)?;
+    let keystore = &unlocked.keystore;
+    let address = keystore.primary_address()
+        .ok_or("Wallet holds no keys")?
+        .clone();
+    let keypair = keystore.keypair_for(&address)?;
 
-    let parent_hash = *chain.state.utxo_set.keys().next()
+    let parent_hash = *chain.state.utxo_set().keys().next()
         .ok_or("No UTXOs available")?;
-    let parent_triangle = chain.state.utxo_set.get(&parent_hash)
+    let parent_triangle = chain.state.get(&parent_hash)
         .ok_or("Parent triangle not found")?
         .clone();
 
@@ -72,11 +67,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let public_key = keypair.public_key.serialize().to_vec();
     tx.sign(signature, public_key);
 
-    let coinbase = CoinbaseTx { reward_area: 1000, beneficiary_address: address };
-
     // Include pending transactions from mempool (prioritized by fee)
     let mempool_txs = chain.mempool.get_transactions_by_fee(100); // Get up to 100 highest-fee transactions
 
+    let last_block = chain.blocks.last()
+        .ok_or("Blockchain is empty")?;
+    let reward_area = trinitychain::blockchain::Blockchain::expected_coinbase_reward(
+        last_block.header.height + 1,
+        &mempool_txs,
+    );
+    let coinbase = CoinbaseTx { reward_area, beneficiary_address: address };
+
     let mut transactions = vec![Transaction::Coinbase(coinbase)];
 
     // Add mempool transactions first (they have fees!)
@@ -87,8 +88,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("⛏️  Mining block (difficulty {})...", chain.difficulty);
 
-    let last_block = chain.blocks.last()
-        .ok_or("Blockchain is empty")?;
     let mut new_block = trinitychain::blockchain::Block::new(
         last_block.header.height + 1,
         last_block.hash,
@@ -1,6 +1,8 @@
 //! Miner CLI for TrinityChain - Beautiful edition!
 
+use trinitychain::address;
 use trinitychain::blockchain::{Blockchain, Block};
+use trinitychain::geometry::Area;
 use trinitychain::persistence::Database;
 use trinitychain::network::NetworkNode;
 use trinitychain::transaction::{Transaction, CoinbaseTx};
@@ -24,7 +26,7 @@ const LOGO: &str = r#"
 "#;
 
 /// Format a large number with thousands separators
-fn format_number(num: u64) -> String {
+fn format_number(num: impl ToString) -> String {
     let num_str = num.to_string();
     let mut result = String::new();
     let chars: Vec<char> = num_str.chars().collect();
@@ -62,7 +64,13 @@ async fn main() {
         println!();
         return;
     }
-    let beneficiary_address = args[1].clone();
+    let beneficiary_address = match address::parse_address(&args[1]) {
+        Ok(address) => address,
+        Err(e) => {
+            eprintln!("{}", format!("❌ {}", e).red());
+            std::process::exit(1);
+        }
+    };
 
     // Parse optional threads flag: --threads N or -t N
     let mut threads: usize = 1;
@@ -136,6 +144,7 @@ async fn main() {
     }
 
     let mut blocks_mined = 0;
+    let mut total_earned = Area::default();
     let start_time = Instant::now();
 
     println!("{}", "╔══════════════════════════════════════════════════════════╗".bright_green());
@@ -163,7 +172,7 @@ async fn main() {
         let difficulty = chain.difficulty;
 
         let coinbase_tx = Transaction::Coinbase(CoinbaseTx {
-            reward_area: 1000,
+            reward_area: Blockchain::expected_coinbase_reward(new_height, &[]),
             beneficiary_address: beneficiary_address.clone(),
         });
 
@@ -262,6 +271,8 @@ async fn main() {
         }
 
         blocks_mined += 1;
+        let reward = Blockchain::expected_coinbase_reward(new_block.header.height, &new_block.transactions);
+        total_earned = total_earned.checked_add(Area::from_units(reward)).unwrap_or(total_earned);
         let elapsed = start_time.elapsed();
         let avg_block_time = elapsed.as_secs_f64() / blocks_mined as f64;
 
@@ -275,7 +286,7 @@ async fn main() {
             }
         };
         let current_supply = Blockchain::calculate_current_supply(current_height);
-        let supply_pct = (current_supply as f64 / trinitychain::blockchain::MAX_SUPPLY as f64) * 100.0;
+        let supply_pct = (current_supply.to_f64() / trinitychain::blockchain::MAX_SUPPLY.to_f64()) * 100.0;
         let current_reward = Blockchain::calculate_block_reward(current_height);
         let halving_era = current_height / 210_000;
         let blocks_to_halving = ((halving_era + 1) * 210_000).saturating_sub(current_height);
@@ -290,10 +301,10 @@ async fn main() {
         println!("{}", format!("║ ⚡ Avg Block Time: {:.1}s{:<34} ║", avg_block_time, "").cyan());
         println!("{}", format!("║ 🎯 Difficulty: {:<41} ║", chain.difficulty).cyan());
         println!("{}", format!("║ 💎 Current Reward: {:<35} ║", current_reward).cyan());
-        println!("{}", format!("║ 🪙  Total Earned: {:<37.1} ║", blocks_mined as f64 * 1000.0).cyan());
+        println!("{}", format!("║ 🪙  Total Earned: {:<37} ║", total_earned.to_string()).cyan());
         println!("{}", format!("║ 📈 Total Supply: {:>10} / {} ({:.3}%){:<6} ║",
-                 format_number(current_supply),
-                 format_number(trinitychain::blockchain::MAX_SUPPLY),
+                 format_number(current_supply.whole_units()),
+                 format_number(trinitychain::blockchain::MAX_SUPPLY.whole_units()),
                  supply_pct, "").cyan());
         println!("{}", format!("║ ⏰ Blocks to Halving: {:<32} ║", format_number(blocks_to_halving)).cyan());
         println!("{}", format!("║ 🎚️  Halving Era: {:<38} ║", halving_era).cyan());
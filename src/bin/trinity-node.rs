@@ -2,7 +2,7 @@
 
 use trinitychain::blockchain::Blockchain;
 use trinitychain::persistence::Database;
-use trinitychain::network::NetworkNode;
+use trinitychain::network::{shutdown_signal, NetworkNode};
 use std::env;
 
 #[tokio::main]
@@ -27,9 +27,11 @@ async fn main() {
     });
     
     println!("📊 Current height: {}", blockchain.blocks.last().unwrap().header.height);
-    println!("💾 UTXO count: {}\n", blockchain.state.count());
+    println!("💾 UTXO count: {}", blockchain.state.count());
+    println!("📥 Mempool: {} pending transactions\n", blockchain.mempool.len());
     
     let node = NetworkNode::new(blockchain, db_path);
+    node.spawn_mempool_saver();
     
     if args.len() >= 4 && args[2] == "--peer" {
         let peer_addr = &args[3];
@@ -46,8 +48,18 @@ async fn main() {
     }
     
     println!("🌐 Ready to accept connections!\n");
-    if let Err(e) = node.start_server(port).await {
-        eprintln!("❌ Server error: {}", e);
+    tokio::select! {
+        result = node.start_server(port) => {
+            if let Err(e) = result {
+                eprintln!("❌ Server error: {}", e);
+            }
+        }
+        _ = shutdown_signal() => println!("\n🛑 Shutting down..."),
+    }
+
+    match node.save_mempool().await {
+        Ok(()) => println!("💾 Mempool saved"),
+        Err(e) => eprintln!("❌ Failed to save mempool: {}", e),
     }
 }
 
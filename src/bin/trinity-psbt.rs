@@ -0,0 +1,264 @@
+//! Partially-signed transactions: build, sign offline, combine and broadcast
+
+use std::path::Path;
+
+use trinitychain::address;
+use trinitychain::keystore::unlock_interactive;
+use trinitychain::network::NetworkNode;
+use trinitychain::persistence::Database;
+use trinitychain::psbt::PartiallySignedTransaction;
+use trinitychain::script::Script;
+use trinitychain::transaction::{Transaction, TransferTx};
+use trinitychain::wallet;
+use trinitychain::watchonly::WatchOnlyWallet;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 2 {
+        print_usage();
+        std::process::exit(1);
+    }
+
+    let command = &args[1];
+
+    match command.as_str() {
+        "create" => create(&args[2..])?,
+        "inspect" | "decode" => inspect(&args[2..])?,
+        "sign" => sign(&args[2..])?,
+        "combine" => combine(&args[2..])?,
+        "finalize" => finalize(&args[2..])?,
+        "extract" => extract(&args[2..]).await?,
+        _ => {
+            eprintln!("Unknown command: {}", command);
+            print_usage();
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_usage() {
+    println!("✍️  Partially-Signed Transactions\n");
+    println!("Usage: trinity-psbt <command> [arguments]\n");
+    println!("Commands:");
+    println!("  create <to> <triangle_hash> [--fee N] [--script FILE] [--out FILE]");
+    println!("                                      Build a transfer for offline signing");
+    println!("  create --from-tx <FILE> [--script FILE] [--out FILE]");
+    println!("                                      Wrap an unsigned transaction");
+    println!("  inspect <file>                      Show the transaction and its signatures");
+    println!("  sign <file> [--out FILE]            Sign with the active wallet");
+    println!("  combine <out> <file>...             Merge signatures from several copies");
+    println!("  finalize <file>                     Attach the signatures to the transaction");
+    println!("  extract <file> [--out FILE] [--broadcast]");
+    println!("                                      Write out or broadcast the signed transaction");
+    println!("\nThe wallet is chosen with WALLET_NAME, like the other tools. When the");
+    println!("active wallet is watch-only, create records derivation hints from it.");
+    println!("\nExamples:");
+    println!("  WALLET_NAME=audit trinity-psbt create abc123... def456... --out tx.psbt");
+    println!("  trinity-psbt sign tx.psbt          (on the air-gapped machine)");
+    println!("  trinity-psbt finalize tx.psbt && trinity-psbt extract tx.psbt --broadcast");
+}
+
+/// Value following `flag` in `args`
+fn flag<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1))
+}
+
+fn create(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let db = Database::open("trinitychain.db")
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let chain = db.load_blockchain()
+        .map_err(|e| format!("Failed to load blockchain: {}", e))?;
+
+    let transaction = if let Some(path) = flag(args, "--from-tx") {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        serde_json::from_str::<Transaction>(&contents)
+            .map_err(|e| format!("Failed to parse unsigned transaction: {}", e))?
+    } else {
+        if args.len() < 2 {
+            eprintln!("Usage: trinity-psbt create <to> <triangle_hash> [--fee N] [--script FILE] [--out FILE]");
+            std::process::exit(1);
+        }
+        let to_address = address::parse_address(&args[0])?;
+        let triangle_prefix = &args[1];
+        let fee = match flag(args, "--fee") {
+            Some(fee) => fee.parse().map_err(|_| "--fee requires a number")?,
+            None => 0,
+        };
+
+        let (hash, triangle) = chain.state.utxo_set().iter()
+            .find(|(h, _)| hex::encode(h).starts_with(triangle_prefix.as_str()))
+            .ok_or_else(|| format!("Triangle with hash prefix {} not found", triangle_prefix))?;
+        Transaction::Transfer(TransferTx::new(
+            *hash, to_address, triangle.owner.clone(), fee, chain.blocks.len() as u64,
+        ))
+    };
+
+    let mut psbt = PartiallySignedTransaction::create(transaction, &chain.state)?;
+    if let Some(path) = flag(args, "--script") {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let script: Script = serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse script: {}", e))?;
+        psbt = psbt.with_script(script)?;
+    }
+
+    // A watch-only wallet knows where its addresses sit in the signer's HD tree
+    let wallet_path = wallet::get_active_wallet_path();
+    if wallet_path.exists() {
+        if let Some(watch_only) = WatchOnlyWallet::load_if_watch_only(&wallet_path)? {
+            for signer in psbt.required_signers() {
+                let path = watch_only.addresses.iter()
+                    .find(|a| a.address == signer)
+                    .and_then(|a| a.derivation_path.clone());
+                if let Some(path) = path {
+                    psbt.add_hint(signer, path);
+                }
+            }
+        }
+    }
+
+    let out = flag(args, "--out").cloned().unwrap_or_else(|| "transaction.psbt".to_string());
+    psbt.save(Path::new(&out))?;
+
+    println!("📝 Partially-signed transaction written to {}", out);
+    println!("   Spends: {}", hex::encode(psbt.input_hash()));
+    println!("   Signers: {}", psbt.required_signers().join(", "));
+    println!("\n✍️  Sign it with: trinity-psbt sign {}", out);
+    Ok(())
+}
+
+fn inspect(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.is_empty() {
+        eprintln!("Usage: trinity-psbt inspect <file>");
+        std::process::exit(1);
+    }
+    let psbt = PartiallySignedTransaction::load(Path::new(&args[0]))?;
+
+    println!("✍️  Partially-signed transaction {}\n", psbt.transaction.hash_str());
+    match &psbt.transaction {
+        Transaction::Transfer(tx) => {
+            println!("  Type:     transfer");
+            println!("  From:     {}", tx.sender);
+            println!("  To:       {}", tx.new_owner);
+        }
+        Transaction::Subdivision(tx) => {
+            println!("  Type:     subdivision");
+            println!("  Owner:    {}", tx.owner_address);
+        }
+        Transaction::Coinbase(_) => println!("  Type:     coinbase"),
+    }
+    println!("  Fee:      {}", psbt.transaction.fee());
+    println!("  Spends:   {} (area {})", hex::encode(psbt.input_hash()), psbt.input.area());
+    println!("  Owner:    {}", psbt.input.owner);
+    if psbt.script.is_some() {
+        println!("  Script:   revealed");
+    }
+
+    let signed = psbt.signers()?;
+    println!("\n  Signers:");
+    for signer in psbt.required_signers() {
+        let mark = if signed.contains(&signer) { "✅" } else { "⏳" };
+        let hint = psbt.hints.iter()
+            .find(|h| h.address == signer)
+            .map(|h| format!(" ({})", h.derivation_path))
+            .unwrap_or_default();
+        println!("    {} {}{}", mark, signer, hint);
+    }
+
+    let status = if psbt.finalized {
+        "finalized"
+    } else if psbt.is_complete() {
+        "ready to finalize"
+    } else {
+        "awaiting signatures"
+    };
+    println!("\n  Status:   {}", status);
+    Ok(())
+}
+
+fn sign(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.is_empty() {
+        eprintln!("Usage: trinity-psbt sign <file> [--out FILE]");
+        std::process::exit(1);
+    }
+    let mut psbt = PartiallySignedTransaction::load(Path::new(&args[0]))?;
+
+    let wallet_file = wallet::get_active_wallet_path();
+    let unlocked = unlock_interactive(&wallet_file)
+        .map_err(|e| format!("Failed to unlock wallet {}: {}", wallet_file.display(), e))?;
+    let added = psbt.sign(&unlocked.keystore)?;
+    if added == 0 {
+        return Err("This wallet holds no key that still needs to sign".into());
+    }
+
+    let out = flag(args, "--out").unwrap_or(&args[0]);
+    psbt.save(Path::new(out))?;
+
+    println!("✅ Added {} signature(s), written to {}", added, out);
+    if psbt.is_complete() {
+        println!("🎉 Enough signatures collected: trinity-psbt finalize {}", out);
+    }
+    Ok(())
+}
+
+fn combine(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        eprintln!("Usage: trinity-psbt combine <out> <file> <file>...");
+        std::process::exit(1);
+    }
+
+    let mut combined = PartiallySignedTransaction::load(Path::new(&args[1]))?;
+    for path in &args[2..] {
+        combined.combine(&PartiallySignedTransaction::load(Path::new(path))?)?;
+    }
+    combined.save(Path::new(&args[0]))?;
+
+    println!("✅ Combined {} copies into {} ({} signature(s))", args.len() - 1, args[0], combined.signatures.len());
+    Ok(())
+}
+
+fn finalize(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.is_empty() {
+        eprintln!("Usage: trinity-psbt finalize <file>");
+        std::process::exit(1);
+    }
+    let path = Path::new(&args[0]);
+    let mut psbt = PartiallySignedTransaction::load(path)?;
+    psbt.finalize()?;
+    psbt.save(path)?;
+
+    println!("✅ Finalized {}", psbt.transaction.hash_str());
+    Ok(())
+}
+
+async fn extract(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.is_empty() {
+        eprintln!("Usage: trinity-psbt extract <file> [--out FILE] [--broadcast]");
+        std::process::exit(1);
+    }
+    let transaction = PartiallySignedTransaction::load(Path::new(&args[0]))?.extract()?;
+
+    if let Some(out) = flag(args, "--out") {
+        std::fs::write(out, serde_json::to_string_pretty(&transaction)?)?;
+        println!("📝 Signed transaction written to {}", out);
+    }
+
+    if args.iter().any(|a| a == "--broadcast") {
+        let db = Database::open("trinitychain.db")?;
+        let mut chain = db.load_blockchain()?;
+        transaction.validate(&chain.state)?;
+        chain.mempool.add_transaction_with_state(transaction.clone(), &chain.state)?;
+
+        let network_node = NetworkNode::new(chain, "trinitychain.db".to_string());
+        network_node.broadcast_transaction(&transaction).await?;
+        println!("✅ Broadcast transaction {}", transaction.hash_str());
+    } else if flag(args, "--out").is_none() {
+        println!("{}", serde_json::to_string_pretty(&transaction)?);
+    }
+    Ok(())
+}
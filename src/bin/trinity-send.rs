@@ -1,10 +1,12 @@
 //! Send triangles to another address - Beautiful edition!
 
+use trinitychain::addressbook;
+use trinitychain::crypto::SignatureScheme;
 use trinitychain::persistence::Database;
 use trinitychain::transaction::{Transaction, TransferTx};
-use trinitychain::crypto::KeyPair;
+use trinitychain::keystore::unlock_interactive;
 use trinitychain::network::NetworkNode;
-use secp256k1::SecretKey;
+use trinitychain::wallet;
 use std::env;
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
@@ -24,7 +26,18 @@ const LOGO: &str = r#"
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let scheme = if args.iter().any(|a| a == "--schnorr") {
+        SignatureScheme::Schnorr
+    } else {
+        SignatureScheme::Ecdsa
+    };
+    args.retain(|a| a != "--schnorr");
+
+    if args.len() == 3 && args[1] == "--sign" {
+        println!("{}", LOGO.bright_cyan());
+        return sign_unsigned_transaction(&args[2], scheme).await;
+    }
 
     if args.len() < 3 {
         println!("{}", LOGO.bright_cyan());
@@ -33,11 +46,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("{}", "╠══════════════════════════════════════════════════════════╣".bright_yellow());
         println!("{}", "║                                                          ║".bright_yellow());
         println!("{}", "║  Usage:                                                  ║".bright_yellow());
-        println!("{}", "║    send <to_address> <triangle_hash> [memo]              ║".white());
+        println!("{}", "║    send <to_address|label> <triangle_hash> [memo]        ║".white());
+        println!("{}", "║    send --sign <unsigned_tx.json>                        ║".white());
         println!("{}", "║                                                          ║".bright_yellow());
         println!("{}", "║  Examples:                                               ║".bright_yellow());
         println!("{}", "║    send abc123... def456...                              ║".white());
         println!("{}", "║    send abc123... def456... \"Payment for services\"      ║".white());
+        println!("{}", "║    send Alice def456...      (label from address book)   ║".white());
+        println!("{}", "║                                                          ║".bright_yellow());
+        println!("{}", "║  Add --schnorr to sign with a Schnorr signature          ║".white());
         println!("{}", "║                                                          ║".bright_yellow());
         println!("{}", "╚══════════════════════════════════════════════════════════╝".bright_yellow());
         println!();
@@ -46,7 +63,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("{}", LOGO.bright_cyan());
 
-    let to_address = &args[1];
+    // Recipients may be given by address book label
+    let to_address = &addressbook::resolve_recipient(&args[1])?;
+    if *to_address != args[1] {
+        println!("{}", format!("📒 {} → {}", args[1], to_address).bright_blue());
+    }
     let triangle_hash = &args[2];
     let memo = if args.len() > 3 {
         Some(args[3..].join(" "))
@@ -70,28 +91,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     pb.set_message("Loading wallet...");
     pb.enable_steady_tick(Duration::from_millis(100));
 
-    let home = std::env::var("HOME")?;
-
-    // Support WALLET_NAME environment variable for multi-wallet support
-    let wallet_name = std::env::var("WALLET_NAME").unwrap_or_else(|_| String::new());
-    let wallet_file = if wallet_name.is_empty() {
-        format!("{}/.trinitychain/wallet.json", home)
-    } else {
-        format!("{}/.trinitychain/wallet_{}.json", home, wallet_name)
-    };
-
-    let wallet_content = std::fs::read_to_string(&wallet_file)
-        .map_err(|e| format!("Wallet not found at {}: {}", wallet_file, e))?;
-    let wallet_data: serde_json::Value = serde_json::from_str(&wallet_content)?;
-
-    let from_address = wallet_data["address"].as_str()
-        .ok_or("Wallet address not found")?
-        .to_string();
-    let secret_hex = wallet_data["secret_key"].as_str()
-        .ok_or("Secret key not found")?;
-    let secret_bytes = hex::decode(secret_hex)?;
-    let secret_key = SecretKey::from_slice(&secret_bytes)?;
-    let keypair = KeyPair::from_secret_key(secret_key);
+    let wallet_file = wallet::get_active_wallet_path();
+    let unlocked = pb.suspend(|| unlock_interactive(&wallet_file))
+        .map_err(|e| format!("Failed to unlock wallet {}: {}", wallet_file.display(), e))?;
+    let keystore = &unlocked.keystore;
 
     pb.set_message("Loading blockchain...");
 
@@ -100,13 +103,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     pb.set_message("Looking up triangle...");
 
-    let full_hash = *chain.state.utxo_set.keys()
-        .find(|h| hex::encode(h).starts_with(triangle_hash))
-        .ok_or_else(|| format!("Triangle with hash prefix {} not found", triangle_hash))?;
+    // Any triangle owned by one of the wallet's addresses can be sent
+    let (full_hash, triangle) = keystore.owned_triangles(&chain.state).into_iter()
+        .find(|(h, _)| hex::encode(h).starts_with(triangle_hash.as_str()))
+        .map(|(h, t)| (h, t.clone()))
+        .ok_or_else(|| format!("Triangle with hash prefix {} not found in this wallet", triangle_hash))?;
 
-    let triangle = chain.state.utxo_set.get(&full_hash)
-        .ok_or("Triangle not found in UTXO set")?
-        .clone();
+    let from_address = triangle.owner.clone();
+    let keypair = keystore.keypair_for(&from_address)?;
 
     pb.finish_and_clear();
 
@@ -131,7 +135,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("{}", "║              🔍 TRANSACTION DETAILS                      ║".bright_cyan().bold());
     println!("{}", "╠══════════════════════════════════════════════════════════╣".bright_cyan());
     println!("{}", format!("║  🔺 Triangle: {:<42} ║", full_hash_display).cyan());
-    println!("{}", format!("║  📐 Area: {:<47.6} ║", triangle.area()).cyan());
+    println!("{}", format!("║  📐 Area: {:<47} ║", triangle.area().to_string()).cyan());
     println!("{}", format!("║  👤 From: {:<47} ║", from_display).cyan());
     println!("{}", format!("║  🎯 To: {:<49} ║", to_display).cyan());
     if let Some(ref m) = memo {
@@ -165,12 +169,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     pb.set_message("Signing transaction...");
 
     let message = tx.signable_message();
-    let signature = keypair.sign(&message)?;
+    let signature = keypair.sign_with(scheme, &message)?;
     let public_key = keypair.public_key.serialize().to_vec();
-    tx.sign(signature, public_key);
+    tx.sign_with_scheme(scheme, signature, public_key);
 
     let transaction = Transaction::Transfer(tx);
-    chain.mempool.add_transaction(transaction.clone())?;
+    chain.mempool.add_transaction_with_state(transaction.clone(), &chain.state)?;
 
     pb.set_message("Broadcasting to network...");
 
@@ -190,4 +194,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!();
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Sign and broadcast an unsigned transaction built by a watch-only wallet
+async fn sign_unsigned_transaction(path: &str, scheme: SignatureScheme) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let mut transaction: Transaction = serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse unsigned transaction: {}", e))?;
+
+    let wallet_file = wallet::get_active_wallet_path();
+    let unlocked = unlock_interactive(&wallet_file)
+        .map_err(|e| format!("Failed to unlock wallet {}: {}", wallet_file.display(), e))?;
+    unlocked.keystore.sign_transaction_with(&mut transaction, scheme)?;
+
+    let db = Database::open("trinitychain.db")?;
+    let mut chain = db.load_blockchain()?;
+    transaction.validate(&chain.state)?;
+    chain.mempool.add_transaction_with_state(transaction.clone(), &chain.state)?;
+
+    let network_node = NetworkNode::new(chain, "trinitychain.db".to_string());
+    network_node.broadcast_transaction(&transaction).await?;
+
+    println!("{}", format!("✅ Signed and broadcast transaction {}", transaction.hash_str()).bright_green().bold());
+    println!();
+
+    Ok(())
+}
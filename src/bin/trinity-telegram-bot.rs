@@ -1,5 +1,7 @@
 use teloxide::{prelude::*, utils::command::BotCommands};
 use log::{info, warn};
+use trinitychain::address;
+use trinitychain::addressbook;
 use trinitychain::persistence::Database;
 use std::sync::Arc;
 use std::collections::HashMap;
@@ -16,7 +18,7 @@ enum Command {
     Help,
     #[command(description = "view blockchain statistics")]
     Stats,
-    #[command(description = "check wallet balance (address)")]
+    #[command(description = "check wallet balance (address or address book label)")]
     Balance(String),
     #[command(description = "view recent blocks")]
     Blocks,
@@ -230,20 +232,26 @@ async fn answer(
             info!("Handled /broadcast command for user: {:?}", message.from());
         }
         Command::Balance(address) => {
+            let address = match addressbook::resolve_recipient(address.trim()) {
+                Ok(resolved) => resolved,
+                Err(e) if address::looks_encoded(address.trim()) => {
+                    bot.send_message(message.chat.id, format!("❌ {}", e)).await?;
+                    return Ok(());
+                }
+                Err(_) => address,
+            };
             let response = match Database::open("trinitychain.db") {
                 Ok(db) => match db.load_blockchain() {
                     Ok(chain) => {
-                        let triangles_owned: Vec<_> = chain.state.utxo_set.iter()
+                        let triangles_owned: Vec<_> = chain.state.utxo_set().iter()
                             .filter(|(_, triangle)| triangle.owner == address)
                             .collect();
 
-                        let balance: f64 = triangles_owned.iter()
-                            .map(|(_, triangle)| triangle.area())
-                            .sum();
+                        let balance = chain.state.balance_of(&address);
 
                         format!(
                             "💰 Balance for {}:\n\n\
-                            Total Area: {:.6} area\n\
+                            Total Area: {} area\n\
                             Number of Triangles: {}",
                             if address.len() > 20 {
                                 format!("{}...{}", &address[..8], &address[address.len()-8..])
@@ -406,7 +414,7 @@ async fn answer(
                             "🔺 TrinityChain Dashboard 🔺\n\n\
                             📊 Live Statistics:\n\n\
                             🏔️ Height: {}\n\
-                            💰 Total Supply: {:.2} area\n\
+                            💰 Total Supply: {} area\n\
                             🎁 Block Reward: {:.2} area\n\
                             🔺 Active Triangles: {}\n\
                             ⚡ Difficulty: {}\n\
@@ -0,0 +1,127 @@
+//! Chain state verification tool
+
+use trinitychain::persistence::Database;
+use trinitychain::verify::{self, SupplyIssue};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 2 {
+        print_usage();
+        std::process::exit(1);
+    }
+
+    let command = &args[1];
+
+    let ok = match command.as_str() {
+        "geometry" => verify_geometry()?,
+        "supply" => verify_supply()?,
+        _ => {
+            eprintln!("Unknown command: {}", command);
+            print_usage();
+            std::process::exit(1);
+        }
+    };
+
+    if !ok {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn print_usage() {
+    println!("🔍 TrinityChain State Verification\n");
+    println!("Usage: trinity-verify <command>\n");
+    println!("Commands:");
+    println!("  geometry    Check that live triangles are valid and never overlap");
+    println!("  supply      Check UTXO area against minted rewards and subdivision burns");
+    println!("\nExamples:");
+    println!("  trinity-verify geometry");
+    println!("  trinity-verify supply");
+}
+
+fn verify_geometry() -> Result<bool, Box<dyn std::error::Error>> {
+    let db = Database::open("trinitychain.db")
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let chain = db.load_blockchain()
+        .map_err(|e| format!("Failed to load blockchain: {}", e))?;
+
+    let height = chain.blocks.last().map(|b| b.header.height).unwrap_or(0);
+    println!("📊 Chain height: {}", height);
+
+    let report = verify::verify_geometry(&chain.state);
+    println!("🔺 Triangles checked: {}", report.triangles_checked);
+
+    for hash in &report.invalid {
+        println!("❌ Invalid geometry: {}", hex::encode(hash));
+    }
+    for hash in &report.mismatched_keys {
+        println!("❌ UTXO key does not match triangle hash: {}", hex::encode(hash));
+    }
+    for (a, b) in &report.overlaps {
+        println!("❌ Overlapping triangles: {} and {}", hex::encode(a), hex::encode(b));
+    }
+
+    if report.is_ok() {
+        println!("✅ Geometry invariants hold");
+    } else {
+        println!(
+            "⚠️  {} invalid, {} mismatched keys, {} overlapping pairs",
+            report.invalid.len(),
+            report.mismatched_keys.len(),
+            report.overlaps.len()
+        );
+    }
+
+    Ok(report.is_ok())
+}
+
+fn verify_supply() -> Result<bool, Box<dyn std::error::Error>> {
+    let db = Database::open("trinitychain.db")
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let chain = db.load_blockchain()
+        .map_err(|e| format!("Failed to load blockchain: {}", e))?;
+
+    let report = verify::verify_supply(&chain);
+    println!("📊 Blocks replayed: {}", report.blocks_checked);
+    println!("🔺 Genesis area:    {}", report.genesis_area);
+    println!("⛏️  Minted:          {}", report.minted);
+    println!("🔥 Burned:          {}", report.burned);
+    println!("📅 Scheduled:       {}", report.scheduled);
+    println!("🪙 Unclaimed:       {}", report.unclaimed);
+    println!("🧮 Expected area:   {}", report.expected_area());
+    println!("💰 UTXO area:       {}", report.utxo_area);
+
+    for d in &report.discrepancies {
+        let block = format!("block #{} ({})", d.height, &hex::encode(d.block_hash)[..16]);
+        match &d.issue {
+            SupplyIssue::RewardMismatch { expected, minted } => {
+                println!("❌ {}: coinbases minted {} but schedule plus fees allow {}", block, minted, expected);
+            }
+            SupplyIssue::CoinbaseAreaMismatch { reward, triangle_area } => {
+                println!("❌ {}: coinbase reward {} but triangle area {}", block, reward, triangle_area);
+            }
+            SupplyIssue::SubdivisionLoss { tx_hash, expected, kept } => {
+                println!(
+                    "❌ {}: subdivision {} kept {} but the parent's subdivision holds {}",
+                    block, &hex::encode(tx_hash)[..16], kept, expected
+                );
+            }
+            SupplyIssue::TotalMismatch { expected, actual } => {
+                println!("❌ {}: UTXO area {} but expected {}", block, actual, expected);
+            }
+            SupplyIssue::ReplayFailed(reason) => {
+                println!("❌ {}: replay failed: {}", block, reason);
+            }
+        }
+    }
+
+    if report.is_ok() {
+        println!("✅ Supply invariants hold");
+    } else {
+        println!("⚠️  {} discrepancies found", report.discrepancies.len());
+    }
+
+    Ok(report.is_ok())
+}
@@ -1,24 +1,63 @@
-//! Backup wallet with password encryption
+//! Backup wallet with password encryption, as SLIP-39 Shamir shares, or
+//! every wallet and the address book as one encrypted bundle
 
-use trinitychain::wallet::{self, EncryptedWallet};
+use trinitychain::backup::{BackupBundle, BackupContents};
+use trinitychain::keystore::{self, KdfParams, Keystore};
+use trinitychain::slip39::{self, GroupSpec, DEFAULT_ITERATION_EXPONENT};
+use trinitychain::wallet::{self, EncryptedWallet, Wallet};
 use std::io::{self, Write};
+use std::path::Path;
+use zeroize::Zeroizing;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+
     println!("🔐 Wallet Backup Tool\n");
 
+    if args.iter().any(|a| a == "--all") {
+        let out = flag(&args, "--out").map(String::as_str).unwrap_or("trinity_backup.json");
+        return bundle_backup(Path::new(out));
+    }
+
+    let shamir = match flag(&args, "--shamir") {
+        Some(spec) => Some(parse_groups(spec, flag(&args, "--group-threshold"))?),
+        None => None,
+    };
+
     // Load current wallet
-    let wallet = wallet::load_default_wallet()?;
+    let wallet_path = wallet::get_active_wallet_path();
+    if !wallet_path.exists() {
+        return Err("No wallet found. Run 'trinity-wallet new' first.".into());
+    }
+    let unlocked = keystore::unlock_interactive(&wallet_path)?;
+    let keystore = &unlocked.keystore;
+    if let Some((group_threshold, groups)) = shamir {
+        return shamir_backup(keystore, group_threshold, &groups, flag(&args, "--out").map(Path::new));
+    }
+    let [key] = keystore.keys.as_slice() else {
+        eprintln!("❌ This wallet holds {} keys and cannot be backed up as a single key.", keystore.keys.len());
+        eprintln!("   Keep its recovery phrase safe and restore with: trinity-wallet-restore --mnemonic");
+        eprintln!("   or split its seed into Shamir shares: trinity-wallet-backup --shamir 2-of-3");
+        std::process::exit(1);
+    };
+    let wallet = Wallet {
+        name: keystore.name.clone(),
+        address: key.address.clone(),
+        secret_key_hex: key.secret_key_hex.clone(),
+        created: key.created.clone(),
+        derivation_path: key.derivation_path.clone(),
+    };
 
     println!("📍 Wallet Address: {}...\n", &wallet.address[..42.min(wallet.address.len())]);
 
     // Get password (twice for confirmation)
     print!("Enter backup password: ");
     io::stdout().flush()?;
-    let password = rpassword::read_password()?;
+    let password = Zeroizing::new(rpassword::read_password()?);
 
     print!("Confirm backup password: ");
     io::stdout().flush()?;
-    let password_confirm = rpassword::read_password()?;
+    let password_confirm = Zeroizing::new(rpassword::read_password()?);
 
     if password != password_confirm {
         eprintln!("\n❌ Passwords do not match!");
@@ -49,3 +88,120 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Value following `name` in `args`
+fn flag<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1))
+}
+
+/// Write every wallet in the wallet directory and the address book into one
+/// encrypted bundle
+fn bundle_backup(out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = BackupContents::collect(&wallet::get_wallet_dir())?;
+    if contents.wallets.is_empty() {
+        return Err("No wallets found. Run 'trinity-wallet new' first.".into());
+    }
+
+    println!("📦 Backing up {} wallet(s):", contents.wallets.len());
+    for record in &contents.wallets {
+        println!("   • {} ({} address(es))", record.wallet_name(), record.addresses.len());
+    }
+    if let Some(book) = &contents.address_book {
+        println!("   • address book ({} entries)", book.list().len());
+    }
+    println!("\nThe bundle gets its own password; wallets inside keep their own encryption.");
+    let password = keystore::prompt_new_password()?;
+
+    println!("\n🔒 Encrypting backup...");
+    BackupBundle::seal(&contents, &password, KdfParams::standard())?.save(out)?;
+
+    println!("✅ Backup written to {}", out.display());
+    println!();
+    println!("⚠️  IMPORTANT:");
+    println!("   • Keep your password safe - it cannot be recovered!");
+    println!("   • Preview a restore with: trinity-wallet-restore {} --dry-run", out.display());
+    println!();
+    Ok(())
+}
+
+/// Parse "2-of-3" or "2-of-3,3-of-5" into a group threshold and groups
+fn parse_groups(spec: &str, group_threshold: Option<&String>) -> Result<(u8, Vec<GroupSpec>), String> {
+    let groups = spec.split(',')
+        .map(|group| {
+            let (threshold, count) = group.split_once("-of-")
+                .ok_or_else(|| format!("Invalid group '{}', expected M-of-N", group))?;
+            let threshold = threshold.trim().parse().map_err(|_| format!("Invalid threshold in '{}'", group))?;
+            let count = count.trim().parse().map_err(|_| format!("Invalid share count in '{}'", group))?;
+            Ok(GroupSpec::new(threshold, count))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let group_threshold = match group_threshold {
+        Some(threshold) => threshold.parse().map_err(|_| "--group-threshold requires a number".to_string())?,
+        None if groups.len() == 1 => 1,
+        None => return Err("Several groups need --group-threshold".to_string()),
+    };
+    Ok((group_threshold, groups))
+}
+
+/// Split the wallet seed into SLIP-39 shares and print them, or write one
+/// file per share into `out_dir`
+fn shamir_backup(
+    keystore: &Keystore,
+    group_threshold: u8,
+    groups: &[GroupSpec],
+    out_dir: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let seed_hex = keystore.seed_hex.as_ref()
+        .ok_or("Only HD wallets have a seed to split into shares")?;
+    let seed = Zeroizing::new(hex::decode(seed_hex)?);
+
+    println!("A passphrase protects the shares: anyone holding enough shares also needs it.");
+    let passphrase = keystore::prompt_password("Enter share passphrase (leave empty for none): ")?;
+    if !passphrase.is_empty() {
+        let confirm = keystore::prompt_password("Confirm share passphrase: ")?;
+        if passphrase != confirm {
+            return Err("Passphrases do not match".into());
+        }
+    }
+
+    let shares = slip39::split_secret(group_threshold, groups, &seed, &passphrase, DEFAULT_ITERATION_EXPONENT)?;
+
+    if let Some(dir) = out_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    println!("\n🧩 Seed split into Shamir shares: any {} of {} group(s) restore it\n", group_threshold, groups.len());
+    for (group, members) in groups.iter().zip(&shares) {
+        let group_number = members[0].group_index + 1;
+        println!("Group {} ({} of {} shares needed)", group_number, group.member_threshold, group.member_count);
+        for share in members {
+            let mnemonic = Zeroizing::new(share.to_mnemonic());
+            match out_dir {
+                Some(dir) => {
+                    let path = dir.join(format!("share_{}_{}.txt", group_number, share.member_index + 1));
+                    write_share(&path, &mnemonic)?;
+                    println!("  {}. {}", share.member_index + 1, path.display());
+                }
+                None => println!("  {}. {}", share.member_index + 1, mnemonic.as_str()),
+            }
+        }
+        println!();
+    }
+
+    println!("⚠️  IMPORTANT:");
+    println!("   • Give each share to a different person or place");
+    println!("   • Restore with: trinity-wallet-restore --shamir [share files...]");
+    println!();
+    Ok(())
+}
+
+fn write_share(path: &Path, mnemonic: &str) -> io::Result<()> {
+    std::fs::write(path, format!("{}\n", mnemonic))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
@@ -1,67 +1,88 @@
 //! Create a second wallet
 
-use trinitychain::crypto::KeyPair;
-use std::fs;
+use trinitychain::hdwallet::HDWallet;
+use trinitychain::keystore::{self, KdfParams, Keystore, UnlockedKeystore};
+use trinitychain::wallet;
+use zeroize::Zeroizing;
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
-    
+
     if args.len() < 2 {
-        println!("Usage: trinity-wallet-new <name>");
+        println!("Usage: trinity-wallet-new <name> [--words 12|15|18|21|24]");
         return;
     }
-    
+
     let wallet_name = &args[1];
-    let wallet_dir = match std::env::var("HOME") {
-        Ok(val) => val + "/.trinitychain",
-        Err(_) => {
-            eprintln!("Error: HOME environment variable not set.");
-            return;
-        }
+    let word_count = match args.iter().position(|a| a == "--words") {
+        Some(i) => match args.get(i + 1).and_then(|w| w.parse::<usize>().ok()) {
+            Some(count) => count,
+            None => {
+                eprintln!("Error: --words requires a number");
+                return;
+            }
+        },
+        None => 12,
     };
-    if let Err(e) = fs::create_dir_all(&wallet_dir) {
-        eprintln!("Error: Failed to create wallet directory '{}': {}", wallet_dir, e);
+
+    if let Err(e) = wallet::ensure_wallet_dir() {
+        eprintln!("Error: {}", e);
         return;
     }
-    
-    let keypair = match KeyPair::generate() {
-        Ok(kp) => kp,
+
+    let wallet_file = wallet::get_named_wallet_path(wallet_name);
+
+    if wallet_file.exists() {
+        println!("⚠️  Wallet '{}' already exists", wallet_name);
+        return;
+    }
+
+    let mnemonic = match HDWallet::generate_mnemonic(word_count) {
+        Ok(m) => Zeroizing::new(m),
         Err(e) => {
-            eprintln!("Error: Failed to generate keypair: {}", e);
+            eprintln!("Error: Failed to generate mnemonic: {}", e);
             return;
         }
     };
-    let address = keypair.address();
-    
-    let wallet_file = format!("{}/wallet_{}.json", wallet_dir, wallet_name);
-    
-    if std::path::Path::new(&wallet_file).exists() {
-        println!("⚠️  Wallet '{}' already exists", wallet_name);
-        return;
-    }
-    
-    let secret_hex = hex::encode(keypair.secret_key.secret_bytes());
-    let wallet_data = serde_json::json!({
-        "name": wallet_name,
-        "address": address,
-        "secret_key": secret_hex,
-        "created": chrono::Utc::now().to_rfc3339(),
-    });
-    
-    let wallet_json = match serde_json::to_string_pretty(&wallet_data) {
-        Ok(json) => json,
+    let seed = match HDWallet::seed_from_mnemonic(&mnemonic, None) {
+        Ok(seed) => Zeroizing::new(seed),
         Err(e) => {
-            eprintln!("Error: Failed to serialize wallet data: {}", e);
+            eprintln!("Error: Failed to derive seed: {}", e);
             return;
         }
     };
-    
-    if let Err(e) = fs::write(&wallet_file, wallet_json) {
-        eprintln!("Error: Failed to write wallet file '{}': {}", wallet_file, e);
-        return;
-    }
-    
+    let keystore = match Keystore::from_seed(Some(wallet_name.clone()), &seed) {
+        Ok(keystore) => keystore,
+        Err(e) => {
+            eprintln!("Error: Failed to derive keys: {}", e);
+            return;
+        }
+    };
+
+    let password = match keystore::prompt_new_password() {
+        Ok(password) => password,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+    let unlocked = match UnlockedKeystore::create(&wallet_file, keystore, &password, KdfParams::standard()) {
+        Ok(unlocked) => unlocked,
+        Err(e) => {
+            eprintln!("Error: Failed to write wallet file '{}': {}", wallet_file.display(), e);
+            return;
+        }
+    };
+
+    let first_key = &unlocked.keystore.keys[0];
     println!("🔑 New wallet '{}' created!", wallet_name);
-    println!("   Address: {}", address);
-    println!("   Location: {}", wallet_file);
+    println!("   Address: {}", trinitychain::address::display_address(&first_key.address));
+    println!("   Path: {}", first_key.derivation_path.as_deref().unwrap_or_default());
+    println!("   Location: {}", wallet_file.display());
+    println!();
+    println!("📝 Recovery phrase (write it down, it will not be shown again):");
+    println!();
+    println!("   {}", *mnemonic);
+    println!();
+    println!("   Restore every address with: trinity-wallet-restore --mnemonic");
 }
@@ -1,14 +1,41 @@
-//! Restore wallet from encrypted backup
+//! Restore wallet from encrypted backup, backup bundle, recovery phrase or
+//! Shamir shares
 
+use trinitychain::backup::{BackupBundle, RestoreAction, RestoreSelection};
+use trinitychain::hdwallet::{self, HDWallet, DEFAULT_GAP_LIMIT, INTERNAL_CHAIN};
+use trinitychain::keystore::{self, KdfParams, Keystore, UnlockedKeystore};
+use trinitychain::persistence::Database;
+use trinitychain::slip39::{Share, ShareSet};
 use trinitychain::wallet::{self, EncryptedWallet};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = std::env::args().collect();
 
     println!("🔓 Wallet Restore Tool\n");
 
+    if args.len() > 1 && (args[1] == "--mnemonic" || args[1] == "--shamir") {
+        let gap_limit = match args.iter().position(|a| a == "--gap") {
+            Some(i) => args.get(i + 1).and_then(|g| g.parse().ok()).ok_or("--gap requires a number")?,
+            None => DEFAULT_GAP_LIMIT,
+        };
+        if args[1] == "--shamir" {
+            let mut share_files = Vec::new();
+            let mut rest = args[2..].iter();
+            while let Some(arg) = rest.next() {
+                if arg == "--gap" {
+                    rest.next();
+                } else {
+                    share_files.push(arg);
+                }
+            }
+            return restore_from_shares(&share_files, gap_limit);
+        }
+        return restore_from_mnemonic(gap_limit);
+    }
+
     // Get backup file path
     let backup_path = if args.len() > 1 {
         PathBuf::from(&args[1])
@@ -19,11 +46,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     if !backup_path.exists() {
         eprintln!("❌ Backup file not found: {}", backup_path.display());
         eprintln!("\nUsage: trinity-wallet-restore [backup_file_path]");
+        eprintln!("       trinity-wallet-restore <bundle> [--only NAME,...] [--no-addressbook] [--overwrite] [--dry-run]");
+        eprintln!("       trinity-wallet-restore --mnemonic [--gap N]");
+        eprintln!("       trinity-wallet-restore --shamir [share files...] [--gap N]");
         std::process::exit(1);
     }
 
     println!("📁 Backup file: {}\n", backup_path.display());
 
+    if BackupBundle::is_bundle_file(&backup_path) {
+        return restore_bundle(&backup_path, &args[2..]);
+    }
+
     // Load encrypted backup
     let encrypted = EncryptedWallet::load(&backup_path)?;
 
@@ -32,7 +66,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Get password
     print!("Enter backup password: ");
     io::stdout().flush()?;
-    let password = rpassword::read_password()?;
+    let password = Zeroizing::new(rpassword::read_password()?);
 
     // Decrypt wallet
     println!("\n🔓 Decrypting wallet...");
@@ -58,14 +92,171 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    // Save wallet
+    // Save wallet, encrypted under the backup password
     wallet::ensure_wallet_dir()?;
-    wallet.save(&wallet_path)?;
+    UnlockedKeystore::create(&wallet_path, Keystore::from_wallet(&wallet), &password, KdfParams::standard())?;
+    keystore::end_session(&wallet_path)?;
 
     println!("✅ Wallet restored successfully!");
     println!("📁 Wallet location: {}", wallet_path.display());
     println!("📍 Address: {}...", &wallet.address[..42.min(wallet.address.len())]);
+    println!("🔐 The wallet is encrypted with your backup password.");
+    println!("   Change it with: trinity-wallet change-password");
+    println!();
+
+    Ok(())
+}
+
+/// Restore wallets and the address book from a backup bundle. With
+/// `--dry-run` only the planned changes are shown.
+fn restore_bundle(path: &Path, args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let selection = RestoreSelection {
+        wallets: args.iter().position(|a| a == "--only")
+            .map(|i| args.get(i + 1).ok_or("--only requires wallet names"))
+            .transpose()?
+            .map(|names| names.split(',').map(|name| name.trim().to_string()).collect()),
+        address_book: !args.iter().any(|a| a == "--no-addressbook"),
+        overwrite: args.iter().any(|a| a == "--overwrite"),
+    };
+    let dry_run = args.iter().any(|a| a == "--dry-run");
+
+    let bundle = BackupBundle::load(path)?;
+    println!("📦 Backup created {}", bundle.created);
+    let password = keystore::prompt_password("Enter backup password: ")?;
+    let contents = bundle.open(&password)
+        .map_err(|e| format!("Failed to open backup - incorrect password or damaged file: {}", e))?;
+    println!("🔍 Integrity verified for {} wallet(s)\n", contents.wallets.len());
+
+    let wallet_dir = wallet::get_wallet_dir();
+    let actions = if dry_run {
+        println!("Dry run - nothing will be written:");
+        contents.plan_restore(&wallet_dir, &selection)?
+    } else {
+        wallet::ensure_wallet_dir()?;
+        contents.restore(&wallet_dir, &selection)?
+    };
+
+    for action in &actions {
+        match action {
+            RestoreAction::Create { file_name } => println!("   ➕ create     {}", file_name),
+            RestoreAction::Overwrite { file_name } => println!("   ✏️  overwrite  {}", file_name),
+            RestoreAction::Unchanged { file_name } => println!("   ✅ unchanged  {}", file_name),
+            RestoreAction::Skip { file_name } => println!("   ⚠️  skip       {} (differs, use --overwrite)", file_name),
+            RestoreAction::MergeAddressBook { added, conflicting } => {
+                println!("   📒 address book: {} entries added", added.len());
+                for label in conflicting {
+                    println!("      ⚠️  kept existing '{}', the backup points it elsewhere", label);
+                }
+            }
+        }
+    }
+
+    if dry_run {
+        println!("\nRun again without --dry-run to apply these changes.");
+    } else {
+        println!("\n✅ Restore complete: {}", wallet_dir.display());
+        println!("   Restored wallets keep the passwords they had when backed up.");
+    }
+    Ok(())
+}
+
+/// Re-derive the wallet from its recovery phrase
+fn restore_from_mnemonic(gap_limit: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let phrase = keystore::prompt_password("Enter recovery phrase: ")?;
+    let passphrase = keystore::prompt_password("Enter passphrase (leave empty if none): ")?;
+    let passphrase = (!passphrase.is_empty()).then_some(passphrase.as_str());
+
+    let seed = Zeroizing::new(HDWallet::seed_from_mnemonic(phrase.trim(), passphrase)?);
+    restore_from_seed(&seed, gap_limit)
+}
+
+/// Combine SLIP-39 shares, read from files or entered one at a time in any
+/// order, into the wallet seed and restore from it
+fn restore_from_shares(share_files: &[&String], gap_limit: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let mut shares = ShareSet::new();
+    for path in share_files {
+        let mnemonic = Zeroizing::new(std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?);
+        shares.add(Share::parse(&mnemonic)?)?;
+    }
+
+    while !shares.is_complete() {
+        report_share_progress(&shares);
+        let mnemonic = keystore::prompt_password(&format!("Enter share {}: ", shares.len() + 1))?;
+        match Share::parse(&mnemonic).and_then(|share| shares.add(share)) {
+            Ok(true) => println!("✅ Share accepted"),
+            Ok(false) => println!("⚠️  That share was already entered"),
+            Err(e) => println!("❌ {}", e),
+        }
+    }
+
+    let passphrase = keystore::prompt_password("Enter share passphrase (leave empty if none): ")?;
+    println!("\n🧩 Combining {} share(s)...", shares.len());
+    let seed = shares.recover(&passphrase)?;
+    restore_from_seed(&seed, gap_limit)
+}
+
+fn report_share_progress(shares: &ShareSet) {
+    let Some(group_threshold) = shares.group_threshold() else {
+        return;
+    };
+    println!("\n🧩 {} of {} group(s) complete", shares.groups_complete(), group_threshold);
+    for (group_index, present, threshold) in shares.group_progress() {
+        println!("   Group {}: {} of {} share(s)", group_index + 1, present, threshold);
+    }
+}
+
+/// Recover every address of `seed` used on chain, scanning until `gap_limit`
+/// consecutive addresses are unused, and save the wallet
+fn restore_from_seed(seed: &[u8], gap_limit: u32) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n🔎 Scanning chain for used addresses (gap limit {})...", gap_limit);
+    let db = Database::open("trinitychain.db")
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let chain = db.load_blockchain()
+        .map_err(|e| format!("Failed to load blockchain: {}", e))?;
+    let on_chain = hdwallet::addresses_on_chain(&chain);
+
+    let discovery = HDWallet::discover(seed, 0, gap_limit, |address| on_chain.contains(address))?;
+    println!("📍 Found {} used address(es)\n", discovery.used.len());
+
+    let wallet_path = wallet::get_default_wallet_path();
+    if wallet_path.exists() {
+        print!("⚠️  WARNING: A wallet already exists. Overwrite? (yes/no): ");
+        io::stdout().flush()?;
+        let mut response = String::new();
+        io::stdin().read_line(&mut response)?;
+
+        if response.trim().to_lowercase() != "yes" {
+            println!("Restore cancelled.");
+            std::process::exit(0);
+        }
+    }
+
+    // The first receive address is always part of the wallet
+    let mut restored = Keystore::from_seed(None, seed)?;
+    for key in &discovery.used {
+        if !restored.contains(&key.address()) {
+            restored.add_derived_key(&key.keypair, key.path(), key.change == INTERNAL_CHAIN)?;
+        }
+    }
+    restored.next_receive_index = restored.next_receive_index.max(discovery.next_receive_index);
+    restored.next_change_index = discovery.next_change_index;
+    restored.refresh_used(&on_chain);
+
+    println!();
+    let password = keystore::prompt_new_password()?;
+    wallet::ensure_wallet_dir()?;
+    let unlocked = UnlockedKeystore::create(&wallet_path, restored, &password, KdfParams::standard())?;
+    keystore::end_session(&wallet_path)?;
+
+    for key in &unlocked.keystore.keys {
+        let status = if key.used { "used" } else { "unused" };
+        println!("✅ {} {} ({})", key.derivation_path.as_deref().unwrap_or_default(), key.address, status);
+    }
+    println!("\n💾 Wallet saved to {}", wallet_path.display());
     println!();
+    println!("📥 Next receive index: {}", discovery.next_receive_index);
+    println!("🔁 Next change index: {}", discovery.next_change_index);
 
     Ok(())
 }
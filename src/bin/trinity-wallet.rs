@@ -1,7 +1,13 @@
 //! Wallet CLI for TrinityChain - Beautiful edition!
 
+use trinitychain::address::{self, display_address};
+use trinitychain::crypto::{self, KeyPair};
+use trinitychain::hdwallet::HDWallet;
+use trinitychain::keystore::{self, KdfParams, UnlockedKeystore, DEFAULT_SESSION_TIMEOUT};
+use std::time::Duration;
 use trinitychain::wallet::{self};
 use colored::*;
+use zeroize::Zeroizing;
 
 const LOGO: &str = r#"
 ╔═══════════════════════════════════════════════════════════════╗
@@ -30,6 +36,27 @@ fn main() {
         "new" => create_wallet(),
         "address" => show_address(),
         "list" => list_wallets(),
+        "addresses" => list_addresses(),
+        "receive" => new_receive_address(args.get(2).cloned()),
+        "label" => match (args.get(2), args.get(3)) {
+            (Some(address), label) => set_label(address, label.cloned()),
+            _ => print_usage(),
+        },
+        "import" => match args.get(2) {
+            Some(secret_hex) => import_key(secret_hex, args.get(3).cloned()),
+            None => print_usage(),
+        },
+        "xpub" => show_xpub(),
+        "sign-message" => sign_message(&args[2..]),
+        "verify-message" => match (args.get(2), args.get(3)) {
+            (Some(address), Some(signature)) if args.len() > 4 => {
+                verify_message(address, signature, &args[4..].join(" "))
+            }
+            _ => print_usage(),
+        },
+        "change-password" => change_password(&args[2..]),
+        "unlock" => unlock(&args[2..]),
+        "lock" => lock(),
         "help" => print_usage(),
         _ => {
             println!("{}", format!("❌ Unknown command: {}", args[1]).red().bold());
@@ -50,23 +77,30 @@ fn create_wallet() {
     println!("{}", "└─────────────────────────────────────────┘".bright_green());
     println!();
 
-    match wallet::create_default_wallet() {
-        Ok(wallet) => {
+    let created = keystore::prompt_new_password()
+        .and_then(|password| wallet::create_default_wallet(&password));
+
+    match created {
+        Ok(unlocked) => {
+            let wallet = &unlocked.keystore.keys[0];
             println!("{}", "╔══════════════════════════════════════════════════════════╗".green());
             println!("{}", "║            ✨ Wallet Created Successfully! ✨            ║".green().bold());
             println!("{}", "╠══════════════════════════════════════════════════════════╣".green());
-            let addr_len = wallet.address.len();
-            let addr_part1 = if addr_len >= 42 { &wallet.address[..42] } else { &wallet.address };
-            let addr_part2 = if addr_len > 42 { &wallet.address[42..] } else { "" };
+            let encoded = display_address(&wallet.address);
+            let addr_len = encoded.len();
+            let addr_part1 = if addr_len >= 42 { &encoded[..42] } else { &encoded };
+            let addr_part2 = if addr_len > 42 { &encoded[42..] } else { "" };
             println!("{}", format!("║  📍 Address: {:<42} ║", addr_part1).green());
             println!("{}", format!("║             {:<42} ║", addr_part2).green());
             println!("{}", format!("║  📁 Location: {:<39} ║", wallet::get_default_wallet_path().display()).green());
             println!("{}", format!("║  📅 Created: {:<40} ║", wallet.created).green());
+            println!("{}", format!("║  🔐 Encryption: {:<39} ║", "argon2id + aes-256-gcm").green());
             println!("{}", "╚══════════════════════════════════════════════════════════╝".green());
             println!();
             println!("{}", "⚠️  IMPORTANT SECURITY NOTICE:".yellow().bold());
             println!("{}", "   • Backup your wallet file immediately!".yellow());
             println!("{}", "   • Never share your secret key".yellow());
+            println!("{}", "   • Remember your password - it cannot be recovered!".yellow());
             println!("{}", "   • Store backups in a secure location".yellow());
             println!();
         },
@@ -89,17 +123,24 @@ fn show_address() {
     println!("{}", "└─────────────────────────────────────────┘".bright_cyan());
     println!();
 
-    match wallet::load_default_wallet() {
-        Ok(wallet) => {
+    match keystore::unlock_interactive(&wallet::get_active_wallet_path()) {
+        Ok(unlocked) => {
+            let keystore = &unlocked.keystore;
+            let Some(key) = keystore.unused_receive_address().or(keystore.keys.first()) else {
+                println!("{}", "❌ Wallet holds no keys".red());
+                return;
+            };
             println!("{}", "╔══════════════════════════════════════════════════════════╗".cyan());
             println!("{}", "║                   Your Wallet Details                    ║".cyan().bold());
             println!("{}", "╠══════════════════════════════════════════════════════════╣".cyan());
-            let addr_len = wallet.address.len();
-            let addr_part1 = if addr_len >= 42 { &wallet.address[..42] } else { &wallet.address };
-            let addr_part2 = if addr_len > 42 { &wallet.address[42..] } else { "" };
+            let encoded = display_address(&key.address);
+            let addr_len = encoded.len();
+            let addr_part1 = if addr_len >= 42 { &encoded[..42] } else { &encoded };
+            let addr_part2 = if addr_len > 42 { &encoded[42..] } else { "" };
             println!("{}", format!("║  📍 Address: {:<42} ║", addr_part1).cyan());
             println!("{}", format!("║             {:<42} ║", addr_part2).cyan());
-            println!("{}", format!("║  📅 Created: {:<40} ║", wallet.created).cyan());
+            println!("{}", format!("║  🔑 Keys in wallet: {:<36} ║", keystore.keys.len()).cyan());
+            println!("{}", format!("║  📅 Created: {:<40} ║", keystore.created).cyan());
             println!("{}", "╚══════════════════════════════════════════════════════════╝".cyan());
             println!();
             println!("{}", "💡 Tip: Share this address to receive triangles!".bright_blue());
@@ -118,6 +159,280 @@ fn show_address() {
     }
 }
 
+/// Unlock the active wallet, printing an error if that fails
+fn load_keystore() -> Option<UnlockedKeystore> {
+    match keystore::unlock_interactive(&wallet::get_active_wallet_path()) {
+        Ok(unlocked) => Some(unlocked),
+        Err(e) => {
+            println!("{}", format!("❌ Error: {}", e).red());
+            println!("{}", "💡 Run 'trinity-wallet new' to create a wallet".yellow());
+            None
+        }
+    }
+}
+
+fn save_keystore(unlocked: &UnlockedKeystore) -> bool {
+    match unlocked.save() {
+        Ok(()) => true,
+        Err(e) => {
+            println!("{}", format!("❌ Error: {}", e).red());
+            false
+        }
+    }
+}
+
+fn list_addresses() {
+    print_banner();
+
+    let Some(unlocked) = load_keystore() else { return };
+    let keystore = &unlocked.keystore;
+
+    println!("{}", format!("🔑 {} key(s){}", keystore.keys.len(), if keystore.is_hd() { " (HD)" } else { "" }).bright_cyan().bold());
+    println!();
+    for (i, key) in keystore.keys.iter().enumerate() {
+        let status = if key.used { "used".yellow() } else { "unused".green() };
+        let kind = match &key.derivation_path {
+            Some(path) => path.clone(),
+            None => "imported".to_string(),
+        };
+        println!("  {}. {} [{}] {}", i + 1, display_address(&key.address), status, kind.bright_black());
+        if let Some(label) = &key.label {
+            println!("     🏷️  {}", label);
+        }
+    }
+    println!();
+}
+
+fn new_receive_address(label: Option<String>) {
+    print_banner();
+
+    let Some(mut unlocked) = load_keystore() else { return };
+    let address = match unlocked.keystore.new_receive_address(label) {
+        Ok(key) => key.address.clone(),
+        Err(e) => {
+            println!("{}", format!("❌ Error: {}", e).red());
+            return;
+        }
+    };
+
+    if save_keystore(&unlocked) {
+        println!("{}", "📥 New receive address:".green().bold());
+        println!("   {}", display_address(&address));
+        println!();
+    }
+}
+
+fn set_label(address: &str, label: Option<String>) {
+    let address = match address::parse_address(address) {
+        Ok(address) => address,
+        Err(e) => {
+            println!("{}", format!("❌ Error: {}", e).red());
+            return;
+        }
+    };
+    let Some(mut unlocked) = load_keystore() else { return };
+    if let Err(e) = unlocked.keystore.set_label(&address, label) {
+        println!("{}", format!("❌ Error: {}", e).red());
+        return;
+    }
+    if save_keystore(&unlocked) {
+        println!("{}", "✅ Label updated".green());
+    }
+}
+
+fn import_key(secret_hex: &str, label: Option<String>) {
+    let keypair = match hex::decode(secret_hex).map_err(|e| e.to_string())
+        .and_then(|bytes| KeyPair::from_secret_bytes(&bytes).map_err(|e| e.to_string()))
+    {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            println!("{}", format!("❌ Invalid secret key: {}", e).red());
+            return;
+        }
+    };
+
+    let Some(mut unlocked) = load_keystore() else { return };
+    let address = match unlocked.keystore.import_key(&keypair, label) {
+        Ok(key) => key.address.clone(),
+        Err(e) => {
+            println!("{}", format!("❌ Error: {}", e).red());
+            return;
+        }
+    };
+    if save_keystore(&unlocked) {
+        println!("{}", format!("✅ Imported {}", address).green());
+    }
+}
+
+/// Print the account extended public key for setting up a watch-only wallet
+fn show_xpub() {
+    let Some(unlocked) = load_keystore() else { return };
+    let Some(seed_hex) = &unlocked.keystore.seed_hex else {
+        println!("{}", "❌ Only HD wallets have an extended public key".red());
+        return;
+    };
+    let xpub = hex::decode(seed_hex)
+        .map_err(|e| e.to_string())
+        .and_then(|seed| HDWallet::account_xpub(&Zeroizing::new(seed), 0));
+    match xpub {
+        Ok(xpub) => {
+            println!("{}", "👀 Account extended public key (watch-only import):".bright_cyan().bold());
+            println!("{}", xpub);
+        }
+        Err(e) => println!("{}", format!("❌ Error: {}", e).red()),
+    }
+}
+
+/// Sign a message with one of the wallet's keys to prove control of its address
+fn sign_message(args: &[String]) {
+    let address = args.iter().position(|a| a == "--address").and_then(|i| args.get(i + 1));
+    let message = args.iter().enumerate()
+        .filter(|(i, a)| *a != "--address" && (*i == 0 || args[i - 1] != "--address"))
+        .map(|(_, a)| a.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if message.is_empty() {
+        print_usage();
+        return;
+    }
+
+    let Some(unlocked) = load_keystore() else { return };
+    let keystore = &unlocked.keystore;
+    let address = match address.map(|a| address::parse_address(a)).transpose() {
+        Ok(address) => address,
+        Err(e) => {
+            println!("{}", format!("❌ Error: {}", e).red());
+            return;
+        }
+    };
+    let Some(address) = address.as_ref().or(keystore.primary_address()) else {
+        println!("{}", "❌ The wallet has no keys".red());
+        return;
+    };
+    let signature = keystore.keypair_for(address)
+        .and_then(|keypair| keypair.sign_message(message.as_bytes()));
+
+    match signature {
+        Ok(signature) => {
+            println!("{}", "✍️  Signed message".bright_cyan().bold());
+            println!("   Address:   {}", display_address(address));
+            println!("   Message:   {}", message);
+            println!("   Signature: {}", hex::encode(signature));
+            println!();
+            println!("{}", "💡 Anyone can check it with: trinity-wallet verify-message <address> <signature> <message>".yellow());
+        }
+        Err(e) => println!("{}", format!("❌ Error: {}", e).red()),
+    }
+}
+
+/// Check a message signature against an address; needs no wallet
+fn verify_message(address: &str, signature: &str, message: &str) {
+    let address = &match address::parse_address(address) {
+        Ok(address) => address,
+        Err(e) => {
+            println!("{}", format!("❌ {}", e).red());
+            std::process::exit(1);
+        }
+    };
+    let result = hex::decode(signature)
+        .map_err(|e| format!("Invalid signature encoding: {}", e))
+        .and_then(|bytes| crypto::recover_message_signer(message.as_bytes(), &bytes).map_err(|e| e.to_string()));
+
+    match result {
+        Ok(signer) if &signer == address => {
+            println!("{}", format!("✅ Valid signature from {}", display_address(address)).green().bold());
+        }
+        Ok(signer) => {
+            println!("{}", "❌ Signature does not match the address".red().bold());
+            println!("   Signed by: {}", display_address(&signer));
+            std::process::exit(1);
+        }
+        Err(e) => {
+            println!("{}", format!("❌ Error: {}", e).red());
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Value following `flag` in `args`, parsed
+fn flag_value<T: std::str::FromStr>(args: &[String], flag: &str) -> Result<Option<T>, String> {
+    match args.iter().position(|a| a == flag) {
+        Some(i) => args.get(i + 1)
+            .and_then(|v| v.parse().ok())
+            .map(Some)
+            .ok_or_else(|| format!("{} requires a number", flag)),
+        None => Ok(None),
+    }
+}
+
+fn change_password(args: &[String]) {
+    print_banner();
+
+    let path = wallet::get_active_wallet_path();
+    let result = (|| -> Result<KdfParams, Box<dyn std::error::Error>> {
+        // Always ask for the current password, even during an unlock session
+        let mut unlocked = if keystore::is_encrypted(&path)? {
+            let password = keystore::prompt_password("Current password: ")?;
+            UnlockedKeystore::unlock(&path, &password)?
+        } else {
+            keystore::unlock_interactive(&path)?
+        };
+
+        let current = unlocked.kdf().clone();
+        let kdf = KdfParams::new(
+            flag_value(args, "--memory-kib")?.unwrap_or(current.memory_kib),
+            flag_value(args, "--iterations")?.unwrap_or(current.iterations),
+            flag_value(args, "--parallelism")?.unwrap_or(current.parallelism),
+        )?;
+
+        let new_password = keystore::prompt_new_password()?;
+        unlocked.change_password(&new_password, kdf.clone())?;
+        Ok(kdf)
+    })();
+
+    match result {
+        Ok(kdf) => {
+            println!("{}", "✅ Password changed".green().bold());
+            println!("{}", format!("   Argon2id: {} KiB, {} iterations, {} lane(s)", kdf.memory_kib, kdf.iterations, kdf.parallelism).green());
+            println!();
+        }
+        Err(e) => println!("{}", format!("❌ Error: {}", e).red()),
+    }
+}
+
+/// Start an unlock session. The token goes to stdout so the command can be
+/// used as `eval $(trinity-wallet unlock)`; everything else goes to stderr.
+fn unlock(args: &[String]) {
+    let path = wallet::get_active_wallet_path();
+    let result = (|| -> Result<(Zeroizing<String>, Duration), Box<dyn std::error::Error>> {
+        let timeout = flag_value::<u64>(args, "--timeout")?
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_SESSION_TIMEOUT);
+        let unlocked = if keystore::is_encrypted(&path)? {
+            let password = keystore::prompt_password(&format!("Password for {}: ", path.display()))?;
+            UnlockedKeystore::unlock(&path, &password)?
+        } else {
+            keystore::unlock_interactive(&path)?
+        };
+        Ok((unlocked.start_session(timeout)?, timeout))
+    })();
+
+    match result {
+        Ok((token, timeout)) => {
+            eprintln!("{}", format!("🔓 Wallet unlocked for {} seconds", timeout.as_secs()).green());
+            println!("export {}={}", keystore::SESSION_ENV, *token);
+        }
+        Err(e) => eprintln!("{}", format!("❌ Error: {}", e).red()),
+    }
+}
+
+fn lock() {
+    match keystore::end_session(&wallet::get_active_wallet_path()) {
+        Ok(()) => println!("{}", "🔒 Wallet locked".green()),
+        Err(e) => println!("{}", format!("❌ Error: {}", e).red()),
+    }
+}
+
 fn list_wallets() {
     print_banner();
 
@@ -164,6 +479,17 @@ fn print_usage() {
     println!("{}", "║    🔑 new       Create a new wallet                     ║".bright_yellow());
     println!("{}", "║    📍 address   Show your wallet address                ║".bright_yellow());
     println!("{}", "║    📋 list      List all available wallets              ║".bright_yellow());
+    println!("{}", "║    🔑 addresses List every address in the wallet        ║".bright_yellow());
+    println!("{}", "║    📥 receive   Create a fresh receive address [label]  ║".bright_yellow());
+    println!("{}", "║    🏷️  label     Label an address: <address> [label]     ║".bright_yellow());
+    println!("{}", "║    📦 import    Import a key: <secret_hex> [label]      ║".bright_yellow());
+    println!("{}", "║    👀 xpub      Show the account xpub for watch-only    ║".bright_yellow());
+    println!("{}", "║    ✍️  sign-message   <message> [--address A]            ║".bright_yellow());
+    println!("{}", "║    🔎 verify-message <address> <signature> <message>    ║".bright_yellow());
+    println!("{}", "║    🔐 change-password  [--memory-kib N] [--iterations N] ║".bright_yellow());
+    println!("{}", "║                        [--parallelism N]                 ║".bright_yellow());
+    println!("{}", "║    🔓 unlock    Keep the wallet unlocked [--timeout S]  ║".bright_yellow());
+    println!("{}", "║    🔒 lock      End the unlock session                  ║".bright_yellow());
     println!("{}", "║    ❓ help      Show this help message                  ║".bright_yellow());
     println!("{}", "║                                                          ║".bright_yellow());
     println!("{}", "╠══════════════════════════════════════════════════════════╣".bright_yellow());
@@ -172,6 +498,9 @@ fn print_usage() {
     println!("{}", "║    $ trinity-wallet new                                  ║".white());
     println!("{}", "║    $ trinity-wallet address                              ║".white());
     println!("{}", "║    $ trinity-wallet list                                 ║".white());
+    println!("{}", "║    $ trinity-wallet receive \"invoice 42\"                ║".white());
+    println!("{}", "║    $ eval $(trinity-wallet unlock --timeout 600)         ║".white());
+    println!("{}", "║    $ trinity-wallet sign-message \"I own this address\"    ║".white());
     println!("{}", "║                                                          ║".bright_yellow());
     println!("{}", "╚══════════════════════════════════════════════════════════╝".bright_yellow());
     println!();
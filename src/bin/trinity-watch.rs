@@ -0,0 +1,189 @@
+//! Watch-only wallet tool: track balances and build unsigned transactions
+
+use trinitychain::address;
+use trinitychain::hdwallet::{self, DEFAULT_GAP_LIMIT};
+use trinitychain::persistence::Database;
+use trinitychain::wallet;
+use trinitychain::watchonly::WatchOnlyWallet;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 2 {
+        print_usage();
+        std::process::exit(1);
+    }
+
+    let command = &args[1];
+
+    match command.as_str() {
+        "import-xpub" => import_xpub(&args[2..])?,
+        "import-addresses" => import_addresses(&args[2..])?,
+        "add" => add_address(&args[2..])?,
+        "sync" => sync()?,
+        "list" | "ls" => list_addresses()?,
+        "unsigned-send" => unsigned_send(&args[2..])?,
+        _ => {
+            eprintln!("Unknown command: {}", command);
+            print_usage();
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_usage() {
+    println!("👀 Watch-Only Wallets\n");
+    println!("Usage: trinity-watch <command> [arguments]\n");
+    println!("Commands:");
+    println!("  import-xpub <name> <xpub> [--gap N]        Watch an account extended public key");
+    println!("  import-addresses <name> <address>...       Watch a list of addresses");
+    println!("  add <address> [label]                      Watch another address");
+    println!("  sync                                       Discover used addresses on chain");
+    println!("  list                                       List watched addresses");
+    println!("  unsigned-send <to> <triangle_hash> [--fee N] [--out FILE]");
+    println!("                                             Build an unsigned transfer");
+    println!("\nThe wallet is chosen with WALLET_NAME, like the other tools.");
+    println!("trinity-balance and trinity-history work on watch-only wallets too.");
+    println!("\nExamples:");
+    println!("  trinity-watch import-xpub audit xpub6C...");
+    println!("  WALLET_NAME=audit trinity-balance");
+    println!("  WALLET_NAME=audit trinity-watch unsigned-send abc123... def456... --out tx.json");
+    println!("  trinity-send --sign tx.json       (on the signing machine)");
+}
+
+/// Value following `flag` in `args`
+fn flag<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1))
+}
+
+fn load_on_chain_addresses() -> Result<std::collections::HashSet<String>, Box<dyn std::error::Error>> {
+    let db = Database::open("trinitychain.db")
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let chain = db.load_blockchain()
+        .map_err(|e| format!("Failed to load blockchain: {}", e))?;
+    Ok(hdwallet::addresses_on_chain(&chain))
+}
+
+fn create(name: &str, mut watch_only: WatchOnlyWallet) -> Result<(), Box<dyn std::error::Error>> {
+    wallet::ensure_wallet_dir()?;
+    let path = wallet::get_named_wallet_path(name);
+    if path.exists() {
+        return Err(format!("Wallet '{}' already exists", name).into());
+    }
+
+    watch_only.sync(&load_on_chain_addresses()?)?;
+    watch_only.save(&path)?;
+
+    let used = watch_only.addresses.iter().filter(|a| a.used).count();
+    println!("✅ Watch-only wallet '{}' created", name);
+    println!("📍 Watching {} address(es), {} used on chain", watch_only.addresses.len(), used);
+    println!("📁 Location: {}", path.display());
+    println!("\n💡 Check its balance with: WALLET_NAME={} trinity-balance", name);
+    Ok(())
+}
+
+fn import_xpub(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 2 {
+        eprintln!("Usage: trinity-watch import-xpub <name> <xpub> [--gap N]");
+        std::process::exit(1);
+    }
+
+    let gap_limit = match flag(args, "--gap") {
+        Some(gap) => gap.parse().map_err(|_| "--gap requires a number")?,
+        None => DEFAULT_GAP_LIMIT,
+    };
+    let watch_only = WatchOnlyWallet::from_xpub(Some(args[0].clone()), &args[1], gap_limit)?;
+    create(&args[0], watch_only)
+}
+
+fn import_addresses(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 2 {
+        eprintln!("Usage: trinity-watch import-addresses <name> <address>...");
+        std::process::exit(1);
+    }
+
+    let watch_only = WatchOnlyWallet::from_addresses(Some(args[0].clone()), args[1..].iter().cloned())?;
+    create(&args[0], watch_only)
+}
+
+fn add_address(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.is_empty() {
+        eprintln!("Usage: trinity-watch add <address> [label]");
+        std::process::exit(1);
+    }
+
+    let path = wallet::get_active_wallet_path();
+    let mut watch_only = WatchOnlyWallet::load(&path)?;
+    let label = (args.len() > 1).then(|| args[1..].join(" "));
+    let address = watch_only.add_address(args[0].clone(), label)?.address.clone();
+    watch_only.sync(&load_on_chain_addresses()?)?;
+    watch_only.save(&path)?;
+
+    println!("✅ Now watching {}", address::display_address(&address));
+    Ok(())
+}
+
+fn sync() -> Result<(), Box<dyn std::error::Error>> {
+    let path = wallet::get_active_wallet_path();
+    let mut watch_only = WatchOnlyWallet::load(&path)?;
+    let added = watch_only.sync(&load_on_chain_addresses()?)?;
+    watch_only.save(&path)?;
+
+    let used = watch_only.addresses.iter().filter(|a| a.used).count();
+    println!("🔄 Synced: {} new address(es), {} of {} used", added, used, watch_only.addresses.len());
+    Ok(())
+}
+
+fn list_addresses() -> Result<(), Box<dyn std::error::Error>> {
+    let watch_only = WatchOnlyWallet::load(&wallet::get_active_wallet_path())?;
+
+    println!("👀 Watch-only wallet{}\n", watch_only.name.as_ref().map(|n| format!(" '{}'", n)).unwrap_or_default());
+    if let Some(xpub) = &watch_only.xpub {
+        println!("🔑 xpub: {}...{}\n", &xpub[..12.min(xpub.len())], &xpub[xpub.len().saturating_sub(8)..]);
+    }
+    for (i, watched) in watch_only.addresses.iter().enumerate() {
+        let status = if watched.used { "used" } else { "unused" };
+        let path = watched.derivation_path.as_deref().unwrap_or("imported");
+        println!("  {}. {} [{}] {}", i + 1, watched.address, status, path);
+        if let Some(label) = &watched.label {
+            println!("     🏷️  {}", label);
+        }
+    }
+    Ok(())
+}
+
+fn unsigned_send(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 2 {
+        eprintln!("Usage: trinity-watch unsigned-send <to> <triangle_hash> [--fee N] [--out FILE]");
+        std::process::exit(1);
+    }
+
+    let to_address = address::parse_address(&args[0])?;
+    let triangle_prefix = &args[1];
+    let fee = match flag(args, "--fee") {
+        Some(fee) => fee.parse().map_err(|_| "--fee requires a number")?,
+        None => 0,
+    };
+    let out = flag(args, "--out").cloned().unwrap_or_else(|| "unsigned_tx.json".to_string());
+
+    let watch_only = WatchOnlyWallet::load(&wallet::get_active_wallet_path())?;
+    let db = Database::open("trinitychain.db")
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let chain = db.load_blockchain()
+        .map_err(|e| format!("Failed to load blockchain: {}", e))?;
+
+    let (hash, _) = watch_only.owned_triangles(&chain.state).into_iter()
+        .find(|(h, _)| hex::encode(h).starts_with(triangle_prefix.as_str()))
+        .ok_or_else(|| format!("Triangle with hash prefix {} not found in this wallet", triangle_prefix))?;
+
+    let tx = watch_only.create_unsigned_transfer(&chain.state, &hash, to_address.clone(), fee, chain.blocks.len() as u64)?;
+    std::fs::write(&out, serde_json::to_string_pretty(&tx)?)?;
+
+    println!("📝 Unsigned transfer written to {}", out);
+    println!("   Triangle: {}", hex::encode(hash));
+    println!("   To: {}", address::display_address(&to_address));
+    println!("\n✍️  Sign it on the machine holding the key: trinity-send --sign {}", out);
+    Ok(())
+}
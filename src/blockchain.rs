@@ -1,11 +1,14 @@
 //! Core blockchain implementation for TrinityChain
 
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
-use crate::geometry::{Triangle, Point};
-use crate::transaction::{Transaction, SubdivisionTx, CoinbaseTx};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use crate::geometry::{Area, BoundingBox, Coord, Triangle, Point};
+use crate::spatial::SpatialIndex;
+use crate::transaction::{Address, Transaction, SubdivisionTx, CoinbaseTx, TransferTx};
 use crate::error::ChainError;
 use chrono::Utc;
+use rayon::prelude::*;
 
 pub type Sha256Hash = [u8; 32];
 pub type BlockHeight = u64;
@@ -13,36 +16,108 @@ pub type BlockHeight = u64;
 /// The genesis triangle - the root of all triangles
 pub fn genesis_triangle() -> Triangle {
     Triangle::new(
-        Point { x: 0.0, y: 0.0 },
-        Point { x: 1.0, y: 0.0 },
-        Point { x: 0.5, y: 0.866025403784 },
+        Point::from_f64(0.0, 0.0),
+        Point::from_f64(1.0, 0.0),
+        Point::from_f64(0.5, 0.866025403784),
         None,
         "genesis_owner".to_string(),
     )
 }
 
 /// Manages the canonical set of all currently valid (unspent) triangles (UTXO set).
+///
+/// A spatial index is kept alongside the UTXO set. Add and remove triangles
+/// through `insert_triangle` and `remove_triangle` so the two stay in sync.
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(from = "TriangleStateData")]
 pub struct TriangleState {
-    pub utxo_set: HashMap<Sha256Hash, Triangle>,
+    utxo_set: HashMap<Sha256Hash, Triangle>,
+    #[serde(skip)]
+    index: SpatialIndex,
+}
+
+/// Serialized form of `TriangleState`; the spatial index is rebuilt on load
+#[derive(serde::Deserialize)]
+struct TriangleStateData {
+    utxo_set: HashMap<Sha256Hash, Triangle>,
+}
+
+impl From<TriangleStateData> for TriangleState {
+    fn from(data: TriangleStateData) -> Self {
+        TriangleState::from_utxo_set(data.utxo_set)
+    }
 }
 
 impl TriangleState {
     pub fn new() -> Self {
         TriangleState {
             utxo_set: HashMap::new(),
+            index: SpatialIndex::new(),
         }
     }
 
+    /// Build a state from an existing UTXO set, indexing every triangle
+    pub fn from_utxo_set(utxo_set: HashMap<Sha256Hash, Triangle>) -> Self {
+        let mut state = TriangleState { utxo_set, index: SpatialIndex::new() };
+        state.rebuild_index();
+        state
+    }
+
     pub fn count(&self) -> usize {
         self.utxo_set.len()
     }
 
+    /// The unspent triangles, keyed by hash
+    pub fn utxo_set(&self) -> &HashMap<Sha256Hash, Triangle> {
+        &self.utxo_set
+    }
+
+    /// Look up an unspent triangle by hash
+    pub fn get(&self, hash: &Sha256Hash) -> Option<&Triangle> {
+        self.utxo_set.get(hash)
+    }
+
+    /// Add a triangle to the UTXO set and the spatial index
+    pub fn insert_triangle(&mut self, hash: Sha256Hash, triangle: Triangle) {
+        self.index.insert(hash, &triangle);
+        self.utxo_set.insert(hash, triangle);
+    }
+
+    /// Remove a triangle from the UTXO set and the spatial index
+    pub fn remove_triangle(&mut self, hash: &Sha256Hash) -> Option<Triangle> {
+        self.index.remove(hash);
+        self.utxo_set.remove(hash)
+    }
+
+    /// Re-index every triangle in the UTXO set
+    fn rebuild_index(&mut self) {
+        self.index.clear();
+        for (hash, triangle) in &self.utxo_set {
+            self.index.insert(*hash, triangle);
+        }
+    }
+
+    /// Triangles whose bounding boxes intersect the given region
+    pub fn triangles_in_region(&self, region: &BoundingBox) -> Vec<(Sha256Hash, &Triangle)> {
+        self.index.query(region)
+            .into_iter()
+            .filter_map(|hash| self.utxo_set.get(&hash).map(|t| (hash, t)))
+            .collect()
+    }
+
+    /// Triangles containing the given point, including on their boundary
+    pub fn triangles_containing(&self, point: &Point) -> Vec<(Sha256Hash, &Triangle)> {
+        self.triangles_in_region(&BoundingBox::new(*point, *point))
+            .into_iter()
+            .filter(|(_, triangle)| triangle.contains_point(point))
+            .collect()
+    }
+
     /// Apply a subdivision transaction to the state
     /// Optimized to minimize hash calculations and clones
     pub fn apply_subdivision(&mut self, tx: &SubdivisionTx) -> Result<(), ChainError> {
         // Use entry API to avoid double lookup
-        if self.utxo_set.remove(&tx.parent_hash).is_none() {
+        if self.remove_triangle(&tx.parent_hash).is_none() {
             return Err(ChainError::TriangleNotFound(format!(
                 "Parent triangle {} not found",
                 hex::encode(tx.parent_hash)
@@ -52,8 +127,7 @@ impl TriangleState {
         // Reserve capacity for children before the loop to avoid reallocations
         // Subdivisions always produce exactly 3 children
         for child in &tx.children {
-            let child_hash = child.hash();
-            self.utxo_set.insert(child_hash, child.clone());
+            self.insert_triangle(child.hash(), child.clone());
         }
 
         Ok(())
@@ -65,30 +139,88 @@ impl TriangleState {
         tx: &CoinbaseTx,
         block_height: BlockHeight,
     ) -> Result<(), ChainError> {
-        // Create a new triangle with a canonical shape based on the reward area
-        // The position is offset by the block height to ensure uniqueness
-        let side = (2.0 * tx.reward_area as f64).sqrt() as f64;
-        if !side.is_finite() || side <= 0.0 {
+        let new_triangle = Self::coinbase_triangle(tx, block_height)?;
+        self.insert_triangle(new_triangle.hash(), new_triangle);
+
+        Ok(())
+    }
+
+    /// Apply a transfer: move ownership and replace metadata if the transfer sets it.
+    /// The triangle keeps its hash, since the hash covers geometry only.
+    pub fn apply_transfer(&mut self, tx: &TransferTx) -> Result<(), ChainError> {
+        let triangle = self.utxo_set.get_mut(&tx.input_hash)
+            .ok_or_else(|| ChainError::TriangleNotFound(
+                format!("Transfer input {} missing from UTXO set", hex::encode(tx.input_hash))
+            ))?;
+        triangle.owner = tx.new_owner.clone();
+        if let Some(ref metadata) = tx.metadata {
+            triangle.metadata = Some(metadata.clone());
+        }
+        Ok(())
+    }
+
+    /// The reward triangle created by a coinbase transaction at the given height.
+    ///
+    /// A right triangle with whole-unit legs whose exact area equals the reward:
+    /// the leg along x is the largest divisor `w` of `2 * reward` not exceeding
+    /// its square root, and the leg along y is `2 * reward / w`. Triangles are
+    /// placed 1000 units apart along x and are at most 45 units wide, so reward
+    /// triangles never collide with each other.
+    ///
+    /// This shape is part of the geometry version 2 hard fork; earlier nodes
+    /// used legs of `sqrt(2 * reward)` (see "Protocol Upgrades" in
+    /// documentation/README.md).
+    pub fn coinbase_triangle(tx: &CoinbaseTx, block_height: BlockHeight) -> Result<Triangle, ChainError> {
+        if tx.reward_area == 0 || tx.reward_area > CoinbaseTx::MAX_REWARD_AREA {
             return Err(ChainError::InvalidTransaction(
                 "Invalid reward area for coinbase transaction".to_string(),
             ));
         }
 
-        // We'll create a right isosceles triangle at a location based on block height
-        // This ensures that reward triangles don't collide with each other
-        let offset = block_height as f64 * 1000.0; // Use a large offset
-        let new_triangle = Triangle::new(
-            Point { x: offset, y: 0.0 },
-            Point { x: offset + side, y: 0.0 },
-            Point { x: offset, y: side },
+        let doubled = 2 * tx.reward_area;
+        let width = (1..=doubled.isqrt()).rev().find(|w| doubled.is_multiple_of(*w)).unwrap_or(1);
+        let height = doubled / width;
+
+        let offset = Coord::from_int(block_height as i64 * 1000);
+        let width = Coord::from_int(width as i64);
+        let height = Coord::from_int(height as i64);
+        Ok(Triangle::new(
+            Point::new(offset, Coord::ZERO),
+            Point::new(offset + width, Coord::ZERO),
+            Point::new(offset, height),
             None,
             tx.beneficiary_address.clone(),
-        );
+        ))
+    }
+
+    /// Exact total area of all live triangles
+    pub fn total_area(&self) -> Area {
+        self.utxo_set.values().map(Triangle::area).sum()
+    }
 
-        let hash = new_triangle.hash();
-        self.utxo_set.insert(hash, new_triangle);
+    /// Exact total area of the live triangles owned by `address`
+    pub fn balance_of(&self, address: &str) -> Area {
+        self.utxo_set.values()
+            .filter(|triangle| triangle.owner == address)
+            .map(Triangle::area)
+            .sum()
+    }
 
-        Ok(())
+    /// Live triangles owned by any of `addresses`
+    pub fn owned_by(&self, addresses: &HashSet<Address>) -> Vec<(Sha256Hash, &Triangle)> {
+        self.utxo_set.iter()
+            .filter(|(_, triangle)| addresses.contains(&triangle.owner))
+            .map(|(hash, triangle)| (*hash, triangle))
+            .collect()
+    }
+
+    /// Find a live triangle whose interior overlaps `triangle`, ignoring the
+    /// hashes in `exclude` (e.g. triangles being spent by the same block).
+    pub fn find_overlap(&self, triangle: &Triangle, exclude: &[Sha256Hash]) -> Option<Sha256Hash> {
+        self.triangles_in_region(&triangle.bounding_box())
+            .into_iter()
+            .find(|(hash, existing)| !exclude.contains(hash) && existing.overlaps(triangle))
+            .map(|(hash, _)| hash)
     }
 }
 
@@ -205,28 +337,388 @@ impl Block {
     }
 }
 
-/// Transaction pool for pending (unconfirmed) transactions
+/// Local (non-consensus) rules deciding which valid transactions a node
+/// accepts into its mempool and relays to peers
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RelayPolicy {
+    /// Subdivisions producing children smaller than this (twice the area in raw
+    /// fixed-point units squared) are considered dust
+    pub dust_child_doubled_area_raw: u128,
+    /// Minimum fee a dust-producing subdivision must pay to be relayed
+    pub min_dust_subdivision_fee: u64,
+    /// Fee a replacement must pay on top of everything it evicts
+    #[serde(default = "RelayPolicy::default_min_replacement_fee_increment")]
+    pub min_replacement_fee_increment: u64,
+    /// Maximum number of transactions a single replacement may evict
+    #[serde(default = "RelayPolicy::default_max_replacement_evictions")]
+    pub max_replacement_evictions: usize,
+    /// Seconds a transaction may wait in the mempool before it is dropped
+    #[serde(default = "RelayPolicy::default_mempool_expiry_secs")]
+    pub mempool_expiry_secs: u64,
+}
+
+impl RelayPolicy {
+    /// Default dust threshold: children under 2^-8 square units
+    pub const DEFAULT_DUST_CHILD_DOUBLED_AREA_RAW: u128 = 1 << 25;
+
+    /// Default fee required to relay a dust-producing subdivision
+    pub const DEFAULT_MIN_DUST_SUBDIVISION_FEE: u64 = 10;
+
+    /// Default extra fee a replacement pays over what it evicts
+    pub const DEFAULT_MIN_REPLACEMENT_FEE_INCREMENT: u64 = 1;
+
+    /// Default eviction limit per replacement (BIP-125 rule 5)
+    pub const DEFAULT_MAX_REPLACEMENT_EVICTIONS: usize = 100;
+
+    /// Default mempool expiry: two weeks
+    pub const DEFAULT_MEMPOOL_EXPIRY_SECS: u64 = 14 * 24 * 60 * 60;
+
+    fn default_min_replacement_fee_increment() -> u64 {
+        Self::DEFAULT_MIN_REPLACEMENT_FEE_INCREMENT
+    }
+
+    fn default_max_replacement_evictions() -> usize {
+        Self::DEFAULT_MAX_REPLACEMENT_EVICTIONS
+    }
+
+    fn default_mempool_expiry_secs() -> u64 {
+        Self::DEFAULT_MEMPOOL_EXPIRY_SECS
+    }
+
+    /// Reject subdivisions that create dust children without paying for them
+    pub fn check_subdivision(&self, tx: &SubdivisionTx) -> Result<(), ChainError> {
+        let creates_dust = tx.children.iter()
+            .any(|child| child.doubled_area_raw() < self.dust_child_doubled_area_raw);
+
+        if creates_dust && tx.fee < self.min_dust_subdivision_fee {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Uneconomic subdivision: dust-sized children require a fee of at least {}",
+                self.min_dust_subdivision_fee
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for RelayPolicy {
+    fn default() -> Self {
+        RelayPolicy {
+            dust_child_doubled_area_raw: Self::DEFAULT_DUST_CHILD_DOUBLED_AREA_RAW,
+            min_dust_subdivision_fee: Self::DEFAULT_MIN_DUST_SUBDIVISION_FEE,
+            min_replacement_fee_increment: Self::DEFAULT_MIN_REPLACEMENT_FEE_INCREMENT,
+            max_replacement_evictions: Self::DEFAULT_MAX_REPLACEMENT_EVICTIONS,
+            mempool_expiry_secs: Self::DEFAULT_MEMPOOL_EXPIRY_SECS,
+        }
+    }
+}
+
+/// A triangle held by a particular owner: what a transaction spends and what
+/// it creates. Transfers keep a triangle's hash, so the owner tells apart the
+/// versions of a triangle before and after an unconfirmed transfer. A spent
+/// outpoint's owner is the transaction's spender (see
+/// [`Transaction::spender`]), never the sender it claims.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Outpoint {
+    pub triangle: Sha256Hash,
+    pub owner: Address,
+}
+
+impl Outpoint {
+    /// The triangle a transaction spends, held by its spender; coinbase and
+    /// unsigned transactions spend none
+    pub fn spent_by(tx: &Transaction) -> Option<Outpoint> {
+        let owner = tx.spender().ok().flatten()?;
+        match tx {
+            Transaction::Transfer(t) => Some(Outpoint { triangle: t.input_hash, owner }),
+            Transaction::Subdivision(s) => Some(Outpoint { triangle: s.parent_hash, owner }),
+            Transaction::Coinbase(_) => None,
+        }
+    }
+
+    /// The triangles a transaction creates, as their new owners hold them
+    pub fn created_by(tx: &Transaction) -> Vec<Outpoint> {
+        match tx {
+            Transaction::Transfer(t) => vec![Outpoint { triangle: t.input_hash, owner: t.new_owner.clone() }],
+            Transaction::Subdivision(s) => s.children.iter()
+                .map(|child| Outpoint { triangle: child.hash(), owner: child.owner.clone() })
+                .collect(),
+            Transaction::Coinbase(_) => Vec::new(),
+        }
+    }
+}
+
+/// Totals over a mempool transaction together with its unconfirmed
+/// ancestors or descendants
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct PackageStats {
+    /// Number of transactions, including the one the package is built around
+    pub count: usize,
+    /// Sum of their fees in whole area units
+    pub fee: u64,
+    /// Sum of their estimated sizes in bytes
+    pub size: usize,
+}
+
+impl PackageStats {
+    /// A package holding just `tx`
+    fn of(tx: &Transaction) -> Self {
+        let mut package = PackageStats::default();
+        package.add(tx);
+        package
+    }
+
+    /// Fees are chosen by whoever submits a transaction, so totals saturate
+    /// rather than overflow
+    fn add(&mut self, tx: &Transaction) {
+        self.count = self.count.saturating_add(1);
+        self.fee = self.fee.saturating_add(tx.fee());
+        self.size = self.size.saturating_add(crate::fees::estimate_transaction_size(tx));
+    }
+
+    fn merge(&mut self, other: &PackageStats) {
+        self.count = self.count.saturating_add(other.count);
+        self.fee = self.fee.saturating_add(other.fee);
+        self.size = self.size.saturating_add(other.size);
+    }
+
+    fn subtract(&mut self, other: &PackageStats) {
+        self.count = self.count.saturating_sub(other.count);
+        self.fee = self.fee.saturating_sub(other.fee);
+        self.size = self.size.saturating_sub(other.size);
+    }
+
+    /// Fee per byte of the whole package
+    pub fn fee_rate(&self) -> f64 {
+        if self.size == 0 {
+            0.0
+        } else {
+            self.fee as f64 / self.size as f64
+        }
+    }
+}
+
+/// Orders mempool transactions by a package's fee rate, compared exactly as
+/// fractions, with the transaction hash breaking ties
+#[derive(Debug, Clone, Copy)]
+struct FeeRateKey {
+    fee: u64,
+    size: usize,
+    hash: Sha256Hash,
+}
+
+impl FeeRateKey {
+    fn new(hash: Sha256Hash, package: &PackageStats) -> Self {
+        FeeRateKey { fee: package.fee, size: package.size, hash }
+    }
+}
+
+impl Ord for FeeRateKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let rate = self.fee as u128 * other.size as u128;
+        let other_rate = other.fee as u128 * self.size as u128;
+        rate.cmp(&other_rate).then_with(|| self.hash.cmp(&other.hash))
+    }
+}
+
+impl PartialOrd for FeeRateKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FeeRateKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FeeRateKey {}
+
+/// A version of a triangle that a transaction spends: the triangle's hash and
+/// the mempool transaction that created it, or `None` for a confirmed
+/// triangle. A confirmed triangle has a single owner, so every spend of it
+/// conflicts whatever sender the transaction claims; only versions created
+/// inside the mempool are told apart, by the transaction creating them.
+type SpentVersion = (Sha256Hash, Option<Sha256Hash>);
+
+/// Transaction pool for pending (unconfirmed) transactions.
+///
+/// A transaction may spend a triangle created by another mempool
+/// transaction, such as a transfer of a child whose subdivision is still
+/// unconfirmed. Each transaction has at most one such parent, recorded when
+/// it is added; the children of each transaction, the outputs of mempool
+/// transactions and the triangle versions they spend are indexed alongside.
+///
+/// Transactions are also indexed by fee rate, so admission, eviction and
+/// selecting the best transactions for a block take logarithmic time rather
+/// than a scan of the pool.
+///
+/// Two transactions spending the same triangle version conflict. A conflict
+/// is rejected unless the transaction already in the pool signals
+/// replaceability and the newcomer pays for replacing it (see
+/// [`Mempool::add_transaction`]).
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(from = "MempoolData")]
 pub struct Mempool {
     /// Pending transactions indexed by their hash
     transactions: HashMap<Sha256Hash, Transaction>,
+    /// Relay policy applied to incoming transactions
+    #[serde(default)]
+    policy: RelayPolicy,
+    /// The mempool transaction each transaction spends an output of
+    #[serde(default)]
+    parents: HashMap<Sha256Hash, Sha256Hash>,
+    /// When each transaction entered the pool, in Unix seconds
+    #[serde(default)]
+    added_at: HashMap<Sha256Hash, i64>,
+    /// Transactions spending an output of each transaction
+    #[serde(skip)]
+    children: HashMap<Sha256Hash, HashSet<Sha256Hash>>,
+    /// Outputs of mempool transactions, mapped to the transaction creating them
+    #[serde(skip)]
+    outputs: HashMap<Outpoint, Sha256Hash>,
+    /// The mempool transaction spending each triangle version. There is at
+    /// most one, since conflicting spends are rejected or replaced.
+    #[serde(skip)]
+    spent: HashMap<SpentVersion, Sha256Hash>,
+    /// Each transaction's descendant package
+    #[serde(skip)]
+    descendant_stats: HashMap<Sha256Hash, PackageStats>,
+    /// Every transaction ordered by the fee rate of its descendant package,
+    /// lowest first: the eviction order
+    #[serde(skip)]
+    by_descendant_fee_rate: BTreeSet<FeeRateKey>,
+    /// Transactions spending confirmed triangles ordered by their own fee
+    /// rate, highest last: the mining order
+    #[serde(skip)]
+    by_fee_rate: BTreeSet<FeeRateKey>,
+    /// Number of pool transactions of each spender, the address of the
+    /// signing key or script
+    #[serde(skip)]
+    sender_counts: HashMap<Address, usize>,
+}
+
+/// Serialized form of `Mempool`; the child and output indexes are rebuilt on load
+#[derive(serde::Deserialize)]
+struct MempoolData {
+    transactions: HashMap<Sha256Hash, Transaction>,
+    #[serde(default)]
+    policy: RelayPolicy,
+    #[serde(default)]
+    parents: HashMap<Sha256Hash, Sha256Hash>,
+    #[serde(default)]
+    added_at: HashMap<Sha256Hash, i64>,
+}
+
+impl From<MempoolData> for Mempool {
+    fn from(data: MempoolData) -> Self {
+        let mut mempool = Mempool::with_policy(data.policy);
+        mempool.parents = data.parents;
+        mempool.parents.retain(|child, parent| {
+            data.transactions.contains_key(child) && data.transactions.contains_key(parent)
+        });
+        // Drop links that never reach a transaction without a parent: they
+        // form a cycle, which `add_transaction` never creates
+        let reaches_root = |child: &Sha256Hash| {
+            let mut current = *child;
+            for _ in 0..=Self::MAX_ANCESTORS {
+                match mempool.parents.get(&current) {
+                    Some(parent) => current = *parent,
+                    None => return true,
+                }
+            }
+            false
+        };
+        let cyclic: Vec<Sha256Hash> = mempool.parents.keys().filter(|child| !reaches_root(child)).copied().collect();
+        for child in cyclic {
+            mempool.parents.remove(&child);
+        }
+        // Pools saved before entry times were kept count from now
+        let now = Utc::now().timestamp();
+        mempool.added_at = data.transactions.keys()
+            .map(|hash| (*hash, data.added_at.get(hash).copied().unwrap_or(now)))
+            .collect();
+        mempool.transactions = data.transactions;
+        mempool.rebuild_indexes();
+        mempool
+    }
 }
 
 impl Mempool {
     /// Maximum number of transactions in mempool (to prevent DoS)
-    const MAX_TRANSACTIONS: usize = 10000;
+    pub const MAX_TRANSACTIONS: usize = 10000;
 
     /// Maximum transactions per address to prevent spam
-    const MAX_PER_ADDRESS: usize = 100;
+    pub const MAX_PER_ADDRESS: usize = 100;
+
+    /// Maximum number of unconfirmed ancestors a transaction may have
+    pub const MAX_ANCESTORS: usize = 25;
 
     pub fn new() -> Self {
+        Self::with_policy(RelayPolicy::default())
+    }
+
+    /// Create an empty mempool enforcing the given relay policy
+    pub fn with_policy(policy: RelayPolicy) -> Self {
         Mempool {
             transactions: HashMap::new(),
+            policy,
+            parents: HashMap::new(),
+            added_at: HashMap::new(),
+            children: HashMap::new(),
+            outputs: HashMap::new(),
+            spent: HashMap::new(),
+            descendant_stats: HashMap::new(),
+            by_descendant_fee_rate: BTreeSet::new(),
+            by_fee_rate: BTreeSet::new(),
+            sender_counts: HashMap::new(),
         }
     }
 
-    /// Add a transaction to the mempool with validation
+    /// The relay policy applied to incoming transactions
+    pub fn policy(&self) -> &RelayPolicy {
+        &self.policy
+    }
+
+    /// Add a transaction to the mempool with validation.
+    ///
+    /// A transaction spending the same triangle version as one already in the
+    /// pool is rejected, unless it qualifies as a replacement under rules
+    /// modelled on BIP-125: the existing transaction signals replaceability,
+    /// the newcomer pays a strictly higher fee rate than it, and its fee
+    /// covers everything it evicts (the existing transaction and its
+    /// descendants) plus the policy's fee increment. A replacement may evict
+    /// at most the policy's limit of transactions.
+    ///
+    /// Transactions are counted per spender, the address of the signing key
+    /// or script (see [`Transaction::spender`]), which must be the sender the
+    /// transaction names; each spender may have at most
+    /// [`Mempool::MAX_PER_ADDRESS`] in the pool. Without the chain state
+    /// whether the spender owns a confirmed input is unknown; nodes admit
+    /// through [`Mempool::add_transaction_with_state`], which checks it
+    /// against the UTXO set.
     pub fn add_transaction(&mut self, tx: Transaction) -> Result<(), ChainError> {
+        self.add_transaction_at(tx, Utc::now().timestamp())
+    }
+
+    /// Add a transaction spending a triangle from `state` or one created in
+    /// the pool. A transaction spending a confirmed triangle its spender does
+    /// not own is rejected, so the per-address limit counts the owner the
+    /// triangle is recorded under.
+    pub fn add_transaction_with_state(&mut self, tx: Transaction, state: &TriangleState) -> Result<(), ChainError> {
+        self.admit(tx, Utc::now().timestamp(), Some(state))
+    }
+
+    /// Add a transaction that entered the pool at `added_at` (Unix seconds).
+    /// Validation is the same as for [`Mempool::add_transaction`].
+    pub fn add_transaction_at(&mut self, tx: Transaction, added_at: i64) -> Result<(), ChainError> {
+        self.admit(tx, added_at, None)
+    }
+
+    /// Admit a transaction that entered the pool at `added_at`, checking a
+    /// confirmed input against `state` when one is given
+    pub(crate) fn admit(&mut self, tx: Transaction, added_at: i64, state: Option<&TriangleState>) -> Result<(), ChainError> {
         let tx_hash = tx.hash();
 
         // Check if transaction already exists
@@ -250,81 +742,312 @@ impl Mempool {
             Transaction::Subdivision(sub_tx) => {
                 // We can still validate the signature without state access, which is a cheap
                 // way to discard obviously invalid transactions.
+                sub_tx.validate_addresses()?;
                 sub_tx.validate_signature()?;
+                sub_tx.validate_dust_rules()?;
+                self.policy.check_subdivision(sub_tx)?;
             }
         }
 
-        // Check per-address limit to prevent spam
-        let sender_address = match &tx {
-            Transaction::Transfer(t) => Some(&t.sender),
-            Transaction::Subdivision(s) => Some(&s.owner_address),
-            Transaction::Coinbase(_) => None,
-        };
+        // The spender comes from the signing key or script; the sender the
+        // transaction names must be that address
+        let spent = Outpoint::spent_by(&tx);
+        if let (Some(outpoint), Some(claimed)) = (&spent, Self::claimed_sender(&tx)) {
+            if outpoint.owner != *claimed {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Sender {} does not match the signing address {}", claimed, outpoint.owner
+                )));
+            }
+        }
 
-        if let Some(sender) = sender_address {
-            // Count transactions from this sender (optimized single pass)
-            let mut count = 0;
-            for tx in self.transactions.values() {
-                let tx_sender = match tx {
-                    Transaction::Transfer(t) => Some(&t.sender),
-                    Transaction::Subdivision(s) => Some(&s.owner_address),
-                    _ => None,
-                };
-                if let Some(tx_sender) = tx_sender {
-                    if tx_sender == sender {
-                        count += 1;
-                        if count >= Self::MAX_PER_ADDRESS {
-                            return Err(ChainError::InvalidTransaction(
-                                format!("Address has reached maximum mempool limit of {}", Self::MAX_PER_ADDRESS)
-                            ));
-                        }
-                    }
-                }
+        // Spending an unconfirmed output makes the creating transaction its parent
+        let parent = spent.as_ref().and_then(|outpoint| self.outputs.get(outpoint)).copied();
+        if let Some(parent) = parent {
+            if self.ancestors(&parent).len() + 1 > Self::MAX_ANCESTORS {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Transaction would exceed the limit of {} unconfirmed ancestors", Self::MAX_ANCESTORS
+                )));
+            }
+        }
+        if let (Some(outpoint), None, Some(state)) = (&spent, parent, state) {
+            Self::check_confirmed_owner(outpoint, state)?;
+        }
+        let version = spent.as_ref().map(|outpoint| (outpoint.triangle, parent));
+        let conflict = version.as_ref().and_then(|version| self.spent.get(version)).copied();
+        if let Some(conflict) = conflict {
+            self.check_replacement(&tx, &conflict)?;
+        }
+
+        // Check per-address limit to prevent spam, keyed on the spender. A
+        // spend of a mempool output matched the owner its parent created it
+        // for, and a confirmed input was checked against the state when
+        // there is one.
+        let owner = spent.map(|outpoint| outpoint.owner);
+        if let Some(owner) = &owner {
+            if self.sender_counts.get(owner).copied().unwrap_or(0) >= Self::MAX_PER_ADDRESS {
+                return Err(ChainError::InvalidTransaction(
+                    format!("Address has reached maximum mempool limit of {}", Self::MAX_PER_ADDRESS)
+                ));
             }
         }
 
-        // If mempool is full, evict lowest fee transaction
-        if self.transactions.len() >= Self::MAX_TRANSACTIONS {
+        if let Some(conflict) = conflict {
+            self.remove_with_descendants(&conflict);
+        } else if self.transactions.len() >= Self::MAX_TRANSACTIONS {
+            // If mempool is full, evict lowest fee transaction
             self.evict_lowest_fee_transaction()?;
+            if parent.is_some_and(|parent| !self.transactions.contains_key(&parent)) {
+                return Err(ChainError::InvalidTransaction(
+                    "Parent transaction was evicted from the full mempool".to_string()
+                ));
+            }
         }
 
+        let own = PackageStats::of(&tx);
+        if let Some(parent) = parent {
+            self.parents.insert(tx_hash, parent);
+            self.children.entry(parent).or_default().insert(tx_hash);
+            for ancestor in self.ancestors(&tx_hash) {
+                let mut stats = self.descendant_stats.get(&ancestor).copied().unwrap_or_default();
+                stats.merge(&own);
+                self.set_descendant_stats(ancestor, stats);
+            }
+        } else {
+            self.by_fee_rate.insert(FeeRateKey::new(tx_hash, &own));
+        }
+        self.set_descendant_stats(tx_hash, own);
+        if let Some(owner) = owner {
+            *self.sender_counts.entry(owner).or_default() += 1;
+        }
+        if let Some(version) = version {
+            self.spent.insert(version, tx_hash);
+        }
+        for outpoint in Outpoint::created_by(&tx) {
+            self.outputs.insert(outpoint, tx_hash);
+        }
+        self.added_at.insert(tx_hash, added_at);
         self.transactions.insert(tx_hash, tx);
         Ok(())
     }
 
-    /// Evict the transaction with the lowest fee to make room for new ones
-    fn evict_lowest_fee_transaction(&mut self) -> Result<(), ChainError> {
-        if self.transactions.is_empty() {
-            return Ok(());
+    /// Check that `tx` may replace the mempool transaction `conflict`, which
+    /// spends the same triangle version, together with its descendants
+    fn check_replacement(&self, tx: &Transaction, conflict: &Sha256Hash) -> Result<(), ChainError> {
+        let existing = &self.transactions[conflict];
+        if !existing.is_replaceable() {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Transaction conflicts with mempool transaction {}, which spends the same triangle",
+                hex::encode(conflict)
+            )));
         }
 
-        // Find transaction with lowest fee
-        let mut lowest_fee = u64::MAX;
-        let mut lowest_hash: Option<Sha256Hash> = None;
+        let evicted = self.descendant_package(conflict).unwrap_or_default();
+        if evicted.count > self.policy.max_replacement_evictions {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Replacement would evict {} transactions, more than the limit of {}",
+                evicted.count, self.policy.max_replacement_evictions
+            )));
+        }
 
-        for (hash, tx) in &self.transactions {
-            let fee = match tx {
-                Transaction::Transfer(t) => t.fee,
-                Transaction::Subdivision(_) => 0, // Subdivisions don't have fees
-                Transaction::Coinbase(_) => 0,
-            };
+        let required_fee = evicted.fee.saturating_add(self.policy.min_replacement_fee_increment);
+        if tx.fee() <= evicted.fee || tx.fee() < required_fee {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Replacement fee {} must be at least {} to replace {} transaction(s) paying {}",
+                tx.fee(), required_fee.max(evicted.fee.saturating_add(1)), evicted.count, evicted.fee
+            )));
+        }
 
-            if fee < lowest_fee {
-                lowest_fee = fee;
-                lowest_hash = Some(*hash);
-            }
+        // Compare fee rates exactly: fee / size against fee / size
+        let size = crate::fees::estimate_transaction_size(tx) as u128;
+        let existing_size = crate::fees::estimate_transaction_size(existing) as u128;
+        if tx.fee() as u128 * existing_size <= existing.fee() as u128 * size {
+            return Err(ChainError::InvalidTransaction(
+                "Replacement must pay a higher fee rate than the transaction it replaces".to_string()
+            ));
         }
 
-        if let Some(hash) = lowest_hash {
-            self.transactions.remove(&hash);
+        Ok(())
+    }
+
+    /// The sender a transaction names, which its spender must match
+    fn claimed_sender(tx: &Transaction) -> Option<&Address> {
+        match tx {
+            Transaction::Transfer(t) => Some(&t.sender),
+            Transaction::Subdivision(s) => Some(&s.owner_address),
+            Transaction::Coinbase(_) => None,
         }
+    }
 
+    /// Check that a confirmed triangle exists and is held by the spender of
+    /// the transaction spending it
+    fn check_confirmed_owner(outpoint: &Outpoint, state: &TriangleState) -> Result<(), ChainError> {
+        let triangle = state.get(&outpoint.triangle).ok_or_else(|| ChainError::TriangleNotFound(format!(
+            "Input triangle {} not found in UTXO set", hex::encode(outpoint.triangle)
+        )))?;
+        if triangle.owner != outpoint.owner {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Triangle {} is not owned by signer {}", hex::encode(outpoint.triangle), outpoint.owner
+            )));
+        }
         Ok(())
     }
 
-    /// Remove a transaction from the mempool
+    /// Record a transaction's descendant package, moving it in the eviction order
+    fn set_descendant_stats(&mut self, tx_hash: Sha256Hash, stats: PackageStats) {
+        if let Some(old) = self.descendant_stats.insert(tx_hash, stats) {
+            self.by_descendant_fee_rate.remove(&FeeRateKey::new(tx_hash, &old));
+        }
+        self.by_descendant_fee_rate.insert(FeeRateKey::new(tx_hash, &stats));
+    }
+
+    /// Evict the transaction whose descendant package pays the lowest fee
+    /// rate, together with those descendants, to make room for new ones
+    fn evict_lowest_fee_transaction(&mut self) -> Result<(), ChainError> {
+        if let Some(lowest) = self.by_descendant_fee_rate.first().map(|key| key.hash) {
+            self.remove_with_descendants(&lowest);
+        }
+
+        Ok(())
+    }
+
+    /// Remove a transaction from the mempool. Its children stay and are
+    /// treated as spending confirmed triangles, as they do once the
+    /// transaction is mined.
     pub fn remove_transaction(&mut self, tx_hash: &Sha256Hash) -> Option<Transaction> {
-        self.transactions.remove(tx_hash)
+        let tx = self.transactions.remove(tx_hash)?;
+        self.added_at.remove(tx_hash);
+
+        // Ancestors lose this transaction and everything below it
+        let removed = self.descendant_stats.remove(tx_hash).unwrap_or_default();
+        self.by_descendant_fee_rate.remove(&FeeRateKey::new(*tx_hash, &removed));
+        for ancestor in self.ancestors(tx_hash) {
+            let mut stats = self.descendant_stats.get(&ancestor).copied().unwrap_or_default();
+            stats.subtract(&removed);
+            self.set_descendant_stats(ancestor, stats);
+        }
+        self.by_fee_rate.remove(&FeeRateKey::new(*tx_hash, &PackageStats::of(&tx)));
+        if let Some(spent) = Outpoint::spent_by(&tx) {
+            if let Some(count) = self.sender_counts.get_mut(&spent.owner) {
+                *count -= 1;
+                if *count == 0 {
+                    self.sender_counts.remove(&spent.owner);
+                }
+            }
+        }
+
+        for outpoint in Outpoint::created_by(&tx) {
+            if self.outputs.get(&outpoint) == Some(tx_hash) {
+                self.outputs.remove(&outpoint);
+            }
+        }
+        let parent = self.parents.remove(tx_hash);
+        if let Some(spent) = Outpoint::spent_by(&tx) {
+            let version = (spent.triangle, parent);
+            if self.spent.get(&version) == Some(tx_hash) {
+                self.spent.remove(&version);
+            }
+        }
+        if let Some(parent) = parent {
+            if let Some(siblings) = self.children.get_mut(&parent) {
+                siblings.remove(tx_hash);
+                if siblings.is_empty() {
+                    self.children.remove(&parent);
+                }
+            }
+        }
+        // Children now spend the triangles as confirmed
+        for child in self.children.remove(tx_hash).unwrap_or_default() {
+            self.parents.remove(&child);
+            let Some(child_tx) = self.transactions.get(&child) else { continue };
+            self.by_fee_rate.insert(FeeRateKey::new(child, &PackageStats::of(child_tx)));
+            if let Some(spent) = Outpoint::spent_by(child_tx) {
+                if self.spent.remove(&(spent.triangle, Some(*tx_hash))) == Some(child) {
+                    self.spent.entry((spent.triangle, None)).or_insert(child);
+                }
+            }
+        }
+
+        Some(tx)
+    }
+
+    /// Remove a transaction and every mempool transaction that depends on it,
+    /// as when it is evicted or conflicts with a confirmed transaction
+    pub fn remove_with_descendants(&mut self, tx_hash: &Sha256Hash) -> Vec<Transaction> {
+        let mut doomed = vec![*tx_hash];
+        doomed.extend(self.descendants(tx_hash));
+        // Remove leaves first so no descendant is ever left without its parent
+        doomed.iter().rev().filter_map(|hash| self.remove_transaction(hash)).collect()
+    }
+
+    /// Remove the mempool transactions that spend the same triangle as a
+    /// transaction in `confirmed`, together with their descendants.
+    /// Call this before removing the confirmed transactions themselves.
+    /// Returns how many transactions were removed.
+    pub fn remove_conflicts(&mut self, confirmed: &[Transaction]) -> usize {
+        let conflicts: Vec<Sha256Hash> = confirmed.iter()
+            .filter_map(|tx| {
+                let spent = Outpoint::spent_by(tx)?;
+                let spender = self.spent.get(&(spent.triangle, None))?;
+                (*spender != tx.hash()).then_some(*spender)
+            })
+            .collect();
+
+        conflicts.iter().map(|hash| self.remove_with_descendants(hash).len()).sum()
+    }
+
+    /// The mempool transaction whose output `tx_hash` spends, if any
+    pub fn parent_of(&self, tx_hash: &Sha256Hash) -> Option<Sha256Hash> {
+        self.parents.get(tx_hash).copied()
+    }
+
+    /// Mempool transactions spending an output of `tx_hash`
+    pub fn children_of(&self, tx_hash: &Sha256Hash) -> Vec<Sha256Hash> {
+        self.children.get(tx_hash).map(|c| c.iter().copied().collect()).unwrap_or_default()
+    }
+
+    /// Unconfirmed ancestors of a transaction, nearest first
+    pub fn ancestors(&self, tx_hash: &Sha256Hash) -> Vec<Sha256Hash> {
+        let mut ancestors = Vec::new();
+        let mut current = *tx_hash;
+        while let Some(parent) = self.parents.get(&current) {
+            ancestors.push(*parent);
+            current = *parent;
+        }
+        ancestors
+    }
+
+    /// Unconfirmed descendants of a transaction, parents before their children
+    pub fn descendants(&self, tx_hash: &Sha256Hash) -> Vec<Sha256Hash> {
+        let mut descendants = self.children_of(tx_hash);
+        let mut i = 0;
+        while i < descendants.len() {
+            let children = self.children_of(&descendants[i]);
+            descendants.extend(children);
+            i += 1;
+        }
+        descendants
+    }
+
+    /// A transaction together with its unconfirmed ancestors: what a miner
+    /// has to include for the transaction to be valid
+    pub fn ancestor_package(&self, tx_hash: &Sha256Hash) -> Option<PackageStats> {
+        self.package(tx_hash, self.ancestors(tx_hash))
+    }
+
+    /// A transaction together with its unconfirmed descendants: what leaves
+    /// the mempool if the transaction is evicted
+    pub fn descendant_package(&self, tx_hash: &Sha256Hash) -> Option<PackageStats> {
+        self.descendant_stats.get(tx_hash).copied()
+    }
+
+    fn package(&self, tx_hash: &Sha256Hash, related: Vec<Sha256Hash>) -> Option<PackageStats> {
+        let mut package = PackageStats::of(self.transactions.get(tx_hash)?);
+        for hash in related {
+            if let Some(tx) = self.transactions.get(&hash) {
+                package.add(tx);
+            }
+        }
+        Some(package)
     }
 
     /// Get all transactions currently in the mempool
@@ -332,27 +1055,49 @@ impl Mempool {
         self.transactions.values().cloned().collect()
     }
 
-    /// Get transactions ordered by fee (highest first) for mining prioritization
-    /// Returns up to `limit` transactions with the highest fees
-    /// Optimized to use partial sorting for better performance when limit < total
+    /// Get transactions ordered by fee rate (highest first) for mining prioritization
+    /// Returns up to `limit` transactions paying the most per byte, read off
+    /// the fee-rate index without sorting the pool
+    ///
+    /// Only transactions spending confirmed triangles are returned: block
+    /// validation checks every transaction against the state before the
+    /// block, so a transaction with an unconfirmed parent waits for the next.
     pub fn get_transactions_by_fee(&self, limit: usize) -> Vec<Transaction> {
-        let mut txs: Vec<Transaction> = self.transactions.values().cloned().collect();
+        self.by_fee_rate.iter().rev()
+            .take(limit)
+            .filter_map(|key| self.transactions.get(&key.hash).cloned())
+            .collect()
+    }
 
-        if limit >= txs.len() {
-            // Just sort normally if we want all transactions
-            txs.sort_unstable_by(|a, b| b.fee().cmp(&a.fee()));
-            return txs;
-        }
+    /// Every transaction with the time it entered the pool, parents before
+    /// their children, so adding them back in order restores the pool
+    pub fn entries(&self) -> Vec<(Transaction, i64)> {
+        self.topological_order().into_iter()
+            .map(|hash| (self.transactions[&hash].clone(), self.added_at.get(&hash).copied().unwrap_or_default()))
+            .collect()
+    }
+
+    /// When a transaction entered the pool, in Unix seconds
+    pub fn added_at(&self, tx_hash: &Sha256Hash) -> Option<i64> {
+        self.added_at.get(tx_hash).copied()
+    }
 
-        // Use partial sort for better performance when limit is small
-        // This is O(n log k) instead of O(n log n) where k = limit
-        txs.select_nth_unstable_by(limit, |a, b| b.fee().cmp(&a.fee()));
+    /// Whether a transaction that entered the pool at `added_at` has waited
+    /// longer than the policy allows by `now`
+    pub fn is_expired(&self, added_at: i64, now: i64) -> bool {
+        let expiry = i64::try_from(self.policy.mempool_expiry_secs).unwrap_or(i64::MAX);
+        now.saturating_sub(added_at) >= expiry
+    }
 
-        // Sort the top `limit` transactions
-        let (top, _, _) = txs.select_nth_unstable_by(limit - 1, |a, b| b.fee().cmp(&a.fee()));
-        top.sort_unstable_by(|a, b| b.fee().cmp(&a.fee()));
+    /// Remove transactions that have expired by `now`, together with their
+    /// descendants. Returns how many transactions were removed.
+    pub fn expire(&mut self, now: i64) -> usize {
+        let expired: Vec<Sha256Hash> = self.added_at.iter()
+            .filter(|(_, added_at)| self.is_expired(**added_at, now))
+            .map(|(hash, _)| *hash)
+            .collect();
 
-        txs.into_iter().take(limit).collect()
+        expired.iter().map(|hash| self.remove_with_descendants(hash).len()).sum()
     }
 
     /// Get a specific transaction by hash
@@ -363,13 +1108,22 @@ impl Mempool {
     /// Remove multiple transactions (e.g., after they're included in a block)
     pub fn remove_transactions(&mut self, tx_hashes: &[Sha256Hash]) {
         for hash in tx_hashes {
-            self.transactions.remove(hash);
+            self.remove_transaction(hash);
         }
     }
 
     /// Clear all transactions from the mempool
     pub fn clear(&mut self) {
         self.transactions.clear();
+        self.parents.clear();
+        self.added_at.clear();
+        self.children.clear();
+        self.outputs.clear();
+        self.spent.clear();
+        self.descendant_stats.clear();
+        self.by_descendant_fee_rate.clear();
+        self.by_fee_rate.clear();
+        self.sender_counts.clear();
     }
 
     /// Get the number of pending transactions
@@ -384,42 +1138,116 @@ impl Mempool {
 
     /// Validate all transactions in mempool against current state
     /// Removes invalid transactions and returns count of removed transactions
-    /// Optimized to collect invalid hashes first to avoid iterator invalidation
+    ///
+    /// Transactions are checked parents first. A transaction with an
+    /// unconfirmed parent is checked against the triangle its parent creates,
+    /// and is removed along with the parent if the parent is invalid.
     pub fn validate_and_prune(&mut self, state: &TriangleState) -> usize {
+        // Triangles created by the transactions kept so far
+        let mut created: HashMap<Sha256Hash, Vec<Triangle>> = HashMap::new();
         let mut to_remove = Vec::new();
 
-        // Single pass through transactions
-        for (hash, tx) in self.transactions.iter() {
-            let is_valid = match tx {
-                Transaction::Subdivision(sub_tx) => {
-                    // Check if parent exists in UTXO set and signature is valid
-                    state.utxo_set.contains_key(&sub_tx.parent_hash) &&
-                    sub_tx.validate(state).is_ok()
+        for hash in self.topological_order() {
+            let tx = &self.transactions[&hash];
+            let Some(spent) = Outpoint::spent_by(tx) else {
+                // Coinbase transactions shouldn't be in mempool
+                to_remove.push(hash);
+                continue;
+            };
+            let input = match self.parents.get(&hash) {
+                Some(parent) => created.get(parent)
+                    .and_then(|triangles| triangles.iter().find(|t| t.hash() == spent.triangle))
+                    .cloned(),
+                None => state.get(&spent.triangle).cloned(),
+            };
+            let input = input.filter(|triangle| triangle.owner == spent.owner);
+
+            let outputs = match (tx, input) {
+                (Transaction::Subdivision(sub_tx), Some(parent)) => {
+                    let view = TriangleState::from_utxo_set(HashMap::from([(spent.triangle, parent)]));
+                    sub_tx.validate(&view).ok().map(|_| sub_tx.children.clone())
                 },
-                Transaction::Transfer(transfer_tx) => {
-                    // Check if input exists in UTXO set and signature is valid
-                    state.utxo_set.contains_key(&transfer_tx.input_hash) &&
-                    transfer_tx.validate().is_ok()
+                (Transaction::Transfer(transfer_tx), Some(mut triangle)) => {
+                    transfer_tx.validate().ok().map(|_| {
+                        triangle.owner = transfer_tx.new_owner.clone();
+                        if let Some(ref metadata) = transfer_tx.metadata {
+                            triangle.metadata = Some(metadata.clone());
+                        }
+                        vec![triangle]
+                    })
                 },
-                Transaction::Coinbase(_) => {
-                    // Coinbase transactions shouldn't be in mempool
-                    false
-                }
+                _ => None,
             };
 
-            if !is_valid {
-                to_remove.push(*hash);
+            match outputs {
+                Some(outputs) => {
+                    created.insert(hash, outputs);
+                }
+                None => to_remove.push(hash),
             }
         }
 
         let removed_count = to_remove.len();
-        // Batch removal to avoid repeated HashMap lookups
-        for hash in to_remove {
-            self.transactions.remove(&hash);
+        // Children come after their parents, so remove leaves first
+        for hash in to_remove.iter().rev() {
+            self.remove_transaction(hash);
         }
 
         removed_count
     }
+
+    /// Every transaction, parents before their children
+    fn topological_order(&self) -> Vec<Sha256Hash> {
+        let mut order: Vec<Sha256Hash> = self.transactions.keys()
+            .filter(|hash| !self.parents.contains_key(*hash))
+            .copied()
+            .collect();
+        let mut i = 0;
+        while i < order.len() {
+            let children = self.children_of(&order[i]);
+            order.extend(children);
+            i += 1;
+        }
+        order
+    }
+
+    /// Rebuild the child, output, spent-version, fee-rate and sender indexes
+    /// from the transactions and their recorded parents
+    fn rebuild_indexes(&mut self) {
+        self.children.clear();
+        self.outputs.clear();
+        self.spent.clear();
+        self.descendant_stats.clear();
+        self.by_descendant_fee_rate.clear();
+        self.by_fee_rate.clear();
+        self.sender_counts.clear();
+        for (child, parent) in &self.parents {
+            self.children.entry(*parent).or_default().insert(*child);
+        }
+        let order = self.topological_order();
+        for hash in &order {
+            let tx = &self.transactions[hash];
+            let own = PackageStats::of(tx);
+            if let Some(spent) = Outpoint::spent_by(tx) {
+                self.spent.entry((spent.triangle, self.parents.get(hash).copied())).or_insert(*hash);
+                *self.sender_counts.entry(spent.owner).or_default() += 1;
+            }
+            for outpoint in Outpoint::created_by(tx) {
+                self.outputs.insert(outpoint, *hash);
+            }
+            if !self.parents.contains_key(hash) {
+                self.by_fee_rate.insert(FeeRateKey::new(*hash, &own));
+            }
+        }
+        // Children come after their parents, so packages build up from the leaves
+        for hash in order.iter().rev() {
+            let mut stats = PackageStats::of(&self.transactions[hash]);
+            for child in self.children_of(hash) {
+                stats.merge(&self.descendant_stats[&child]);
+            }
+            self.set_descendant_stats(*hash, stats);
+        }
+    }
 }
 
 /// The blockchain itself
@@ -455,14 +1283,14 @@ const MAX_HALVINGS: u64 = 64;
 /// Calculate maximum supply: sum of geometric series
 /// Max supply = INITIAL_REWARD * HALVING_INTERVAL * (1 + 1/2 + 1/4 + ... ≈ 2)
 /// = 1000 * 210,000 * 2 = 420,000,000 area units
-pub const MAX_SUPPLY: u64 = INITIAL_MINING_REWARD * REWARD_HALVING_INTERVAL * 2;
+pub const MAX_SUPPLY: Area = Area::from_units(INITIAL_MINING_REWARD * REWARD_HALVING_INTERVAL * 2);
 
 impl Blockchain {
     pub fn new() -> Self {
         let mut state = TriangleState::new();
         let genesis = genesis_triangle();
         let genesis_hash = genesis.hash();
-        state.utxo_set.insert(genesis_hash, genesis);
+        state.insert_triangle(genesis_hash, genesis);
 
         let mut genesis_block = Block {
             header: BlockHeader {
@@ -537,6 +1365,12 @@ impl Blockchain {
     }
 
     pub fn validate_block(&self, block: &Block) -> Result<(), ChainError> {
+        self.validate_block_state(block).map(|_| ())
+    }
+
+    /// Validate a block, returning the state with its transactions applied.
+    /// The live state is left untouched.
+    fn validate_block_state(&self, block: &Block) -> Result<TriangleState, ChainError> {
         if !self.block_index.contains_key(&block.header.previous_hash) {
             return Err(ChainError::InvalidBlockLinkage);
         }
@@ -576,11 +1410,11 @@ impl Blockchain {
 
         // Validate coinbase transaction rules
         let mut coinbase_count = 0;
-        let mut coinbase_reward = 0u64;
+        let mut coinbase_reward = Area::ZERO;
         for (i, tx) in block.transactions.iter().enumerate() {
             if let Transaction::Coinbase(coinbase_tx) = tx {
                 coinbase_count += 1;
-                coinbase_reward = coinbase_tx.reward_area;
+                coinbase_reward = coinbase_tx.reward();
                 // Coinbase must be the first transaction
                 if i != 0 {
                     return Err(ChainError::InvalidTransaction(
@@ -599,11 +1433,11 @@ impl Blockchain {
 
         // Validate coinbase reward doesn't exceed block reward + fees
         if block.header.height > 0 {
-            let block_reward = Self::calculate_block_reward(block.header.height);
+            let block_reward = Area::from_units(Self::calculate_block_reward(block.header.height));
             let total_fees = Self::calculate_total_fees(&block.transactions);
 
-            // Use saturating_add to prevent integer overflow
-            let max_reward = block_reward.saturating_add(total_fees);
+            // u128 quanta cannot overflow for any sum of u64 unit amounts
+            let max_reward = block_reward + total_fees;
 
             if coinbase_reward > max_reward {
                 return Err(ChainError::InvalidTransaction(
@@ -613,35 +1447,90 @@ impl Blockchain {
             }
         }
 
+        // Stateless phase: verify every signature in the block in parallel.
+        // Verified signatures land in the signature cache, along with those
+        // checked when the transactions entered the mempool, so the state
+        // phase below finds them there instead of verifying them again.
+        block.transactions.par_iter().try_for_each(Transaction::verify_signatures)?;
+
+        // State phase: inputs, spend conditions and geometry, in block order.
+        // Each transaction is checked against a working copy of the state with
+        // every earlier transaction applied, so a triangle cannot be spent
+        // twice and may be spent after the transaction creating it.
+        let mut state = self.state.clone();
         for tx in block.transactions.iter() {
             match tx {
                 Transaction::Subdivision(tx) => {
-                    if !self.state.utxo_set.contains_key(&tx.parent_hash) {
-                        return Err(ChainError::InvalidTransaction(
+                    let parent = state.get(&tx.parent_hash).ok_or_else(|| {
+                        ChainError::InvalidTransaction(
                             format!("Parent triangle {} not in UTXO set", hex::encode(tx.parent_hash))
-                        ));
-                    }
-                    tx.validate(&self.state)?;
+                        )
+                    })?;
+                    crate::script::authorize_spend(
+                        &parent.owner,
+                        &tx.owner_address,
+                        tx.public_key.as_deref(),
+                        tx.script_spend.as_ref(),
+                        &tx.signable_message(),
+                        block.header.height,
+                    )?;
+                    tx.validate(&state)?;
+                    Self::check_no_overlap(&state, &tx.children, &[tx.parent_hash])?;
+                    state.apply_subdivision(tx)?;
                 },
                 Transaction::Coinbase(cb_tx) => {
                     cb_tx.validate()?;
+                    let triangle = TriangleState::coinbase_triangle(cb_tx, block.header.height)?;
+                    Self::check_no_overlap(&state, &[triangle], &[])?;
+                    state.apply_coinbase(cb_tx, block.header.height)?;
                 },
                 Transaction::Transfer(tx) => {
-                    if !self.state.utxo_set.contains_key(&tx.input_hash) {
-                        return Err(ChainError::InvalidTransaction(
+                    let input = state.get(&tx.input_hash).ok_or_else(|| {
+                        ChainError::InvalidTransaction(
                             format!("Transfer input {} not in UTXO set", hex::encode(tx.input_hash))
-                        ));
-                    }
+                        )
+                    })?;
+                    crate::script::authorize_spend(
+                        &input.owner,
+                        &tx.sender,
+                        tx.public_key.as_deref(),
+                        tx.script_spend.as_ref(),
+                        &tx.signable_message(),
+                        block.header.height,
+                    )?;
                     tx.validate()?;
+                    state.apply_transfer(tx)?;
                 },
             }
         }
 
+        Ok(state)
+    }
+
+    /// Consensus invariant: live triangles never overlap. Triangles a
+    /// transaction creates must not overlap the state it applies to, other
+    /// than the parent it spends, nor each other.
+    fn check_no_overlap(state: &TriangleState, created: &[Triangle], spent: &[Sha256Hash]) -> Result<(), ChainError> {
+        for (i, triangle) in created.iter().enumerate() {
+            if let Some(existing) = state.find_overlap(triangle, spent) {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "New triangle {} overlaps existing triangle {}",
+                    triangle.hash_str(), hex::encode(existing)
+                )));
+            }
+            if created[..i].iter().any(|other| other.overlaps(triangle)) {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "New triangle {} overlaps another triangle created in the same block",
+                    triangle.hash_str()
+                )));
+            }
+        }
         Ok(())
     }
 
     pub fn apply_block(&mut self, valid_block: Block) -> Result<(), ChainError> {
-        self.validate_block(&valid_block)?;
+        // Nothing touches the live state until the whole block has validated
+        let new_state = self.validate_block_state(&valid_block)?;
 
         let parent_hash = valid_block.header.previous_hash;
         let last_block_hash = self.blocks.last().expect("Blockchain should have at least a genesis block").hash;
@@ -653,23 +1542,7 @@ impl Blockchain {
                 .map(|tx| tx.hash())
                 .collect();
 
-            for tx in valid_block.transactions.iter() {
-                match tx {
-                    Transaction::Subdivision(sub_tx) => {
-                        self.state.apply_subdivision(sub_tx)?;
-                    },
-                    Transaction::Coinbase(cb_tx) => {
-                        self.state.apply_coinbase(cb_tx, valid_block.header.height)?;
-                    },
-                    Transaction::Transfer(tx) => {
-                        let triangle = self.state.utxo_set.get_mut(&tx.input_hash)
-                            .ok_or_else(|| ChainError::TriangleNotFound(
-                                format!("Transfer input {} missing from UTXO set", hex::encode(tx.input_hash))
-                            ))?;
-                        triangle.owner = tx.new_owner.clone();
-                    }
-                }
-            }
+            self.state = new_state;
 
             let block_height = valid_block.header.height;
             self.blocks.push(valid_block.clone());
@@ -681,8 +1554,10 @@ impl Blockchain {
                 self.adjust_difficulty();
             }
 
+            self.mempool.remove_conflicts(&valid_block.transactions);
             self.mempool.remove_transactions(&tx_hashes);
             self.mempool.validate_and_prune(&self.state);
+            self.mempool.expire(Utc::now().timestamp());
 
         } else if self.block_index.contains_key(&parent_hash) {
             // Case 2: The new block creates a fork
@@ -752,11 +1627,11 @@ impl Blockchain {
 
     /// Builds a new TriangleState by replaying all transactions from a given chain of blocks.
     /// This is a pure function and doesn't modify the blockchain's current state.
-    fn build_state_for_chain(blocks: &[Block]) -> Result<TriangleState, ChainError> {
+    pub(crate) fn build_state_for_chain(blocks: &[Block]) -> Result<TriangleState, ChainError> {
         let mut new_state = TriangleState::new();
         // Initialize with genesis triangle
         let genesis = genesis_triangle();
-        new_state.utxo_set.insert(genesis.hash(), genesis);
+        new_state.insert_triangle(genesis.hash(), genesis);
 
         // Replay all transactions, skipping the genesis block (as it has no transactions)
         for block in blocks.iter().skip(1) {
@@ -769,11 +1644,7 @@ impl Blockchain {
                         new_state.apply_coinbase(cb_tx, block.header.height)?;
                     }
                     Transaction::Transfer(transfer_tx) => {
-                        let triangle = new_state.utxo_set.get_mut(&transfer_tx.input_hash)
-                            .ok_or_else(|| ChainError::TriangleNotFound(
-                                format!("During fork rebuild, transfer input {} not found", hex::encode(transfer_tx.input_hash))
-                            ))?;
-                        triangle.owner = transfer_tx.new_owner.clone();
+                        new_state.apply_transfer(transfer_tx)?;
                     }
                 }
             }
@@ -793,9 +1664,9 @@ impl Blockchain {
 
     /// Calculate the total supply that has been mined up to a given block height
     /// This accounts for all halvings that have occurred
-    pub fn calculate_current_supply(height: BlockHeight) -> u64 {
+    pub fn calculate_current_supply(height: BlockHeight) -> Area {
         if height == 0 {
-            return 0;
+            return Area::ZERO;
         }
 
         let mut total_supply = 0u64;
@@ -807,11 +1678,11 @@ impl Blockchain {
             current_height += 1;
         }
 
-        total_supply
+        Area::from_units(total_supply)
     }
 
     /// Calculate remaining supply that can still be mined
-    pub fn calculate_remaining_supply(&self) -> u64 {
+    pub fn calculate_remaining_supply(&self) -> Area {
         let current = Self::calculate_current_supply(self.blocks.last().expect("Chain cannot be empty").header.height);
         MAX_SUPPLY.saturating_sub(current)
     }
@@ -819,7 +1690,7 @@ impl Blockchain {
     /// Get percentage of total supply mined
     pub fn supply_percentage(&self) -> f64 {
         let current = Self::calculate_current_supply(self.blocks.last().expect("Chain cannot be empty").header.height);
-        (current as f64 / MAX_SUPPLY as f64) * 100.0
+        (current.to_f64() / MAX_SUPPLY.to_f64()) * 100.0
     }
 
     /// Get the current halving era (0 = first era, 1 = first halving, etc.)
@@ -834,12 +1705,24 @@ impl Blockchain {
         next_halving_height.saturating_sub(current_height)
     }
 
+    /// The reward a coinbase should claim at `height` for a block carrying
+    /// `transactions`: the scheduled reward plus fees, capped at the maximum
+    /// coinbase reward.
+    pub fn expected_coinbase_reward(height: BlockHeight, transactions: &[Transaction]) -> u64 {
+        let fees = transactions.iter()
+            .filter(|tx| !matches!(tx, Transaction::Coinbase(_)))
+            .fold(0u64, |acc, tx| acc.saturating_add(tx.fee()));
+        Self::calculate_block_reward(height)
+            .saturating_add(fees)
+            .min(CoinbaseTx::MAX_REWARD_AREA)
+    }
+
     /// Calculate total transaction fees in a block
-    pub fn calculate_total_fees(transactions: &[Transaction]) -> u64 {
+    pub fn calculate_total_fees(transactions: &[Transaction]) -> Area {
         transactions.iter()
             .filter(|tx| !matches!(tx, Transaction::Coinbase(_)))
-            .map(|tx| tx.fee())
-            .fold(0u64, |acc, fee| acc.saturating_add(fee))
+            .map(Transaction::fee_area)
+            .sum()
     }
 
     fn adjust_difficulty(&mut self) {
@@ -892,16 +1775,16 @@ impl Blockchain {
 mod tests {
     use super::*;
     use crate::transaction::{SubdivisionTx, Transaction};
-    use crate::crypto::KeyPair;
+    use crate::crypto::{KeyPair, SignatureScheme};
 
     #[test]
     fn test_genesis_triangle_is_canonical() {
         let genesis = genesis_triangle();
-        assert_eq!(genesis.a.x, 0.0);
-        assert_eq!(genesis.a.y, 0.0);
-        assert_eq!(genesis.b.x, 1.0);
-        assert_eq!(genesis.c.x, 0.5);
-        assert!((genesis.c.y - 0.866025403784).abs() < 1e-10);
+        assert_eq!(genesis.a.x, Coord::ZERO);
+        assert_eq!(genesis.a.y, Coord::ZERO);
+        assert_eq!(genesis.b.x, Coord::ONE);
+        assert_eq!(genesis.c.x, Coord::from_f64(0.5));
+        assert_eq!(genesis.c.y, Coord::from_f64(0.866025403784));
     }
 
     #[test]
@@ -969,12 +1852,11 @@ mod tests {
         let mut chain = Blockchain::new();
         let initial_count = chain.state.count();
 
-        let genesis_hash = *chain.state.utxo_set.keys().next().expect("Test setup should ensure this exists");
-        let genesis_tri = chain.state.utxo_set.get(&genesis_hash).expect("Test setup should ensure this exists").clone();
-        let children = genesis_tri.subdivide();
-
         let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
         let address = keypair.address();
+        let genesis_tri = give_genesis_to(&mut chain, &keypair);
+        let genesis_hash = genesis_tri.hash();
+        let children = genesis_tri.subdivide();
 
         let mut tx = SubdivisionTx::new(genesis_hash, children.to_vec(), address.clone(), 0, 1);
         let message = tx.signable_message();
@@ -1019,14 +1901,76 @@ mod tests {
     }
 
     #[test]
-    fn test_block_validation_success() {
-        let chain = Blockchain::new();
-        let genesis_hash = *chain.state.utxo_set.keys().next().expect("Test setup should ensure this exists");
-        let genesis_tri = chain.state.utxo_set.get(&genesis_hash).expect("Test setup should ensure this exists").clone();
-        let children = genesis_tri.subdivide();
+    fn test_coinbase_triangle_has_exact_reward_area() {
+        for reward in [1, 7, 31, 125, 997, 1000] {
+            let coinbase = CoinbaseTx { reward_area: reward, beneficiary_address: "miner".to_string() };
+            let triangle = TriangleState::coinbase_triangle(&coinbase, 3).expect("Reward should be valid");
+            assert_eq!(triangle.area(), coinbase.reward());
+            assert!(triangle.bounding_box().max.x - triangle.a.x <= Coord::from_int(45));
+        }
+
+        let zero = CoinbaseTx { reward_area: 0, beneficiary_address: "miner".to_string() };
+        assert!(TriangleState::coinbase_triangle(&zero, 3).is_err());
+    }
 
+    #[test]
+    fn test_state_area_totals_are_exact() {
+        let mut state = TriangleState::new();
+        let genesis = genesis_triangle();
+        let mut expected_total = genesis.area();
+        state.insert_triangle(genesis.hash(), genesis.clone());
+
+        // Subdivide repeatedly; the exact total keeps exactly 3/4 of each parent
+        let mut parent = genesis;
+        for _ in 0..10 {
+            let children = parent.subdivide();
+            state.remove_triangle(&parent.hash());
+            for child in &children {
+                state.insert_triangle(child.hash(), child.clone());
+            }
+            expected_total = expected_total - parent.area() + children.iter().map(Triangle::area).sum();
+            parent = children[0].clone();
+        }
+        assert_eq!(state.total_area(), expected_total);
+        assert_eq!(state.balance_of("genesis_owner"), expected_total);
+        assert_eq!(state.balance_of("nobody"), Area::ZERO);
+    }
+
+    #[test]
+    fn test_apply_transfer_replaces_metadata() {
+        use crate::metadata::TriangleMetadata;
+
+        let mut state = TriangleState::new();
+        let genesis = genesis_triangle();
+        let hash = genesis.hash();
+        state.insert_triangle(hash, genesis);
+
+        let plain = TransferTx::new(hash, "bob".to_string(), "genesis_owner".to_string(), 0, 1);
+        state.apply_transfer(&plain).expect("Transfer should apply");
+        assert!(state.utxo_set()[&hash].metadata.is_none());
+
+        let metadata = TriangleMetadata::new().with_name("Genesis");
+        let inscribed = TransferTx::new(hash, "carol".to_string(), "bob".to_string(), 0, 2)
+            .with_metadata(metadata.clone())
+            .expect("Metadata should be valid");
+        state.apply_transfer(&inscribed).expect("Transfer should apply");
+        assert_eq!(state.utxo_set()[&hash].owner, "carol");
+        assert_eq!(state.utxo_set()[&hash].metadata, Some(metadata.clone()));
+        assert_eq!(state.utxo_set()[&hash].hash(), hash);
+
+        // Later transfers without metadata keep the inscription
+        state.apply_transfer(&plain).expect("Transfer should apply");
+        assert_eq!(state.utxo_set()[&hash].metadata, Some(metadata));
+    }
+
+    #[test]
+    fn test_block_validation_success() {
+        let mut chain = Blockchain::new();
         let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
         let address = keypair.address();
+        let genesis_tri = give_genesis_to(&mut chain, &keypair);
+        let genesis_hash = genesis_tri.hash();
+        let children = genesis_tri.subdivide();
 
         let mut tx = SubdivisionTx::new(genesis_hash, children.to_vec(), address.clone(), 0, 1);
         let message = tx.signable_message();
@@ -1064,6 +2008,222 @@ mod tests {
         assert!(chain.validate_block(&new_block).is_ok());
     }
 
+    #[test]
+    fn test_block_validation_rejects_foreign_key() {
+        let mut chain = Blockchain::new();
+        let victim = KeyPair::generate().expect("Test setup should ensure this exists");
+        let genesis = give_genesis_to(&mut chain, &victim);
+
+        // The attacker names the victim as sender but can only sign with
+        // their own key
+        let attacker = KeyPair::generate().expect("Test setup should ensure this exists");
+        let mut theft = TransferTx::new(genesis.hash(), attacker.address(), victim.address(), 0, 1);
+        let signature = attacker.sign(&theft.signable_message()).expect("Test setup should ensure this exists");
+        theft.sign(signature, attacker.public_key_bytes());
+        assert!(theft.validate().is_ok());
+
+        let coinbase = CoinbaseTx { reward_area: 1000, beneficiary_address: attacker.address() };
+        let last_block = chain.blocks.last().expect("Test setup should ensure this exists");
+        let mut block = Block::new(
+            last_block.header.height + 1,
+            last_block.hash,
+            chain.difficulty,
+            vec![Transaction::Coinbase(coinbase), Transaction::Transfer(theft)],
+        );
+        block.header.timestamp = last_block.header.timestamp + 1;
+        block.hash = block.calculate_hash();
+        while !block.verify_proof_of_work() {
+            block.header.nonce += 1;
+            block.hash = block.calculate_hash();
+        }
+        let result = chain.validate_block(&block);
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("Signing key")));
+        assert!(chain.apply_block(block).is_err());
+        assert_eq!(chain.state.get(&genesis.hash()).map(|t| t.owner.clone()), Some(victim.address()));
+    }
+
+    /// A mined block on the chain's tip paying `miner` and holding `transactions`
+    fn mine_block(chain: &Blockchain, miner: &KeyPair, transactions: Vec<Transaction>) -> Block {
+        let coinbase = CoinbaseTx { reward_area: 1000, beneficiary_address: miner.address() };
+        let last_block = chain.blocks.last().expect("Test setup should ensure this exists");
+        let mut block = Block::new(
+            last_block.header.height + 1,
+            last_block.hash,
+            chain.difficulty,
+            std::iter::once(Transaction::Coinbase(coinbase)).chain(transactions).collect(),
+        );
+        block.header.timestamp = last_block.header.timestamp + 1;
+        block.hash = block.calculate_hash();
+        while !block.verify_proof_of_work() {
+            block.header.nonce += 1;
+            block.hash = block.calculate_hash();
+        }
+        block
+    }
+
+    #[test]
+    fn test_block_validation_applies_transactions_in_order() {
+        let mut chain = Blockchain::new();
+        let (owner, subdivision, recipient, transfer) = unconfirmed_chain(0);
+        give_genesis_to(&mut chain, &owner);
+        let before = chain.state.utxo_set().clone();
+
+        // Two spends of the same triangle in one block
+        let mut second = TransferTx::new(subdivision.parent_hash, recipient.address(), owner.address(), 0, 9);
+        let signature = owner.sign(&second.signable_message()).expect("Test setup should ensure this exists");
+        second.sign(signature, owner.public_key_bytes());
+        let double_spend = mine_block(&chain, &owner, vec![
+            Transaction::Subdivision(subdivision.clone()),
+            Transaction::Transfer(second),
+        ]);
+        assert!(chain.validate_block(&double_spend).is_err());
+        assert!(chain.apply_block(double_spend).is_err());
+        assert_eq!(chain.state.utxo_set(), &before);
+        assert_eq!(chain.blocks.len(), 1);
+
+        // A transfer of a child created earlier in the same block
+        let block = mine_block(&chain, &owner, vec![
+            Transaction::Subdivision(subdivision),
+            Transaction::Transfer(transfer.clone()),
+        ]);
+        chain.apply_block(block).expect("Test setup should ensure this exists");
+        assert_eq!(chain.state.get(&transfer.input_hash).map(|t| t.owner.clone()), Some(recipient.address()));
+    }
+
+    #[test]
+    fn test_mempool_signatures_are_cached_for_block_validation() {
+        let mut chain = Blockchain::new();
+        let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
+        let genesis = give_genesis_to(&mut chain, &keypair);
+        let genesis_hash = genesis.hash();
+        let children = genesis.subdivide();
+        let mut tx = SubdivisionTx::new(genesis_hash, children.to_vec(), keypair.address(), 0, 1);
+        let signature = keypair.sign_schnorr(&tx.signable_message()).expect("Test setup should ensure this exists");
+        tx.sign_with_scheme(SignatureScheme::Schnorr, signature.clone(), keypair.public_key_bytes());
+
+        let cache = crate::sigcache::global();
+        assert!(!cache.contains(SignatureScheme::Schnorr, &keypair.public_key_bytes(), &tx.signable_message(), &signature));
+        chain.mempool.add_transaction(Transaction::Subdivision(tx.clone())).expect("Test setup should ensure this exists");
+        assert!(cache.contains(SignatureScheme::Schnorr, &keypair.public_key_bytes(), &tx.signable_message(), &signature));
+
+        let coinbase = CoinbaseTx { reward_area: 1000, beneficiary_address: keypair.address() };
+        let last_block = chain.blocks.last().expect("Test setup should ensure this exists");
+        let mut block = Block::new(
+            last_block.header.height + 1,
+            last_block.hash,
+            chain.difficulty,
+            vec![Transaction::Coinbase(coinbase), Transaction::Subdivision(tx.clone())],
+        );
+        block.header.timestamp = last_block.header.timestamp + 1;
+        block.hash = block.calculate_hash();
+        while !block.verify_proof_of_work() {
+            block.header.nonce += 1;
+            block.hash = block.calculate_hash();
+        }
+        assert!(chain.validate_block(&block).is_ok());
+
+        // A signature that never verified is not served from the cache
+        let mut forged = tx;
+        forged.fee = 1;
+        block.transactions[1] = Transaction::Subdivision(forged);
+        assert!(matches!(
+            block.transactions.par_iter().try_for_each(Transaction::verify_signatures),
+            Err(ChainError::InvalidTransaction(_))
+        ));
+    }
+
+    #[test]
+    fn test_block_validation_script_locked_transfer() {
+        use crate::script::{Script, ScriptSpend, Witness, WitnessSignature};
+        use crate::transaction::TransferTx;
+
+        let mut chain = Blockchain::new();
+        let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
+        let script = Script::All(vec![
+            Script::Key(keypair.address()),
+            Script::AfterHeight(1),
+        ]);
+        let genesis_hash = *chain.state.utxo_set().keys().next().expect("Test setup should ensure this exists");
+        let mut locked = chain.state.remove_triangle(&genesis_hash).expect("Test setup should ensure this exists");
+        locked.owner = script.address();
+        chain.state.insert_triangle(genesis_hash, locked);
+
+        let build_block = |chain: &Blockchain, transfer: TransferTx| {
+            let coinbase = CoinbaseTx {
+                reward_area: 1000,
+                beneficiary_address: keypair.address(),
+            };
+            let last_block = chain.blocks.last().expect("Test setup should ensure this exists");
+            let mut block = Block::new(
+                last_block.header.height + 1,
+                last_block.hash,
+                chain.difficulty,
+                vec![Transaction::Coinbase(coinbase), Transaction::Transfer(transfer)],
+            );
+            block.header.timestamp = last_block.header.timestamp + 1;
+            block.hash = block.calculate_hash();
+            while !block.verify_proof_of_work() {
+                block.header.nonce += 1;
+                block.hash = block.calculate_hash();
+            }
+            block
+        };
+
+        let transfer = TransferTx::new(genesis_hash, "ab".repeat(32), script.address(), 0, 1);
+        let unlocked = build_block(&chain, transfer.clone());
+        assert!(chain.validate_block(&unlocked).is_err());
+
+        let witness = Witness {
+            signatures: vec![WitnessSignature {
+                public_key: keypair.public_key_bytes(),
+                signature: keypair.sign(&transfer.signable_message()).expect("Test setup should ensure this exists"),
+                scheme: SignatureScheme::Ecdsa,
+            }],
+            preimages: vec![],
+        };
+        let transfer = transfer.with_script_spend(ScriptSpend::new(script, witness));
+        let block = build_block(&chain, transfer);
+        assert!(chain.validate_block(&block).is_ok());
+    }
+
+    #[test]
+    fn test_block_validation_rejects_overlapping_coinbase() {
+        let mut chain = Blockchain::new();
+        let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
+        let coinbase = CoinbaseTx {
+            reward_area: 1000,
+            beneficiary_address: keypair.address(),
+        };
+
+        // Occupy the spot where the next reward triangle will be placed
+        let squatter = Triangle::new(
+            Point::from_f64(1001.0, 1.0),
+            Point::from_f64(1005.0, 1.0),
+            Point::from_f64(1001.0, 5.0),
+            None,
+            "squatter".to_string(),
+        );
+        chain.state.insert_triangle(squatter.hash(), squatter.clone());
+
+        let last_block = chain.blocks.last().expect("Test setup should ensure this exists");
+        let mut block = Block::new(
+            last_block.header.height + 1,
+            last_block.hash,
+            chain.difficulty,
+            vec![Transaction::Coinbase(coinbase)],
+        );
+        block.header.timestamp = last_block.header.timestamp + 1;
+        block.hash = block.calculate_hash();
+        while !block.verify_proof_of_work() {
+            block.header.nonce += 1;
+            block.hash = block.calculate_hash();
+        }
+
+        assert!(chain.validate_block(&block).is_err());
+        chain.state.remove_triangle(&squatter.hash());
+        assert!(chain.validate_block(&block).is_ok());
+    }
+
     #[test]
     fn test_block_validation_failure_linkage() {
         let chain = Blockchain::new();
@@ -1104,8 +2264,8 @@ mod tests {
     #[test]
     fn test_block_validation_double_spend_in_block() {
         let mut chain = Blockchain::new();
-        let genesis_hash = *chain.state.utxo_set.keys().next().expect("Test setup should ensure this exists");
-        let genesis_tri = chain.state.utxo_set.get(&genesis_hash).expect("Test setup should ensure this exists").clone();
+        let genesis_hash = *chain.state.utxo_set().keys().next().expect("Test setup should ensure this exists");
+        let genesis_tri = chain.state.get(&genesis_hash).expect("Test setup should ensure this exists").clone();
         let children = genesis_tri.subdivide();
 
         let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
@@ -1237,7 +2397,7 @@ mod tests {
         let mut state = TriangleState::new();
         let genesis = genesis_triangle();
         let genesis_hash = genesis.hash();
-        state.utxo_set.insert(genesis_hash, genesis.clone());
+        state.insert_triangle(genesis_hash, genesis.clone());
         let children = genesis.subdivide();
         let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
         let address = keypair.address();
@@ -1253,13 +2413,43 @@ mod tests {
         assert!(!mempool.is_empty());
     }
 
+    #[test]
+    fn test_mempool_relay_policy_rejects_dust_subdivision() {
+        let mut mempool = Mempool::new();
+        let side = 1.0 / 8.0;
+        let parent = Triangle::new(
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(side, 0.0),
+            Point::from_f64(0.0, side),
+            None,
+            "owner".to_string(),
+        );
+        let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
+        let sign = |fee: u64| {
+            let mut tx = SubdivisionTx::new(parent.hash(), parent.subdivide().to_vec(), keypair.address(), fee, 1);
+            let signature = keypair.sign(&tx.signable_message()).expect("Test setup should ensure this exists");
+            tx.sign(signature, keypair.public_key_bytes());
+            Transaction::Subdivision(tx)
+        };
+
+        assert!(mempool.add_transaction(sign(0)).is_err());
+        assert!(mempool.add_transaction(sign(RelayPolicy::DEFAULT_MIN_DUST_SUBDIVISION_FEE)).is_ok());
+
+        let mut permissive = Mempool::with_policy(RelayPolicy {
+            dust_child_doubled_area_raw: 0,
+            min_dust_subdivision_fee: 0,
+            ..RelayPolicy::default()
+        });
+        assert!(permissive.add_transaction(sign(0)).is_ok());
+    }
+
     #[test]
     fn test_mempool_remove_transaction() {
         let mut mempool = Mempool::new();
         let mut state = TriangleState::new();
         let genesis = genesis_triangle();
         let genesis_hash = genesis.hash();
-        state.utxo_set.insert(genesis_hash, genesis.clone());
+        state.insert_triangle(genesis_hash, genesis.clone());
         let children = genesis.subdivide();
         let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
         let address = keypair.address();
@@ -1285,7 +2475,7 @@ mod tests {
         let mut state = TriangleState::new();
         let genesis = genesis_triangle();
         let genesis_hash = genesis.hash();
-        state.utxo_set.insert(genesis_hash, genesis.clone());
+        state.insert_triangle(genesis_hash, genesis.clone());
         let children = genesis.subdivide();
         let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
         let address = keypair.address();
@@ -1308,15 +2498,16 @@ mod tests {
         let mut mempool = Mempool::new();
         let mut state = TriangleState::new();
 
-        // Add genesis triangle to state
-        let genesis = genesis_triangle();
+        // Add genesis triangle to state, owned by the key subdividing it
+        let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
+        let address = keypair.address();
+        let mut genesis = genesis_triangle();
+        genesis.owner = address.clone();
         let genesis_hash = genesis.hash();
-        state.utxo_set.insert(genesis_hash, genesis.clone());
+        state.insert_triangle(genesis_hash, genesis.clone());
 
         // Create valid subdivision transaction
         let children = genesis.subdivide();
-        let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
-        let address = keypair.address();
         let mut valid_tx = SubdivisionTx::new(genesis_hash, children.to_vec(), address, 0, 1);
         let message = valid_tx.signable_message();
         let signature = keypair.sign(&message).expect("Test setup should ensure this exists");
@@ -1347,17 +2538,357 @@ mod tests {
         assert_eq!(mempool.len(), 1);
     }
 
+    /// A subdivision of the genesis triangle by a fresh key, with children
+    /// owned by that key, plus a transfer of the first child to a second key
+    fn unconfirmed_chain(fee: u64) -> (KeyPair, SubdivisionTx, KeyPair, TransferTx) {
+        let genesis = genesis_triangle();
+        let owner = KeyPair::generate().expect("Test setup should ensure this exists");
+        let children: Vec<Triangle> = genesis.subdivide().into_iter()
+            .map(|mut child| {
+                child.owner = owner.address();
+                child
+            })
+            .collect();
+        let mut subdivision = SubdivisionTx::new(genesis.hash(), children.clone(), owner.address(), fee, 1);
+        let signature = owner.sign(&subdivision.signable_message()).expect("Test setup should ensure this exists");
+        subdivision.sign(signature, owner.public_key_bytes());
+
+        let recipient = KeyPair::generate().expect("Test setup should ensure this exists");
+        let mut transfer = TransferTx::new(children[0].hash(), recipient.address(), owner.address(), fee, 2);
+        let signature = owner.sign(&transfer.signable_message()).expect("Test setup should ensure this exists");
+        transfer.sign(signature, owner.public_key_bytes());
+        (owner, subdivision, recipient, transfer)
+    }
+
+    #[test]
+    fn test_mempool_tracks_unconfirmed_parents() {
+        let (owner, subdivision, recipient, transfer) = unconfirmed_chain(10);
+        let mut state = TriangleState::new();
+        let mut genesis = genesis_triangle();
+        genesis.owner = owner.address();
+        state.insert_triangle(genesis.hash(), genesis);
+
+        let mut onward = TransferTx::new(transfer.input_hash, "cd".repeat(32), recipient.address(), 30, 3);
+        let signature = recipient.sign(&onward.signable_message()).expect("Test setup should ensure this exists");
+        onward.sign(signature, recipient.public_key_bytes());
+
+        let (subdivision, transfer, onward) = (
+            Transaction::Subdivision(subdivision),
+            Transaction::Transfer(transfer),
+            Transaction::Transfer(onward),
+        );
+        let mut mempool = Mempool::new();
+        for tx in [&subdivision, &transfer, &onward] {
+            mempool.add_transaction(tx.clone()).expect("Test setup should ensure this exists");
+        }
+
+        assert_eq!(mempool.parent_of(&subdivision.hash()), None);
+        assert_eq!(mempool.parent_of(&transfer.hash()), Some(subdivision.hash()));
+        assert_eq!(mempool.ancestors(&onward.hash()), vec![transfer.hash(), subdivision.hash()]);
+        assert_eq!(mempool.descendants(&subdivision.hash()), vec![transfer.hash(), onward.hash()]);
+
+        let ancestors = mempool.ancestor_package(&onward.hash()).expect("Test setup should ensure this exists");
+        assert_eq!((ancestors.count, ancestors.fee), (3, 50));
+        let descendants = mempool.descendant_package(&transfer.hash()).expect("Test setup should ensure this exists");
+        assert_eq!((descendants.count, descendants.fee), (2, 40));
+
+        // Outputs that exist only in the mempool keep their spenders valid
+        assert_eq!(mempool.validate_and_prune(&state), 0);
+        assert_eq!(mempool.len(), 3);
+
+        // Only the transaction spending a confirmed triangle can be mined now
+        let minable = mempool.get_transactions_by_fee(10);
+        assert_eq!(minable.len(), 1);
+        assert_eq!(minable[0].hash(), subdivision.hash());
+
+        // Once the parent is mined, its children spend confirmed triangles
+        state.apply_subdivision(match &subdivision {
+            Transaction::Subdivision(tx) => tx,
+            _ => unreachable!(),
+        }).expect("Test setup should ensure this exists");
+        mempool.remove_transactions(&[subdivision.hash()]);
+        assert_eq!(mempool.parent_of(&transfer.hash()), None);
+        assert_eq!(mempool.validate_and_prune(&state), 0);
+        assert_eq!(mempool.get_transactions_by_fee(10).len(), 1);
+
+        assert_eq!(mempool.remove_with_descendants(&transfer.hash()).len(), 2);
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn test_mempool_package_fees_saturate() {
+        let (_, subdivision, _, transfer) = unconfirmed_chain(u64::MAX);
+        let (subdivision, transfer) = (Transaction::Subdivision(subdivision), Transaction::Transfer(transfer));
+        let mut mempool = Mempool::new();
+        mempool.add_transaction(subdivision.clone()).expect("Test setup should ensure this exists");
+        mempool.add_transaction(transfer.clone()).expect("Test setup should ensure this exists");
+
+        assert_eq!(mempool.descendant_package(&subdivision.hash()).map(|p| p.fee), Some(u64::MAX));
+        assert_eq!(mempool.ancestor_package(&transfer.hash()).map(|p| p.fee), Some(u64::MAX));
+    }
+
+    #[test]
+    fn test_mempool_removes_descendants_with_parent() {
+        let mut state = TriangleState::new();
+        let genesis = genesis_triangle();
+        state.insert_triangle(genesis.hash(), genesis);
+
+        // A parent spending a triangle that is not confirmed takes its child along
+        let (_, subdivision, _, transfer) = unconfirmed_chain(0);
+        let mut mempool = Mempool::new();
+        mempool.add_transaction(Transaction::Subdivision(subdivision.clone())).expect("Test setup should ensure this exists");
+        mempool.add_transaction(Transaction::Transfer(transfer.clone())).expect("Test setup should ensure this exists");
+        assert_eq!(mempool.validate_and_prune(&TriangleState::new()), 2);
+        assert!(mempool.is_empty());
+
+        // Confirming the parent itself is not a conflict
+        mempool.add_transaction(Transaction::Subdivision(subdivision.clone())).expect("Test setup should ensure this exists");
+        mempool.add_transaction(Transaction::Transfer(transfer)).expect("Test setup should ensure this exists");
+        assert_eq!(mempool.remove_conflicts(&[Transaction::Subdivision(subdivision)]), 0);
+
+        // A confirmed spend of the same triangle conflicts with the parent,
+        // whoever it claims as the owner
+        let (_, competing, _, _) = unconfirmed_chain(5);
+        assert_eq!(mempool.remove_conflicts(&[Transaction::Subdivision(competing)]), 2);
+        assert!(mempool.is_empty());
+    }
+
+    /// Hand the chain's genesis triangle to `owner`, returning it
+    fn give_genesis_to(chain: &mut Blockchain, owner: &KeyPair) -> Triangle {
+        let genesis_hash = *chain.state.utxo_set().keys().next().expect("Test setup should ensure this exists");
+        let mut genesis = chain.state.remove_triangle(&genesis_hash).expect("Test setup should ensure this exists");
+        genesis.owner = owner.address();
+        chain.state.insert_triangle(genesis_hash, genesis.clone());
+        genesis
+    }
+
+    /// A subdivision of the genesis triangle by `owner`, optionally signalling
+    /// replaceability
+    fn genesis_subdivision(owner: &KeyPair, fee: u64, nonce: u64, replaceable: bool) -> Transaction {
+        let genesis = genesis_triangle();
+        let children: Vec<Triangle> = genesis.subdivide().into_iter()
+            .map(|mut child| {
+                child.owner = owner.address();
+                child
+            })
+            .collect();
+        let mut tx = SubdivisionTx::new(genesis.hash(), children, owner.address(), fee, nonce);
+        if replaceable {
+            tx = tx.signal_replaceable();
+        }
+        let signature = owner.sign(&tx.signable_message()).expect("Test setup should ensure this exists");
+        tx.sign(signature, owner.public_key_bytes());
+        Transaction::Subdivision(tx)
+    }
+
+    #[test]
+    fn test_mempool_rejects_conflicting_spend() {
+        let owner = KeyPair::generate().expect("Test setup should ensure this exists");
+        let mut mempool = Mempool::new();
+        let original = genesis_subdivision(&owner, 10, 1, false);
+        mempool.add_transaction(original.clone()).expect("Test setup should ensure this exists");
+
+        // Without the replaceable signal no fee is enough
+        let result = mempool.add_transaction(genesis_subdivision(&owner, 1_000, 2, true));
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("conflicts")));
+        assert_eq!(mempool.len(), 1);
+        assert!(mempool.get_transaction(&original.hash()).is_some());
+    }
+
+    #[test]
+    fn test_mempool_conflicts_ignore_claimed_sender() {
+        let genesis = genesis_triangle();
+        let recipient = KeyPair::generate().expect("Test setup should ensure this exists");
+        let transfer = |nonce: u64| {
+            let sender = KeyPair::generate().expect("Test setup should ensure this exists");
+            let mut tx = TransferTx::new(genesis.hash(), recipient.address(), sender.address(), 10, nonce);
+            let signature = sender.sign(&tx.signable_message()).expect("Test setup should ensure this exists");
+            tx.sign(signature, sender.public_key_bytes());
+            Transaction::Transfer(tx)
+        };
+
+        // Two spends of one confirmed triangle under different senders
+        let mut mempool = Mempool::new();
+        let first = transfer(1);
+        mempool.add_transaction(first.clone()).expect("Test setup should ensure this exists");
+        let result = mempool.add_transaction(transfer(2));
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("conflicts")));
+        let pending: Vec<Sha256Hash> = mempool.get_transactions_by_fee(10).iter().map(Transaction::hash).collect();
+        assert_eq!(pending, vec![first.hash()]);
+
+        // A confirmed spend under yet another sender evicts the pending one
+        assert_eq!(mempool.remove_conflicts(&[transfer(3)]), 1);
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn test_mempool_replacement_message_saturates() {
+        let owner = KeyPair::generate().expect("Test setup should ensure this exists");
+        let mut mempool = Mempool::new();
+        mempool.add_transaction(genesis_subdivision(&owner, u64::MAX, 1, true)).expect("Test setup should ensure this exists");
+        let result = mempool.add_transaction(genesis_subdivision(&owner, u64::MAX, 2, true));
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("Replacement fee")));
+    }
+
+    #[test]
+    fn test_mempool_replace_by_fee() {
+        let (owner, _, _, transfer) = unconfirmed_chain(10);
+        let original = genesis_subdivision(&owner, 10, 1, true);
+        let mut mempool = Mempool::new();
+        mempool.add_transaction(original.clone()).expect("Test setup should ensure this exists");
+        mempool.add_transaction(Transaction::Transfer(transfer)).expect("Test setup should ensure this exists");
+        assert_eq!(mempool.len(), 2);
+
+        // The fee must cover the original, its child and the increment
+        let result = mempool.add_transaction(genesis_subdivision(&owner, 20, 3, false));
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("at least 21")));
+
+        // The eviction limit applies to the whole package
+        let mut strict = mempool.clone();
+        strict.policy.max_replacement_evictions = 1;
+        let result = strict.add_transaction(genesis_subdivision(&owner, 100, 3, false));
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("evict 2")));
+
+        let replacement = genesis_subdivision(&owner, 21, 3, false);
+        mempool.add_transaction(replacement.clone()).expect("Test setup should ensure this exists");
+        assert_eq!(mempool.len(), 1);
+        assert!(mempool.get_transaction(&original.hash()).is_none());
+        assert!(mempool.get_transaction(&replacement.hash()).is_some());
+
+        // The replacement did not signal, so it cannot be replaced in turn
+        assert!(mempool.add_transaction(genesis_subdivision(&owner, 1_000, 4, true)).is_err());
+    }
+
+    #[test]
+    fn test_mempool_fee_rate_index() {
+        let (owner, _, recipient, transfer) = unconfirmed_chain(10);
+        let subdivision = genesis_subdivision(&owner, 30, 1, false);
+        let transfer = Transaction::Transfer(transfer);
+        let mut mempool = Mempool::new();
+        mempool.add_transaction(subdivision.clone()).expect("Test setup should ensure this exists");
+        mempool.add_transaction(transfer.clone()).expect("Test setup should ensure this exists");
+        assert_eq!(
+            mempool.descendant_package(&subdivision.hash()),
+            Some(PackageStats { count: 2, fee: 40, size: 410 })
+        );
+
+        // A smaller transaction paying a lower fee can still pay a higher rate
+        let mut cheap = TransferTx::new([7; 32], owner.address(), recipient.address(), 20, 1);
+        let signature = recipient.sign(&cheap.signable_message()).expect("Test setup should ensure this exists");
+        cheap.sign(signature, recipient.public_key_bytes());
+        let cheap = Transaction::Transfer(cheap);
+        mempool.add_transaction(cheap.clone()).expect("Test setup should ensure this exists");
+        let hashes: Vec<Sha256Hash> = mempool.get_transactions_by_fee(10).iter().map(Transaction::hash).collect();
+        assert_eq!(hashes, vec![cheap.hash(), subdivision.hash()]);
+
+        // Confirming the parent makes its child minable
+        mempool.remove_transaction(&subdivision.hash());
+        let hashes: Vec<Sha256Hash> = mempool.get_transactions_by_fee(10).iter().map(Transaction::hash).collect();
+        assert_eq!(hashes, vec![cheap.hash(), transfer.hash()]);
+        assert_eq!(mempool.descendant_package(&transfer.hash()).map(|p| p.count), Some(1));
+    }
+
+    #[test]
+    fn test_mempool_per_address_limit() {
+        let sender = KeyPair::generate().expect("Test setup should ensure this exists");
+        let transfer = |nonce: u64| {
+            let mut tx = TransferTx::new([nonce as u8; 32], "ab".repeat(32), sender.address(), 1, nonce);
+            let signature = sender.sign(&tx.signable_message()).expect("Test setup should ensure this exists");
+            tx.sign(signature, sender.public_key_bytes());
+            Transaction::Transfer(tx)
+        };
+        let mut mempool = Mempool::new();
+        for nonce in 0..Mempool::MAX_PER_ADDRESS as u64 {
+            mempool.add_transaction(transfer(nonce)).expect("Test setup should ensure this exists");
+        }
+
+        let extra = transfer(Mempool::MAX_PER_ADDRESS as u64);
+        assert!(mempool.add_transaction(extra.clone()).is_err());
+        mempool.remove_transaction(&transfer(0).hash());
+        assert!(mempool.add_transaction(extra).is_ok());
+    }
+
+    #[test]
+    fn test_mempool_with_state_counts_recorded_owner() {
+        let (owner, subdivision, _, transfer) = unconfirmed_chain(1);
+        let mut genesis = genesis_triangle();
+        genesis.owner = owner.address();
+        let mut state = TriangleState::new();
+        state.insert_triangle(genesis.hash(), genesis.clone());
+        let mut mempool = Mempool::new();
+
+        // A spend of a confirmed triangle under a sender that does not own it
+        let forger = KeyPair::generate().expect("Test setup should ensure this exists");
+        let mut forged = TransferTx::new(genesis.hash(), forger.address(), forger.address(), 1, 1);
+        let signature = forger.sign(&forged.signable_message()).expect("Test setup should ensure this exists");
+        forged.sign(signature, forger.public_key_bytes());
+        let result = mempool.add_transaction_with_state(Transaction::Transfer(forged), &state);
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("not owned")));
+
+        // Naming the owner as sender does not make the forger's key the owner's,
+        // with or without the state
+        let mut impersonation = TransferTx::new(genesis.hash(), forger.address(), owner.address(), 1, 2);
+        let signature = forger.sign(&impersonation.signable_message()).expect("Test setup should ensure this exists");
+        impersonation.sign(signature, forger.public_key_bytes());
+        let impersonation = Transaction::Transfer(impersonation);
+        for result in [
+            mempool.add_transaction_with_state(impersonation.clone(), &state),
+            mempool.add_transaction(impersonation),
+        ] {
+            assert!(matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("signing address")));
+        }
+        assert!(mempool.sender_counts.is_empty());
+        let unknown = genesis_subdivision(&owner, 1, 1, false);
+        let result = mempool.add_transaction_with_state(unknown, &TriangleState::new());
+        assert!(matches!(result, Err(ChainError::TriangleNotFound(_))));
+        assert!(mempool.is_empty());
+
+        // The confirmed input and the child created in the pool both count
+        // against the owner they are recorded under
+        mempool.add_transaction_with_state(Transaction::Subdivision(subdivision), &state).expect("Test setup should ensure this exists");
+        mempool.add_transaction_with_state(Transaction::Transfer(transfer), &state).expect("Test setup should ensure this exists");
+        assert_eq!(mempool.sender_counts, HashMap::from([(owner.address(), 2)]));
+    }
+
+    #[test]
+    fn test_mempool_expiry() {
+        let (_, subdivision, _, transfer) = unconfirmed_chain(0);
+        let mut mempool = Mempool::new();
+        mempool.add_transaction_at(Transaction::Subdivision(subdivision), 1_000).expect("Test setup should ensure this exists");
+        mempool.add_transaction(Transaction::Transfer(transfer.clone())).expect("Test setup should ensure this exists");
+        let expiry = RelayPolicy::DEFAULT_MEMPOOL_EXPIRY_SECS as i64;
+
+        assert_eq!(mempool.expire(1_000 + expiry - 1), 0);
+        // The child has not expired itself but cannot outlive its parent
+        assert_eq!(mempool.expire(1_000 + expiry), 2);
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn test_mempool_indexes_survive_serialization() {
+        let (_, subdivision, _, transfer) = unconfirmed_chain(0);
+        let mut mempool = Mempool::new();
+        mempool.add_transaction(Transaction::Subdivision(subdivision.clone())).expect("Test setup should ensure this exists");
+        mempool.add_transaction(Transaction::Transfer(transfer.clone())).expect("Test setup should ensure this exists");
+
+        let bytes = bincode::serialize(&mempool).expect("Test setup should ensure this exists");
+        let restored: Mempool = bincode::deserialize(&bytes).expect("Test setup should ensure this exists");
+        let parent = Transaction::Subdivision(subdivision).hash();
+        assert_eq!(restored.children_of(&parent), vec![Transaction::Transfer(transfer).hash()]);
+        assert_eq!(restored.descendant_package(&parent).map(|p| p.count), Some(2));
+    }
+
     #[test]
     fn test_blockchain_with_mempool() {
         let mut chain = Blockchain::new();
         assert!(chain.mempool.is_empty());
 
         // Add a transaction to mempool
-        let genesis = genesis_triangle();
-        let genesis_hash = genesis.hash();
-        let children = genesis.subdivide();
         let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
         let address = keypair.address();
+        let genesis = give_genesis_to(&mut chain, &keypair);
+        let genesis_hash = genesis.hash();
+        let children = genesis.subdivide();
         let mut valid_tx = SubdivisionTx::new(genesis_hash, children.to_vec(), address, 0, 1);
         let message = valid_tx.signable_message();
         let signature = keypair.sign(&message).expect("Test setup should ensure this exists");
@@ -1371,7 +2902,7 @@ mod tests {
         let last_block = chain.blocks.last().expect("Test setup should ensure this exists");
         let coinbase = CoinbaseTx {
             reward_area: 1000,
-            beneficiary_address: "miner_address".to_string(),
+            beneficiary_address: "cd".repeat(32),
         };
         let mut new_block = Block::new(
             last_block.header.height + 1,
@@ -1446,6 +2977,10 @@ mod tests {
             memo: None,
             signature: None,
             public_key: None,
+            signature_scheme: SignatureScheme::Ecdsa,
+            script_spend: None,
+            metadata: None,
+            replaceable: false,
         };
         let tx2 = Transaction::Transfer(transfer_tx);
         assert_eq!(tx2.fee(), 50);
@@ -1453,7 +2988,7 @@ mod tests {
         // Test total fees calculation
         let transactions = vec![tx1, tx2];
         let total_fees = Blockchain::calculate_total_fees(&transactions);
-        assert_eq!(total_fees, 150);
+        assert_eq!(total_fees, Area::from_units(150));
     }
 
     #[test]
@@ -1462,14 +2997,14 @@ mod tests {
 
         let mut chain = Blockchain::new();
         let genesis = genesis_triangle();
-        let genesis_hash = genesis.hash();
-        let children = genesis.subdivide();
+        let grandchildren: Vec<Triangle> = genesis.subdivide().iter().flat_map(Triangle::subdivide).collect();
         let keypair = KeyPair::generate().expect("Test setup should ensure this exists");
         let address = keypair.address();
 
-        // Create transactions with different fees
+        // Create non-conflicting transactions with different fees
         for (i, fee) in [10u64, 50, 25, 100, 5].iter().enumerate() {
-            let mut tx = SubdivisionTx::new(genesis_hash, children.to_vec(), address.clone(), *fee, i as u64);
+            let parent = &grandchildren[i];
+            let mut tx = SubdivisionTx::new(parent.hash(), parent.subdivide().to_vec(), address.clone(), *fee, i as u64);
             let message = tx.signable_message();
             let signature = keypair.sign(&message).expect("Test setup should ensure this exists");
             let public_key = keypair.public_key.serialize().to_vec();
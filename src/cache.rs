@@ -7,7 +7,7 @@
 //! - Block hashes
 
 use crate::blockchain::{Block, Sha256Hash};
-use crate::geometry::Triangle;
+use crate::geometry::{Area, Triangle};
 use lru::LruCache;
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
@@ -116,7 +116,7 @@ impl Clone for UtxoCache {
 
 /// Cache for address balances
 pub struct BalanceCache {
-    cache: Arc<RwLock<HashMap<String, f64>>>,
+    cache: Arc<RwLock<HashMap<String, Area>>>,
 }
 
 impl BalanceCache {
@@ -128,13 +128,13 @@ impl BalanceCache {
     }
 
     /// Get cached balance for address
-    pub async fn get(&self, address: &str) -> Option<f64> {
+    pub async fn get(&self, address: &str) -> Option<Area> {
         let cache = self.cache.read().await;
         cache.get(address).copied()
     }
 
     /// Set balance for address
-    pub async fn set(&self, address: String, balance: f64) {
+    pub async fn set(&self, address: String, balance: Area) {
         let mut cache = self.cache.write().await;
         cache.insert(address, balance);
     }
@@ -240,9 +240,10 @@ mod tests {
         let cache = BalanceCache::new();
         let addr = "test_address".to_string();
 
-        cache.set(addr.clone(), 100.5).await;
+        let area = Area::parse_decimal("100.5").unwrap();
+        cache.set(addr.clone(), area).await;
         let balance = cache.get(&addr).await;
-        assert_eq!(balance, Some(100.5));
+        assert_eq!(balance, Some(area));
 
         cache.invalidate(&addr).await;
         let balance = cache.get(&addr).await;
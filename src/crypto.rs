@@ -1,10 +1,68 @@
 //! Cryptographic primitives for TrinityChain
 
 use sha2::{Digest, Sha256};
-use secp256k1::{Secp256k1, SecretKey, PublicKey, Message, ecdsa::Signature};
+use std::sync::LazyLock;
+use secp256k1::{Secp256k1, SecretKey, PublicKey, Message, VerifyOnly, ecdsa::Signature};
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{schnorr, Keypair as SchnorrKeypair, XOnlyPublicKey};
 use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
 use crate::error::ChainError;
 
+/// Verification context shared by every signature check. Building a context
+/// precomputes tables, so it is done once rather than per signature.
+static VERIFICATION_CONTEXT: LazyLock<Secp256k1<VerifyOnly>> = LazyLock::new(Secp256k1::verification_only);
+
+/// The shared secp256k1 verification context
+pub fn verification_context() -> &'static Secp256k1<VerifyOnly> {
+    &VERIFICATION_CONTEXT
+}
+
+/// Length of a BIP-340 Schnorr signature
+pub const SCHNORR_SIGNATURE_LEN: usize = 64;
+
+/// Length of an x-only public key
+pub const X_ONLY_PUBLIC_KEY_LEN: usize = 32;
+
+/// Signature scheme of a transaction or witness signature.
+///
+/// Both schemes sign the SHA-256 digest of the same message with the same
+/// secp256k1 key, and the signer's public key is carried in compressed form
+/// either way, so a key has one address whichever scheme it signs with.
+/// Schnorr signatures are verified against the x-only form of that key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    /// Compact ECDSA, the scheme of every signature made before Schnorr support
+    #[default]
+    Ecdsa,
+    /// BIP-340 Schnorr
+    Schnorr,
+}
+
+impl SignatureScheme {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SignatureScheme::Ecdsa => "ecdsa",
+            SignatureScheme::Schnorr => "schnorr",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ecdsa" => Some(SignatureScheme::Ecdsa),
+            "schnorr" => Some(SignatureScheme::Schnorr),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SignatureScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct KeyPair {
     pub secret_key: SecretKey,
@@ -39,10 +97,7 @@ impl KeyPair {
     }
     
     pub fn address(&self) -> String {
-        let pubkey_bytes = self.public_key.serialize();
-        let mut hasher = Sha256::new();
-        hasher.update(&pubkey_bytes);
-        format!("{:x}", hasher.finalize())
+        pubkey_address(&self.public_key)
     }
 
     pub fn public_key_bytes(&self) -> Vec<u8> {
@@ -62,6 +117,92 @@ impl KeyPair {
         let signature = secp.sign_ecdsa(&message, &self.secret_key);
         Ok(signature.serialize_compact().to_vec())
     }
+
+    /// The x-only form of this key's public key, as used by BIP-340
+    pub fn x_only_public_key(&self) -> [u8; X_ONLY_PUBLIC_KEY_LEN] {
+        self.public_key.x_only_public_key().0.serialize()
+    }
+
+    /// Sign the SHA-256 digest of `message` with a BIP-340 Schnorr signature
+    pub fn sign_schnorr(&self, message: &[u8]) -> Result<Vec<u8>, ChainError> {
+        let secp = Secp256k1::new();
+        let keypair = SchnorrKeypair::from_secret_key(&secp, &self.secret_key);
+        let digest = Message::from_digest(Sha256::digest(message).into());
+        Ok(secp.sign_schnorr(&digest, &keypair).serialize().to_vec())
+    }
+
+    /// Sign `message` with the given scheme
+    pub fn sign_with(&self, scheme: SignatureScheme, message: &[u8]) -> Result<Vec<u8>, ChainError> {
+        match scheme {
+            SignatureScheme::Ecdsa => self.sign(message),
+            SignatureScheme::Schnorr => self.sign_schnorr(message),
+        }
+    }
+
+    /// Sign an arbitrary message to prove control of this key's address.
+    ///
+    /// The signature is recoverable, so the verifier derives the address from
+    /// it, and it is made over [`message_digest`], which can never equal the
+    /// digest of a transaction's signable message.
+    pub fn sign_message(&self, message: &[u8]) -> Result<Vec<u8>, ChainError> {
+        let secp = Secp256k1::new();
+        let digest = Message::from_digest(message_digest(message));
+        let (recovery_id, compact) = secp
+            .sign_ecdsa_recoverable(&digest, &self.secret_key)
+            .serialize_compact();
+
+        let mut signature = Vec::with_capacity(MESSAGE_SIGNATURE_LEN);
+        signature.push(MESSAGE_SIGNATURE_HEADER + recovery_id.to_i32() as u8);
+        signature.extend_from_slice(&compact);
+        Ok(signature)
+    }
+}
+
+/// Domain prefix of signed messages. Transaction messages start with a type
+/// tag or a triangle hash instead, so a message signature cannot be replayed
+/// as a transaction signature.
+pub const MESSAGE_PREFIX: &[u8] = b"\x1dTrinityChain Signed Message:\n";
+
+/// Length of a message signature: one header byte and the compact signature
+pub const MESSAGE_SIGNATURE_LEN: usize = 65;
+
+/// Header byte of a message signature before the recovery id is added
+/// (Bitcoin's value for compressed keys)
+const MESSAGE_SIGNATURE_HEADER: u8 = 31;
+
+/// Digest signed by [`KeyPair::sign_message`]: SHA-256 over the domain prefix,
+/// the message length and the message
+pub fn message_digest(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(MESSAGE_PREFIX);
+    hasher.update((message.len() as u64).to_le_bytes());
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// Recover the address that produced a message signature
+pub fn recover_message_signer(message: &[u8], signature: &[u8]) -> Result<Address, ChainError> {
+    if signature.len() != MESSAGE_SIGNATURE_LEN {
+        return Err(ChainError::CryptoError(format!(
+            "Message signature must be {} bytes, got {}", MESSAGE_SIGNATURE_LEN, signature.len()
+        )));
+    }
+    let recovery_id = signature[0]
+        .checked_sub(MESSAGE_SIGNATURE_HEADER)
+        .and_then(|id| RecoveryId::from_i32(id as i32).ok())
+        .ok_or_else(|| ChainError::CryptoError("Invalid message signature header".to_string()))?;
+    let signature = RecoverableSignature::from_compact(&signature[1..], recovery_id)
+        .map_err(|e| ChainError::CryptoError(format!("Invalid signature: {}", e)))?;
+
+    let public_key = verification_context()
+        .recover_ecdsa(&Message::from_digest(message_digest(message)), &signature)
+        .map_err(|e| ChainError::CryptoError(format!("Failed to recover signer: {}", e)))?;
+    Ok(pubkey_address(&public_key))
+}
+
+/// Check that `signature` over `message` was made by the key of `address`
+pub fn verify_message(address: &str, message: &[u8], signature: &[u8]) -> Result<bool, ChainError> {
+    Ok(recover_message_signer(message, signature)? == address)
 }
 
 pub fn verify_signature(
@@ -82,8 +223,6 @@ pub fn verify_signature(
         return Err(ChainError::CryptoError("Signature cannot be empty".to_string()));
     }
 
-    let secp = Secp256k1::new();
-    
     let public_key = PublicKey::from_slice(public_key_bytes)
         .map_err(|e| ChainError::CryptoError(format!("Invalid public key: {}", e)))?;
     
@@ -97,7 +236,68 @@ pub fn verify_signature(
     let signature = Signature::from_compact(signature_bytes)
         .map_err(|e| ChainError::CryptoError(format!("Invalid signature: {}", e)))?;
     
-    Ok(secp.verify_ecdsa(&message, &signature, &public_key).is_ok())
+    Ok(verification_context().verify_ecdsa(&message, &signature, &public_key).is_ok())
+}
+
+/// Verify a BIP-340 signature over the SHA-256 digest of `message` against
+/// an x-only public key
+pub fn verify_schnorr(
+    x_only_public_key: &[u8],
+    message: &[u8],
+    signature_bytes: &[u8],
+) -> Result<bool, ChainError> {
+    if message.is_empty() {
+        return Err(ChainError::CryptoError("Message cannot be empty".to_string()));
+    }
+
+    let public_key = XOnlyPublicKey::from_slice(x_only_public_key)
+        .map_err(|e| ChainError::CryptoError(format!("Invalid x-only public key: {}", e)))?;
+    let signature = schnorr::Signature::from_slice(signature_bytes)
+        .map_err(|e| ChainError::CryptoError(format!("Invalid Schnorr signature: {}", e)))?;
+
+    let digest = Message::from_digest(Sha256::digest(message).into());
+    Ok(verification_context().verify_schnorr(&signature, &digest, &public_key).is_ok())
+}
+
+/// Verify a signature made with `scheme` against a serialized public key.
+/// Schnorr signatures are checked against the key's x-only form.
+pub fn verify_signature_with(
+    scheme: SignatureScheme,
+    public_key_bytes: &[u8],
+    message: &[u8],
+    signature_bytes: &[u8],
+) -> Result<bool, ChainError> {
+    match scheme {
+        SignatureScheme::Ecdsa => verify_signature(public_key_bytes, message, signature_bytes),
+        SignatureScheme::Schnorr => {
+            let public_key = PublicKey::from_slice(public_key_bytes)
+                .map_err(|e| ChainError::CryptoError(format!("Invalid public key: {}", e)))?;
+            let x_only = public_key.x_only_public_key().0.serialize();
+            verify_schnorr(&x_only, message, signature_bytes)
+        }
+    }
+}
+
+/// Derive the key address for a serialized public key.
+///
+/// The key is parsed and re-serialized in compressed form, so the result
+/// matches `KeyPair::address` for the same key regardless of encoding.
+pub fn address_from_public_key(public_key_bytes: &[u8]) -> Result<Address, ChainError> {
+    let public_key = PublicKey::from_slice(public_key_bytes)
+        .map_err(|e| ChainError::CryptoError(format!("Invalid public key: {}", e)))?;
+    Ok(pubkey_address(&public_key))
+}
+
+fn pubkey_address(public_key: &PublicKey) -> Address {
+    let mut hasher = Sha256::new();
+    hasher.update(public_key.serialize());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Returns true if `address` has the form of a key address: the hex SHA-256
+/// of a public key, 64 lowercase hex characters
+pub fn is_key_address(address: &str) -> bool {
+    address.len() == 64 && address.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
 }
 
 pub type Address = String;
@@ -156,4 +356,45 @@ mod tests {
         let is_valid = verify_signature(&pubkey_bytes, tampered, &signature).unwrap();
         assert!(!is_valid);
     }
+
+    #[test]
+    fn test_schnorr_signing_and_verification() {
+        let keypair = KeyPair::generate().unwrap();
+        let message = b"Hello, Schnorr!";
+
+        let signature = keypair.sign_schnorr(message).unwrap();
+        assert_eq!(signature.len(), SCHNORR_SIGNATURE_LEN);
+        assert!(verify_schnorr(&keypair.x_only_public_key(), message, &signature).unwrap());
+        assert!(!verify_schnorr(&keypair.x_only_public_key(), b"Tampered", &signature).unwrap());
+
+        let public_key = keypair.public_key_bytes();
+        assert!(verify_signature_with(SignatureScheme::Schnorr, &public_key, message, &signature).unwrap());
+        assert!(!verify_signature_with(SignatureScheme::Ecdsa, &public_key, message, &signature).unwrap());
+
+        let ecdsa = keypair.sign_with(SignatureScheme::Ecdsa, message).unwrap();
+        assert!(!verify_signature_with(SignatureScheme::Schnorr, &public_key, message, &ecdsa).unwrap());
+    }
+
+    #[test]
+    fn test_message_signature_recovers_address() {
+        let keypair = KeyPair::generate().unwrap();
+        let message = b"I control this address";
+
+        let signature = keypair.sign_message(message).unwrap();
+        assert_eq!(signature.len(), MESSAGE_SIGNATURE_LEN);
+        assert_eq!(recover_message_signer(message, &signature).unwrap(), keypair.address());
+        assert!(verify_message(&keypair.address(), message, &signature).unwrap());
+        assert!(!verify_message(&keypair.address(), b"Something else", &signature).unwrap());
+        assert!(recover_message_signer(message, &signature[1..]).is_err());
+    }
+
+    #[test]
+    fn test_message_signature_is_not_a_transaction_signature() {
+        let keypair = KeyPair::generate().unwrap();
+        let message = b"TRANSFER:looks like a transaction";
+
+        let signature = keypair.sign_message(message).unwrap();
+        let is_valid = verify_signature(&keypair.public_key_bytes(), message, &signature[1..]).unwrap();
+        assert!(!is_valid);
+    }
 }
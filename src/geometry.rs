@@ -1,29 +1,325 @@
 //! Core geometric primitives and triangle logic for TrinityChain.
 //! Defines the Point and Triangle structs, subdivision logic, and validation.
 
-use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub};
+
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+use serde::de::{self, Visitor};
 use sha2::{Digest, Sha256};
 use crate::blockchain::Sha256Hash;
+use crate::metadata::TriangleMetadata;
+
+/// Version of the consensus geometry encoding.
+/// Version 1 used raw `f64` coordinates; version 2 uses fixed-point `Coord`.
+pub const GEOMETRY_VERSION: u32 = 2;
+
+// ----------------------------------------------------------------------------
+// 1.5 Fixed-Point Coordinates
+// ----------------------------------------------------------------------------
+
+/// Fixed-point coordinate used for all consensus geometry.
+///
+/// The value is a signed count of `2^-FRACTIONAL_BITS` units, so halving is
+/// exact for even raw values and every node computes identical results.
+/// `f64` conversions exist only for input parsing and display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Coord(i64);
+
+impl Coord {
+    /// Number of fractional bits in the fixed-point representation
+    pub const FRACTIONAL_BITS: u32 = 16;
+    pub const ZERO: Coord = Coord(0);
+    pub const ONE: Coord = Coord(1 << Self::FRACTIONAL_BITS);
+
+    /// Creates a coordinate from its raw fixed-point representation.
+    #[inline]
+    pub const fn from_raw(raw: i64) -> Self {
+        Coord(raw)
+    }
+
+    /// Returns the raw fixed-point representation.
+    #[inline]
+    pub const fn raw(self) -> i64 {
+        self.0
+    }
+
+    /// Creates a coordinate from a whole number of units.
+    #[inline]
+    pub const fn from_int(value: i64) -> Self {
+        Coord(value << Self::FRACTIONAL_BITS)
+    }
+
+    /// Converts a floating point value, rounding to the nearest representable coordinate.
+    /// Non-finite values saturate, and are rejected later by `Point::is_valid`.
+    pub fn from_f64(value: f64) -> Self {
+        Coord((value * Self::ONE.0 as f64).round() as i64)
+    }
+
+    /// Converts to floating point for display purposes.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / Self::ONE.0 as f64
+    }
+
+    /// Deterministic midpoint of two coordinates.
+    /// Exact when the raw sum is even; otherwise rounds towards negative infinity.
+    #[inline]
+    pub fn midpoint(self, other: Coord) -> Coord {
+        Coord(((self.0 as i128 + other.0 as i128) >> 1) as i64)
+    }
+
+    #[inline]
+    pub fn abs(self) -> Coord {
+        Coord(self.0.saturating_abs())
+    }
+}
+
+impl Add for Coord {
+    type Output = Coord;
+
+    fn add(self, other: Coord) -> Coord {
+        Coord(self.0 + other.0)
+    }
+}
+
+impl Sub for Coord {
+    type Output = Coord;
+
+    fn sub(self, other: Coord) -> Coord {
+        Coord(self.0 - other.0)
+    }
+}
+
+impl Neg for Coord {
+    type Output = Coord;
+
+    fn neg(self) -> Coord {
+        Coord(-self.0)
+    }
+}
+
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f64(), f)
+    }
+}
+
+impl Serialize for Coord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Coord {
+    /// Human-readable formats also accept the legacy `f64` encoding, so
+    /// existing JSON (databases, wallets, API clients) can still be read.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CoordVisitor;
+
+        impl Visitor<'_> for CoordVisitor {
+            type Value = Coord;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a fixed-point integer or a legacy floating point coordinate")
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Coord, E> {
+                Ok(Coord(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Coord, E> {
+                i64::try_from(v)
+                    .map(Coord)
+                    .map_err(|_| E::custom("coordinate out of range"))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Coord, E> {
+                if !v.is_finite() {
+                    return Err(E::custom("coordinate must be finite"));
+                }
+                Ok(Coord::from_f64(v))
+            }
+        }
 
-/// Coordinate type for high-precision geometric calculations.
-pub type Coord = f64;
-/// Tolerance for floating point comparisons to check for degeneracy/equality.
-const GEOMETRIC_TOLERANCE: Coord = 1e-9; 
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(CoordVisitor)
+        } else {
+            deserializer.deserialize_i64(CoordVisitor)
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// 1.10 Exact Area
+// ----------------------------------------------------------------------------
+
+/// Exact area, counted in quanta of `2^-AREA_FRACTIONAL_BITS` area units.
+///
+/// A triangle with fixed-point vertices always has an area that is a whole
+/// number of quanta (its doubled raw area), so balances, supply and rewards
+/// can be summed and compared without rounding. Human-readable formats use
+/// an exact decimal string such as `"1000"` or `"0.375"`; binary formats use
+/// the raw quanta.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Area(u128);
+
+impl Area {
+    /// Quanta per area unit: one bit for the halving in the Shoelace formula,
+    /// plus the fractional bits of both coordinate factors
+    pub const FRACTIONAL_BITS: u32 = 2 * Coord::FRACTIONAL_BITS + 1;
+    pub const ZERO: Area = Area(0);
+
+    /// Creates an area from a raw number of quanta.
+    #[inline]
+    pub const fn from_raw(raw: u128) -> Self {
+        Area(raw)
+    }
+
+    /// Returns the raw number of quanta.
+    #[inline]
+    pub const fn raw(self) -> u128 {
+        self.0
+    }
+
+    /// Creates an area from a whole number of units (rewards, fees, supply).
+    #[inline]
+    pub const fn from_units(units: u64) -> Self {
+        Area((units as u128) << Self::FRACTIONAL_BITS)
+    }
+
+    /// Whole units contained in this area, rounding down.
+    pub fn whole_units(self) -> u128 {
+        self.0 >> Self::FRACTIONAL_BITS
+    }
+
+    /// Converts to floating point for display purposes.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / (1u128 << Self::FRACTIONAL_BITS) as f64
+    }
+
+    pub fn checked_add(self, other: Area) -> Option<Area> {
+        self.0.checked_add(other.0).map(Area)
+    }
+
+    pub fn checked_sub(self, other: Area) -> Option<Area> {
+        self.0.checked_sub(other.0).map(Area)
+    }
+
+    pub fn saturating_sub(self, other: Area) -> Area {
+        Area(self.0.saturating_sub(other.0))
+    }
+
+    /// Parses an exact decimal such as `"12"` or `"0.125"`.
+    /// Fails if the value is not a whole number of quanta.
+    pub fn parse_decimal(text: &str) -> Option<Area> {
+        let (int_part, frac_part) = text.split_once('.').unwrap_or((text, ""));
+        if int_part.is_empty() || !int_part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        if !frac_part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let frac_part = frac_part.trim_end_matches('0');
+        if frac_part.len() > Self::FRACTIONAL_BITS as usize {
+            return None;
+        }
+
+        let units: u128 = int_part.parse().ok()?;
+        let mut quanta = units.checked_mul(1u128 << Self::FRACTIONAL_BITS)?;
+        if !frac_part.is_empty() {
+            // n / 10^k quanta-units = (n / 5^k) * 2^(FRACTIONAL_BITS - k)
+            let digits = frac_part.len() as u32;
+            let numerator: u128 = frac_part.parse().ok()?;
+            let five_pow = 5u128.pow(digits);
+            if !numerator.is_multiple_of(five_pow) {
+                return None;
+            }
+            quanta = quanta.checked_add((numerator / five_pow) << (Self::FRACTIONAL_BITS - digits))?;
+        }
+        Some(Area(quanta))
+    }
+}
+
+impl Add for Area {
+    type Output = Area;
+
+    fn add(self, other: Area) -> Area {
+        Area(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Area {
+    fn add_assign(&mut self, other: Area) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Area {
+    type Output = Area;
+
+    fn sub(self, other: Area) -> Area {
+        Area(self.0 - other.0)
+    }
+}
+
+impl Sum for Area {
+    fn sum<I: Iterator<Item = Area>>(iter: I) -> Area {
+        iter.fold(Area::ZERO, Add::add)
+    }
+}
+
+impl fmt::Display for Area {
+    /// Exact decimal representation; every quantum has a finite decimal expansion.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = self.whole_units();
+        let frac = self.0 & ((1u128 << Self::FRACTIONAL_BITS) - 1);
+        if frac == 0 {
+            return write!(f, "{}", units);
+        }
+        // frac / 2^k == frac * 5^k / 10^k
+        let digits = frac * 5u128.pow(Self::FRACTIONAL_BITS);
+        let decimals = format!("{:0width$}", digits, width = Self::FRACTIONAL_BITS as usize);
+        write!(f, "{}.{}", units, decimals.trim_end_matches('0'))
+    }
+}
+
+impl Serialize for Area {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u128(self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Area {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let text = String::deserialize(deserializer)?;
+            Area::parse_decimal(&text)
+                .ok_or_else(|| de::Error::custom(format!("invalid exact area: {}", text)))
+        } else {
+            u128::deserialize(deserializer).map(Area)
+        }
+    }
+}
 
 // ----------------------------------------------------------------------------
 // 1.4 Coordinate System: Point
 // ----------------------------------------------------------------------------
 
-/// Represents a 2D point with high-precision coordinates.
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+/// Represents a 2D point with fixed-point coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Point {
     pub x: Coord,
     pub y: Coord,
 }
 
 impl Point {
-    /// Maximum allowed coordinate value to prevent overflow/precision issues
-    pub const MAX_COORDINATE: Coord = 1e10;
+    /// Maximum allowed coordinate value, keeping all area arithmetic within i128
+    pub const MAX_COORDINATE: Coord = Coord::from_int(10_000_000_000);
 
     /// Creates a new Point.
     /// Note: Does not validate bounds - use is_valid() to check if coordinates are within acceptable ranges.
@@ -32,32 +328,29 @@ impl Point {
         Point { x, y }
     }
 
-    /// Validates that the point has finite coordinates within reasonable bounds
+    /// Creates a Point from floating point values, rounding to the fixed-point grid.
+    pub fn from_f64(x: f64, y: f64) -> Self {
+        Point::new(Coord::from_f64(x), Coord::from_f64(y))
+    }
+
+    /// Validates that the point's coordinates are within bounds
     pub fn is_valid(&self) -> bool {
-        self.x.is_finite() &&
-        self.y.is_finite() &&
         self.x.abs() < Self::MAX_COORDINATE &&
         self.y.abs() < Self::MAX_COORDINATE
     }
 
     /// Calculates the midpoint between this point and another.
-    /// Optimized for inline computation.
     #[inline]
     pub fn midpoint(&self, other: &Point) -> Point {
-        Point::new(
-            (self.x + other.x) * 0.5,
-            (self.y + other.y) * 0.5,
-        )
+        Point::new(self.x.midpoint(other.x), self.y.midpoint(other.y))
     }
 
-    /// Calculates a simple cryptographic hash of the point data.
-    /// Optimized to use direct byte conversion instead of string formatting.
-    /// Uses a pre-allocated buffer for better performance.
+    /// Calculates a cryptographic hash of the point's raw fixed-point coordinates.
     #[inline]
     pub fn hash(&self) -> Sha256Hash {
         let mut hasher = Sha256::new();
-        hasher.update(self.x.to_le_bytes());
-        hasher.update(self.y.to_le_bytes());
+        hasher.update(self.x.raw().to_le_bytes());
+        hasher.update(self.y.raw().to_le_bytes());
         hasher.finalize().into()
     }
 
@@ -65,11 +358,67 @@ impl Point {
         hex::encode(self.hash())
     }
 
-    /// Checks for equality with another point within a small tolerance
-    /// to handle floating-point inaccuracies.
+    /// Checks for exact equality with another point.
     pub fn equals(&self, other: &Point) -> bool {
-        (self.x - other.x).abs() < GEOMETRIC_TOLERANCE &&
-        (self.y - other.y).abs() < GEOMETRIC_TOLERANCE
+        self == other
+    }
+}
+
+/// Axis-aligned bounding box, inclusive of its edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl BoundingBox {
+    /// Creates the bounding box spanned by two corner points, in any order.
+    pub fn new(p: Point, q: Point) -> Self {
+        BoundingBox {
+            min: Point::new(p.x.min(q.x), p.y.min(q.y)),
+            max: Point::new(p.x.max(q.x), p.y.max(q.y)),
+        }
+    }
+
+    /// Checks whether two boxes share at least one point.
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x &&
+        self.min.y <= other.max.y && other.min.y <= self.max.y
+    }
+
+    pub fn contains_point(&self, p: &Point) -> bool {
+        self.min.x <= p.x && p.x <= self.max.x &&
+        self.min.y <= p.y && p.y <= self.max.y
+    }
+}
+
+// ----------------------------------------------------------------------------
+// 1.9 Exact Geometric Predicates
+// ----------------------------------------------------------------------------
+
+/// Direction of the turn a -> b -> c.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    CounterClockwise,
+    Clockwise,
+    Collinear,
+}
+
+/// Twice the signed area of triangle (a, b, c), computed exactly.
+/// Positive for a counter-clockwise turn, negative for clockwise, zero if collinear.
+pub fn orient2d(a: &Point, b: &Point, c: &Point) -> i128 {
+    let (ax, ay) = (a.x.raw() as i128, a.y.raw() as i128);
+    let (bx, by) = (b.x.raw() as i128, b.y.raw() as i128);
+    let (cx, cy) = (c.x.raw() as i128, c.y.raw() as i128);
+    (bx - ax) * (cy - ay) - (cx - ax) * (by - ay)
+}
+
+/// Exact orientation test for three points.
+pub fn orientation(a: &Point, b: &Point, c: &Point) -> Orientation {
+    match orient2d(a, b, c).signum() {
+        1 => Orientation::CounterClockwise,
+        -1 => Orientation::Clockwise,
+        _ => Orientation::Collinear,
     }
 }
 
@@ -85,23 +434,90 @@ pub struct Triangle {
     pub c: Point,
     pub parent_hash: Option<Sha256Hash>,
     pub owner: String,
+    /// Number of subdivisions between this triangle and its root (genesis or coinbase).
+    /// Triangles stored before depth tracking was added load as depth 0.
+    #[serde(default)]
+    pub depth: u32,
+    /// Optional inscription; not part of the triangle hash (see `crate::metadata`)
+    #[serde(default)]
+    pub metadata: Option<TriangleMetadata>,
 }
 
 impl Triangle {
     /// Creates a new Triangle from three vertices.
     pub fn new(a: Point, b: Point, c: Point, parent_hash: Option<Sha256Hash>, owner: String) -> Self {
-        Triangle { a, b, c, parent_hash, owner }
+        Triangle { a, b, c, parent_hash, owner, depth: 0, metadata: None }
+    }
+
+    /// Twice the signed area in raw fixed-point units squared (Shoelace formula).
+    /// Computed exactly in i128; positive when the vertices are counter-clockwise.
+    pub fn doubled_signed_area_raw(&self) -> i128 {
+        orient2d(&self.a, &self.b, &self.c)
+    }
+
+    /// Twice the unsigned area in raw fixed-point units squared.
+    pub fn doubled_area_raw(&self) -> u128 {
+        self.doubled_signed_area_raw().unsigned_abs()
+    }
+
+    /// Smallest axis-aligned box containing all three vertices.
+    pub fn bounding_box(&self) -> BoundingBox {
+        BoundingBox {
+            min: Point::new(self.a.x.min(self.b.x).min(self.c.x), self.a.y.min(self.b.y).min(self.c.y)),
+            max: Point::new(self.a.x.max(self.b.x).max(self.c.x), self.a.y.max(self.b.y).max(self.c.y)),
+        }
+    }
+
+    /// Exact test whether `p` lies inside the triangle or on its boundary.
+    /// Works for either vertex winding.
+    pub fn contains_point(&self, p: &Point) -> bool {
+        let d1 = orient2d(&self.a, &self.b, p);
+        let d2 = orient2d(&self.b, &self.c, p);
+        let d3 = orient2d(&self.c, &self.a, p);
+
+        let has_neg = d1 < 0 || d2 < 0 || d3 < 0;
+        let has_pos = d1 > 0 || d2 > 0 || d3 > 0;
+        !(has_neg && has_pos)
+    }
+
+    /// Exact test whether two triangles share any point, including touching
+    /// edges or vertices.
+    pub fn intersects(&self, other: &Triangle) -> bool {
+        !self.is_separated_from(other, false)
+    }
+
+    /// Exact test whether the interiors of two triangles overlap.
+    /// Triangles that only share an edge or a vertex do not overlap.
+    pub fn overlaps(&self, other: &Triangle) -> bool {
+        !self.is_separated_from(other, true)
     }
 
-    /// Calculates the center point (centroid) of the triangle.
+    /// Separating axis test over the six edges of both triangles. With
+    /// `allow_touching`, a line that the other triangle merely touches still
+    /// counts as separating. Degenerate triangles never overlap anything.
+    fn is_separated_from(&self, other: &Triangle, allow_touching: bool) -> bool {
+        fn separated_by_edge_of(t: &Triangle, other: &Triangle, allow_touching: bool) -> bool {
+            let winding = t.doubled_signed_area_raw().signum();
+            if winding == 0 {
+                return allow_touching;
+            }
+            let vertices = [t.a, t.b, t.c];
+            (0..3).any(|i| {
+                let (p, q) = (&vertices[i], &vertices[(i + 1) % 3]);
+                [other.a, other.b, other.c].iter().all(|v| {
+                    let side = orient2d(p, q, v).signum() * winding;
+                    if allow_touching { side <= 0 } else { side < 0 }
+                })
+            })
+        }
+
+        separated_by_edge_of(self, other, allow_touching) ||
+        separated_by_edge_of(other, self, allow_touching)
+    }
 
-    /// Calculates the area of the triangle using the Shoelace formula.
-    pub fn area(&self) -> Coord {
-        let val = (self.a.x * (self.b.y - self.c.y) 
-                 + self.b.x * (self.c.y - self.a.y) 
-                 + self.c.x * (self.a.y - self.b.y))
-                 .abs();
-        val / 2.0
+    /// Exact area of the triangle.
+    pub fn area(&self) -> Area {
+        Area::from_raw(self.doubled_area_raw())
     }
 
     /// Calculates the unique cryptographic hash of the triangle.
@@ -128,14 +544,14 @@ impl Triangle {
 
     /// Defines the canonical Genesis Triangle for the TrinityChain.
     pub fn genesis() -> Self {
-        const SQRT3: Coord = 1.7320508075688772;
-        const HALF_SQRT3: Coord = 0.8660254037844386;
-        const ONE_POINT_FIVE: Coord = 1.5;
+        const SQRT3: f64 = 1.7320508075688772;
+        const HALF_SQRT3: f64 = 0.8660254037844386;
+        const ONE_POINT_FIVE: f64 = 1.5;
 
         Triangle::new(
-            Point::new(0.0, 0.0),
-            Point::new(SQRT3, 0.0),
-            Point::new(HALF_SQRT3, ONE_POINT_FIVE),
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(SQRT3, 0.0),
+            Point::from_f64(HALF_SQRT3, ONE_POINT_FIVE),
             None,
             "genesis_owner".to_string(),
         )
@@ -146,35 +562,29 @@ impl Triangle {
     // ------------------------------------------------------------------------
 
     /// Subdivides the current triangle into three smaller, valid triangles.
-    /// Optimized to minimize allocations and reuse computed values.
+    /// Midpoints use fixed-point arithmetic, so every node derives identical children.
+    /// Children inherit the parent's owner and metadata.
     #[inline]
     pub fn subdivide(&self) -> [Triangle; 3] {
-        // Compute midpoints inline to reduce function call overhead
-        let mid_ab = Point::new(
-            (self.a.x + self.b.x) * 0.5,
-            (self.a.y + self.b.y) * 0.5,
-        );
-        let mid_bc = Point::new(
-            (self.b.x + self.c.x) * 0.5,
-            (self.b.y + self.c.y) * 0.5,
-        );
-        let mid_ca = Point::new(
-            (self.c.x + self.a.x) * 0.5,
-            (self.c.y + self.a.y) * 0.5,
-        );
+        let mid_ab = self.a.midpoint(&self.b);
+        let mid_bc = self.b.midpoint(&self.c);
+        let mid_ca = self.c.midpoint(&self.a);
 
         let parent_hash = Some(self.hash());
-
-        // Child 1 (A-mid_ab-mid_ca)
-        let t1 = Triangle::new(self.a, mid_ab, mid_ca, parent_hash, self.owner.clone());
-
-        // Child 2 (mid_ab-B-mid_bc)
-        let t2 = Triangle::new(mid_ab, self.b, mid_bc, parent_hash, self.owner.clone());
-
-        // Child 3 (mid_ca-mid_bc-C)
-        let t3 = Triangle::new(mid_ca, mid_bc, self.c, parent_hash, self.owner.clone());
-
-        [t1, t2, t3]
+        let child = |a, b, c| Triangle {
+            depth: self.depth + 1,
+            metadata: self.metadata.clone(),
+            ..Triangle::new(a, b, c, parent_hash, self.owner.clone())
+        };
+
+        [
+            // Child 1 (A-mid_ab-mid_ca)
+            child(self.a, mid_ab, mid_ca),
+            // Child 2 (mid_ab-B-mid_bc)
+            child(mid_ab, self.b, mid_bc),
+            // Child 3 (mid_ca-mid_bc-C)
+            child(mid_ca, mid_bc, self.c),
+        ]
     }
 
     // ------------------------------------------------------------------------
@@ -183,8 +593,8 @@ impl Triangle {
 
     /// Checks if the triangle is geometrically valid.
     /// This checks:
-    /// 1. All points have valid, finite coordinates within bounds
-    /// 2. The triangle is non-degenerate (Area > Tolerance)
+    /// 1. All points have coordinates within bounds
+    /// 2. The triangle is non-degenerate (exactly non-zero area)
     pub fn is_valid(&self) -> bool {
         // Check all points are valid
         if !self.a.is_valid() || !self.b.is_valid() || !self.c.is_valid() {
@@ -192,11 +602,12 @@ impl Triangle {
         }
 
         // A valid triangle must have a non-zero area (i.e., not collinear points).
-        self.area() > GEOMETRIC_TOLERANCE
+        self.doubled_signed_area_raw() != 0
     }
 }
 
 
+
 // ----------------------------------------------------------------------------
 // Testing
 // ----------------------------------------------------------------------------
@@ -207,9 +618,9 @@ mod tests {
 
     fn setup_test_triangle() -> Triangle {
         Triangle::new(
-            Point::new(0.0, 0.0),
-            Point::new(10.0, 0.0),
-            Point::new(0.0, 10.0),
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(10.0, 0.0),
+            Point::from_f64(0.0, 10.0),
             None,
             "test_owner".to_string(),
         )
@@ -217,23 +628,63 @@ mod tests {
 
     #[test]
     fn test_point_midpoint() {
-        let p1 = Point::new(1.0, 1.0);
-        let p2 = Point::new(5.0, 5.0);
+        let p1 = Point::from_f64(1.0, 1.0);
+        let p2 = Point::from_f64(5.0, 5.0);
         let midpoint = p1.midpoint(&p2);
-        assert_eq!(midpoint, Point::new(3.0, 3.0));
+        assert_eq!(midpoint, Point::from_f64(3.0, 3.0));
     }
 
     #[test]
     fn test_triangle_area() {
         let t = setup_test_triangle();
-        assert_eq!(t.area(), 50.0);
+        assert_eq!(t.area(), Area::from_units(50));
+    }
+
+    #[test]
+    fn test_area_exact_decimal() {
+        assert_eq!(Area::from_units(1000).to_string(), "1000");
+        let eighth = Area::from_raw(1 << (Area::FRACTIONAL_BITS - 3));
+        assert_eq!((Area::from_units(2) + eighth).to_string(), "2.125");
+        assert_eq!(Area::parse_decimal("2.125"), Some(Area::from_units(2) + eighth));
+        assert_eq!(Area::parse_decimal("7.000"), Some(Area::from_units(7)));
+        assert_eq!(Area::parse_decimal("0.1"), None);
+        assert_eq!(Area::parse_decimal("-1"), None);
+
+        let quantum = Area::from_raw(1);
+        assert_eq!(Area::parse_decimal(&quantum.to_string()), Some(quantum));
+
+        let json = serde_json::to_string(&eighth).unwrap();
+        assert_eq!(json, "\"0.125\"");
+        assert_eq!(serde_json::from_str::<Area>(&json).unwrap(), eighth);
+        let bytes = bincode::serialize(&eighth).unwrap();
+        assert_eq!(bincode::deserialize::<Area>(&bytes).unwrap(), eighth);
+    }
+
+    #[test]
+    fn test_coord_fixed_point_conversion() {
+        assert_eq!(Coord::from_f64(1.5).raw(), 3 << (Coord::FRACTIONAL_BITS - 1));
+        assert_eq!(Coord::from_int(3).to_f64(), 3.0);
+        assert_eq!(Coord::from_int(1).midpoint(Coord::from_int(2)), Coord::from_f64(1.5));
+    }
+
+    #[test]
+    fn test_coord_serde_accepts_legacy_floats() {
+        let legacy: Point = serde_json::from_str(r#"{"x":0.5,"y":2.0}"#).unwrap();
+        assert_eq!(legacy, Point::from_f64(0.5, 2.0));
+
+        let json = serde_json::to_string(&legacy).unwrap();
+        assert_eq!(json, r#"{"x":32768,"y":131072}"#);
+        assert_eq!(serde_json::from_str::<Point>(&json).unwrap(), legacy);
+
+        let bytes = bincode::serialize(&legacy).unwrap();
+        assert_eq!(bincode::deserialize::<Point>(&bytes).unwrap(), legacy);
     }
 
     #[test]
     fn test_triangle_hash_is_canonical() {
-        let p1 = Point::new(1.0, 2.0);
-        let p2 = Point::new(3.0, 4.0);
-        let p3 = Point::new(5.0, 6.0);
+        let p1 = Point::from_f64(1.0, 2.0);
+        let p2 = Point::from_f64(3.0, 4.0);
+        let p3 = Point::from_f64(5.0, 6.0);
 
         let t1 = Triangle::new(p1, p2, p3, None, "owner1".to_string());
         let t2 = Triangle::new(p3, p1, p2, None, "owner1".to_string());
@@ -245,19 +696,98 @@ mod tests {
     fn test_genesis_triangle_is_canonical() {
         let g1 = Triangle::genesis();
         let expected_area = 1.299038105676658;
-        assert!((g1.area() - expected_area).abs() < 1e-15, "Genesis triangle area is incorrect.");
+        assert!((g1.area().to_f64() - expected_area).abs() < 1e-4, "Genesis triangle area is incorrect.");
+        assert_eq!(g1.hash(), Triangle::genesis().hash());
     }
 
     #[test]
     fn test_subdivision_correctness() {
         let parent = setup_test_triangle();
-        let parent_area = parent.area(); 
+        let parent_area = parent.area();
         let children = parent.subdivide();
-        let total_child_area: Coord = children.iter().map(|t| t.area()).sum();
-        
-        assert!((total_child_area - parent_area * 0.75).abs() < 1e-9);
+        let total_child_area: Area = children.iter().map(|t| t.area()).sum();
+
+        assert_eq!(total_child_area.raw() * 4, parent_area.raw() * 3);
+        let doubled: i128 = children.iter().map(|t| t.doubled_signed_area_raw()).sum();
+        assert_eq!(doubled * 4, parent.doubled_signed_area_raw() * 3);
     }
     
+    #[test]
+    fn test_subdivision_increments_depth() {
+        let parent = setup_test_triangle();
+        assert_eq!(parent.depth, 0);
+        for child in parent.subdivide() {
+            assert_eq!(child.depth, 1);
+            assert!(child.subdivide().iter().all(|g| g.depth == 2));
+        }
+    }
+
+    #[test]
+    fn test_contains_point() {
+        let t = setup_test_triangle();
+        assert!(t.contains_point(&Point::from_f64(1.0, 1.0)));
+        assert!(t.contains_point(&Point::from_f64(5.0, 5.0)), "Points on an edge are contained");
+        assert!(t.contains_point(&Point::from_f64(0.0, 0.0)), "Vertices are contained");
+        assert!(!t.contains_point(&Point::from_f64(5.0, 5.1)));
+        assert!(!t.contains_point(&Point::from_f64(-0.1, 1.0)));
+
+        let reversed = Triangle::new(t.c, t.b, t.a, None, "owner".to_string());
+        assert!(reversed.contains_point(&Point::from_f64(1.0, 1.0)));
+    }
+
+    #[test]
+    fn test_orientation() {
+        let a = Point::from_f64(0.0, 0.0);
+        let b = Point::from_f64(1.0, 0.0);
+        assert_eq!(orientation(&a, &b, &Point::from_f64(0.0, 1.0)), Orientation::CounterClockwise);
+        assert_eq!(orientation(&a, &b, &Point::from_f64(0.0, -1.0)), Orientation::Clockwise);
+        assert_eq!(orientation(&a, &b, &Point::from_f64(7.0, 0.0)), Orientation::Collinear);
+    }
+
+    #[test]
+    fn test_triangle_overlap_and_intersection() {
+        let t = setup_test_triangle();
+        let children = t.subdivide();
+
+        // Subdivision children only touch each other and lie inside the parent
+        for (i, a) in children.iter().enumerate() {
+            for b in children.iter().skip(i + 1) {
+                assert!(a.intersects(b));
+                assert!(!a.overlaps(b));
+            }
+            assert!(a.overlaps(&t));
+        }
+
+        // Sharing an edge is touching, not overlapping
+        let mirrored = Triangle::new(
+            Point::from_f64(10.0, 0.0),
+            Point::from_f64(0.0, 10.0),
+            Point::from_f64(10.0, 10.0),
+            None,
+            "owner".to_string(),
+        );
+        assert!(t.intersects(&mirrored));
+        assert!(!t.overlaps(&mirrored));
+
+        let shifted = Triangle::new(
+            Point::from_f64(5.0, 1.0),
+            Point::from_f64(15.0, 1.0),
+            Point::from_f64(5.0, 11.0),
+            None,
+            "owner".to_string(),
+        );
+        assert!(t.overlaps(&shifted));
+
+        let far = Triangle::new(
+            Point::from_f64(100.0, 100.0),
+            Point::from_f64(101.0, 100.0),
+            Point::from_f64(100.0, 101.0),
+            None,
+            "owner".to_string(),
+        );
+        assert!(!t.intersects(&far));
+    }
+
     #[test]
     fn test_geometric_validation_valid() {
         let t = setup_test_triangle();
@@ -271,9 +801,9 @@ mod tests {
     fn test_geometric_validation_invalid_degenerate() {
         // Degenerate triangle: all points are collinear (on a straight line)
         let t_degenerate = Triangle::new(
-            Point::new(1.0, 1.0),
-            Point::new(2.0, 2.0),
-            Point::new(3.0, 3.0),
+            Point::from_f64(1.0, 1.0),
+            Point::from_f64(2.0, 2.0),
+            Point::from_f64(3.0, 3.0),
             None,
             "owner".to_string()
         );
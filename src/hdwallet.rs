@@ -1,11 +1,84 @@
+use std::collections::HashSet;
+
+use bip32::{ChildNumber, Prefix, XPrv, XPub};
 use bip39::Mnemonic;
 use rand::RngCore;
 
-/// Lightweight HD wallet helpers using BIP-39 for mnemonic and seed derivation.
+use crate::blockchain::Blockchain;
+use crate::crypto::{self, KeyPair};
+use crate::transaction::{Address, Transaction};
+
+/// TrinityChain's coin type in BIP-44 derivation paths (m/44'/coin'/...)
+pub const TRINITY_COIN_TYPE: u32 = 3333;
+
+/// BIP-44 purpose field
+const BIP44_PURPOSE: u32 = 44;
+
+/// Number of consecutive unused addresses after which discovery stops (BIP-44)
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
+
+/// Chain index for receiving addresses
+pub const EXTERNAL_CHAIN: u32 = 0;
+/// Chain index for change addresses
+pub const INTERNAL_CHAIN: u32 = 1;
+
+/// A key derived at m/44'/TRINITY_COIN_TYPE'/account'/change/index
+#[derive(Debug, Clone)]
+pub struct DerivedKey {
+    pub account: u32,
+    pub change: u32,
+    pub index: u32,
+    pub keypair: KeyPair,
+}
+
+impl DerivedKey {
+    pub fn address(&self) -> Address {
+        self.keypair.address()
+    }
+
+    pub fn path(&self) -> String {
+        HDWallet::derivation_path(self.account, self.change, self.index)
+    }
+}
+
+/// An address derived from an account's extended public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivedAddress {
+    pub account: u32,
+    pub change: u32,
+    pub index: u32,
+    pub address: Address,
+}
+
+impl DerivedAddress {
+    pub fn path(&self) -> String {
+        HDWallet::derivation_path(self.account, self.change, self.index)
+    }
+}
+
+/// Result of scanning an account's receive and change chains
+#[derive(Debug, Clone)]
+pub struct Discovery<K = DerivedKey> {
+    /// Every derived key whose address has been used, in derivation order
+    pub used: Vec<K>,
+    /// First unused index on the receive chain
+    pub next_receive_index: u32,
+    /// First unused index on the change chain
+    pub next_change_index: u32,
+}
+
+impl<K> Default for Discovery<K> {
+    fn default() -> Self {
+        Discovery { used: Vec::new(), next_receive_index: 0, next_change_index: 0 }
+    }
+}
+
+/// Lightweight HD wallet helpers using BIP-39 for mnemonic and seed derivation
+/// and BIP-32 for key derivation.
 ///
-/// This module currently provides mnemonic generation and seed derivation
-/// (BIP-39). It intentionally keeps the API small: callers can obtain the
-/// mnemonic phrase and derive the 64-byte seed (PBKDF2-HMAC-SHA512) from it.
+/// Callers can obtain the mnemonic phrase, derive the 64-byte seed
+/// (PBKDF2-HMAC-SHA512) from it, and derive `KeyPair`s along BIP-44 paths
+/// under TrinityChain's coin type.
 pub struct HDWallet;
 
 impl HDWallet {
@@ -35,11 +108,165 @@ impl HDWallet {
         let pass = passphrase.unwrap_or("");
         Ok(m.to_seed_normalized(pass).to_vec())
     }
+
+    /// The BIP-44 path for an address: m/44'/TRINITY_COIN_TYPE'/account'/change/index
+    pub fn derivation_path(account: u32, change: u32, index: u32) -> String {
+        format!("m/{}'/{}'/{}'/{}/{}", BIP44_PURPOSE, TRINITY_COIN_TYPE, account, change, index)
+    }
+
+    /// Derive the secret key bytes at an arbitrary BIP-32 path such as "m/0'/1/2'".
+    pub fn derive_secret(seed: &[u8], path: &str) -> Result<[u8; 32], String> {
+        let path: bip32::DerivationPath = path.parse().map_err(|e| format!("invalid derivation path: {}", e))?;
+        let xprv = XPrv::derive_from_path(seed, &path).map_err(|e| format!("key derivation failed: {}", e))?;
+        Ok(xprv.to_bytes())
+    }
+
+    /// Derive the keypair for one address of an account.
+    pub fn derive_keypair(seed: &[u8], account: u32, change: u32, index: u32) -> Result<KeyPair, String> {
+        let secret = Self::derive_secret(seed, &Self::derivation_path(account, change, index))?;
+        KeyPair::from_secret_bytes(&secret).map_err(|e| e.to_string())
+    }
+
+    /// Scan the receive and change chains of an account, deriving addresses
+    /// until `gap_limit` consecutive ones are unused according to `is_used`.
+    pub fn discover<F>(seed: &[u8], account: u32, gap_limit: u32, is_used: F) -> Result<Discovery, String>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let account_path = format!("m/{}'/{}'/{}'", BIP44_PURPOSE, TRINITY_COIN_TYPE, account);
+        let account_path: bip32::DerivationPath = account_path.parse().map_err(|e| format!("invalid derivation path: {}", e))?;
+        let account_key = XPrv::derive_from_path(seed, &account_path).map_err(|e| format!("key derivation failed: {}", e))?;
+        let chain_keys = [
+            account_key.derive_child(child_number(EXTERNAL_CHAIN)?).map_err(|e| format!("key derivation failed: {}", e))?,
+            account_key.derive_child(child_number(INTERNAL_CHAIN)?).map_err(|e| format!("key derivation failed: {}", e))?,
+        ];
+
+        scan_chains(gap_limit, |change, index| {
+            let child = chain_keys[change as usize]
+                .derive_child(child_number(index)?)
+                .map_err(|e| format!("key derivation failed: {}", e))?;
+            let keypair = KeyPair::from_secret_bytes(&child.to_bytes()).map_err(|e| e.to_string())?;
+            let used = is_used(&keypair.address());
+            Ok((DerivedKey { account, change, index, keypair }, used))
+        })
+    }
+
+    /// The extended public key of an account (m/44'/TRINITY_COIN_TYPE'/account'),
+    /// from which watch-only wallets derive its addresses without any secret key.
+    pub fn account_xpub(seed: &[u8], account: u32) -> Result<String, String> {
+        let account_path = format!("m/{}'/{}'/{}'", BIP44_PURPOSE, TRINITY_COIN_TYPE, account);
+        let account_path: bip32::DerivationPath = account_path.parse().map_err(|e| format!("invalid derivation path: {}", e))?;
+        let account_key = XPrv::derive_from_path(seed, &account_path).map_err(|e| format!("key derivation failed: {}", e))?;
+        Ok(account_key.public_key().to_string(Prefix::XPUB))
+    }
+
+    /// Parse an account extended public key, returning it with its account number
+    pub fn parse_xpub(xpub: &str) -> Result<(XPub, u32), String> {
+        let xpub: XPub = xpub.trim().parse().map_err(|e| format!("invalid extended public key: {}", e))?;
+        let child = xpub.attrs().child_number;
+        if xpub.attrs().depth != 3 || !child.is_hardened() {
+            return Err("extended public key must be an account key (m/44'/coin'/account')".into());
+        }
+        Ok((xpub, child.index()))
+    }
+
+    /// Derive the address at change/index below an account extended public key
+    pub fn derive_address(xpub: &str, change: u32, index: u32) -> Result<DerivedAddress, String> {
+        let (xpub, account) = Self::parse_xpub(xpub)?;
+        let child = xpub
+            .derive_child(child_number(change)?)
+            .and_then(|chain_key| chain_key.derive_child(ChildNumber::new(index, false)?))
+            .map_err(|e| format!("key derivation failed: {}", e))?;
+        let address = crypto::address_from_public_key(&child.to_bytes()).map_err(|e| e.to_string())?;
+        Ok(DerivedAddress { account, change, index, address })
+    }
+
+    /// Gap-limit discovery from an account extended public key
+    pub fn discover_xpub<F>(xpub: &str, gap_limit: u32, is_used: F) -> Result<Discovery<DerivedAddress>, String>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let (xpub, account) = Self::parse_xpub(xpub)?;
+        let chain_keys = [
+            xpub.derive_child(child_number(EXTERNAL_CHAIN)?).map_err(|e| format!("key derivation failed: {}", e))?,
+            xpub.derive_child(child_number(INTERNAL_CHAIN)?).map_err(|e| format!("key derivation failed: {}", e))?,
+        ];
+
+        scan_chains(gap_limit, |change, index| {
+            let child = chain_keys[change as usize]
+                .derive_child(child_number(index)?)
+                .map_err(|e| format!("key derivation failed: {}", e))?;
+            let address = crypto::address_from_public_key(&child.to_bytes()).map_err(|e| e.to_string())?;
+            let used = is_used(&address);
+            Ok((DerivedAddress { account, change, index, address }, used))
+        })
+    }
+}
+
+/// Non-hardened child number
+fn child_number(index: u32) -> Result<ChildNumber, String> {
+    ChildNumber::new(index, false).map_err(|e| e.to_string())
+}
+
+/// Walk the receive then change chain until `gap_limit` consecutive entries
+/// are unused. `derive` returns each entry and whether it has been used.
+fn scan_chains<K, D>(gap_limit: u32, mut derive: D) -> Result<Discovery<K>, String>
+where
+    D: FnMut(u32, u32) -> Result<(K, bool), String>,
+{
+    let mut discovery = Discovery::default();
+    for change in [EXTERNAL_CHAIN, INTERNAL_CHAIN] {
+        let mut next_unused = 0;
+        let mut index = 0;
+        while index < next_unused + gap_limit {
+            let (entry, used) = derive(change, index)?;
+            if used {
+                discovery.used.push(entry);
+                next_unused = index + 1;
+            }
+            index += 1;
+        }
+
+        if change == EXTERNAL_CHAIN {
+            discovery.next_receive_index = next_unused;
+        } else {
+            discovery.next_change_index = next_unused;
+        }
+    }
+
+    Ok(discovery)
+}
+
+/// Every address that appears on the chain: current triangle owners plus all
+/// transaction senders, recipients and coinbase beneficiaries.
+pub fn addresses_on_chain(chain: &Blockchain) -> HashSet<Address> {
+    let mut addresses: HashSet<Address> = chain.state.utxo_set().values()
+        .map(|triangle| triangle.owner.clone())
+        .collect();
+
+    for block in &chain.blocks {
+        for tx in &block.transactions {
+            match tx {
+                Transaction::Transfer(t) => {
+                    addresses.insert(t.sender.clone());
+                    addresses.insert(t.new_owner.clone());
+                }
+                Transaction::Subdivision(s) => {
+                    addresses.insert(s.owner_address.clone());
+                }
+                Transaction::Coinbase(c) => {
+                    addresses.insert(c.beneficiary_address.clone());
+                }
+            }
+        }
+    }
+
+    addresses
 }
 
 #[cfg(test)]
 mod tests {
-    use super::HDWallet;
+    use super::*;
 
     #[test]
     fn test_generate_mnemonic_and_seed() {
@@ -57,4 +284,47 @@ mod tests {
         let r = HDWallet::generate_mnemonic(13);
         assert!(r.is_err());
     }
+
+    #[test]
+    fn test_bip32_test_vector() {
+        // BIP-32 test vector 1, chain m/0'/1/2'/2/1000000000
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let secret = HDWallet::derive_secret(&seed, "m/0'/1/2'/2/1000000000").unwrap();
+        assert_eq!(
+            hex::encode(secret),
+            "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8"
+        );
+    }
+
+    #[test]
+    fn test_derivation_is_deterministic() {
+        let seed = HDWallet::seed_from_mnemonic(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            None,
+        ).unwrap();
+        let a = HDWallet::derive_keypair(&seed, 0, EXTERNAL_CHAIN, 0).unwrap();
+        let b = HDWallet::derive_keypair(&seed, 0, EXTERNAL_CHAIN, 0).unwrap();
+        let c = HDWallet::derive_keypair(&seed, 0, EXTERNAL_CHAIN, 1).unwrap();
+        assert_eq!(a.address(), b.address());
+        assert_ne!(a.address(), c.address());
+        assert_eq!(HDWallet::derivation_path(0, 1, 5), "m/44'/3333'/0'/1/5");
+    }
+
+    #[test]
+    fn test_gap_limit_discovery() {
+        let seed = [7u8; 64];
+        let used: HashSet<Address> = [(EXTERNAL_CHAIN, 0), (EXTERNAL_CHAIN, 4), (INTERNAL_CHAIN, 2)]
+            .iter()
+            .map(|&(change, index)| HDWallet::derive_keypair(&seed, 0, change, index).unwrap().address())
+            .collect();
+        // Beyond a gap of 5 from index 4, so not discovered
+        let hidden = HDWallet::derive_keypair(&seed, 0, EXTERNAL_CHAIN, 10).unwrap().address();
+
+        let discovery = HDWallet::discover(&seed, 0, 5, |address| used.contains(address) || address == hidden).unwrap();
+        let found: Vec<(u32, u32)> = discovery.used.iter().map(|k| (k.change, k.index)).collect();
+        assert_eq!(found, vec![(EXTERNAL_CHAIN, 0), (EXTERNAL_CHAIN, 4), (INTERNAL_CHAIN, 2)]);
+        assert_eq!(discovery.next_receive_index, 5);
+        assert_eq!(discovery.next_change_index, 3);
+        assert_eq!(discovery.used[1].path(), "m/44'/3333'/0'/0/4");
+    }
 }
@@ -0,0 +1,920 @@
+//! Multi-address keystore wallets
+//!
+//! A keystore holds any number of keys: HD-derived ones (when the keystore
+//! carries a BIP-39 seed) and imported ones. Each key tracks a label and
+//! whether its address has been seen on chain, so receiving on a fresh
+//! address no longer means creating another `wallet_<name>.json` file.
+//!
+//! Wallet files are encrypted at rest: the keystore is sealed with
+//! AES-256-GCM under a key derived from the wallet password with Argon2id,
+//! whose parameters are stored in the file. Plaintext files, including
+//! single-key wallets, are read by [`Keystore::load`] and converted with
+//! [`UnlockedKeystore::migrate`]. The CLIs can keep a wallet unlocked for a
+//! limited time through a session token held in `TRINITY_SESSION`.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::blockchain::{Sha256Hash, TriangleState};
+use crate::crypto::{KeyPair, SignatureScheme};
+use crate::error::ChainError;
+use crate::geometry::{Area, Triangle};
+use crate::hdwallet::{HDWallet, EXTERNAL_CHAIN, INTERNAL_CHAIN};
+use crate::transaction::{Address, Transaction};
+use crate::wallet::Wallet;
+use crate::watchonly::WatchOnlyWallet;
+
+/// Current keystore file format version
+pub const KEYSTORE_VERSION: u32 = 1;
+
+/// HD account used for keystore-derived addresses
+const KEYSTORE_ACCOUNT: u32 = 0;
+
+/// One key held by a keystore
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyEntry {
+    pub address: Address,
+    #[serde(rename = "secret_key")]
+    pub secret_key_hex: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// BIP-44 path for HD-derived keys; `None` for imported keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivation_path: Option<String>,
+    /// Whether the address has appeared on chain
+    #[serde(default)]
+    pub used: bool,
+    /// Whether the key sits on the HD change chain
+    #[serde(default)]
+    pub change: bool,
+    pub created: String,
+}
+
+impl KeyEntry {
+    fn new(keypair: &KeyPair, label: Option<String>, derivation_path: Option<String>, change: bool) -> Self {
+        KeyEntry {
+            address: keypair.address(),
+            secret_key_hex: hex::encode(keypair.secret_key.secret_bytes()),
+            label,
+            derivation_path,
+            used: false,
+            change,
+            created: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn keypair(&self) -> Result<KeyPair, ChainError> {
+        let secret_bytes = Zeroizing::new(hex::decode(&self.secret_key_hex)
+            .map_err(|e| ChainError::WalletError(format!("Failed to decode secret key: {}", e)))?);
+        KeyPair::from_secret_bytes(&secret_bytes)
+    }
+}
+
+impl Drop for KeyEntry {
+    fn drop(&mut self) {
+        self.secret_key_hex.zeroize();
+    }
+}
+
+/// A wallet holding many keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub name: Option<String>,
+    /// BIP-39 seed (hex) used to derive fresh addresses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_hex: Option<String>,
+    #[serde(default)]
+    pub next_receive_index: u32,
+    #[serde(default)]
+    pub next_change_index: u32,
+    pub keys: Vec<KeyEntry>,
+    pub created: String,
+}
+
+impl Keystore {
+    /// Create an empty keystore of imported keys
+    pub fn new(name: Option<String>) -> Self {
+        Keystore {
+            version: KEYSTORE_VERSION,
+            name,
+            seed_hex: None,
+            next_receive_index: 0,
+            next_change_index: 0,
+            keys: Vec::new(),
+            created: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Create an HD keystore from a BIP-39 seed, holding its first receive address
+    pub fn from_seed(name: Option<String>, seed: &[u8]) -> Result<Self, ChainError> {
+        let mut keystore = Self::new(name);
+        keystore.seed_hex = Some(hex::encode(seed));
+        keystore.new_receive_address(None)?;
+        Ok(keystore)
+    }
+
+    /// Wrap a single-key wallet as a keystore holding one imported key
+    pub fn from_wallet(wallet: &Wallet) -> Self {
+        let mut keystore = Self::new(wallet.name.clone());
+        keystore.created = wallet.created.clone();
+        keystore.keys.push(KeyEntry {
+            address: wallet.address.clone(),
+            secret_key_hex: wallet.secret_key_hex.clone(),
+            label: None,
+            derivation_path: wallet.derivation_path.clone(),
+            used: false,
+            change: false,
+            created: wallet.created.clone(),
+        });
+        keystore
+    }
+
+    /// Load a plaintext keystore, accepting single-key wallet files as well.
+    /// Encrypted wallets are opened with [`UnlockedKeystore::unlock`].
+    pub fn load(path: &Path) -> Result<Self, ChainError> {
+        let contents = Zeroizing::new(fs::read_to_string(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to read wallet: {}", e)))?);
+
+        let value: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse wallet: {}", e)))?;
+
+        if value.get("ciphertext").is_some() {
+            Err(ChainError::WalletError("Wallet is encrypted; unlock it with its password".to_string()))
+        } else if WatchOnlyWallet::is_watch_only(&value) {
+            Err(ChainError::WalletError("Watch-only wallet holds no secret keys".to_string()))
+        } else if value.get("keys").is_some() {
+            serde_json::from_value(value)
+                .map_err(|e| ChainError::WalletError(format!("Failed to parse keystore: {}", e)))
+        } else {
+            let wallet: Wallet = serde_json::from_value(value)
+                .map_err(|e| ChainError::WalletError(format!("Failed to parse wallet: {}", e)))?;
+            Ok(Self::from_wallet(&wallet))
+        }
+    }
+
+    /// Save the keystore as plaintext JSON, readable by the owner only.
+    /// Wallet files should be written through [`UnlockedKeystore::save`].
+    pub fn save(&self, path: &Path) -> Result<(), ChainError> {
+        let json = Zeroizing::new(serde_json::to_string_pretty(self)
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize keystore: {}", e)))?);
+        write_private(path, json.as_bytes())
+    }
+
+    pub fn is_hd(&self) -> bool {
+        self.seed_hex.is_some()
+    }
+
+    /// Add an existing key, rejecting duplicates
+    pub fn import_key(&mut self, keypair: &KeyPair, label: Option<String>) -> Result<&KeyEntry, ChainError> {
+        let entry = KeyEntry::new(keypair, label, None, false);
+        self.push(entry)
+    }
+
+    /// Add a key derived elsewhere from this keystore's seed, such as during restore
+    pub fn add_derived_key(&mut self, keypair: &KeyPair, path: String, change: bool) -> Result<&KeyEntry, ChainError> {
+        let entry = KeyEntry::new(keypair, None, Some(path), change);
+        self.push(entry)
+    }
+
+    /// Create a fresh address for receiving: the next HD receive address, or a
+    /// newly generated key for keystores without a seed
+    pub fn new_receive_address(&mut self, label: Option<String>) -> Result<&KeyEntry, ChainError> {
+        self.next_address(EXTERNAL_CHAIN, label)
+    }
+
+    /// Create a fresh address for transaction change
+    pub fn new_change_address(&mut self) -> Result<&KeyEntry, ChainError> {
+        self.next_address(INTERNAL_CHAIN, None)
+    }
+
+    fn next_address(&mut self, change: u32, label: Option<String>) -> Result<&KeyEntry, ChainError> {
+        let is_change = change == INTERNAL_CHAIN;
+        let Some(seed_hex) = &self.seed_hex else {
+            let entry = KeyEntry::new(&KeyPair::generate()?, label, None, is_change);
+            return self.push(entry);
+        };
+
+        let seed = Zeroizing::new(hex::decode(seed_hex)
+            .map_err(|e| ChainError::WalletError(format!("Failed to decode seed: {}", e)))?);
+        let counter = if is_change { &mut self.next_change_index } else { &mut self.next_receive_index };
+
+        // Skip indexes already present, e.g. recovered by a restore
+        loop {
+            let index = *counter;
+            *counter += 1;
+            let keypair = HDWallet::derive_keypair(&seed, KEYSTORE_ACCOUNT, change, index)
+                .map_err(ChainError::WalletError)?;
+            if self.keys.iter().all(|k| k.address != keypair.address()) {
+                let path = HDWallet::derivation_path(KEYSTORE_ACCOUNT, change, index);
+                let entry = KeyEntry::new(&keypair, label, Some(path), is_change);
+                return self.push(entry);
+            }
+        }
+    }
+
+    fn push(&mut self, entry: KeyEntry) -> Result<&KeyEntry, ChainError> {
+        if self.contains(&entry.address) {
+            return Err(ChainError::WalletError(format!("Key for {} is already in the wallet", entry.address)));
+        }
+        self.keys.push(entry);
+        Ok(self.keys.last().expect("key was just pushed"))
+    }
+
+    pub fn contains(&self, address: &str) -> bool {
+        self.keys.iter().any(|k| k.address == address)
+    }
+
+    pub fn get(&self, address: &str) -> Option<&KeyEntry> {
+        self.keys.iter().find(|k| k.address == address)
+    }
+
+    pub fn addresses(&self) -> HashSet<Address> {
+        self.keys.iter().map(|k| k.address.clone()).collect()
+    }
+
+    /// The address shown by default: the first key of the wallet
+    pub fn primary_address(&self) -> Option<&Address> {
+        self.keys.first().map(|k| &k.address)
+    }
+
+    /// The first receive address not yet seen on chain
+    pub fn unused_receive_address(&self) -> Option<&KeyEntry> {
+        self.keys.iter().find(|k| !k.used && !k.change)
+    }
+
+    pub fn keypair_for(&self, address: &str) -> Result<KeyPair, ChainError> {
+        self.get(address)
+            .ok_or_else(|| ChainError::WalletError(format!("No key for address {}", address)))?
+            .keypair()
+    }
+
+    /// Derive the keypair at a BIP-32 path from the keystore's seed, for keys
+    /// that were never added to the keystore
+    pub fn derive_keypair_at(&self, path: &str) -> Result<KeyPair, ChainError> {
+        let seed_hex = self.seed_hex.as_ref()
+            .ok_or_else(|| ChainError::WalletError("Keystore has no seed to derive keys from".to_string()))?;
+        let seed = Zeroizing::new(hex::decode(seed_hex)
+            .map_err(|e| ChainError::WalletError(format!("Failed to decode seed: {}", e)))?);
+        let secret = Zeroizing::new(HDWallet::derive_secret(&seed, path).map_err(ChainError::WalletError)?);
+        KeyPair::from_secret_bytes(secret.as_ref())
+    }
+
+    pub fn set_label(&mut self, address: &str, label: Option<String>) -> Result<(), ChainError> {
+        let entry = self.keys.iter_mut()
+            .find(|k| k.address == address)
+            .ok_or_else(|| ChainError::WalletError(format!("No key for address {}", address)))?;
+        entry.label = label;
+        Ok(())
+    }
+
+    /// Mark every key whose address appears in `on_chain` as used.
+    /// Returns how many keys changed state.
+    pub fn refresh_used(&mut self, on_chain: &HashSet<Address>) -> usize {
+        let mut updated = 0;
+        for key in self.keys.iter_mut().filter(|k| !k.used) {
+            if on_chain.contains(&key.address) {
+                key.used = true;
+                updated += 1;
+            }
+        }
+        updated
+    }
+
+    /// Triangles owned by any of the wallet's addresses
+    pub fn owned_triangles<'a>(&self, state: &'a TriangleState) -> Vec<(Sha256Hash, &'a Triangle)> {
+        state.owned_by(&self.addresses())
+    }
+
+    /// Complete an unsigned transfer or subdivision, such as one built by a
+    /// watch-only wallet, with the key of its sender
+    pub fn sign_transaction(&self, tx: &mut Transaction) -> Result<(), ChainError> {
+        self.sign_transaction_with(tx, SignatureScheme::Ecdsa)
+    }
+
+    /// Like [`Keystore::sign_transaction`], signing with the given scheme
+    pub fn sign_transaction_with(&self, tx: &mut Transaction, scheme: SignatureScheme) -> Result<(), ChainError> {
+        match tx {
+            Transaction::Transfer(transfer) => {
+                let keypair = self.keypair_for(&transfer.sender)?;
+                let signature = keypair.sign_with(scheme, &transfer.signable_message())?;
+                transfer.sign_with_scheme(scheme, signature, keypair.public_key.serialize().to_vec());
+            }
+            Transaction::Subdivision(subdivision) => {
+                let keypair = self.keypair_for(&subdivision.owner_address)?;
+                let signature = keypair.sign_with(scheme, &subdivision.signable_message())?;
+                subdivision.sign_with_scheme(scheme, signature, keypair.public_key.serialize().to_vec());
+            }
+            Transaction::Coinbase(_) => {
+                return Err(ChainError::InvalidTransaction("Coinbase transactions are not signed".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Total area owned across all of the wallet's addresses
+    pub fn balance(&self, state: &TriangleState) -> Area {
+        self.owned_triangles(state).iter().map(|(_, t)| t.area()).sum()
+    }
+}
+
+
+impl Drop for Keystore {
+    fn drop(&mut self) {
+        if let Some(seed) = self.seed_hex.as_mut() {
+            seed.zeroize();
+        }
+    }
+}
+
+/// Write a file readable by the owner only
+pub(crate) fn write_private(path: &Path, contents: &[u8]) -> Result<(), ChainError> {
+    fs::write(path, contents)
+        .map_err(|e| ChainError::WalletError(format!("Failed to write {}: {}", path.display(), e)))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| ChainError::WalletError(format!("Failed to set file permissions: {}", e)))?;
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Encryption at rest
+// ============================================================================
+
+/// Current encrypted wallet file format version. Version 3 authenticates the
+/// cleartext header along with the ciphertext.
+pub const ENCRYPTED_KEYSTORE_VERSION: u32 = 3;
+
+/// Last version whose ciphertext only authenticates the cipher name
+const UNBOUND_HEADER_VERSION: u32 = 2;
+
+/// Minimum wallet password length
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+const KDF_ALGORITHM: &str = "argon2id";
+pub(crate) const CIPHER_ALGORITHM: &str = "aes-256-gcm";
+
+/// Argon2id parameters used to derive a wallet's encryption key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub algorithm: String,
+    /// Memory cost in KiB
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    /// Base64 encoded salt
+    pub salt: String,
+}
+
+impl KdfParams {
+    /// Parameters with the given costs and a fresh random salt
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self, ChainError> {
+        Params::new(memory_kib, iterations, parallelism, Some(32))
+            .map_err(|e| ChainError::CryptoError(format!("Invalid Argon2 parameters: {}", e)))?;
+
+        let mut salt = [0u8; 16];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+
+        Ok(KdfParams {
+            algorithm: KDF_ALGORITHM.to_string(),
+            memory_kib,
+            iterations,
+            parallelism,
+            salt: general_purpose::STANDARD.encode(salt),
+        })
+    }
+
+    /// Argon2's recommended costs with a fresh random salt
+    pub fn standard() -> Self {
+        Self::new(Params::DEFAULT_M_COST, Params::DEFAULT_T_COST, Params::DEFAULT_P_COST)
+            .expect("default Argon2 parameters are valid")
+    }
+
+    /// The same costs with a fresh random salt
+    pub fn resalted(&self) -> Result<Self, ChainError> {
+        Self::new(self.memory_kib, self.iterations, self.parallelism)
+    }
+
+    /// Derive the 256-bit wallet encryption key from a password
+    pub fn derive_key(&self, password: &str) -> Result<Zeroizing<[u8; 32]>, ChainError> {
+        if self.algorithm != KDF_ALGORITHM {
+            return Err(ChainError::CryptoError(format!("Unsupported key derivation: {}", self.algorithm)));
+        }
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| ChainError::CryptoError(format!("Invalid Argon2 parameters: {}", e)))?;
+        let salt = general_purpose::STANDARD.decode(&self.salt)
+            .map_err(|e| ChainError::CryptoError(format!("Invalid salt: {}", e)))?;
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| ChainError::CryptoError(format!("Password hashing failed: {}", e)))?;
+        Ok(key)
+    }
+}
+
+/// AES-256-GCM encrypt `plaintext`, returning (nonce, ciphertext)
+pub(crate) fn seal(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Result<(Vec<u8>, Vec<u8>), ChainError> {
+    let cipher = Aes256Gcm::new_from_slice(key)
+        .map_err(|e| ChainError::CryptoError(format!("Failed to create cipher: {}", e)))?;
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(&Nonce::from(nonce), Payload { msg: plaintext, aad })
+        .map_err(|e| ChainError::CryptoError(format!("Encryption failed: {}", e)))?;
+    Ok((nonce.to_vec(), ciphertext))
+}
+
+pub(crate) fn open(key: &[u8; 32], nonce: &str, ciphertext: &str, aad: &[u8]) -> Result<Zeroizing<Vec<u8>>, ChainError> {
+    let nonce: [u8; 12] = general_purpose::STANDARD.decode(nonce).ok()
+        .and_then(|n| n.try_into().ok())
+        .ok_or_else(|| ChainError::CryptoError("Invalid nonce".to_string()))?;
+    let ciphertext = general_purpose::STANDARD.decode(ciphertext)
+        .map_err(|e| ChainError::CryptoError(format!("Invalid ciphertext: {}", e)))?;
+    let cipher = Aes256Gcm::new_from_slice(key)
+        .map_err(|e| ChainError::CryptoError(format!("Failed to create cipher: {}", e)))?;
+    cipher
+        .decrypt(&Nonce::from(nonce), Payload { msg: &ciphertext, aad })
+        .map(Zeroizing::new)
+        .map_err(|_| ChainError::CryptoError("Decryption failed - wrong password?".to_string()))
+}
+
+/// On-disk format of an encrypted wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedKeystore {
+    pub version: u32,
+    pub name: Option<String>,
+    /// The wallet's addresses, readable without the password
+    pub addresses: Vec<Address>,
+    pub kdf: KdfParams,
+    pub cipher: String,
+    /// Base64 encoded nonce
+    pub nonce: String,
+    /// Base64 encoded ciphertext of the keystore JSON
+    pub ciphertext: String,
+}
+
+impl EncryptedKeystore {
+    /// Encrypt a keystore under a key derived with `kdf`
+    pub fn seal(keystore: &Keystore, key: &[u8; 32], kdf: KdfParams) -> Result<Self, ChainError> {
+        let plaintext = Zeroizing::new(serde_json::to_vec(keystore)
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize keystore: {}", e)))?);
+
+        let mut encrypted = EncryptedKeystore {
+            version: ENCRYPTED_KEYSTORE_VERSION,
+            name: keystore.name.clone(),
+            addresses: keystore.keys.iter().map(|k| k.address.clone()).collect(),
+            kdf,
+            cipher: CIPHER_ALGORITHM.to_string(),
+            nonce: String::new(),
+            ciphertext: String::new(),
+        };
+        let (nonce, ciphertext) = seal(key, &plaintext, &encrypted.associated_data()?)?;
+        encrypted.nonce = general_purpose::STANDARD.encode(nonce);
+        encrypted.ciphertext = general_purpose::STANDARD.encode(ciphertext);
+        Ok(encrypted)
+    }
+
+    /// Decrypt the keystore with the derived key
+    pub fn open(&self, key: &[u8; 32]) -> Result<Keystore, ChainError> {
+        if self.cipher != CIPHER_ALGORITHM {
+            return Err(ChainError::CryptoError(format!("Unsupported cipher: {}", self.cipher)));
+        }
+        if self.version > ENCRYPTED_KEYSTORE_VERSION {
+            return Err(ChainError::WalletError(format!("Unsupported encrypted wallet version {}", self.version)));
+        }
+        let plaintext = open(key, &self.nonce, &self.ciphertext, &self.associated_data()?)?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse keystore: {}", e)))
+    }
+
+    /// The cleartext header the ciphertext authenticates, so the name and
+    /// address list can't be swapped without the password. Older files only
+    /// bound the cipher name and are rewritten in the current format on save.
+    fn associated_data(&self) -> Result<Vec<u8>, ChainError> {
+        if self.version <= UNBOUND_HEADER_VERSION {
+            return Ok(self.cipher.as_bytes().to_vec());
+        }
+        serde_json::to_vec(&(self.version, &self.name, &self.addresses, &self.kdf, &self.cipher))
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize wallet header: {}", e)))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ChainError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to read wallet: {}", e)))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse encrypted wallet: {}", e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ChainError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize wallet: {}", e)))?;
+        write_private(path, json.as_bytes())
+    }
+}
+
+/// Whether a wallet file is stored encrypted
+pub fn is_encrypted(path: &Path) -> Result<bool, ChainError> {
+    let contents = Zeroizing::new(fs::read_to_string(path)
+        .map_err(|e| ChainError::WalletError(format!("Failed to read wallet: {}", e)))?);
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| ChainError::WalletError(format!("Failed to parse wallet: {}", e)))?;
+    Ok(value.get("ciphertext").is_some())
+}
+
+/// The addresses of a wallet file, read without unlocking it.
+/// Watch-only wallets are accepted too.
+pub fn wallet_addresses(path: &Path) -> Result<Vec<Address>, ChainError> {
+    if is_encrypted(path)? {
+        Ok(EncryptedKeystore::load(path)?.addresses)
+    } else if let Some(watch_only) = WatchOnlyWallet::load_if_watch_only(path)? {
+        Ok(watch_only.addresses.into_iter().map(|a| a.address).collect())
+    } else {
+        Ok(Keystore::load(path)?.keys.iter().map(|k| k.address.clone()).collect())
+    }
+}
+
+/// A decrypted keystore together with the key needed to save it again
+pub struct UnlockedKeystore {
+    pub keystore: Keystore,
+    path: PathBuf,
+    kdf: KdfParams,
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl UnlockedKeystore {
+    /// Decrypt the wallet at `path` with its password
+    pub fn unlock(path: &Path, password: &str) -> Result<Self, ChainError> {
+        let encrypted = EncryptedKeystore::load(path)?;
+        let key = encrypted.kdf.derive_key(password)?;
+        let keystore = encrypted.open(&key)?;
+        Ok(UnlockedKeystore { keystore, path: path.to_path_buf(), kdf: encrypted.kdf, key })
+    }
+
+    /// Encrypt a new keystore and write it to `path`
+    pub fn create(path: &Path, keystore: Keystore, password: &str, kdf: KdfParams) -> Result<Self, ChainError> {
+        check_password(password)?;
+        let key = kdf.derive_key(password)?;
+        let unlocked = UnlockedKeystore { keystore, path: path.to_path_buf(), kdf, key };
+        unlocked.save()?;
+        Ok(unlocked)
+    }
+
+    /// Encrypt an existing plaintext wallet file in place
+    pub fn migrate(path: &Path, password: &str, kdf: KdfParams) -> Result<Self, ChainError> {
+        let keystore = Keystore::load(path)?;
+        Self::create(path, keystore, password, kdf)
+    }
+
+    /// Re-encrypt the keystore and write it back to its file
+    pub fn save(&self) -> Result<(), ChainError> {
+        EncryptedKeystore::seal(&self.keystore, &self.key, self.kdf.clone())?.save(&self.path)
+    }
+
+    /// Re-encrypt under a new password and Argon2 parameters, ending any unlock session
+    pub fn change_password(&mut self, new_password: &str, kdf: KdfParams) -> Result<(), ChainError> {
+        check_password(new_password)?;
+        self.key = kdf.derive_key(new_password)?;
+        self.kdf = kdf;
+        self.save()?;
+        end_session(&self.path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn kdf(&self) -> &KdfParams {
+        &self.kdf
+    }
+}
+
+pub(crate) fn check_password(password: &str) -> Result<(), ChainError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(ChainError::WalletError(format!(
+            "Password must be at least {} characters", MIN_PASSWORD_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Unlock sessions
+// ============================================================================
+
+/// Environment variable holding the session token printed by `trinity-wallet unlock`
+pub const SESSION_ENV: &str = "TRINITY_SESSION";
+
+/// How long a wallet stays unlocked by default
+pub const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The wallet key encrypted under a session token, stored next to the wallet.
+/// Neither the file nor the token alone unlocks the wallet.
+#[derive(Serialize, Deserialize)]
+struct SessionFile {
+    expires_at: i64,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn session_path(wallet_path: &Path) -> PathBuf {
+    let stem = wallet_path.file_stem().and_then(|s| s.to_str()).unwrap_or("wallet");
+    wallet_path.with_file_name(format!(".session_{}.json", stem))
+}
+
+impl UnlockedKeystore {
+    /// Keep the wallet unlocked for `timeout`, returning the session token
+    pub fn start_session(&self, timeout: Duration) -> Result<Zeroizing<String>, ChainError> {
+        let mut token = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(token.as_mut());
+
+        let expires_at = chrono::Utc::now().timestamp() + timeout.as_secs() as i64;
+        let (nonce, ciphertext) = seal(&token, self.key.as_ref(), &expires_at.to_le_bytes())?;
+        let session = SessionFile {
+            expires_at,
+            nonce: general_purpose::STANDARD.encode(nonce),
+            ciphertext: general_purpose::STANDARD.encode(ciphertext),
+        };
+        let json = serde_json::to_string(&session)
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize session: {}", e)))?;
+        write_private(&session_path(&self.path), json.as_bytes())?;
+
+        Ok(Zeroizing::new(hex::encode(token.as_ref())))
+    }
+
+    /// Unlock the wallet from a live session. Returns `None` when there is no
+    /// session, it has expired, or the token does not match.
+    pub fn resume_session(path: &Path, token: &str) -> Result<Option<Self>, ChainError> {
+        let session_file = session_path(path);
+        let Ok(contents) = fs::read_to_string(&session_file) else {
+            return Ok(None);
+        };
+        let session: SessionFile = serde_json::from_str(&contents)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse session: {}", e)))?;
+
+        if chrono::Utc::now().timestamp() >= session.expires_at {
+            end_session(path)?;
+            return Ok(None);
+        }
+
+        let Some(token) = hex::decode(token).ok().and_then(|t| <[u8; 32]>::try_from(t).ok()) else {
+            return Ok(None);
+        };
+        let token = Zeroizing::new(token);
+        let Ok(key) = open(&token, &session.nonce, &session.ciphertext, &session.expires_at.to_le_bytes()) else {
+            return Ok(None);
+        };
+        let key: Zeroizing<[u8; 32]> = Zeroizing::new(key.as_slice().try_into()
+            .map_err(|_| ChainError::CryptoError("Invalid session key".to_string()))?);
+
+        let encrypted = EncryptedKeystore::load(path)?;
+        let Ok(keystore) = encrypted.open(&key) else {
+            return Ok(None);
+        };
+        Ok(Some(UnlockedKeystore { keystore, path: path.to_path_buf(), kdf: encrypted.kdf, key }))
+    }
+
+    /// Unlock from the session named by `TRINITY_SESSION`, if any
+    pub fn from_env_session(path: &Path) -> Result<Option<Self>, ChainError> {
+        match std::env::var(SESSION_ENV) {
+            Ok(token) if !token.is_empty() => Self::resume_session(path, &token),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Lock the wallet by removing its session
+pub fn end_session(wallet_path: &Path) -> Result<(), ChainError> {
+    match fs::remove_file(session_path(wallet_path)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(ChainError::WalletError(format!("Failed to remove session: {}", e)))
+        }
+        _ => Ok(()),
+    }
+}
+
+// ============================================================================
+// Terminal prompts for the CLIs
+// ============================================================================
+
+/// Ask for a wallet password on the terminal
+pub fn prompt_password(prompt: &str) -> Result<Zeroizing<String>, ChainError> {
+    rpassword::prompt_password(prompt)
+        .map(Zeroizing::new)
+        .map_err(|e| ChainError::WalletError(format!("Failed to read password: {}", e)))
+}
+
+/// Ask for a new wallet password twice
+pub fn prompt_new_password() -> Result<Zeroizing<String>, ChainError> {
+    let password = prompt_password("New wallet password: ")?;
+    check_password(&password)?;
+    let confirm = prompt_password("Confirm wallet password: ")?;
+    if *password != *confirm {
+        return Err(ChainError::WalletError("Passwords do not match".to_string()));
+    }
+    Ok(password)
+}
+
+/// Unlock a wallet for a CLI: reuse a live session, otherwise ask for the
+/// password. Plaintext wallets are encrypted in place under a new password.
+pub fn unlock_interactive(path: &Path) -> Result<UnlockedKeystore, ChainError> {
+    if !path.exists() {
+        return Err(ChainError::WalletError(format!(
+            "No wallet found at {}. Run 'trinity-wallet new' first.", path.display()
+        )));
+    }
+
+    if !is_encrypted(path)? {
+        eprintln!("🔐 {} is stored unencrypted. Choose a password to encrypt it.", path.display());
+        let password = prompt_new_password()?;
+        return UnlockedKeystore::migrate(path, &password, KdfParams::standard());
+    }
+
+    if let Some(unlocked) = UnlockedKeystore::from_env_session(path)? {
+        return Ok(unlocked);
+    }
+
+    let password = prompt_password(&format!("Password for {}: ", path.display()))?;
+    UnlockedKeystore::unlock(path, &password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Point;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_hd_keystore_derives_fresh_addresses() {
+        let seed = HDWallet::seed_from_mnemonic(PHRASE, None).unwrap();
+        let mut keystore = Keystore::from_seed(None, &seed).unwrap();
+        let first = keystore.keys[0].address.clone();
+        assert_eq!(keystore.keys[0].derivation_path.as_deref(), Some("m/44'/3333'/0'/0/0"));
+
+        let second = keystore.new_receive_address(Some("shop".to_string())).unwrap().address.clone();
+        let change = keystore.new_change_address().unwrap().clone();
+        assert_ne!(first, second);
+        assert!(change.change);
+        assert_eq!(change.derivation_path.as_deref(), Some("m/44'/3333'/0'/1/0"));
+
+        let on_chain: HashSet<Address> = [first.clone()].into_iter().collect();
+        assert_eq!(keystore.refresh_used(&on_chain), 1);
+        assert_eq!(keystore.unused_receive_address().unwrap().address, second);
+        assert_eq!(keystore.get(&second).unwrap().label.as_deref(), Some("shop"));
+
+        let imported = KeyPair::generate().unwrap();
+        keystore.import_key(&imported, None).unwrap();
+        assert!(keystore.import_key(&imported, None).is_err());
+        assert_eq!(keystore.keypair_for(&imported.address()).unwrap().address(), imported.address());
+    }
+
+    #[test]
+    fn test_balance_spans_all_addresses() {
+        let mut keystore = Keystore::new(Some("multi".to_string()));
+        let a = keystore.new_receive_address(None).unwrap().address.clone();
+        let b = keystore.new_receive_address(None).unwrap().address.clone();
+
+        let mut state = TriangleState::new();
+        for (i, owner) in [a, b, "someone-else".to_string()].into_iter().enumerate() {
+            let x = i as f64 * 10.0;
+            let triangle = Triangle::new(
+                Point::from_f64(x, 0.0), Point::from_f64(x + 2.0, 0.0), Point::from_f64(x, 2.0), None, owner,
+            );
+            state.insert_triangle(triangle.hash(), triangle);
+        }
+
+        assert_eq!(keystore.owned_triangles(&state).len(), 2);
+        assert_eq!(keystore.balance(&state), Area::from_units(4));
+    }
+
+    #[test]
+    fn test_load_accepts_single_key_wallet() {
+        let path = std::env::temp_dir().join("test_keystore_legacy.json");
+        let wallet = Wallet::new(Some("legacy".to_string())).unwrap();
+        wallet.save(&path).unwrap();
+
+        let keystore = Keystore::load(&path).unwrap();
+        assert_eq!(keystore.primary_address(), Some(&wallet.address));
+        assert!(!keystore.is_hd());
+
+        keystore.save(&path).unwrap();
+        let reloaded = Keystore::load(&path).unwrap();
+        assert_eq!(reloaded.keys.len(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// Cheap Argon2 costs so tests stay fast
+    fn test_kdf() -> KdfParams {
+        KdfParams::new(64, 1, 1).unwrap()
+    }
+
+    #[test]
+    fn test_encrypted_keystore_roundtrip() {
+        let path = std::env::temp_dir().join("test_keystore_encrypted.json");
+        let mut keystore = Keystore::new(Some("vault".to_string()));
+        let secret = keystore.new_receive_address(None).unwrap().secret_key_hex.clone();
+        let address = keystore.keys[0].address.clone();
+
+        UnlockedKeystore::create(&path, keystore, "correct horse", test_kdf()).unwrap();
+        let on_disk = fs::read_to_string(&path).unwrap();
+        assert!(!on_disk.contains(&secret));
+        assert!(is_encrypted(&path).unwrap());
+        assert!(Keystore::load(&path).is_err());
+        assert_eq!(wallet_addresses(&path).unwrap(), vec![address.clone()]);
+
+        assert!(UnlockedKeystore::unlock(&path, "wrong horse").is_err());
+        let unlocked = UnlockedKeystore::unlock(&path, "correct horse").unwrap();
+        assert_eq!(unlocked.keystore.keys[0].secret_key_hex, secret);
+        assert_eq!(unlocked.kdf().memory_kib, 64);
+
+        assert!(UnlockedKeystore::create(&path, Keystore::new(None), "short", test_kdf()).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_header_is_authenticated() {
+        let mut keystore = Keystore::new(Some("vault".to_string()));
+        keystore.new_receive_address(None).unwrap();
+        let kdf = test_kdf();
+        let key = kdf.derive_key("correct horse").unwrap();
+        let sealed = EncryptedKeystore::seal(&keystore, &key, kdf).unwrap();
+        assert!(sealed.open(&key).is_ok());
+
+        let mut renamed = sealed.clone();
+        renamed.name = Some("other".to_string());
+        assert!(renamed.open(&key).is_err());
+
+        let mut swapped = sealed.clone();
+        swapped.addresses = vec!["ab".repeat(32)];
+        assert!(swapped.open(&key).is_err());
+
+        // Files from before the header was bound still open
+        let plaintext = serde_json::to_vec(&keystore).unwrap();
+        let (nonce, ciphertext) = seal(&key, &plaintext, CIPHER_ALGORITHM.as_bytes()).unwrap();
+        let mut legacy = sealed;
+        legacy.version = UNBOUND_HEADER_VERSION;
+        legacy.nonce = general_purpose::STANDARD.encode(nonce);
+        legacy.ciphertext = general_purpose::STANDARD.encode(ciphertext);
+        assert_eq!(legacy.open(&key).unwrap().keys.len(), 1);
+    }
+
+    #[test]
+    fn test_migrate_and_change_password() {
+        let path = std::env::temp_dir().join("test_keystore_migrate.json");
+        let wallet = Wallet::new(None).unwrap();
+        wallet.save(&path).unwrap();
+        assert!(!is_encrypted(&path).unwrap());
+
+        UnlockedKeystore::migrate(&path, "first password", test_kdf()).unwrap();
+        assert!(is_encrypted(&path).unwrap());
+
+        let mut unlocked = UnlockedKeystore::unlock(&path, "first password").unwrap();
+        assert_eq!(unlocked.keystore.primary_address(), Some(&wallet.address));
+        assert_eq!(unlocked.keystore.keys[0].secret_key_hex, wallet.secret_key_hex);
+
+        let stronger = KdfParams::new(128, 2, 1).unwrap();
+        unlocked.change_password("second password", stronger).unwrap();
+        assert!(UnlockedKeystore::unlock(&path, "first password").is_err());
+        let reopened = UnlockedKeystore::unlock(&path, "second password").unwrap();
+        assert_eq!(reopened.kdf().memory_kib, 128);
+        assert_eq!(reopened.kdf().iterations, 2);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unlock_session() {
+        let path = std::env::temp_dir().join("test_keystore_session.json");
+        let mut keystore = Keystore::new(None);
+        keystore.new_receive_address(None).unwrap();
+        let unlocked = UnlockedKeystore::create(&path, keystore, "session password", test_kdf()).unwrap();
+
+        let token = unlocked.start_session(Duration::from_secs(60)).unwrap();
+        let resumed = UnlockedKeystore::resume_session(&path, &token).unwrap().unwrap();
+        assert_eq!(resumed.keystore.primary_address(), unlocked.keystore.primary_address());
+        assert!(UnlockedKeystore::resume_session(&path, &"00".repeat(32)).unwrap().is_none());
+
+        end_session(&path).unwrap();
+        assert!(UnlockedKeystore::resume_session(&path, &token).unwrap().is_none());
+
+        let expired = unlocked.start_session(Duration::ZERO).unwrap();
+        assert!(UnlockedKeystore::resume_session(&path, &expired).unwrap().is_none());
+        assert!(!session_path(&path).exists());
+        fs::remove_file(&path).unwrap();
+    }
+}
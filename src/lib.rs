@@ -1,9 +1,14 @@
 pub mod geometry;
+pub mod spatial;
 pub mod blockchain;
 pub mod transaction;
+pub mod script;
+pub mod metadata;
 pub mod error;
 pub mod miner;
 pub mod crypto;
+pub mod address;
+pub mod sigcache;
 pub mod persistence;
 pub mod network;
 pub mod wallet;
@@ -15,3 +20,9 @@ pub mod cache;
 pub mod discovery;
 pub mod fees;
 pub mod hdwallet;
+pub mod keystore;
+pub mod watchonly;
+pub mod psbt;
+pub mod slip39;
+pub mod backup;
+pub mod verify;
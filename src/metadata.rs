@@ -0,0 +1,130 @@
+//! Metadata inscribed on triangles
+//!
+//! A triangle can carry a small, optional metadata record (a name, a content
+//! hash and a URI) that lets applications treat it as a unique collectible.
+//! Metadata does not affect the triangle's hash, which identifies geometry
+//! only; instead every transaction that sets it commits to
+//! [`TriangleMetadata::commitment`] in its hash and signed message.
+//!
+//! Inheritance rules:
+//! - Subdivision children inherit the parent's metadata unchanged.
+//! - If the parent carries no metadata, the subdivider may inscribe each child.
+//! - A transfer may replace the metadata of the triangle it moves.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::blockchain::Sha256Hash;
+use crate::error::ChainError;
+
+/// Optional application data attached to a triangle
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriangleMetadata {
+    pub name: Option<String>,
+    pub content_hash: Option<Sha256Hash>,
+    pub uri: Option<String>,
+}
+
+impl TriangleMetadata {
+    /// Maximum name length in bytes
+    pub const MAX_NAME_LENGTH: usize = 64;
+    /// Maximum URI length in bytes
+    pub const MAX_URI_LENGTH: usize = 256;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_content_hash(mut self, content_hash: Sha256Hash) -> Self {
+        self.content_hash = Some(content_hash);
+        self
+    }
+
+    pub fn with_uri(mut self, uri: impl Into<String>) -> Self {
+        self.uri = Some(uri.into());
+        self
+    }
+
+    /// Check size bounds and that at least one field is set
+    pub fn validate(&self) -> Result<(), ChainError> {
+        if self.name.is_none() && self.content_hash.is_none() && self.uri.is_none() {
+            return Err(ChainError::InvalidTransaction(
+                "Triangle metadata must set at least one field".to_string()
+            ));
+        }
+        check_text("name", self.name.as_deref(), Self::MAX_NAME_LENGTH)?;
+        check_text("URI", self.uri.as_deref(), Self::MAX_URI_LENGTH)?;
+        Ok(())
+    }
+
+    /// Hash committing to every field, used in transaction hashes and signatures
+    pub fn commitment(&self) -> Sha256Hash {
+        let mut hasher = Sha256::new();
+        hasher.update(b"TRINITY_METADATA:");
+        let fields: [Option<&[u8]>; 3] = [
+            self.name.as_deref().map(str::as_bytes),
+            self.content_hash.as_ref().map(|h| h.as_slice()),
+            self.uri.as_deref().map(str::as_bytes),
+        ];
+        for field in fields {
+            match field {
+                Some(bytes) => {
+                    hasher.update([1u8]);
+                    hasher.update((bytes.len() as u32).to_le_bytes());
+                    hasher.update(bytes);
+                }
+                None => hasher.update([0u8]),
+            }
+        }
+        hasher.finalize().into()
+    }
+}
+
+fn check_text(field: &str, value: Option<&str>, max_len: usize) -> Result<(), ChainError> {
+    let Some(value) = value else {
+        return Ok(());
+    };
+    if value.is_empty() || value.len() > max_len {
+        return Err(ChainError::InvalidTransaction(format!(
+            "Metadata {} must be between 1 and {} bytes",
+            field, max_len
+        )));
+    }
+    if value.chars().any(char::is_control) {
+        return Err(ChainError::InvalidTransaction(format!(
+            "Metadata {} contains control characters",
+            field
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_validation() {
+        assert!(TriangleMetadata::new().validate().is_err());
+        assert!(TriangleMetadata::new().with_name("Sierpinski #1").validate().is_ok());
+        assert!(TriangleMetadata::new().with_content_hash([1u8; 32]).validate().is_ok());
+        assert!(TriangleMetadata::new().with_name("").validate().is_err());
+        assert!(TriangleMetadata::new().with_name("a".repeat(65)).validate().is_err());
+        assert!(TriangleMetadata::new().with_uri("ipfs://x\n").validate().is_err());
+        assert!(TriangleMetadata::new().with_uri("u".repeat(256)).validate().is_ok());
+    }
+
+    #[test]
+    fn test_commitment_distinguishes_fields() {
+        let name = TriangleMetadata::new().with_name("abc");
+        let uri = TriangleMetadata::new().with_uri("abc");
+        assert_ne!(name.commitment(), uri.commitment());
+        assert_eq!(name.commitment(), name.clone().commitment());
+        assert_ne!(name.commitment(), name.clone().with_uri("x").commitment());
+    }
+}
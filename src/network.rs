@@ -2,10 +2,11 @@
 
 use tokio::net::{TcpListener, TcpStream};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 use crate::blockchain::Blockchain;
 use crate::error::ChainError;
+use crate::persistence::{Database, MEMPOOL_SAVE_INTERVAL};
 use crate::sync::NodeSynchronizer;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -28,16 +29,44 @@ pub struct NetworkNode {
     blockchain: Arc<RwLock<Blockchain>>,
     peers: Arc<RwLock<Vec<Node>>>,
     synchronizer: Arc<NodeSynchronizer>,
+    db_path: String,
+    /// Connection used to save the mempool, opened on the first save
+    mempool_db: Arc<Mutex<Option<Database>>>,
 }
 
 impl NetworkNode {
-    pub fn new(blockchain: Blockchain, _db_path: String) -> Self {
+    pub fn new(blockchain: Blockchain, db_path: String) -> Self {
         NetworkNode {
             blockchain: Arc::new(RwLock::new(blockchain)),
             peers: Arc::new(RwLock::new(Vec::new())),
             synchronizer: Arc::new(NodeSynchronizer::new()),
+            db_path,
+            mempool_db: Arc::new(Mutex::new(None)),
         }
     }
+
+    /// Save the mempool to the node's database
+    pub async fn save_mempool(&self) -> Result<(), ChainError> {
+        save_mempool(&self.blockchain, &self.mempool_db, &self.db_path).await
+    }
+
+    /// Save the mempool every `MEMPOOL_SAVE_INTERVAL` in the background
+    pub fn spawn_mempool_saver(&self) {
+        let blockchain = self.blockchain.clone();
+        let mempool_db = self.mempool_db.clone();
+        let db_path = self.db_path.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(MEMPOOL_SAVE_INTERVAL);
+            // The first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = save_mempool(&blockchain, &mempool_db, &db_path).await {
+                    eprintln!("❌ Failed to save mempool: {}", e);
+                }
+            }
+        });
+    }
     
     /// Get a reference to the synchronizer
     pub fn synchronizer(&self) -> &Arc<NodeSynchronizer> {
@@ -355,11 +384,62 @@ enum NetworkMessage {
     GetPeers,
     Peers(Vec<Node>),
     GetBlockchain,
-    Blockchain(Blockchain),
+    Blockchain(Box<Blockchain>),
     Ping,
     Pong,
 }
 
+/// Save a snapshot of the mempool on a blocking thread, so SQLite I/O never
+/// stalls the async workers. The connection is opened once and reused.
+async fn save_mempool(
+    blockchain: &RwLock<Blockchain>,
+    mempool_db: &Arc<Mutex<Option<Database>>>,
+    db_path: &str,
+) -> Result<(), ChainError> {
+    let mempool = blockchain.read().await.mempool.clone();
+    let mempool_db = mempool_db.clone();
+    let db_path = db_path.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut cached = mempool_db.lock()
+            .map_err(|_| ChainError::DatabaseError("Mempool database lock is poisoned".to_string()))?;
+        let db = match cached.take() {
+            Some(db) => db,
+            None => Database::open(&db_path)?,
+        };
+        let result = db.save_mempool(&mempool);
+        *cached = Some(db);
+        result
+    })
+    .await
+    .map_err(|e| ChainError::DatabaseError(format!("Mempool save task failed: {}", e)))?
+}
+
+/// Resolve once the process is asked to stop, by Ctrl-C or (on Unix) SIGTERM
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
 async fn handle_connection(
     mut socket: TcpStream,
     blockchain: Arc<RwLock<Blockchain>>,
@@ -455,7 +535,7 @@ async fn handle_connection(
         }
         NetworkMessage::GetBlockchain => {
             let chain = blockchain.read().await;
-            let response = NetworkMessage::Blockchain(chain.clone());
+            let response = NetworkMessage::Blockchain(Box::new(chain.clone()));
             let data = bincode::serialize(&response)
                 .map_err(|e| ChainError::NetworkError(format!("Serialization failed: {}", e)))?;
             
@@ -468,8 +548,9 @@ async fn handle_connection(
             println!("📤 Sent blockchain to peer");
         }
         NetworkMessage::NewTransaction(tx) => {
-            let mut chain = blockchain.write().await;
-            if let Err(e) = chain.mempool.add_transaction(*tx) {
+            let mut guard = blockchain.write().await;
+            let chain = &mut *guard;
+            if let Err(e) = chain.mempool.add_transaction_with_state(*tx, &chain.state) {
                 eprintln!("❌ Failed to add new transaction to mempool: {}", e);
             } else {
                 println!("✅ Added new transaction to mempool");
@@ -1,11 +1,46 @@
 //! Database persistence layer for TrinityChain
 
-use rusqlite::{Connection, params};
-use crate::blockchain::{Blockchain, Block, BlockHeader, TriangleState, Mempool};
-use crate::transaction::Transaction;
-use crate::geometry::Triangle;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use crate::blockchain::{genesis_triangle, Blockchain, Block, BlockHeader, BlockHeight, Sha256Hash, TriangleState, Mempool};
+use crate::transaction::{Address, Transaction};
+use crate::geometry::{Triangle, GEOMETRY_VERSION};
 use crate::error::ChainError;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// Upper bound on ancestry walks, well above the maximum subdivision depth
+const MAX_LINEAGE_WALK: usize = 256;
+
+/// How often running nodes save their mempool, bounding what a crash loses
+pub const MEMPOOL_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A block-level event in a triangle's life: creation, transfer or spend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineageEvent {
+    /// Transaction responsible, or None for the genesis triangle
+    pub tx_hash: Option<Sha256Hash>,
+    pub block_hash: Sha256Hash,
+    pub height: BlockHeight,
+}
+
+/// A change of ownership recorded for a triangle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnershipChange {
+    pub event: LineageEvent,
+    pub new_owner: Address,
+}
+
+/// Provenance of a single triangle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriangleLineage {
+    pub hash: Sha256Hash,
+    pub parent_hash: Option<Sha256Hash>,
+    pub created: LineageEvent,
+    pub spent: Option<LineageEvent>,
+    pub children: Vec<Sha256Hash>,
+    pub transfers: Vec<OwnershipChange>,
+}
 
 pub struct Database {
     conn: Connection,
@@ -46,14 +81,327 @@ impl Database {
             [],
         ).map_err(|e| ChainError::DatabaseError(format!("Failed to create metadata table: {}", e)))?;
 
-        Ok(Database { conn })
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS triangle_lineage (
+                hash BLOB PRIMARY KEY,
+                parent_hash BLOB,
+                created_tx BLOB,
+                created_block BLOB NOT NULL,
+                created_height INTEGER NOT NULL,
+                spent_tx BLOB,
+                spent_block BLOB,
+                spent_height INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS idx_lineage_parent ON triangle_lineage (parent_hash);
+            CREATE TABLE IF NOT EXISTS triangle_transfers (
+                hash BLOB NOT NULL,
+                tx_hash BLOB NOT NULL,
+                block_hash BLOB NOT NULL,
+                height INTEGER NOT NULL,
+                new_owner TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_transfers_hash ON triangle_transfers (hash);",
+        ).map_err(|e| ChainError::DatabaseError(format!("Failed to create lineage tables: {}", e)))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS mempool (
+                position INTEGER PRIMARY KEY,
+                hash BLOB NOT NULL,
+                added_at INTEGER NOT NULL,
+                transaction_data TEXT NOT NULL
+            )",
+            [],
+        ).map_err(|e| ChainError::DatabaseError(format!("Failed to create mempool table: {}", e)))?;
+
+        let db = Database { conn };
+        db.migrate_geometry()?;
+        db.backfill_lineage()?;
+        Ok(db)
+    }
+
+    /// Build the lineage index for databases created before it existed
+    fn backfill_lineage(&self) -> Result<(), ChainError> {
+        let indexed: i64 = self.conn.query_row("SELECT COUNT(*) FROM triangle_lineage", [], |row| row.get(0))
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to count lineage: {}", e)))?;
+        if indexed > 0 {
+            return Ok(());
+        }
+        self.rebuild_lineage()
+    }
+
+    /// Rebuild the lineage index from scratch by replaying every stored block
+    pub fn rebuild_lineage(&self) -> Result<(), ChainError> {
+        let blocks = self.load_blocks()?;
+
+        let tx = self.conn.unchecked_transaction()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to start transaction: {}", e)))?;
+        tx.execute_batch("DELETE FROM triangle_lineage; DELETE FROM triangle_transfers;")
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to clear lineage: {}", e)))?;
+        for block in &blocks {
+            Self::record_lineage(&tx, block)?;
+        }
+        tx.commit()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to commit transaction: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Record the triangles created, transferred and spent by a block.
+    ///
+    /// Entries from this height upwards are discarded first, so re-saving a
+    /// height after a reorganization leaves no stale lineage behind.
+    fn record_lineage(conn: &Connection, block: &Block) -> Result<(), ChainError> {
+        let height = block.header.height as i64;
+        let block_hash = block.hash.to_vec();
+        let db_err = |e: rusqlite::Error| ChainError::DatabaseError(format!("Failed to record lineage: {}", e));
+
+        conn.execute("DELETE FROM triangle_lineage WHERE created_height >= ?1", params![height]).map_err(db_err)?;
+        conn.execute(
+            "UPDATE triangle_lineage SET spent_tx = NULL, spent_block = NULL, spent_height = NULL WHERE spent_height >= ?1",
+            params![height],
+        ).map_err(db_err)?;
+        conn.execute("DELETE FROM triangle_transfers WHERE height >= ?1", params![height]).map_err(db_err)?;
+
+        let insert_created = |hash: Sha256Hash, parent: Option<Sha256Hash>, tx_hash: Option<Sha256Hash>| {
+            conn.execute(
+                "INSERT OR REPLACE INTO triangle_lineage (hash, parent_hash, created_tx, created_block, created_height)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![hash.to_vec(), parent.map(|p| p.to_vec()), tx_hash.map(|t| t.to_vec()), block_hash, height],
+            ).map_err(db_err)
+        };
+
+        if block.header.height == 0 {
+            insert_created(genesis_triangle().hash(), None, None)?;
+        }
+
+        for tx in &block.transactions {
+            let tx_hash = tx.hash();
+            match tx {
+                Transaction::Coinbase(cb_tx) => {
+                    if let Ok(triangle) = TriangleState::coinbase_triangle(cb_tx, block.header.height) {
+                        insert_created(triangle.hash(), None, Some(tx_hash))?;
+                    }
+                }
+                Transaction::Subdivision(sub_tx) => {
+                    for child in &sub_tx.children {
+                        insert_created(child.hash(), Some(sub_tx.parent_hash), Some(tx_hash))?;
+                    }
+                    conn.execute(
+                        "UPDATE triangle_lineage SET spent_tx = ?1, spent_block = ?2, spent_height = ?3 WHERE hash = ?4",
+                        params![tx_hash.to_vec(), block_hash, height, sub_tx.parent_hash.to_vec()],
+                    ).map_err(db_err)?;
+                }
+                Transaction::Transfer(transfer_tx) => {
+                    conn.execute(
+                        "INSERT INTO triangle_transfers (hash, tx_hash, block_hash, height, new_owner)
+                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![transfer_tx.input_hash.to_vec(), tx_hash.to_vec(), block_hash, height, transfer_tx.new_owner],
+                    ).map_err(db_err)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Look up the recorded provenance of a triangle
+    pub fn get_lineage(&self, hash: &Sha256Hash) -> Result<Option<TriangleLineage>, ChainError> {
+        let db_err = |e: rusqlite::Error| ChainError::DatabaseError(format!("Failed to query lineage: {}", e));
+
+        let row = self.conn.query_row(
+            "SELECT parent_hash, created_tx, created_block, created_height, spent_tx, spent_block, spent_height
+             FROM triangle_lineage WHERE hash = ?1",
+            params![hash.to_vec()],
+            |row| {
+                let parent: Option<Vec<u8>> = row.get(0)?;
+                let created_tx: Option<Vec<u8>> = row.get(1)?;
+                let created_block: Vec<u8> = row.get(2)?;
+                let created_height: i64 = row.get(3)?;
+                let spent_tx: Option<Vec<u8>> = row.get(4)?;
+                let spent_block: Option<Vec<u8>> = row.get(5)?;
+                let spent_height: Option<i64> = row.get(6)?;
+
+                let created = LineageEvent {
+                    tx_hash: created_tx.as_deref().map(to_hash),
+                    block_hash: to_hash(&created_block),
+                    height: created_height as BlockHeight,
+                };
+                let spent = match (spent_block, spent_height) {
+                    (Some(block), Some(height)) => Some(LineageEvent {
+                        tx_hash: spent_tx.as_deref().map(to_hash),
+                        block_hash: to_hash(&block),
+                        height: height as BlockHeight,
+                    }),
+                    _ => None,
+                };
+                Ok((parent.as_deref().map(to_hash), created, spent))
+            },
+        ).optional().map_err(db_err)?;
+
+        let Some((parent_hash, created, spent)) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some(TriangleLineage {
+            hash: *hash,
+            parent_hash,
+            created,
+            spent,
+            children: self.get_children(hash)?,
+            transfers: self.get_transfers(hash)?,
+        }))
+    }
+
+    /// Triangles created by subdividing the given triangle
+    pub fn get_children(&self, hash: &Sha256Hash) -> Result<Vec<Sha256Hash>, ChainError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT hash FROM triangle_lineage WHERE parent_hash = ?1 ORDER BY rowid"
+        ).map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+
+        let rows = stmt.query_map(params![hash.to_vec()], |row| {
+            let child: Vec<u8> = row.get(0)?;
+            Ok(to_hash(&child))
+        }).map_err(|e| ChainError::DatabaseError(format!("Failed to query children: {}", e)))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to read child: {}", e)))
+    }
+
+    fn get_transfers(&self, hash: &Sha256Hash) -> Result<Vec<OwnershipChange>, ChainError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT tx_hash, block_hash, height, new_owner FROM triangle_transfers WHERE hash = ?1 ORDER BY height, rowid"
+        ).map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+
+        let rows = stmt.query_map(params![hash.to_vec()], |row| {
+            let tx_hash: Vec<u8> = row.get(0)?;
+            let block_hash: Vec<u8> = row.get(1)?;
+            let height: i64 = row.get(2)?;
+            Ok(OwnershipChange {
+                event: LineageEvent {
+                    tx_hash: Some(to_hash(&tx_hash)),
+                    block_hash: to_hash(&block_hash),
+                    height: height as BlockHeight,
+                },
+                new_owner: row.get(3)?,
+            })
+        }).map_err(|e| ChainError::DatabaseError(format!("Failed to query transfers: {}", e)))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to read transfer: {}", e)))
+    }
+
+    /// Ancestors of a triangle, nearest first, up to its root
+    pub fn get_ancestors(&self, hash: &Sha256Hash) -> Result<Vec<Sha256Hash>, ChainError> {
+        let mut ancestors = Vec::new();
+        let mut current = *hash;
+
+        while ancestors.len() < MAX_LINEAGE_WALK {
+            let parent: Option<Option<Vec<u8>>> = self.conn.query_row(
+                "SELECT parent_hash FROM triangle_lineage WHERE hash = ?1",
+                params![current.to_vec()],
+                |row| row.get(0),
+            ).optional().map_err(|e| ChainError::DatabaseError(format!("Failed to query lineage: {}", e)))?;
+
+            match parent.flatten() {
+                Some(parent) => {
+                    current = to_hash(&parent);
+                    ancestors.push(current);
+                }
+                None => break,
+            }
+        }
+
+        Ok(ancestors)
+    }
+
+    /// Descendants of a triangle in breadth-first order, up to `limit` entries
+    pub fn get_descendants(&self, hash: &Sha256Hash, limit: usize) -> Result<Vec<Sha256Hash>, ChainError> {
+        let mut descendants = Vec::new();
+        let mut queue = VecDeque::from([*hash]);
+
+        while let Some(next) = queue.pop_front() {
+            for child in self.get_children(&next)? {
+                if descendants.len() >= limit {
+                    return Ok(descendants);
+                }
+                descendants.push(child);
+                queue.push_back(child);
+            }
+        }
+
+        Ok(descendants)
+    }
+
+    /// Upgrades a database written with legacy `f64` geometry.
+    ///
+    /// Geometry version 2 is a hard fork. Triangle hashes changed with the
+    /// coordinate encoding, and with them the hashes, merkle roots and
+    /// signed messages of every transaction that names a triangle, so blocks
+    /// mined before the fork cannot be rewritten into ones that validate. A
+    /// database holding any is refused, untouched, and the node must resync
+    /// from upgraded peers (see "Protocol Upgrades" in the README). A database
+    /// with no mined blocks has its stored UTXO set re-keyed in place.
+    fn migrate_geometry(&self) -> Result<(), ChainError> {
+        let version: Option<u32> = self.conn.query_row(
+            "SELECT value FROM metadata WHERE key = 'geometry_version'",
+            [],
+            |row| {
+                let val: String = row.get(0)?;
+                Ok(val.parse::<u32>().ok())
+            }
+        ).unwrap_or(None);
+
+        if version.is_some_and(|v| v >= GEOMETRY_VERSION) {
+            return Ok(());
+        }
+
+        let mined: i64 = self.conn.query_row("SELECT COUNT(*) FROM blocks WHERE height > 0", [], |row| row.get(0))
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to count blocks: {}", e)))?;
+        if mined > 0 {
+            return Err(ChainError::DatabaseError(format!(
+                "Database holds {} blocks mined before the geometry version {} hard fork, which cannot be \
+                 re-validated under the new rules. Move the database aside and resync from upgraded peers.",
+                mined, GEOMETRY_VERSION
+            )));
+        }
+
+        let state = self.load_utxo_set()?;
+
+        let tx = self.conn.unchecked_transaction()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to start transaction: {}", e)))?;
+
+        tx.execute("DELETE FROM utxo_set", [])
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to clear utxo_set: {}", e)))?;
+
+        for triangle in state.utxo_set().values() {
+            let triangle_json = serde_json::to_string(triangle)
+                .map_err(|e| ChainError::DatabaseError(format!("Failed to serialize triangle: {}", e)))?;
+
+            tx.execute(
+                "INSERT OR REPLACE INTO utxo_set (hash, triangle_data) VALUES (?1, ?2)",
+                params![triangle.hash().to_vec(), triangle_json],
+            ).map_err(|e| ChainError::DatabaseError(format!("Failed to save UTXO: {}", e)))?;
+        }
+
+        tx.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('geometry_version', ?1)",
+            params![GEOMETRY_VERSION.to_string()],
+        ).map_err(|e| ChainError::DatabaseError(format!("Failed to save geometry version: {}", e)))?;
+
+        tx.commit()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to commit transaction: {}", e)))?;
+
+        Ok(())
     }
 
     pub fn save_block(&self, block: &Block) -> Result<(), ChainError> {
         let transactions_json = serde_json::to_string(&block.transactions)
             .map_err(|e| ChainError::DatabaseError(format!("Failed to serialize transactions: {}", e)))?;
 
-        self.conn.execute(
+        let tx = self.conn.unchecked_transaction()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to start transaction: {}", e)))?;
+
+        tx.execute(
             "INSERT OR REPLACE INTO blocks (height, hash, previous_hash, timestamp, difficulty, nonce, merkle_root, transactions)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             params![
@@ -68,6 +416,11 @@ impl Database {
             ],
         ).map_err(|e| ChainError::DatabaseError(format!("Failed to save block: {}", e)))?;
 
+        Self::record_lineage(&tx, block)?;
+
+        tx.commit()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to commit transaction: {}", e)))?;
+
         Ok(())
     }
 
@@ -79,7 +432,7 @@ impl Database {
         tx.execute("DELETE FROM utxo_set", [])
             .map_err(|e| ChainError::DatabaseError(format!("Failed to clear utxo_set: {}", e)))?;
 
-        for (hash, triangle) in &state.utxo_set {
+        for (hash, triangle) in state.utxo_set() {
             let triangle_json = serde_json::to_string(triangle)
                 .map_err(|e| ChainError::DatabaseError(format!("Failed to serialize triangle: {}", e)))?;
 
@@ -120,7 +473,7 @@ impl Database {
             utxo_set.insert(hash, triangle);
         }
 
-        Ok(TriangleState { utxo_set })
+        Ok(TriangleState::from_utxo_set(utxo_set))
     }
 
     pub fn save_difficulty(&self, difficulty: u64) -> Result<(), ChainError> {
@@ -157,11 +510,13 @@ impl Database {
             ],
         ).map_err(|e| ChainError::DatabaseError(format!("Failed to save block: {}", e)))?;
 
+        Self::record_lineage(&tx, block)?;
+
         // Save UTXO set
         tx.execute("DELETE FROM utxo_set", [])
             .map_err(|e| ChainError::DatabaseError(format!("Failed to clear utxo_set: {}", e)))?;
 
-        for (hash, triangle) in &state.utxo_set {
+        for (hash, triangle) in state.utxo_set() {
             let triangle_json = serde_json::to_string(triangle)
                 .map_err(|e| ChainError::DatabaseError(format!("Failed to serialize triangle: {}", e)))?;
 
@@ -184,7 +539,76 @@ impl Database {
         Ok(())
     }
 
-    pub fn load_blockchain(&self) -> Result<Blockchain, ChainError> {
+    /// Replace the saved mempool with the current one. Transactions are
+    /// stored parents first, with the time each entered the pool.
+    pub fn save_mempool(&self, mempool: &Mempool) -> Result<(), ChainError> {
+        let tx = self.conn.unchecked_transaction()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to start transaction: {}", e)))?;
+
+        tx.execute("DELETE FROM mempool", [])
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to clear mempool: {}", e)))?;
+
+        for (position, (transaction, added_at)) in mempool.entries().iter().enumerate() {
+            let transaction_json = serde_json::to_string(transaction)
+                .map_err(|e| ChainError::DatabaseError(format!("Failed to serialize transaction: {}", e)))?;
+
+            tx.execute(
+                "INSERT INTO mempool (position, hash, added_at, transaction_data) VALUES (?1, ?2, ?3, ?4)",
+                params![position as i64, transaction.hash().to_vec(), added_at, transaction_json],
+            ).map_err(|e| ChainError::DatabaseError(format!("Failed to save mempool transaction: {}", e)))?;
+        }
+
+        tx.commit()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to commit transaction: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Restore the saved mempool on top of `state`.
+    ///
+    /// Every transaction goes through mempool admission again and the pool is
+    /// then revalidated against the state, so transactions mined or
+    /// invalidated while the node was down are dropped, as are those that
+    /// expired in the meantime.
+    pub fn load_mempool(&self, state: &TriangleState) -> Result<Mempool, ChainError> {
+        let mut stmt = self.conn.prepare("SELECT added_at, transaction_data FROM mempool ORDER BY position ASC")
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare mempool query: {}", e)))?;
+
+        let rows = stmt.query_map([], |row| {
+            let added_at: i64 = row.get(0)?;
+            let transaction_json: String = row.get(1)?;
+            Ok((added_at, transaction_json))
+        }).map_err(|e| ChainError::DatabaseError(format!("Failed to query mempool: {}", e)))?;
+
+        let mut mempool = Mempool::new();
+        let now = chrono::Utc::now().timestamp();
+        let mut saved = 0;
+        for row_result in rows {
+            let (added_at, transaction_json) = row_result
+                .map_err(|e| ChainError::DatabaseError(format!("Failed to read mempool row: {}", e)))?;
+            saved += 1;
+
+            // A saved transaction that no longer parses or passes admission is
+            // dropped like any other invalid one
+            let Ok(transaction) = serde_json::from_str::<Transaction>(&transaction_json) else {
+                continue;
+            };
+            if !mempool.is_expired(added_at, now) {
+                let _ = mempool.admit(transaction, added_at, Some(state));
+            }
+        }
+        mempool.validate_and_prune(state);
+
+        if mempool.len() < saved {
+            eprintln!("⚠️  Dropped {} of {} saved mempool transactions (expired, mined or no longer valid)",
+                      saved - mempool.len(), saved);
+        }
+
+        Ok(mempool)
+    }
+
+    /// Load every stored block in height order
+    fn load_blocks(&self) -> Result<Vec<Block>, ChainError> {
         let mut stmt = self.conn.prepare(
             "SELECT height, hash, previous_hash, timestamp, difficulty, nonce, merkle_root, transactions
              FROM blocks ORDER BY height ASC"
@@ -230,6 +654,12 @@ impl Database {
             blocks.push(block_result.map_err(|e| ChainError::DatabaseError(format!("Failed to load block: {}", e)))?);
         }
 
+        Ok(blocks)
+    }
+
+    pub fn load_blockchain(&self) -> Result<Blockchain, ChainError> {
+        let blocks = self.load_blocks()?;
+
         if blocks.is_empty() {
             return Ok(Blockchain::new());
         }
@@ -289,7 +719,7 @@ impl Database {
         let block_index = blocks.iter().map(|b| (b.hash, b.clone())).collect();
 
         let state = self.load_utxo_set()?;
-        let mempool = Mempool::new();
+        let mempool = self.load_mempool(&state)?;
         let blockchain = Blockchain {
             blocks,
             block_index,
@@ -307,6 +737,14 @@ impl Database {
     }
 }
 
+/// Convert a stored hash column to a fixed-size hash
+fn to_hash(bytes: &[u8]) -> Sha256Hash {
+    let mut hash = [0u8; 32];
+    let len = bytes.len().min(32);
+    hash[..len].copy_from_slice(&bytes[..len]);
+    hash
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -333,4 +771,160 @@ mod tests {
         assert_eq!(loaded_chain.blocks[0].header.height, 0);
         assert_eq!(loaded_chain.difficulty, chain.difficulty);
     }
+
+    #[test]
+    fn test_mempool_persists_across_restarts() {
+        use crate::blockchain::RelayPolicy;
+        use crate::crypto::KeyPair;
+        use crate::transaction::TransferTx;
+
+        let db = Database::open(":memory:").unwrap();
+        db.save_block(&Blockchain::new().blocks[0]).unwrap();
+        let owner = KeyPair::generate().unwrap();
+        let mut state = TriangleState::new();
+        let mut triangles = genesis_triangle().subdivide();
+        for triangle in triangles.iter_mut() {
+            triangle.owner = owner.address();
+            state.insert_triangle(triangle.hash(), triangle.clone());
+        }
+        db.save_utxo_set(&state).unwrap();
+
+        let transfer = |input_hash: Sha256Hash| {
+            let mut tx = TransferTx::new(input_hash, "ab".repeat(32), owner.address(), 1, 1);
+            tx.sign(owner.sign(&tx.signable_message()).unwrap(), owner.public_key_bytes());
+            Transaction::Transfer(tx)
+        };
+        let pending = transfer(triangles[0].hash());
+        let expired = transfer(triangles[1].hash());
+        let unknown_input = transfer([9; 32]);
+
+        let mut mempool = Mempool::new();
+        mempool.add_transaction(pending.clone()).unwrap();
+        mempool.add_transaction(unknown_input).unwrap();
+        let long_ago = chrono::Utc::now().timestamp() - RelayPolicy::DEFAULT_MEMPOOL_EXPIRY_SECS as i64 - 1;
+        mempool.add_transaction_at(expired, long_ago).unwrap();
+        db.save_mempool(&mempool).unwrap();
+
+        let loaded = db.load_blockchain().unwrap();
+        assert_eq!(loaded.mempool.len(), 1);
+        assert!(loaded.mempool.get_transaction(&pending.hash()).is_some());
+        assert_eq!(loaded.mempool.added_at(&pending.hash()), mempool.added_at(&pending.hash()));
+    }
+
+    #[test]
+    fn test_triangle_metadata_persists() {
+        use crate::metadata::TriangleMetadata;
+
+        let db = Database::open(":memory:").unwrap();
+        let mut state = TriangleState::new();
+        let mut triangle = genesis_triangle();
+        triangle.metadata = Some(TriangleMetadata::new().with_name("Genesis").with_uri("ipfs://genesis"));
+        state.insert_triangle(triangle.hash(), triangle.clone());
+        db.save_utxo_set(&state).unwrap();
+
+        let loaded = db.load_utxo_set().unwrap();
+        assert_eq!(loaded.utxo_set()[&triangle.hash()].metadata, triangle.metadata);
+    }
+
+    #[test]
+    fn test_legacy_geometry_migration() {
+        let db = Database::open(":memory:").unwrap();
+        let legacy_json = r#"{"a":{"x":0.0,"y":0.0},"b":{"x":1.0,"y":0.0},"c":{"x":0.5,"y":0.866},"parent_hash":null,"owner":"alice"}"#;
+        db.conn.execute(
+            "INSERT INTO utxo_set (hash, triangle_data) VALUES (?1, ?2)",
+            params![vec![7u8; 32], legacy_json],
+        ).unwrap();
+        db.conn.execute("DELETE FROM metadata WHERE key = 'geometry_version'", []).unwrap();
+
+        db.migrate_geometry().unwrap();
+
+        let state = db.load_utxo_set().unwrap();
+        assert_eq!(state.count(), 1);
+        let (hash, triangle) = state.utxo_set().iter().next().unwrap();
+        assert_eq!(*hash, triangle.hash());
+        assert_eq!(triangle.owner, "alice");
+
+        let version: String = db.conn.query_row(
+            "SELECT value FROM metadata WHERE key = 'geometry_version'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(version, GEOMETRY_VERSION.to_string());
+    }
+
+    #[test]
+    fn test_legacy_blocks_require_resync() {
+        let db = Database::open(":memory:").unwrap();
+        db.save_block(&Blockchain::new().blocks[0]).unwrap();
+
+        // A block stored by a legacy node, with float coordinates
+        let legacy_transactions = r#"[{"Coinbase":{"reward_area":50,"beneficiary_address":"miner"}},{"Transfer":{"input_hash":[7,7,7],"new_owner":"bob","sender":"alice","fee":0,"nonce":1,"signature":null,"public_key":null}}]"#;
+        db.conn.execute(
+            "INSERT INTO blocks (height, hash, previous_hash, timestamp, difficulty, nonce, merkle_root, transactions)
+             VALUES (1, ?1, ?2, 0, 1, 0, ?3, ?4)",
+            params![vec![1u8; 32], vec![0u8; 32], vec![2u8; 32], legacy_transactions],
+        ).unwrap();
+        db.conn.execute("DELETE FROM metadata WHERE key = 'geometry_version'", []).unwrap();
+
+        let result = db.migrate_geometry();
+        assert!(matches!(result, Err(ChainError::DatabaseError(msg)) if msg.contains("resync")));
+
+        // The legacy block is left byte-identical and the database unmigrated
+        let stored: String = db.conn.query_row("SELECT transactions FROM blocks WHERE height = 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(stored, legacy_transactions);
+        let version: Option<String> = db.conn.query_row(
+            "SELECT value FROM metadata WHERE key = 'geometry_version'", [], |row| row.get(0),
+        ).optional().unwrap();
+        assert_eq!(version, None);
+    }
+
+    #[test]
+    fn test_triangle_lineage() {
+        use crate::transaction::SubdivisionTx;
+
+        let db = Database::open(":memory:").unwrap();
+        let chain = Blockchain::new();
+        let genesis = genesis_triangle();
+        let genesis_hash = genesis.hash();
+        db.save_block(&chain.blocks[0]).unwrap();
+
+        let children = genesis.subdivide();
+        let sub_tx = SubdivisionTx::new(genesis_hash, children.to_vec(), "alice".to_string(), 0, 1);
+        let sub_hash = Transaction::Subdivision(sub_tx.clone()).hash();
+        let grandchildren = children[0].subdivide();
+        let sub_tx2 = SubdivisionTx::new(children[0].hash(), grandchildren.to_vec(), "alice".to_string(), 0, 2);
+
+        let mut block1 = Block::new(1, chain.blocks[0].hash, chain.difficulty, vec![Transaction::Subdivision(sub_tx)]);
+        block1.hash = [1u8; 32];
+        let mut block2 = Block::new(2, block1.hash, chain.difficulty, vec![Transaction::Subdivision(sub_tx2)]);
+        block2.hash = [2u8; 32];
+        db.save_block(&block1).unwrap();
+        db.save_block(&block2).unwrap();
+
+        let root = db.get_lineage(&genesis_hash).unwrap().unwrap();
+        assert!(root.parent_hash.is_none());
+        assert!(root.created.tx_hash.is_none());
+        let spent = root.spent.unwrap();
+        assert_eq!(spent.tx_hash, Some(sub_hash));
+        assert_eq!(spent.height, 1);
+        assert_eq!(root.children, children.iter().map(|c| c.hash()).collect::<Vec<_>>());
+
+        let leaf = grandchildren[2].hash();
+        let lineage = db.get_lineage(&leaf).unwrap().unwrap();
+        assert_eq!(lineage.created.block_hash, block2.hash);
+        assert!(lineage.spent.is_none());
+        assert_eq!(db.get_ancestors(&leaf).unwrap(), vec![children[0].hash(), genesis_hash]);
+        assert_eq!(db.get_descendants(&genesis_hash, 100).unwrap().len(), 6);
+        assert_eq!(db.get_descendants(&genesis_hash, 4).unwrap().len(), 4);
+
+        // Re-saving a height replaces the lineage recorded by the old block
+        let mut replacement = Block::new(2, block1.hash, chain.difficulty, vec![]);
+        replacement.hash = [3u8; 32];
+        db.save_block(&replacement).unwrap();
+        assert!(db.get_lineage(&leaf).unwrap().is_none());
+        assert!(db.get_lineage(&children[0].hash()).unwrap().unwrap().spent.is_none());
+
+        db.rebuild_lineage().unwrap();
+        assert_eq!(db.get_descendants(&genesis_hash, 100).unwrap().len(), 3);
+    }
 }
@@ -0,0 +1,540 @@
+//! Partially-signed transactions
+//!
+//! A [`PartiallySignedTransaction`] carries an unsigned transaction from the
+//! machine that builds it to the machines holding the keys, in the spirit of
+//! Bitcoin's PSBT. Next to the transaction it holds the triangle being spent,
+//! so an offline signer can check what it signs without a copy of the chain,
+//! derivation hints telling HD wallets which key to use, and the signatures
+//! collected so far. Copies signed by different parties are merged with
+//! [`PartiallySignedTransaction::combine`]; once enough signatures are present,
+//! `finalize` attaches them to the transaction and `extract` returns it ready
+//! for broadcast.
+//!
+//! Inputs locked to a [`Script`] collect one signature per key and are
+//! finalized into a [`ScriptSpend`] witness, which makes threshold spends
+//! between several signers possible.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::{BlockHeight, Sha256Hash, TriangleState};
+use crate::crypto::{self, KeyPair, SignatureScheme};
+use crate::error::ChainError;
+use crate::geometry::Triangle;
+use crate::keystore::Keystore;
+use crate::script::{is_script_address, Script, ScriptSpend, Witness, WitnessSignature};
+use crate::transaction::{Address, Transaction};
+
+/// Current partially-signed transaction format version
+pub const PSBT_VERSION: u32 = 1;
+
+/// Tells a signer where the key for an address sits in its HD wallet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyHint {
+    pub address: Address,
+    pub derivation_path: String,
+}
+
+/// An unsigned transaction together with everything needed to sign it offline.
+///
+/// Triangle hashes commit to geometry only, so a signer trusts the creator for
+/// the owner recorded in `input`; the chain checks it again at broadcast.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartiallySignedTransaction {
+    pub version: u32,
+    /// The transaction; unsigned until finalized
+    pub transaction: Transaction,
+    /// The triangle the transaction spends
+    pub input: Triangle,
+    /// Spending script, required when the input is locked to one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<Script>,
+    #[serde(default)]
+    pub hints: Vec<KeyHint>,
+    /// Signatures collected so far, over the transaction's signable message
+    #[serde(default)]
+    pub signatures: Vec<WitnessSignature>,
+    /// Hashlock preimages to include in a script witness
+    #[serde(default)]
+    pub preimages: Vec<Vec<u8>>,
+    #[serde(default)]
+    pub finalized: bool,
+}
+
+impl PartiallySignedTransaction {
+    /// Wrap `transaction`, which spends `input`. Any signature or witness
+    /// already attached is dropped.
+    pub fn new(mut transaction: Transaction, input: Triangle) -> Result<Self, ChainError> {
+        let input_hash = spent_hash(&transaction)?;
+        if input.hash() != input_hash {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Input triangle does not match the spent hash {}", hex::encode(input_hash)
+            )));
+        }
+
+        match &mut transaction {
+            Transaction::Transfer(tx) => {
+                tx.signature = None;
+                tx.public_key = None;
+                tx.signature_scheme = SignatureScheme::Ecdsa;
+                tx.script_spend = None;
+            }
+            Transaction::Subdivision(tx) => {
+                tx.signature = None;
+                tx.public_key = None;
+                tx.signature_scheme = SignatureScheme::Ecdsa;
+                tx.script_spend = None;
+            }
+            Transaction::Coinbase(_) => return Err(coinbase_error()),
+        }
+
+        Ok(PartiallySignedTransaction {
+            version: PSBT_VERSION,
+            transaction,
+            input,
+            script: None,
+            hints: Vec::new(),
+            signatures: Vec::new(),
+            preimages: Vec::new(),
+            finalized: false,
+        })
+    }
+
+    /// Wrap `transaction`, looking up the triangle it spends in `state`
+    pub fn create(transaction: Transaction, state: &TriangleState) -> Result<Self, ChainError> {
+        let input_hash = spent_hash(&transaction)?;
+        let input = state.get(&input_hash)
+            .ok_or_else(|| ChainError::TriangleNotFound(hex::encode(input_hash)))?;
+        Self::new(transaction, input.clone())
+    }
+
+    /// Reveal the script the input is locked to
+    pub fn with_script(mut self, script: Script) -> Result<Self, ChainError> {
+        script.check_limits()?;
+        if script.address() != self.input.owner {
+            return Err(ChainError::InvalidTransaction(
+                "Script does not match the owner of the input triangle".to_string()
+            ));
+        }
+        self.script = Some(script);
+        Ok(self)
+    }
+
+    /// Record where the key for `address` can be derived
+    pub fn add_hint(&mut self, address: Address, derivation_path: String) {
+        let hint = KeyHint { address, derivation_path };
+        if !self.hints.contains(&hint) {
+            self.hints.push(hint);
+        }
+    }
+
+    /// Add a hashlock preimage for the script witness
+    pub fn add_preimage(&mut self, preimage: Vec<u8>) {
+        if !self.preimages.contains(&preimage) {
+            self.preimages.push(preimage);
+        }
+    }
+
+    /// Hash of the triangle being spent
+    pub fn input_hash(&self) -> Sha256Hash {
+        self.input.hash()
+    }
+
+    /// The message every signer signs
+    pub fn signable_message(&self) -> Vec<u8> {
+        match &self.transaction {
+            Transaction::Transfer(tx) => tx.signable_message(),
+            Transaction::Subdivision(tx) => tx.signable_message(),
+            Transaction::Coinbase(_) => Vec::new(),
+        }
+    }
+
+    /// Addresses whose signatures can authorize the spend
+    pub fn required_signers(&self) -> Vec<Address> {
+        match &self.script {
+            Some(script) => {
+                let mut keys = Vec::new();
+                script_keys(script, &mut keys);
+                keys
+            }
+            None => vec![self.input.owner.clone()],
+        }
+    }
+
+    /// Addresses that have signed so far
+    pub fn signers(&self) -> Result<HashSet<Address>, ChainError> {
+        self.signatures.iter()
+            .map(|sig| crypto::address_from_public_key(&sig.public_key))
+            .collect()
+    }
+
+    /// Sign with one key. Returns false if that key has already signed.
+    pub fn sign_with(&mut self, keypair: &KeyPair) -> Result<bool, ChainError> {
+        self.ensure_not_finalized()?;
+        let address = keypair.address();
+        if !self.required_signers().contains(&address) {
+            return Err(ChainError::WalletError(format!("{} is not a signer of this transaction", address)));
+        }
+        if self.signers()?.contains(&address) {
+            return Ok(false);
+        }
+
+        let signature = keypair.sign(&self.signable_message())?;
+        self.signatures.push(WitnessSignature {
+            public_key: keypair.public_key_bytes(),
+            signature,
+            scheme: SignatureScheme::Ecdsa,
+        });
+        Ok(true)
+    }
+
+    /// Sign with every key of `keystore` that is a signer and has not signed
+    /// yet, deriving keys from hints when the keystore does not hold them.
+    /// Returns how many signatures were added.
+    pub fn sign(&mut self, keystore: &Keystore) -> Result<usize, ChainError> {
+        self.ensure_not_finalized()?;
+        let mut added = 0;
+        for address in self.required_signers() {
+            let Some(keypair) = self.find_key(keystore, &address) else {
+                continue;
+            };
+            if self.sign_with(&keypair)? {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    fn find_key(&self, keystore: &Keystore, address: &str) -> Option<KeyPair> {
+        if let Ok(keypair) = keystore.keypair_for(address) {
+            return Some(keypair);
+        }
+        self.hints.iter()
+            .filter(|hint| hint.address == address)
+            .filter_map(|hint| keystore.derive_keypair_at(&hint.derivation_path).ok())
+            .find(|keypair| keypair.address() == address)
+    }
+
+    /// Merge the hints, signatures and preimages of another copy of the same
+    /// transaction. Invalid signatures are rejected.
+    pub fn combine(&mut self, other: &PartiallySignedTransaction) -> Result<(), ChainError> {
+        self.ensure_not_finalized()?;
+        if self.transaction.hash() != other.transaction.hash() || self.input_hash() != other.input_hash() {
+            return Err(ChainError::InvalidTransaction(
+                "Cannot combine partially-signed copies of different transactions".to_string()
+            ));
+        }
+        match (&self.script, &other.script) {
+            (Some(ours), Some(theirs)) if ours != theirs => {
+                return Err(ChainError::InvalidTransaction("Copies reveal different scripts".to_string()));
+            }
+            (None, Some(theirs)) => self.script = Some(theirs.clone()),
+            _ => {}
+        }
+
+        let message = self.signable_message();
+        for sig in &other.signatures {
+            if self.signatures.iter().any(|s| s.public_key == sig.public_key) {
+                continue;
+            }
+            if !crypto::verify_signature_with(sig.scheme, &sig.public_key, &message, &sig.signature)? {
+                return Err(ChainError::InvalidTransaction("Invalid signature in partially-signed copy".to_string()));
+            }
+            self.signatures.push(sig.clone());
+        }
+        for hint in &other.hints {
+            self.add_hint(hint.address.clone(), hint.derivation_path.clone());
+        }
+        for preimage in &other.preimages {
+            self.add_preimage(preimage.clone());
+        }
+        Ok(())
+    }
+
+    /// Whether enough signatures are present to finalize
+    pub fn is_complete(&self) -> bool {
+        self.finalized || self.build_final().is_ok()
+    }
+
+    /// Attach the collected signatures to the transaction. Timelocks in a
+    /// script are checked when the spend is included in a block.
+    pub fn finalize(&mut self) -> Result<(), ChainError> {
+        if !self.finalized {
+            self.transaction = self.build_final()?;
+            self.finalized = true;
+        }
+        Ok(())
+    }
+
+    /// The finalized transaction, checked against the spent triangle
+    pub fn extract(&self) -> Result<Transaction, ChainError> {
+        if !self.finalized {
+            return Err(ChainError::InvalidTransaction("Transaction has not been finalized".to_string()));
+        }
+        self.transaction.validate(&self.input_state())?;
+        Ok(self.transaction.clone())
+    }
+
+    fn build_final(&self) -> Result<Transaction, ChainError> {
+        let message = self.signable_message();
+        let mut transaction = self.transaction.clone();
+
+        if is_script_address(&self.input.owner) {
+            let script = self.script.clone().ok_or_else(|| ChainError::InvalidTransaction(
+                "Input is locked to a script that has not been provided".to_string()
+            ))?;
+            let keys: HashSet<Address> = self.required_signers().into_iter().collect();
+            let mut signatures = Vec::new();
+            for sig in &self.signatures {
+                if keys.contains(&crypto::address_from_public_key(&sig.public_key)?) {
+                    signatures.push(sig.clone());
+                }
+            }
+            let spend = ScriptSpend::new(script, Witness { signatures, preimages: self.preimages.clone() });
+            spend.verify(&self.input.owner, &message, BlockHeight::MAX)?;
+
+            match &mut transaction {
+                Transaction::Transfer(tx) => tx.script_spend = Some(spend),
+                Transaction::Subdivision(tx) => tx.script_spend = Some(spend),
+                Transaction::Coinbase(_) => return Err(coinbase_error()),
+            }
+        } else {
+            let mut owner_signature = None;
+            for sig in &self.signatures {
+                if crypto::address_from_public_key(&sig.public_key)? == self.input.owner {
+                    owner_signature = Some(sig.clone());
+                }
+            }
+            let WitnessSignature { public_key, signature, scheme } = owner_signature.ok_or_else(|| {
+                ChainError::InvalidTransaction(format!("Missing signature from {}", self.input.owner))
+            })?;
+
+            match &mut transaction {
+                Transaction::Transfer(tx) => tx.sign_with_scheme(scheme, signature, public_key),
+                Transaction::Subdivision(tx) => tx.sign_with_scheme(scheme, signature, public_key),
+                Transaction::Coinbase(_) => return Err(coinbase_error()),
+            }
+        }
+
+        transaction.validate(&self.input_state())?;
+        Ok(transaction)
+    }
+
+    /// A state holding only the spent triangle, enough for stateful checks
+    fn input_state(&self) -> TriangleState {
+        let mut state = TriangleState::new();
+        state.insert_triangle(self.input_hash(), self.input.clone());
+        state
+    }
+
+    fn ensure_not_finalized(&self) -> Result<(), ChainError> {
+        if self.finalized {
+            return Err(ChainError::InvalidTransaction("Transaction is already finalized".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Re-check what `new` and `with_script` enforce, for copies that did
+    /// not come through them: the input must be the triangle the transaction
+    /// actually spends, and a revealed script must be the one it is locked to.
+    fn check_consistency(&self) -> Result<(), ChainError> {
+        let input_hash = spent_hash(&self.transaction)?;
+        if self.input.hash() != input_hash {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Input triangle does not match the spent hash {}", hex::encode(input_hash)
+            )));
+        }
+        if let Some(script) = &self.script {
+            script.check_limits()?;
+            if script.address() != self.input.owner {
+                return Err(ChainError::InvalidTransaction(
+                    "Script does not match the owner of the input triangle".to_string()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a copy from disk, rejecting files whose input does not match
+    /// the transaction they carry
+    pub fn load(path: &Path) -> Result<Self, ChainError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ChainError::InvalidTransaction(format!("Failed to read {}: {}", path.display(), e)))?;
+        let psbt: Self = serde_json::from_str(&contents)
+            .map_err(|e| ChainError::InvalidTransaction(format!("Failed to parse partially-signed transaction: {}", e)))?;
+        if psbt.version > PSBT_VERSION {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Unsupported partially-signed transaction version {}", psbt.version
+            )));
+        }
+        psbt.check_consistency()?;
+        Ok(psbt)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ChainError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ChainError::InvalidTransaction(format!("Failed to serialize partially-signed transaction: {}", e)))?;
+        fs::write(path, json)
+            .map_err(|e| ChainError::InvalidTransaction(format!("Failed to write {}: {}", path.display(), e)))
+    }
+}
+
+/// Hash of the triangle a transaction spends
+fn spent_hash(transaction: &Transaction) -> Result<Sha256Hash, ChainError> {
+    match transaction {
+        Transaction::Transfer(tx) => Ok(tx.input_hash),
+        Transaction::Subdivision(tx) => Ok(tx.parent_hash),
+        Transaction::Coinbase(_) => Err(coinbase_error()),
+    }
+}
+
+fn coinbase_error() -> ChainError {
+    ChainError::InvalidTransaction("Coinbase transactions are not signed".to_string())
+}
+
+/// Key addresses referenced by a script, in order of appearance
+fn script_keys(script: &Script, keys: &mut Vec<Address>) {
+    match script {
+        Script::Key(address) => {
+            if !keys.contains(address) {
+                keys.push(address.clone());
+            }
+        }
+        Script::Threshold { keys: addresses, .. } => {
+            for address in addresses {
+                if !keys.contains(address) {
+                    keys.push(address.clone());
+                }
+            }
+        }
+        Script::AfterHeight(_) | Script::HashLock(_) => {}
+        Script::All(children) | Script::Any(children) => {
+            for child in children {
+                script_keys(child, keys);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Point;
+    use crate::hdwallet::HDWallet;
+    use crate::transaction::TransferTx;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn triangle(owner: Address) -> Triangle {
+        Triangle::new(Point::from_f64(0.0, 0.0), Point::from_f64(2.0, 0.0), Point::from_f64(0.0, 2.0), None, owner)
+    }
+
+    fn transfer(input: &Triangle) -> Transaction {
+        Transaction::Transfer(TransferTx::new(input.hash(), "ab".repeat(32), input.owner.clone(), 1, 7))
+    }
+
+    #[test]
+    fn test_offline_signing_with_derivation_hint() {
+        let seed = HDWallet::seed_from_mnemonic(PHRASE, None).unwrap();
+        let signer = Keystore::from_seed(None, &seed).unwrap();
+        let keypair = HDWallet::derive_keypair(&seed, 0, 1, 4).unwrap();
+        let input = triangle(keypair.address());
+
+        let mut psbt = PartiallySignedTransaction::new(transfer(&input), input.clone()).unwrap();
+        assert!(!psbt.is_complete());
+
+        // The signer does not hold the key until the hint tells it where to look
+        assert_eq!(psbt.sign(&signer).unwrap(), 0);
+        psbt.add_hint(keypair.address(), HDWallet::derivation_path(0, 1, 4));
+        assert_eq!(psbt.sign(&signer).unwrap(), 1);
+        assert_eq!(psbt.sign(&signer).unwrap(), 0);
+
+        psbt.finalize().unwrap();
+        let tx = psbt.extract().unwrap();
+        let mut state = TriangleState::new();
+        state.insert_triangle(input.hash(), input);
+        assert!(tx.validate(&state).is_ok());
+        assert!(psbt.sign_with(&keypair).is_err());
+    }
+
+    #[test]
+    fn test_threshold_signers_combine() {
+        let keys: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate().unwrap()).collect();
+        let script = Script::Threshold { threshold: 2, keys: keys.iter().map(|k| k.address()).collect() };
+        let input = triangle(script.address());
+
+        let base = PartiallySignedTransaction::new(transfer(&input), input).unwrap()
+            .with_script(script).unwrap();
+        assert_eq!(base.required_signers().len(), 3);
+
+        let mut first = base.clone();
+        first.sign_with(&keys[0]).unwrap();
+        assert!(first.finalize().is_err());
+
+        let mut second = base.clone();
+        second.sign_with(&keys[2]).unwrap();
+        first.combine(&second).unwrap();
+        assert_eq!(first.signatures.len(), 2);
+
+        // Signatures over a different transaction are refused
+        let mut other = base.clone();
+        if let Transaction::Transfer(tx) = &mut other.transaction {
+            tx.fee = 2;
+        }
+        assert!(first.combine(&other).is_err());
+
+        first.finalize().unwrap();
+        match first.extract().unwrap() {
+            Transaction::Transfer(tx) => assert_eq!(tx.script_spend.unwrap().witness.signatures.len(), 2),
+            _ => panic!("expected a transfer"),
+        }
+    }
+
+    #[test]
+    fn test_rejects_mismatched_input_and_script() {
+        let keypair = KeyPair::generate().unwrap();
+        let input = triangle(keypair.address());
+        let other = Triangle::new(
+            Point::from_f64(0.0, 0.0), Point::from_f64(4.0, 0.0), Point::from_f64(0.0, 4.0), None, "someone_else".to_string(),
+        );
+        let tx = transfer(&other);
+
+        assert!(PartiallySignedTransaction::new(tx.clone(), input.clone()).is_err());
+        let psbt = PartiallySignedTransaction::new(tx, other).unwrap();
+        assert!(psbt.with_script(Script::Key(keypair.address())).is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_inconsistent_files() {
+        let keypair = KeyPair::generate().unwrap();
+        let decoy = triangle(keypair.address());
+        let real = Triangle::new(
+            Point::from_f64(0.0, 0.0), Point::from_f64(8.0, 0.0), Point::from_f64(0.0, 8.0), None, keypair.address(),
+        );
+        let path = std::env::temp_dir().join(format!("test_psbt_load_{}.json", rand::random::<u32>()));
+
+        let mut psbt = PartiallySignedTransaction::new(transfer(&decoy), decoy.clone()).unwrap();
+        psbt.save(&path).unwrap();
+        assert!(PartiallySignedTransaction::load(&path).is_ok());
+
+        // The shown input is a decoy while the transaction spends another triangle
+        psbt.transaction = transfer(&real);
+        psbt.save(&path).unwrap();
+        assert!(PartiallySignedTransaction::load(&path).is_err());
+
+        // A coinbase is refused on load, and finalizing one errors instead of panicking
+        psbt.transaction = Transaction::Coinbase(crate::transaction::CoinbaseTx {
+            reward_area: 1,
+            beneficiary_address: keypair.address(),
+        });
+        psbt.save(&path).unwrap();
+        assert!(PartiallySignedTransaction::load(&path).is_err());
+        psbt.sign_with(&keypair).unwrap();
+        assert!(psbt.finalize().is_err());
+
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,553 @@
+//! Spending-condition scripts for TrinityChain
+//!
+//! A triangle's `owner` is normally the address of a single key. It can
+//! instead be the address of a [`Script`]: a small, non-Turing-complete
+//! predicate tree built from key checks, thresholds, timelocks and
+//! hashlocks. Spending a script-locked triangle requires revealing the
+//! script together with a [`Witness`] that satisfies it. The interpreter
+//! runs during block validation under fixed cost limits.
+
+use std::cell::Cell;
+use std::collections::HashSet;
+
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::blockchain::{BlockHeight, Sha256Hash};
+use crate::crypto::SignatureScheme;
+use crate::error::ChainError;
+use crate::transaction::Address;
+
+/// Prefix that distinguishes script addresses from key addresses
+pub const SCRIPT_ADDRESS_PREFIX: &str = "script:";
+
+/// Maximum nesting depth of a script
+pub const MAX_SCRIPT_DEPTH: usize = 8;
+
+/// Maximum number of nodes in a script tree
+pub const MAX_SCRIPT_NODES: usize = 64;
+
+/// Maximum number of keys referenced by a single script
+pub const MAX_SCRIPT_KEYS: usize = 16;
+
+/// Maximum number of signatures a witness may carry (bounds signature checks)
+pub const MAX_WITNESS_SIGNATURES: usize = 16;
+
+/// Maximum number of preimages a witness may carry
+pub const MAX_WITNESS_PREIMAGES: usize = 8;
+
+/// Maximum size of a single hashlock preimage in bytes
+pub const MAX_PREIMAGE_SIZE: usize = 128;
+
+/// Domain separator for script address hashing
+const SCRIPT_HASH_DOMAIN: &[u8] = b"TRINITY_SCRIPT:";
+
+/// A spending condition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Script {
+    /// Satisfied by a valid signature from the key with this address
+    Key(Address),
+    /// Satisfied by valid signatures from at least `threshold` of `keys`
+    Threshold { threshold: u8, keys: Vec<Address> },
+    /// Satisfied once the spending block is at or above this height
+    AfterHeight(BlockHeight),
+    /// Satisfied by a witness preimage whose SHA-256 digest is this hash
+    HashLock(Sha256Hash),
+    /// Satisfied when every sub-script is satisfied
+    All(Vec<Script>),
+    /// Satisfied when at least one sub-script is satisfied
+    Any(Vec<Script>),
+}
+
+/// Wire form of [`Script`]. The variants mirror `Script` one-to-one so the
+/// encoding is unchanged; nested scripts decode through `Script`'s own
+/// `Deserialize`, which bounds the nesting depth.
+#[derive(Deserialize)]
+#[serde(rename = "Script")]
+enum ScriptRepr {
+    Key(Address),
+    Threshold { threshold: u8, keys: Vec<Address> },
+    AfterHeight(BlockHeight),
+    HashLock(Sha256Hash),
+    All(Vec<Script>),
+    Any(Vec<Script>),
+}
+
+impl From<ScriptRepr> for Script {
+    fn from(repr: ScriptRepr) -> Self {
+        match repr {
+            ScriptRepr::Key(address) => Script::Key(address),
+            ScriptRepr::Threshold { threshold, keys } => Script::Threshold { threshold, keys },
+            ScriptRepr::AfterHeight(height) => Script::AfterHeight(height),
+            ScriptRepr::HashLock(hash) => Script::HashLock(hash),
+            ScriptRepr::All(children) => Script::All(children),
+            ScriptRepr::Any(children) => Script::Any(children),
+        }
+    }
+}
+
+thread_local! {
+    /// Nesting depth of the script currently being decoded on this thread
+    static DECODE_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Leaves one level of script nesting when dropped, including on error
+struct DecodeDepthGuard;
+
+impl Drop for DecodeDepthGuard {
+    fn drop(&mut self) {
+        DECODE_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+impl<'de> Deserialize<'de> for Script {
+    /// Nesting is bounded while decoding, before `check_limits` can run,
+    /// so a deeply nested payload is rejected instead of exhausting the stack.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let depth = DECODE_DEPTH.with(|depth| {
+            depth.set(depth.get() + 1);
+            depth.get()
+        });
+        let _guard = DecodeDepthGuard;
+        if depth > MAX_SCRIPT_DEPTH {
+            return Err(de::Error::custom(format!(
+                "Script exceeds maximum depth of {}",
+                MAX_SCRIPT_DEPTH
+            )));
+        }
+        ScriptRepr::deserialize(deserializer).map(Script::from)
+    }
+}
+
+/// A signature provided in a witness, with the key that produced it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WitnessSignature {
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+    #[serde(default)]
+    pub scheme: SignatureScheme,
+}
+
+/// Data that satisfies a script
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Witness {
+    pub signatures: Vec<WitnessSignature>,
+    pub preimages: Vec<Vec<u8>>,
+}
+
+/// The revealed script and witness attached to a spend of a script-locked triangle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptSpend {
+    pub script: Script,
+    pub witness: Witness,
+}
+
+impl Script {
+    /// Address that locks a triangle to this script
+    pub fn address(&self) -> Address {
+        let encoded = bincode::serialize(self).expect("Script serialization cannot fail");
+        let mut hasher = Sha256::new();
+        hasher.update(SCRIPT_HASH_DOMAIN);
+        hasher.update(&encoded);
+        format!("{}{:x}", SCRIPT_ADDRESS_PREFIX, hasher.finalize())
+    }
+
+    /// Check the script against the structural cost limits
+    pub fn check_limits(&self) -> Result<(), ChainError> {
+        let mut nodes = 0;
+        let mut keys = 0;
+        self.check_node(1, &mut nodes, &mut keys)
+    }
+
+    fn check_node(&self, depth: usize, nodes: &mut usize, keys: &mut usize) -> Result<(), ChainError> {
+        if depth > MAX_SCRIPT_DEPTH {
+            return Err(script_error(format!("Script exceeds maximum depth of {}", MAX_SCRIPT_DEPTH)));
+        }
+
+        *nodes += 1;
+        if *nodes > MAX_SCRIPT_NODES {
+            return Err(script_error(format!("Script exceeds maximum of {} nodes", MAX_SCRIPT_NODES)));
+        }
+
+        match self {
+            Script::Key(address) => {
+                if address.is_empty() || is_script_address(address) {
+                    return Err(script_error("Key condition must reference a key address".to_string()));
+                }
+                *keys += 1;
+            }
+            Script::Threshold { threshold, keys: addresses } => {
+                if *threshold == 0 || *threshold as usize > addresses.len() {
+                    return Err(script_error(format!(
+                        "Threshold {} is invalid for {} keys",
+                        threshold,
+                        addresses.len()
+                    )));
+                }
+                let distinct: HashSet<&Address> = addresses.iter().collect();
+                if distinct.len() != addresses.len() {
+                    return Err(script_error("Threshold keys must be distinct".to_string()));
+                }
+                if addresses.iter().any(|a| a.is_empty() || is_script_address(a)) {
+                    return Err(script_error("Threshold keys must be key addresses".to_string()));
+                }
+                *keys += addresses.len();
+            }
+            Script::AfterHeight(_) | Script::HashLock(_) => {}
+            Script::All(children) | Script::Any(children) => {
+                if children.is_empty() {
+                    return Err(script_error("Script combinator cannot be empty".to_string()));
+                }
+                for child in children {
+                    child.check_node(depth + 1, nodes, keys)?;
+                }
+            }
+        }
+
+        if *keys > MAX_SCRIPT_KEYS {
+            return Err(script_error(format!("Script references more than {} keys", MAX_SCRIPT_KEYS)));
+        }
+
+        Ok(())
+    }
+
+    fn evaluate(&self, ctx: &EvalContext) -> bool {
+        match self {
+            Script::Key(address) => ctx.signers.contains(address),
+            Script::Threshold { threshold, keys } => {
+                keys.iter().filter(|k| ctx.signers.contains(*k)).count() >= *threshold as usize
+            }
+            Script::AfterHeight(height) => ctx.height >= *height,
+            Script::HashLock(hash) => ctx.preimage_hashes.contains(hash),
+            Script::All(children) => children.iter().all(|c| c.evaluate(ctx)),
+            Script::Any(children) => children.iter().any(|c| c.evaluate(ctx)),
+        }
+    }
+}
+
+impl Witness {
+    /// Check the witness against the cost limits
+    pub fn check_limits(&self) -> Result<(), ChainError> {
+        if self.signatures.len() > MAX_WITNESS_SIGNATURES {
+            return Err(script_error(format!(
+                "Witness exceeds maximum of {} signatures",
+                MAX_WITNESS_SIGNATURES
+            )));
+        }
+        if self.preimages.len() > MAX_WITNESS_PREIMAGES {
+            return Err(script_error(format!(
+                "Witness exceeds maximum of {} preimages",
+                MAX_WITNESS_PREIMAGES
+            )));
+        }
+        if self.preimages.iter().any(|p| p.len() > MAX_PREIMAGE_SIZE) {
+            return Err(script_error(format!(
+                "Witness preimage exceeds maximum size of {} bytes",
+                MAX_PREIMAGE_SIZE
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Facts established from a witness before the script is evaluated
+struct EvalContext {
+    signers: HashSet<Address>,
+    preimage_hashes: HashSet<Sha256Hash>,
+    height: BlockHeight,
+}
+
+impl ScriptSpend {
+    pub fn new(script: Script, witness: Witness) -> Self {
+        ScriptSpend { script, witness }
+    }
+
+    /// Stateless checks: cost limits on the script and witness
+    pub fn check_limits(&self) -> Result<(), ChainError> {
+        self.script.check_limits()?;
+        self.witness.check_limits()
+    }
+
+    /// Verify every witness signature over `message` and return the
+    /// addresses of the signers. This needs no chain state, so block
+    /// validation runs it ahead of the state checks.
+    pub fn verify_signatures(&self, message: &[u8]) -> Result<HashSet<Address>, ChainError> {
+        // Each witness signature is checked exactly once, so the number of
+        // signature operations is bounded by MAX_WITNESS_SIGNATURES.
+        let mut signers = HashSet::new();
+        for sig in &self.witness.signatures {
+            let is_valid = crate::sigcache::verify(sig.scheme, &sig.public_key, message, &sig.signature)?;
+            if !is_valid {
+                return Err(script_error("Invalid witness signature".to_string()));
+            }
+            signers.insert(crate::crypto::address_from_public_key(&sig.public_key)?);
+        }
+        Ok(signers)
+    }
+
+    /// Verify that this spend unlocks a triangle owned by `owner`.
+    ///
+    /// `message` is the transaction's signable message and `height` is the
+    /// height of the block the spend is included in.
+    pub fn verify(&self, owner: &str, message: &[u8], height: BlockHeight) -> Result<(), ChainError> {
+        self.check_limits()?;
+
+        if self.script.address() != owner {
+            return Err(script_error("Revealed script does not match the triangle owner".to_string()));
+        }
+
+        let signers = self.verify_signatures(message)?;
+        let preimage_hashes = self
+            .witness
+            .preimages
+            .iter()
+            .map(|p| Sha256::digest(p).into())
+            .collect();
+
+        let ctx = EvalContext { signers, preimage_hashes, height };
+        if !self.script.evaluate(&ctx) {
+            return Err(script_error("Witness does not satisfy the spending script".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns true if the address refers to a script rather than a key
+pub fn is_script_address(address: &str) -> bool {
+    match address.strip_prefix(SCRIPT_ADDRESS_PREFIX) {
+        Some(hash) => hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+/// Check the spend conditions for a triangle owned by `owner`.
+///
+/// The transaction must name the owner as its `sender`. Script-locked
+/// triangles require a satisfying `ScriptSpend`; key-locked triangles must
+/// not carry one and must be signed with the owner's key.
+pub fn authorize_spend(
+    owner: &str,
+    sender: &str,
+    public_key: Option<&[u8]>,
+    spend: Option<&ScriptSpend>,
+    message: &[u8],
+    height: BlockHeight,
+) -> Result<(), ChainError> {
+    if sender != owner {
+        return Err(script_error(format!(
+            "Transaction spends as {} but the triangle is owned by {}",
+            sender, owner
+        )));
+    }
+
+    match (is_script_address(owner), spend) {
+        (true, Some(spend)) => spend.verify(owner, message, height),
+        (true, None) => Err(script_error(format!(
+            "Triangle is locked to script {} and requires a witness",
+            owner
+        ))),
+        (false, Some(_)) => Err(script_error("Script spend provided for a key-locked triangle".to_string())),
+        (false, None) => {
+            let public_key = public_key.ok_or_else(|| script_error("Transaction not signed".to_string()))?;
+            if crate::crypto::address_from_public_key(public_key)? != owner {
+                return Err(script_error("Signing key does not belong to the triangle owner".to_string()));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn script_error(message: String) -> ChainError {
+    ChainError::InvalidTransaction(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+
+    fn sign(keypair: &KeyPair, message: &[u8]) -> WitnessSignature {
+        WitnessSignature {
+            public_key: keypair.public_key_bytes(),
+            signature: keypair.sign(message).unwrap(),
+            scheme: SignatureScheme::Ecdsa,
+        }
+    }
+
+    #[test]
+    fn test_script_address_is_distinct_from_key_address() {
+        let keypair = KeyPair::generate().unwrap();
+        let script = Script::Key(keypair.address());
+
+        assert!(is_script_address(&script.address()));
+        assert!(!is_script_address(&keypair.address()));
+        assert_eq!(script.address(), Script::Key(keypair.address()).address());
+    }
+
+    #[test]
+    fn test_threshold_script() {
+        let keys: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate().unwrap()).collect();
+        let script = Script::Threshold {
+            threshold: 2,
+            keys: keys.iter().map(|k| k.address()).collect(),
+        };
+        let owner = script.address();
+        let message = b"spend";
+
+        let one = ScriptSpend::new(script.clone(), Witness {
+            signatures: vec![sign(&keys[0], message)],
+            preimages: vec![],
+        });
+        assert!(one.verify(&owner, message, 1).is_err());
+
+        let two = ScriptSpend::new(script, Witness {
+            signatures: vec![sign(&keys[0], message), sign(&keys[2], message)],
+            preimages: vec![],
+        });
+        assert!(two.verify(&owner, message, 1).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_signatures_count_once() {
+        let keys: Vec<KeyPair> = (0..2).map(|_| KeyPair::generate().unwrap()).collect();
+        let script = Script::Threshold {
+            threshold: 2,
+            keys: keys.iter().map(|k| k.address()).collect(),
+        };
+        let message = b"spend";
+        let spend = ScriptSpend::new(script.clone(), Witness {
+            signatures: vec![sign(&keys[0], message), sign(&keys[0], message)],
+            preimages: vec![],
+        });
+
+        assert!(spend.verify(&script.address(), message, 1).is_err());
+    }
+
+    #[test]
+    fn test_timelock_and_hashlock() {
+        let preimage = b"secret".to_vec();
+        let script = Script::All(vec![
+            Script::AfterHeight(10),
+            Script::HashLock(Sha256::digest(&preimage).into()),
+        ]);
+        let owner = script.address();
+        let spend = ScriptSpend::new(script, Witness {
+            signatures: vec![],
+            preimages: vec![preimage],
+        });
+
+        assert!(spend.verify(&owner, b"spend", 9).is_err());
+        assert!(spend.verify(&owner, b"spend", 10).is_ok());
+    }
+
+    #[test]
+    fn test_wrong_script_rejected() {
+        let keypair = KeyPair::generate().unwrap();
+        let owner = Script::AfterHeight(100).address();
+        let spend = ScriptSpend::new(Script::Key(keypair.address()), Witness {
+            signatures: vec![sign(&keypair, b"spend")],
+            preimages: vec![],
+        });
+
+        assert!(spend.verify(&owner, b"spend", 200).is_err());
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let mut script = Script::AfterHeight(0);
+        for _ in 0..MAX_SCRIPT_DEPTH {
+            script = Script::Any(vec![script]);
+        }
+        assert!(script.check_limits().is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_deep_nesting() {
+        let mut script = Script::AfterHeight(0);
+        for _ in 1..MAX_SCRIPT_DEPTH {
+            script = Script::All(vec![script]);
+        }
+        let encoded = bincode::serialize(&script).unwrap();
+        assert_eq!(bincode::deserialize::<Script>(&encoded).unwrap(), script);
+        let json = serde_json::to_string(&script).unwrap();
+        assert_eq!(serde_json::from_str::<Script>(&json).unwrap(), script);
+
+        let too_deep = Script::All(vec![script]);
+        assert!(bincode::deserialize::<Script>(&bincode::serialize(&too_deep).unwrap()).is_err());
+
+        // A hostile payload nesting `All` far beyond any stack budget is
+        // rejected at the depth limit rather than decoded recursively.
+        let mut payload = Vec::new();
+        for _ in 0..100_000 {
+            payload.extend_from_slice(&4u32.to_le_bytes());
+            payload.extend_from_slice(&1u64.to_le_bytes());
+        }
+        assert!(bincode::deserialize::<Script>(&payload).is_err());
+
+        // The depth counter is released after a failed decode
+        assert!(bincode::deserialize::<Script>(&encoded).is_ok());
+    }
+
+    #[test]
+    fn test_authorize_spend() {
+        let keypair = KeyPair::generate().unwrap();
+        let key = keypair.public_key_bytes();
+        let owner = keypair.address();
+        let script = Script::AfterHeight(0);
+        let spend = ScriptSpend::new(script.clone(), Witness::default());
+
+        assert!(authorize_spend(&owner, &owner, Some(&key), None, b"spend", 1).is_ok());
+        assert!(authorize_spend(&owner, &owner, None, None, b"spend", 1).is_err());
+        assert!(authorize_spend(&owner, &owner, Some(&key), Some(&spend), b"spend", 1).is_err());
+        assert!(authorize_spend(&script.address(), &script.address(), None, None, b"spend", 1).is_err());
+        assert!(authorize_spend(&script.address(), &script.address(), None, Some(&spend), b"spend", 1).is_ok());
+    }
+
+    #[test]
+    fn test_authorize_spend_rejects_foreign_key() {
+        let owner = KeyPair::generate().unwrap();
+        let attacker = KeyPair::generate().unwrap();
+
+        // Naming the owner as sender does not help a key the owner does not hold
+        let result = authorize_spend(
+            &owner.address(), &owner.address(), Some(&attacker.public_key_bytes()), None, b"spend", 1,
+        );
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("Signing key")));
+
+        // Nor does naming the attacker, whose key it is
+        let result = authorize_spend(
+            &owner.address(), &attacker.address(), Some(&attacker.public_key_bytes()), None, b"spend", 1,
+        );
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("owned by")));
+    }
+
+    #[test]
+    fn test_witness_mixes_signature_schemes() {
+        let keys: Vec<KeyPair> = (0..2).map(|_| KeyPair::generate().unwrap()).collect();
+        let script = Script::Threshold {
+            threshold: 2,
+            keys: keys.iter().map(|k| k.address()).collect(),
+        };
+        let message = b"spend";
+        let schnorr = WitnessSignature {
+            public_key: keys[1].public_key_bytes(),
+            signature: keys[1].sign_schnorr(message).unwrap(),
+            scheme: SignatureScheme::Schnorr,
+        };
+
+        let spend = ScriptSpend::new(script.clone(), Witness {
+            signatures: vec![sign(&keys[0], message), schnorr.clone()],
+            preimages: vec![],
+        });
+        assert!(spend.verify(&script.address(), message, 1).is_ok());
+
+        let mistagged = WitnessSignature { scheme: SignatureScheme::Ecdsa, ..schnorr };
+        let spend = ScriptSpend::new(script.clone(), Witness {
+            signatures: vec![sign(&keys[0], message), mistagged],
+            preimages: vec![],
+        });
+        assert!(spend.verify(&script.address(), message, 1).is_err());
+    }
+}
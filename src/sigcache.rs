@@ -0,0 +1,165 @@
+//! Cache of verified signatures
+//!
+//! A transaction's signatures are checked when it enters the mempool and
+//! again when it arrives in a block. The cache remembers every signature that
+//! verified, keyed by a digest of the scheme, public key, message and
+//! signature, so the second check is a lookup. Only successful verifications
+//! are stored: a cache hit can never turn an invalid signature into a valid
+//! one.
+
+use std::num::NonZeroUsize;
+use std::sync::{LazyLock, Mutex};
+
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{self, SignatureScheme};
+use crate::error::ChainError;
+
+/// Number of verified signatures the shared cache remembers
+pub const DEFAULT_CAPACITY: usize = 100_000;
+
+/// Domain separator for cache keys
+const CACHE_KEY_DOMAIN: &[u8] = b"TRINITY_SIGCACHE:";
+
+static GLOBAL: LazyLock<SignatureCache> = LazyLock::new(|| SignatureCache::new(DEFAULT_CAPACITY));
+
+/// The cache shared by mempool admission and block validation
+pub fn global() -> &'static SignatureCache {
+    &GLOBAL
+}
+
+/// Verify a signature through the shared cache
+pub fn verify(
+    scheme: SignatureScheme,
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<bool, ChainError> {
+    global().verify(scheme, public_key, message, signature)
+}
+
+/// A bounded, thread-safe set of signatures known to be valid
+pub struct SignatureCache {
+    entries: Mutex<LruCache<[u8; 32], ()>>,
+}
+
+impl SignatureCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        SignatureCache { entries: Mutex::new(LruCache::new(capacity)) }
+    }
+
+    /// Verify a signature, skipping the check if it has verified before
+    pub fn verify(
+        &self,
+        scheme: SignatureScheme,
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, ChainError> {
+        let key = cache_key(scheme, public_key, message, signature);
+        if self.lock().get(&key).is_some() {
+            return Ok(true);
+        }
+
+        // Verify without holding the lock so checks run in parallel
+        let is_valid = crypto::verify_signature_with(scheme, public_key, message, signature)?;
+        if is_valid {
+            self.lock().put(key, ());
+        }
+        Ok(is_valid)
+    }
+
+    /// Whether this signature has verified before
+    pub fn contains(&self, scheme: SignatureScheme, public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        self.lock().contains(&cache_key(scheme, public_key, message, signature))
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<[u8; 32], ()>> {
+        // The cache holds no invariants a panicking thread could break
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Digest identifying one (scheme, key, message, signature) combination.
+/// Every variable-length part is length-prefixed so no two combinations
+/// share an encoding.
+fn cache_key(scheme: SignatureScheme, public_key: &[u8], message: &[u8], signature: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(CACHE_KEY_DOMAIN);
+    hasher.update(scheme.name().as_bytes());
+    for part in [public_key, message, signature] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+
+    #[test]
+    fn test_only_valid_signatures_are_cached() {
+        let cache = SignatureCache::new(16);
+        let keypair = KeyPair::generate().unwrap();
+        let public_key = keypair.public_key_bytes();
+        let signature = keypair.sign(b"message").unwrap();
+
+        assert!(!cache.verify(SignatureScheme::Ecdsa, &public_key, b"tampered", &signature).unwrap());
+        assert!(cache.is_empty());
+
+        assert!(cache.verify(SignatureScheme::Ecdsa, &public_key, b"message", &signature).unwrap());
+        assert!(cache.contains(SignatureScheme::Ecdsa, &public_key, b"message", &signature));
+        assert!(!cache.contains(SignatureScheme::Schnorr, &public_key, b"message", &signature));
+        assert!(cache.verify(SignatureScheme::Ecdsa, &public_key, b"message", &signature).unwrap());
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_bad_signature_misses_for_cached_key_and_message() {
+        let cache = SignatureCache::new(16);
+        let keypair = KeyPair::generate().unwrap();
+        let public_key = keypair.public_key_bytes();
+        let signature = keypair.sign(b"message").unwrap();
+        assert!(cache.verify(SignatureScheme::Ecdsa, &public_key, b"message", &signature).unwrap());
+
+        // Same key and message with a signature that does not verify: a cache
+        // keyed on (key, message) alone would wrongly accept these
+        let other = keypair.sign(b"another message").unwrap();
+        assert!(!cache.verify(SignatureScheme::Ecdsa, &public_key, b"message", &other).unwrap());
+        let mut corrupted = signature.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0x01;
+        assert!(!cache.verify(SignatureScheme::Ecdsa, &public_key, b"message", &corrupted).unwrap_or(false));
+
+        assert!(!cache.contains(SignatureScheme::Ecdsa, &public_key, b"message", &other));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_capacity_is_bounded() {
+        let cache = SignatureCache::new(2);
+        let keypair = KeyPair::generate().unwrap();
+        let public_key = keypair.public_key_bytes();
+        for message in [b"one", b"two", b"six"] {
+            let signature = keypair.sign(message).unwrap();
+            assert!(cache.verify(SignatureScheme::Ecdsa, &public_key, message, &signature).unwrap());
+        }
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -0,0 +1,669 @@
+//! SLIP-39 Shamir backups of wallet seeds
+//!
+//! The seed is encrypted with a passphrase and split into mnemonic shares
+//! following SLIP-39: a two-level scheme where `group_threshold` of the groups
+//! must be recovered, each from `member_threshold` of its members. Every share
+//! is a sequence of words from the SLIP-39 wordlist ending in an RS1024
+//! checksum, and the polynomial of each split carries a digest share so a
+//! wrong combination is detected instead of yielding a bad seed. Shares can
+//! be combined in any order.
+
+use std::collections::BTreeMap;
+
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore};
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::error::ChainError;
+
+/// Maximum number of groups, and of members in a group
+pub const MAX_SHARE_COUNT: u8 = 16;
+
+/// Minimum length of the shared secret in bytes
+pub const MIN_SECRET_LENGTH: usize = 16;
+
+/// Iteration exponent used for new shares: 10000 << 1 PBKDF2 iterations
+pub const DEFAULT_ITERATION_EXPONENT: u8 = 1;
+
+const WORDLIST: &str = include_str!("slip39_wordlist.txt");
+const RADIX_BITS: usize = 10;
+const ID_LENGTH_BITS: u32 = 15;
+const ITERATION_EXP_LENGTH_BITS: u32 = 4;
+const CHECKSUM_LENGTH_WORDS: usize = 3;
+/// Identifier, extendable flag and iteration exponent (2 words), group and
+/// member parameters (2 words) and the checksum
+const METADATA_LENGTH_WORDS: usize = 4 + CHECKSUM_LENGTH_WORDS;
+const MIN_MNEMONIC_LENGTH_WORDS: usize = METADATA_LENGTH_WORDS + (MIN_SECRET_LENGTH * 8).div_ceil(RADIX_BITS);
+const DIGEST_LENGTH_BYTES: usize = 4;
+const SECRET_INDEX: u8 = 255;
+const DIGEST_INDEX: u8 = 254;
+const BASE_ITERATION_COUNT: u32 = 10000;
+const ROUND_COUNT: u8 = 4;
+const CUSTOMIZATION_STRING: &[u8] = b"shamir";
+const CUSTOMIZATION_STRING_EXTENDABLE: &[u8] = b"shamir_extendable";
+
+/// Threshold and size of one group of shares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupSpec {
+    pub member_threshold: u8,
+    pub member_count: u8,
+}
+
+impl GroupSpec {
+    pub fn new(member_threshold: u8, member_count: u8) -> Self {
+        GroupSpec { member_threshold, member_count }
+    }
+}
+
+/// One SLIP-39 share
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    /// Random identifier shared by every share of one split
+    pub identifier: u16,
+    /// Whether the encryption salt omits the identifier
+    pub extendable: bool,
+    pub iteration_exponent: u8,
+    pub group_index: u8,
+    pub group_threshold: u8,
+    pub group_count: u8,
+    pub member_index: u8,
+    pub member_threshold: u8,
+    value: Vec<u8>,
+}
+
+impl Share {
+    /// Parse a share mnemonic, checking its words, checksum and padding
+    pub fn parse(mnemonic: &str) -> Result<Self, ChainError> {
+        let indices = mnemonic.split_whitespace()
+            .map(word_index)
+            .collect::<Result<Vec<u16>, ChainError>>()?;
+
+        if indices.len() < MIN_MNEMONIC_LENGTH_WORDS {
+            return Err(share_error(format!(
+                "Share must be at least {} words, got {}", MIN_MNEMONIC_LENGTH_WORDS, indices.len()
+            )));
+        }
+        let value_words = indices.len() - METADATA_LENGTH_WORDS;
+        let padding_bits = (RADIX_BITS * value_words) % 16;
+        if padding_bits > 8 {
+            return Err(share_error("Share has an invalid length".to_string()));
+        }
+
+        let id_exp = (u32::from(indices[0]) << RADIX_BITS) | u32::from(indices[1]);
+        let identifier = (id_exp >> (ITERATION_EXP_LENGTH_BITS + 1)) as u16;
+        let extendable = (id_exp >> ITERATION_EXP_LENGTH_BITS) & 1 == 1;
+        let iteration_exponent = (id_exp & ((1 << ITERATION_EXP_LENGTH_BITS) - 1)) as u8;
+
+        if !verify_checksum(&indices, extendable) {
+            return Err(share_error("Share checksum is invalid; check the words".to_string()));
+        }
+
+        let params = (u32::from(indices[2]) << RADIX_BITS) | u32::from(indices[3]);
+        let group_index = (params >> 16) as u8;
+        let group_threshold = ((params >> 12) & 0xf) as u8 + 1;
+        let group_count = ((params >> 8) & 0xf) as u8 + 1;
+        let member_index = ((params >> 4) & 0xf) as u8;
+        let member_threshold = (params & 0xf) as u8 + 1;
+        if group_threshold > group_count {
+            return Err(share_error("Share group threshold exceeds the group count".to_string()));
+        }
+
+        let value_byte_count = (RADIX_BITS * value_words - padding_bits) / 8;
+        let value = words_to_bytes(&indices[4..4 + value_words], value_byte_count)
+            .ok_or_else(|| share_error("Share has invalid padding".to_string()))?;
+
+        Ok(Share {
+            identifier,
+            extendable,
+            iteration_exponent,
+            group_index,
+            group_threshold,
+            group_count,
+            member_index,
+            member_threshold,
+            value,
+        })
+    }
+
+    /// The share as a mnemonic of SLIP-39 words
+    pub fn to_mnemonic(&self) -> String {
+        let id_exp = (u32::from(self.identifier) << (ITERATION_EXP_LENGTH_BITS + 1))
+            | (u32::from(self.extendable) << ITERATION_EXP_LENGTH_BITS)
+            | u32::from(self.iteration_exponent);
+        let params = (u32::from(self.group_index) << 16)
+            | (u32::from(self.group_threshold - 1) << 12)
+            | (u32::from(self.group_count - 1) << 8)
+            | (u32::from(self.member_index) << 4)
+            | u32::from(self.member_threshold - 1);
+
+        let mut indices = vec![
+            (id_exp >> RADIX_BITS) as u16,
+            (id_exp & 0x3ff) as u16,
+            (params >> RADIX_BITS) as u16,
+            (params & 0x3ff) as u16,
+        ];
+        indices.extend(bytes_to_words(&self.value));
+        let checksum = create_checksum(&indices, self.extendable);
+        indices.extend(checksum);
+
+        let words: Vec<&str> = WORDLIST.lines().collect();
+        let mnemonic = indices.iter().map(|&i| words[i as usize]).collect::<Vec<_>>().join(" ");
+        indices.zeroize();
+        mnemonic
+    }
+
+    /// Whether `other` comes from the same split as this share
+    fn is_compatible(&self, other: &Share) -> bool {
+        self.identifier == other.identifier
+            && self.extendable == other.extendable
+            && self.iteration_exponent == other.iteration_exponent
+            && self.group_threshold == other.group_threshold
+            && self.group_count == other.group_count
+            && self.value.len() == other.value.len()
+    }
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+/// Encrypt `secret` with `passphrase` and split it into shares. Returns the
+/// shares of each group, in group order.
+pub fn split_secret(
+    group_threshold: u8,
+    groups: &[GroupSpec],
+    secret: &[u8],
+    passphrase: &str,
+    iteration_exponent: u8,
+) -> Result<Vec<Vec<Share>>, ChainError> {
+    if secret.len() < MIN_SECRET_LENGTH || !secret.len().is_multiple_of(2) {
+        return Err(share_error(format!(
+            "Secret must be an even number of bytes, at least {}", MIN_SECRET_LENGTH
+        )));
+    }
+    if groups.is_empty() || groups.len() > MAX_SHARE_COUNT as usize {
+        return Err(share_error(format!("Between 1 and {} groups are required", MAX_SHARE_COUNT)));
+    }
+    if group_threshold == 0 || group_threshold as usize > groups.len() {
+        return Err(share_error(format!(
+            "Group threshold {} is invalid for {} groups", group_threshold, groups.len()
+        )));
+    }
+    for group in groups {
+        if group.member_threshold == 0 || group.member_threshold > group.member_count
+            || group.member_count > MAX_SHARE_COUNT
+        {
+            return Err(share_error(format!(
+                "Group of {}-of-{} shares is invalid", group.member_threshold, group.member_count
+            )));
+        }
+        if group.member_threshold == 1 && group.member_count > 1 {
+            return Err(share_error(
+                "A 1-of-N group would copy the group secret; use 1-of-1 instead".to_string()
+            ));
+        }
+    }
+    if iteration_exponent >= 1 << ITERATION_EXP_LENGTH_BITS {
+        return Err(share_error(format!("Iteration exponent {} is too large", iteration_exponent)));
+    }
+    check_passphrase(passphrase)?;
+
+    let identifier = OsRng.gen_range(0..1u16 << ID_LENGTH_BITS);
+    let extendable = true;
+    let encrypted = encrypt(secret, passphrase.as_bytes(), iteration_exponent, identifier, extendable);
+
+    let group_secrets = split(group_threshold, groups.len() as u8, &encrypted);
+    let mut shares = Vec::with_capacity(groups.len());
+    for (group, (group_index, group_secret)) in groups.iter().zip(group_secrets.iter()) {
+        let members = split(group.member_threshold, group.member_count, group_secret)
+            .into_iter()
+            .map(|(member_index, value)| Share {
+                identifier,
+                extendable,
+                iteration_exponent,
+                group_index: *group_index,
+                group_threshold,
+                group_count: groups.len() as u8,
+                member_index,
+                member_threshold: group.member_threshold,
+                value: value.to_vec(),
+            })
+            .collect();
+        shares.push(members);
+    }
+    Ok(shares)
+}
+
+/// Shares collected towards recovering a secret, in any order
+#[derive(Debug, Default)]
+pub struct ShareSet {
+    shares: Vec<Share>,
+}
+
+impl ShareSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a share. Returns false if it was already in the set.
+    pub fn add(&mut self, share: Share) -> Result<bool, ChainError> {
+        if let Some(first) = self.shares.first() {
+            if !first.is_compatible(&share) {
+                return Err(share_error("Share belongs to a different backup".to_string()));
+            }
+        }
+        for existing in self.shares.iter().filter(|s| s.group_index == share.group_index) {
+            if existing.member_threshold != share.member_threshold {
+                return Err(share_error(format!(
+                    "Shares of group {} disagree on the member threshold", share.group_index + 1
+                )));
+            }
+            if existing.member_index == share.member_index {
+                if existing.value != share.value {
+                    return Err(share_error("Two different shares have the same index".to_string()));
+                }
+                return Ok(false);
+            }
+        }
+        self.shares.push(share);
+        Ok(true)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shares.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shares.is_empty()
+    }
+
+    /// Number of groups that must be complete, once a share has been added
+    pub fn group_threshold(&self) -> Option<u8> {
+        self.shares.first().map(|s| s.group_threshold)
+    }
+
+    /// For each group with shares: (group index, shares present, member threshold)
+    pub fn group_progress(&self) -> Vec<(u8, usize, u8)> {
+        self.groups().into_iter()
+            .map(|(index, members)| (index, members.len(), members[0].member_threshold))
+            .collect()
+    }
+
+    pub fn groups_complete(&self) -> usize {
+        self.groups().values()
+            .filter(|members| members.len() >= members[0].member_threshold as usize)
+            .count()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.group_threshold().is_some_and(|threshold| self.groups_complete() >= threshold as usize)
+    }
+
+    /// Combine the shares and decrypt the secret with `passphrase`. A wrong
+    /// passphrase yields a different secret rather than an error.
+    pub fn recover(&self, passphrase: &str) -> Result<Zeroizing<Vec<u8>>, ChainError> {
+        let first = self.shares.first()
+            .ok_or_else(|| share_error("No shares provided".to_string()))?;
+        if !self.is_complete() {
+            return Err(share_error(format!(
+                "{} of {} required groups are complete",
+                self.groups_complete(), first.group_threshold
+            )));
+        }
+        check_passphrase(passphrase)?;
+
+        let mut group_secrets = Vec::new();
+        for (group_index, members) in self.groups() {
+            let threshold = members[0].member_threshold;
+            if members.len() < threshold as usize {
+                continue;
+            }
+            let points: Vec<(u8, &[u8])> = members.iter()
+                .take(threshold as usize)
+                .map(|s| (s.member_index, s.value.as_slice()))
+                .collect();
+            group_secrets.push((group_index, recover(threshold, &points)?));
+            if group_secrets.len() == first.group_threshold as usize {
+                break;
+            }
+        }
+
+        let points: Vec<(u8, &[u8])> = group_secrets.iter().map(|(i, v)| (*i, v.as_slice())).collect();
+        let encrypted = recover(first.group_threshold, &points)?;
+        Ok(decrypt(&encrypted, passphrase.as_bytes(), first.iteration_exponent, first.identifier, first.extendable))
+    }
+
+    fn groups(&self) -> BTreeMap<u8, Vec<&Share>> {
+        let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+        for share in &self.shares {
+            groups.entry(share.group_index).or_default().push(share);
+        }
+        groups
+    }
+}
+
+/// Recover a secret from share mnemonics given in any order
+pub fn combine_mnemonics<S: AsRef<str>>(mnemonics: &[S], passphrase: &str) -> Result<Zeroizing<Vec<u8>>, ChainError> {
+    let mut set = ShareSet::new();
+    for mnemonic in mnemonics {
+        set.add(Share::parse(mnemonic.as_ref())?)?;
+    }
+    set.recover(passphrase)
+}
+
+fn share_error(message: String) -> ChainError {
+    ChainError::WalletError(message)
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), ChainError> {
+    if passphrase.bytes().all(|b| (32..=126).contains(&b)) {
+        Ok(())
+    } else {
+        Err(share_error("Passphrase must contain printable ASCII characters only".to_string()))
+    }
+}
+
+fn word_index(word: &str) -> Result<u16, ChainError> {
+    let word = word.to_lowercase();
+    WORDLIST.lines()
+        .position(|w| w == word)
+        .map(|i| i as u16)
+        .ok_or_else(|| share_error(format!("'{}' is not a SLIP-39 word", word)))
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    if extendable { CUSTOMIZATION_STRING_EXTENDABLE } else { CUSTOMIZATION_STRING }
+}
+
+fn rs1024_polymod(values: impl Iterator<Item = u32>) -> u32 {
+    const GEN: [u32; 10] = [
+        0xe0e040, 0x1c1c080, 0x3838100, 0x7070200, 0xe0e0009,
+        0x1c0c2412, 0x38086c24, 0x3090fc48, 0x21b1f890, 0x3f3f120,
+    ];
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 20;
+        chk = ((chk & 0xfffff) << 10) ^ value;
+        for (i, generator) in GEN.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn create_checksum(data: &[u16], extendable: bool) -> [u16; CHECKSUM_LENGTH_WORDS] {
+    let values = customization(extendable).iter().map(|&b| u32::from(b))
+        .chain(data.iter().map(|&w| u32::from(w)))
+        .chain(std::iter::repeat_n(0, CHECKSUM_LENGTH_WORDS));
+    let polymod = rs1024_polymod(values) ^ 1;
+    [(polymod >> 20) as u16 & 0x3ff, (polymod >> 10) as u16 & 0x3ff, polymod as u16 & 0x3ff]
+}
+
+fn verify_checksum(indices: &[u16], extendable: bool) -> bool {
+    let values = customization(extendable).iter().map(|&b| u32::from(b))
+        .chain(indices.iter().map(|&w| u32::from(w)));
+    rs1024_polymod(values) == 1
+}
+
+/// Pack bytes into 10-bit words, left-padding with zero bits
+fn bytes_to_words(bytes: &[u8]) -> Vec<u16> {
+    let word_count = (bytes.len() * 8).div_ceil(RADIX_BITS);
+    let mut acc: u32 = 0;
+    let mut acc_bits = word_count * RADIX_BITS - bytes.len() * 8;
+    let mut words = Vec::with_capacity(word_count);
+    for &byte in bytes {
+        acc = (acc << 8) | u32::from(byte);
+        acc_bits += 8;
+        if acc_bits >= RADIX_BITS {
+            acc_bits -= RADIX_BITS;
+            words.push((acc >> acc_bits) as u16);
+            acc &= (1 << acc_bits) - 1;
+        }
+    }
+    words
+}
+
+/// Unpack 10-bit words into `byte_count` bytes; the padding bits must be zero
+fn words_to_bytes(words: &[u16], byte_count: usize) -> Option<Vec<u8>> {
+    let padding = (words.len() * RADIX_BITS).checked_sub(byte_count * 8)?;
+    let mut acc: u32 = 0;
+    let mut acc_bits = 0;
+    let mut skipped = 0;
+    let mut bytes = Vec::with_capacity(byte_count);
+    for &word in words {
+        acc = (acc << RADIX_BITS) | u32::from(word);
+        acc_bits += RADIX_BITS;
+        if skipped < padding {
+            let take = (padding - skipped).min(acc_bits);
+            if acc >> (acc_bits - take) != 0 {
+                return None;
+            }
+            acc_bits -= take;
+            skipped += take;
+        }
+        while acc_bits >= 8 {
+            acc_bits -= 8;
+            bytes.push((acc >> acc_bits) as u8);
+            acc &= (1 << acc_bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+/// Exponent and logarithm tables of GF(256) with the Rijndael polynomial
+const fn gf256_tables() -> ([u8; 255], [u8; 256]) {
+    let mut exp = [0u8; 255];
+    let mut log = [0u8; 256];
+    let mut poly: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = poly as u8;
+        log[poly as usize] = i as u8;
+        // Multiply by the generator 3
+        poly = (poly << 1) ^ poly;
+        if poly & 0x100 != 0 {
+            poly ^= 0x11b;
+        }
+        i += 1;
+    }
+    (exp, log)
+}
+
+const GF256: ([u8; 255], [u8; 256]) = gf256_tables();
+
+/// Evaluate at `x` the polynomial through `points` with Lagrange interpolation
+fn interpolate(points: &[(u8, &[u8])], x: u8) -> Vec<u8> {
+    let (exp, log) = &GF256;
+    if let Some((_, value)) = points.iter().find(|(px, _)| *px == x) {
+        return value.to_vec();
+    }
+
+    let log_product: i32 = points.iter().map(|(px, _)| i32::from(log[(px ^ x) as usize])).sum();
+    let mut result = vec![0u8; points[0].1.len()];
+    for (px, value) in points {
+        let log_basis = (log_product
+            - i32::from(log[(px ^ x) as usize])
+            - points.iter()
+                .filter(|(other, _)| other != px)
+                .map(|(other, _)| i32::from(log[(px ^ other) as usize]))
+                .sum::<i32>())
+            .rem_euclid(255);
+        for (out, &byte) in result.iter_mut().zip(value.iter()) {
+            if byte != 0 {
+                *out ^= exp[((i32::from(log[byte as usize]) + log_basis) % 255) as usize];
+            }
+        }
+    }
+    result
+}
+
+fn create_digest(random: &[u8], secret: &[u8]) -> [u8; DIGEST_LENGTH_BYTES] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(random).expect("HMAC accepts keys of any length");
+    mac.update(secret);
+    let mut digest = [0u8; DIGEST_LENGTH_BYTES];
+    digest.copy_from_slice(&mac.finalize().into_bytes()[..DIGEST_LENGTH_BYTES]);
+    digest
+}
+
+/// Split `secret` into `count` points of a random polynomial of degree
+/// `threshold - 1` that also passes through a digest of the secret
+fn split(threshold: u8, count: u8, secret: &[u8]) -> Vec<(u8, Zeroizing<Vec<u8>>)> {
+    if threshold == 1 {
+        return (0..count).map(|i| (i, Zeroizing::new(secret.to_vec()))).collect();
+    }
+
+    let mut rng = OsRng;
+    let random_count = threshold - 2;
+    let mut points: Vec<(u8, Zeroizing<Vec<u8>>)> = (0..random_count)
+        .map(|i| {
+            let mut value = Zeroizing::new(vec![0u8; secret.len()]);
+            rng.fill_bytes(&mut value);
+            (i, value)
+        })
+        .collect();
+
+    let mut digest_share = Zeroizing::new(vec![0u8; secret.len()]);
+    rng.fill_bytes(&mut digest_share[DIGEST_LENGTH_BYTES..]);
+    let digest = create_digest(&digest_share[DIGEST_LENGTH_BYTES..], secret);
+    digest_share[..DIGEST_LENGTH_BYTES].copy_from_slice(&digest);
+
+    let mut base: Vec<(u8, &[u8])> = points.iter().map(|(i, v)| (*i, v.as_slice())).collect();
+    base.push((DIGEST_INDEX, &digest_share));
+    base.push((SECRET_INDEX, secret));
+    let derived: Vec<(u8, Zeroizing<Vec<u8>>)> = (random_count..count)
+        .map(|i| (i, Zeroizing::new(interpolate(&base, i))))
+        .collect();
+
+    points.extend(derived);
+    points
+}
+
+/// Recover the secret from `threshold` points, checking its digest
+fn recover(threshold: u8, points: &[(u8, &[u8])]) -> Result<Zeroizing<Vec<u8>>, ChainError> {
+    if threshold == 1 {
+        return Ok(Zeroizing::new(points[0].1.to_vec()));
+    }
+
+    let secret = Zeroizing::new(interpolate(points, SECRET_INDEX));
+    let digest_share = Zeroizing::new(interpolate(points, DIGEST_INDEX));
+    if digest_share[..DIGEST_LENGTH_BYTES] != create_digest(&digest_share[DIGEST_LENGTH_BYTES..], &secret) {
+        return Err(share_error("Shares do not combine to a valid secret".to_string()));
+    }
+    Ok(secret)
+}
+
+fn feistel_salt(identifier: u16, extendable: bool) -> Vec<u8> {
+    if extendable {
+        return Vec::new();
+    }
+    let mut salt = CUSTOMIZATION_STRING.to_vec();
+    salt.extend_from_slice(&identifier.to_be_bytes());
+    salt
+}
+
+fn round_function(round: u8, passphrase: &[u8], iteration_exponent: u8, salt: &[u8], r: &[u8]) -> Zeroizing<Vec<u8>> {
+    let mut password = Zeroizing::new(vec![round]);
+    password.extend_from_slice(passphrase);
+    let mut round_salt = salt.to_vec();
+    round_salt.extend_from_slice(r);
+
+    let iterations = (BASE_ITERATION_COUNT << iteration_exponent) / u32::from(ROUND_COUNT);
+    let mut output = Zeroizing::new(vec![0u8; r.len()]);
+    pbkdf2::pbkdf2_hmac::<Sha256>(&password, &round_salt, iterations, &mut output);
+    output
+}
+
+/// Four-round Feistel network keyed by the passphrase. Decryption runs the
+/// rounds in reverse.
+fn feistel(data: &[u8], passphrase: &[u8], iteration_exponent: u8, identifier: u16, extendable: bool, rounds: &[u8]) -> Zeroizing<Vec<u8>> {
+    let half = data.len() / 2;
+    let mut left = Zeroizing::new(data[..half].to_vec());
+    let mut right = Zeroizing::new(data[half..].to_vec());
+    let salt = feistel_salt(identifier, extendable);
+
+    for &round in rounds {
+        let f = round_function(round, passphrase, iteration_exponent, &salt, &right);
+        let mixed: Vec<u8> = left.iter().zip(f.iter()).map(|(a, b)| a ^ b).collect();
+        left = std::mem::replace(&mut right, Zeroizing::new(mixed));
+    }
+
+    let mut output = Zeroizing::new(right.to_vec());
+    output.extend_from_slice(&left);
+    output
+}
+
+fn encrypt(secret: &[u8], passphrase: &[u8], iteration_exponent: u8, identifier: u16, extendable: bool) -> Zeroizing<Vec<u8>> {
+    feistel(secret, passphrase, iteration_exponent, identifier, extendable, &[0, 1, 2, 3])
+}
+
+fn decrypt(encrypted: &[u8], passphrase: &[u8], iteration_exponent: u8, identifier: u16, extendable: bool) -> Zeroizing<Vec<u8>> {
+    feistel(encrypted, passphrase, iteration_exponent, identifier, extendable, &[3, 2, 1, 0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slip39_reference_vectors() {
+        // Vectors 1, 2 and 4 of the SLIP-39 specification
+        let single = "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard";
+        assert_eq!(hex::encode(&*combine_mnemonics(&[single], "TREZOR").unwrap()), "bb54aac4b89dc868ba37d9cc21b2cece");
+
+        let bad_checksum = "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney";
+        assert!(Share::parse(bad_checksum).is_err());
+
+        let two_of_three = [
+            "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+            "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+        ];
+        assert_eq!(hex::encode(&*combine_mnemonics(&two_of_three, "TREZOR").unwrap()), "b43ceb7e57a0ea8766221624d01b0864");
+        assert!(combine_mnemonics(&two_of_three[..1], "TREZOR").is_err());
+
+        // Shares re-encode to the same words
+        assert_eq!(Share::parse(single).unwrap().to_mnemonic(), single);
+    }
+
+    #[test]
+    fn test_group_shares_recover_seed_in_any_order() {
+        let seed: Vec<u8> = (0..64).collect();
+        let groups = [GroupSpec::new(2, 3), GroupSpec::new(1, 1), GroupSpec::new(3, 5)];
+        let shares = split_secret(2, &groups, &seed, "correct horse", 0).unwrap();
+        let mnemonics: Vec<Vec<String>> = shares.iter()
+            .map(|group| group.iter().map(Share::to_mnemonic).collect())
+            .collect();
+
+        // Group 3 (3 of 5) and group 1 (2 of 3), interleaved
+        let chosen = [&mnemonics[2][4], &mnemonics[0][2], &mnemonics[2][0], &mnemonics[0][0], &mnemonics[2][1]];
+        assert_eq!(&*combine_mnemonics(&chosen, "correct horse").unwrap(), &seed);
+        assert_ne!(&*combine_mnemonics(&chosen, "").unwrap(), &seed);
+
+        // Only one group complete is not enough
+        let mut set = ShareSet::new();
+        for mnemonic in &mnemonics[2][..3] {
+            set.add(Share::parse(mnemonic).unwrap()).unwrap();
+        }
+        assert_eq!(set.groups_complete(), 1);
+        assert!(!set.is_complete());
+        assert!(!set.add(Share::parse(&mnemonics[2][0]).unwrap()).unwrap());
+
+        // Shares of another backup are refused
+        let other = split_secret(1, &[GroupSpec::new(1, 1)], &seed, "", 0).unwrap();
+        assert!(set.add(other[0][0].clone()).is_err());
+    }
+
+    #[test]
+    fn test_invalid_split_parameters() {
+        let seed = [7u8; 32];
+        assert!(split_secret(1, &[GroupSpec::new(1, 3)], &seed, "", 0).is_err());
+        assert!(split_secret(2, &[GroupSpec::new(2, 3)], &seed, "", 0).is_err());
+        assert!(split_secret(1, &[GroupSpec::new(2, 3)], &seed[..15], "", 0).is_err());
+        assert!(split_secret(1, &[GroupSpec::new(2, 17)], &seed, "", 0).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Spatial index over the UTXO triangle set
+//!
+//! A hashed loose quadtree over fixed-point bounding boxes. Each triangle is
+//! stored in exactly one cell: the cell containing its bounding-box centre, at
+//! the finest level whose cells are at least twice the triangle's extent.
+//! Cells are queried with "loose" bounds (expanded by half a cell on every
+//! side), so a triangle never needs to be split across cells even when it
+//! straddles a cell boundary. Only occupied cells are stored.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::blockchain::Sha256Hash;
+use crate::geometry::{BoundingBox, Triangle};
+
+/// log2 of the coarsest cell size in raw fixed-point units.
+/// Covers every coordinate allowed by `Point::MAX_COORDINATE`.
+const ROOT_SHIFT: u32 = 51;
+
+/// log2 of the finest cell size in raw fixed-point units
+const MIN_SHIFT: u32 = 8;
+
+type CellKey = (u32, i64, i64);
+
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    /// Occupied cells by level (keyed by cell-size shift), then by cell coordinates
+    levels: BTreeMap<u32, HashMap<(i64, i64), Vec<Sha256Hash>>>,
+    /// Bounding box and cell of every indexed triangle
+    entries: HashMap<Sha256Hash, (BoundingBox, CellKey)>,
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of indexed triangles
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Index a triangle, replacing any previous entry for the same hash
+    pub fn insert(&mut self, hash: Sha256Hash, triangle: &Triangle) {
+        self.remove(&hash);
+
+        let bbox = triangle.bounding_box();
+        let key = cell_for(&bbox);
+        let (shift, cx, cy) = key;
+        self.levels
+            .entry(shift)
+            .or_default()
+            .entry((cx, cy))
+            .or_default()
+            .push(hash);
+        self.entries.insert(hash, (bbox, key));
+    }
+
+    /// Remove a triangle from the index. Returns false if it was not indexed.
+    pub fn remove(&mut self, hash: &Sha256Hash) -> bool {
+        let Some((_, (shift, cx, cy))) = self.entries.remove(hash) else {
+            return false;
+        };
+
+        if let Some(cells) = self.levels.get_mut(&shift) {
+            if let Some(bucket) = cells.get_mut(&(cx, cy)) {
+                bucket.retain(|h| h != hash);
+                if bucket.is_empty() {
+                    cells.remove(&(cx, cy));
+                }
+            }
+            if cells.is_empty() {
+                self.levels.remove(&shift);
+            }
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.levels.clear();
+        self.entries.clear();
+    }
+
+    /// Hashes of all triangles whose bounding boxes intersect `region`.
+    /// Callers apply exact geometric tests to the candidates as needed.
+    pub fn query(&self, region: &BoundingBox) -> Vec<Sha256Hash> {
+        let mut result = Vec::new();
+
+        for (&shift, cells) in &self.levels {
+            let half = 1i64 << (shift - 1);
+            let min_x = region.min.x.raw().saturating_sub(half) >> shift;
+            let max_x = region.max.x.raw().saturating_add(half) >> shift;
+            let min_y = region.min.y.raw().saturating_sub(half) >> shift;
+            let max_y = region.max.y.raw().saturating_add(half) >> shift;
+
+            let span = (max_x - min_x).saturating_add(1).saturating_mul((max_y - min_y).saturating_add(1));
+            let mut visit = |bucket: &Vec<Sha256Hash>| {
+                for hash in bucket {
+                    if self.entries.get(hash).is_some_and(|(bbox, _)| bbox.intersects(region)) {
+                        result.push(*hash);
+                    }
+                }
+            };
+
+            // Enumerate whichever is smaller: the candidate cell range or the occupied cells
+            if span <= cells.len() as i64 {
+                for x in min_x..=max_x {
+                    for y in min_y..=max_y {
+                        if let Some(bucket) = cells.get(&(x, y)) {
+                            visit(bucket);
+                        }
+                    }
+                }
+            } else {
+                for (&(x, y), bucket) in cells {
+                    if (min_x..=max_x).contains(&x) && (min_y..=max_y).contains(&y) {
+                        visit(bucket);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// Choose the cell for a bounding box: the finest level whose cells are at
+/// least twice the box's extent, at the cell containing the box centre.
+fn cell_for(bbox: &BoundingBox) -> CellKey {
+    let width = (bbox.max.x - bbox.min.x).raw() as u64;
+    let height = (bbox.max.y - bbox.min.y).raw() as u64;
+    let extent = width.max(height).saturating_mul(2);
+
+    let needed = u64::BITS - extent.leading_zeros();
+    let shift = needed.clamp(MIN_SHIFT, ROOT_SHIFT);
+
+    let cx = bbox.min.x.midpoint(bbox.max.x).raw() >> shift;
+    let cy = bbox.min.y.midpoint(bbox.max.y).raw() >> shift;
+    (shift, cx, cy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Point;
+
+    fn triangle(x: f64, y: f64, side: f64) -> Triangle {
+        Triangle::new(
+            Point::from_f64(x, y),
+            Point::from_f64(x + side, y),
+            Point::from_f64(x, y + side),
+            None,
+            "owner".to_string(),
+        )
+    }
+
+    fn region(x0: f64, y0: f64, x1: f64, y1: f64) -> BoundingBox {
+        BoundingBox::new(Point::from_f64(x0, y0), Point::from_f64(x1, y1))
+    }
+
+    #[test]
+    fn test_query_finds_intersecting_triangles() {
+        let mut index = SpatialIndex::new();
+        let small = triangle(0.0, 0.0, 1.0);
+        let large = triangle(1000.0, 0.0, 44.0);
+        let straddling = triangle(-0.5, -0.5, 1.0);
+        index.insert(small.hash(), &small);
+        index.insert(large.hash(), &large);
+        index.insert(straddling.hash(), &straddling);
+
+        let hits = index.query(&region(0.25, 0.25, 0.5, 0.5));
+        assert_eq!(hits.len(), 2);
+        assert!(hits.contains(&small.hash()));
+        assert!(hits.contains(&straddling.hash()));
+
+        assert_eq!(index.query(&region(1020.0, 10.0, 1020.0, 10.0)), vec![large.hash()]);
+        assert!(index.query(&region(500.0, 500.0, 600.0, 600.0)).is_empty());
+        assert_eq!(index.query(&region(-1e9, -1e9, 1e9, 1e9)).len(), 3);
+    }
+
+    #[test]
+    fn test_remove_and_reinsert() {
+        let mut index = SpatialIndex::new();
+        let t = triangle(5.0, 5.0, 2.0);
+        index.insert(t.hash(), &t);
+        index.insert(t.hash(), &t);
+        assert_eq!(index.len(), 1);
+
+        assert!(index.remove(&t.hash()));
+        assert!(!index.remove(&t.hash()));
+        assert!(index.is_empty());
+        assert!(index.query(&region(5.0, 5.0, 7.0, 7.0)).is_empty());
+    }
+}
@@ -2,11 +2,19 @@
 
 use sha2::{Digest, Sha256};
 use crate::blockchain::{Sha256Hash, TriangleState};
-use crate::geometry::Triangle;
+use crate::crypto::SignatureScheme;
+use crate::geometry::{Area, Triangle};
 use crate::error::ChainError;
+use crate::metadata::TriangleMetadata;
+use crate::script::ScriptSpend;
 
 pub type Address = String;
 
+/// Appended to the hash and signed message of a transaction that signals
+/// replaceability; absent otherwise, so other transactions hash and sign
+/// exactly as before the signal existed
+const REPLACEABLE_TAG: &[u8] = b"REPLACEABLE";
+
 /// A transaction that can occur in a block
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum Transaction {
@@ -20,7 +28,7 @@ impl Transaction {
         hex::encode(self.hash())
     }
 
-    /// Get the fee for this transaction
+    /// Get the fee for this transaction, in whole area units
     pub fn fee(&self) -> u64 {
         match self {
             Transaction::Subdivision(tx) => tx.fee,
@@ -29,6 +37,43 @@ impl Transaction {
         }
     }
 
+    /// Whether the sender allows a conflicting transaction paying a higher
+    /// fee to replace this one in mempools
+    pub fn is_replaceable(&self) -> bool {
+        match self {
+            Transaction::Subdivision(tx) => tx.replaceable,
+            Transaction::Transfer(tx) => tx.replaceable,
+            Transaction::Coinbase(_) => false,
+        }
+    }
+
+    /// The address authorizing the spend: that of the signing key, or of the
+    /// revealed script for script spends. Unlike the sender a transaction
+    /// names, it cannot be chosen freely. Coinbase transactions spend nothing.
+    pub fn spender(&self) -> Result<Option<Address>, ChainError> {
+        match self {
+            Transaction::Subdivision(tx) => spender_address(tx.public_key.as_deref(), tx.script_spend.as_ref()).map(Some),
+            Transaction::Transfer(tx) => spender_address(tx.public_key.as_deref(), tx.script_spend.as_ref()).map(Some),
+            Transaction::Coinbase(_) => Ok(None),
+        }
+    }
+
+    /// Get the fee for this transaction as an exact area
+    pub fn fee_area(&self) -> Area {
+        Area::from_units(self.fee())
+    }
+
+    /// Verify every signature the transaction carries, including script
+    /// witness signatures. Needs no chain state, so block validation runs it
+    /// for all transactions in parallel; verified signatures are cached.
+    pub fn verify_signatures(&self) -> Result<(), ChainError> {
+        match self {
+            Transaction::Subdivision(tx) => tx.validate_signature(),
+            Transaction::Transfer(tx) => tx.validate_signature(),
+            Transaction::Coinbase(_) => Ok(()),
+        }
+    }
+
     /// Calculate the hash of this transaction
     pub fn hash(&self) -> [u8; 32] {
         let mut hasher = Sha256::new();
@@ -41,6 +86,10 @@ impl Transaction {
                 hasher.update(tx.owner_address.as_bytes());
                 hasher.update(tx.fee.to_le_bytes());
                 hasher.update(tx.nonce.to_le_bytes());
+                hasher.update(tx.metadata_commitment());
+                if tx.replaceable {
+                    hasher.update(REPLACEABLE_TAG);
+                }
             }
             Transaction::Coinbase(tx) => {
                 hasher.update("coinbase".as_bytes());
@@ -54,6 +103,12 @@ impl Transaction {
                 hasher.update(tx.sender.as_bytes());
                 hasher.update(tx.fee.to_le_bytes());
                 hasher.update(tx.nonce.to_le_bytes());
+                if let Some(ref metadata) = tx.metadata {
+                    hasher.update(metadata.commitment());
+                }
+                if tx.replaceable {
+                    hasher.update(REPLACEABLE_TAG);
+                }
             }
         };
         hasher.finalize().into()
@@ -69,6 +124,15 @@ impl Transaction {
     }
 }
 
+/// Address of the key that signed a transaction, or of the script it reveals
+fn spender_address(public_key: Option<&[u8]>, script_spend: Option<&ScriptSpend>) -> Result<Address, ChainError> {
+    match (script_spend, public_key) {
+        (Some(spend), _) => Ok(spend.script.address()),
+        (None, Some(public_key)) => crate::crypto::address_from_public_key(public_key),
+        (None, None) => Err(ChainError::InvalidTransaction("Transaction not signed".to_string())),
+    }
+}
+
 /// Subdivision transaction: splits one parent triangle into three children
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct SubdivisionTx {
@@ -79,9 +143,33 @@ pub struct SubdivisionTx {
     pub nonce: u64,
     pub signature: Option<Vec<u8>>,
     pub public_key: Option<Vec<u8>>,
+    /// Scheme of `signature`; transactions from before Schnorr support are ECDSA
+    #[serde(default)]
+    pub signature_scheme: SignatureScheme,
+    /// Script and witness, required when the parent is locked to a script
+    #[serde(default)]
+    pub script_spend: Option<ScriptSpend>,
+    /// Allows a conflicting transaction paying a higher fee to replace this
+    /// one in mempools (opt-in replace-by-fee)
+    #[serde(default)]
+    pub replaceable: bool,
 }
 
 impl SubdivisionTx {
+    /// Maximum lineage depth a subdivided triangle may reach.
+    ///
+    /// Child vertices are edge midpoints rounded down to the fixed-point grid
+    /// (see `Coord::midpoint`). Halving is exact only while the parent's raw
+    /// coordinates are even, which for the genesis triangle stops at depth 3;
+    /// below that children are rounded and no longer keep exactly 3/4 of the
+    /// parent's area. The bound equals the fixed-point precision so the
+    /// rounding cannot compound indefinitely.
+    pub const MAX_DEPTH: u32 = crate::geometry::Coord::FRACTIONAL_BITS;
+
+    /// Minimum area of each child, as twice the area in raw fixed-point units
+    /// squared (see `Triangle::doubled_area_raw`). Equals 2^-16 square units.
+    pub const MIN_CHILD_DOUBLED_AREA_RAW: u128 = 1 << 17;
+
     pub fn new(
         parent_hash: Sha256Hash,
         children: Vec<Triangle>,
@@ -97,6 +185,9 @@ impl SubdivisionTx {
             nonce,
             signature: None,
             public_key: None,
+            signature_scheme: SignatureScheme::Ecdsa,
+            script_spend: None,
+            replaceable: false,
         }
     }
 
@@ -109,17 +200,62 @@ impl SubdivisionTx {
         message.extend_from_slice(self.owner_address.as_bytes());
         message.extend_from_slice(&self.fee.to_le_bytes());
         message.extend_from_slice(&self.nonce.to_le_bytes());
+        message.extend_from_slice(&self.metadata_commitment());
+        if self.replaceable {
+            message.extend_from_slice(REPLACEABLE_TAG);
+        }
         message
     }
 
+    /// Commitment to the children's metadata; empty when no child carries any,
+    /// so uninscribed subdivisions hash and sign exactly as before
+    fn metadata_commitment(&self) -> Vec<u8> {
+        if self.children.iter().all(|child| child.metadata.is_none()) {
+            return Vec::new();
+        }
+        let mut commitment = b"METADATA:".to_vec();
+        for child in &self.children {
+            match child.metadata {
+                Some(ref metadata) => commitment.extend_from_slice(&metadata.commitment()),
+                None => commitment.extend_from_slice(&[0u8; 32]),
+            }
+        }
+        commitment
+    }
+
     pub fn sign(&mut self, signature: Vec<u8>, public_key: Vec<u8>) {
+        self.sign_with_scheme(SignatureScheme::Ecdsa, signature, public_key);
+    }
+
+    /// Attach a signature made with `scheme`
+    pub fn sign_with_scheme(&mut self, scheme: SignatureScheme, signature: Vec<u8>, public_key: Vec<u8>) {
         self.signature = Some(signature);
         self.public_key = Some(public_key);
+        self.signature_scheme = scheme;
+    }
+
+    /// Attach the script and witness that unlock a script-locked parent
+    pub fn with_script_spend(mut self, spend: ScriptSpend) -> Self {
+        self.script_spend = Some(spend);
+        self
+    }
+
+    /// Signal that this subdivision may be replaced by one paying a higher fee
+    pub fn signal_replaceable(mut self) -> Self {
+        self.replaceable = true;
+        self
     }
 
     /// Validates just the signature of the transaction, without access to blockchain state.
     /// This is useful for early validation in the mempool.
+    /// For script spends the witness signatures and script cost limits are
+    /// checked; whether the witness satisfies the script needs the parent.
     pub fn validate_signature(&self) -> Result<(), ChainError> {
+        if let Some(ref spend) = self.script_spend {
+            spend.check_limits()?;
+            return spend.verify_signatures(&self.signable_message()).map(|_| ());
+        }
+
         if self.signature.is_none() || self.public_key.is_none() {
             return Err(ChainError::InvalidTransaction(
                 "Transaction not signed".to_string(),
@@ -127,7 +263,8 @@ impl SubdivisionTx {
         }
 
         let message = self.signable_message();
-        let is_valid = crate::crypto::verify_signature(
+        let is_valid = crate::sigcache::verify(
+            self.signature_scheme,
             self.public_key.as_ref().unwrap(),
             &message,
             self.signature.as_ref().unwrap(),
@@ -142,20 +279,53 @@ impl SubdivisionTx {
         Ok(())
     }
 
+    /// The owner address must be in canonical form, as transfer addresses are
+    pub fn validate_addresses(&self) -> Result<(), ChainError> {
+        if self.owner_address.is_empty() {
+            return Err(ChainError::InvalidTransaction("Owner address cannot be empty".to_string()));
+        }
+        if !crate::address::is_canonical(&self.owner_address) {
+            return Err(ChainError::InvalidTransaction(format!("Malformed owner address {}", self.owner_address)));
+        }
+        Ok(())
+    }
+
+    /// Consensus dust rules that can be checked without blockchain state:
+    /// every child must meet the minimum area and stay within the depth limit.
+    pub fn validate_dust_rules(&self) -> Result<(), ChainError> {
+        for (i, child) in self.children.iter().enumerate() {
+            if child.depth > Self::MAX_DEPTH {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Child {} depth {} exceeds maximum subdivision depth {}",
+                    i, child.depth, Self::MAX_DEPTH
+                )));
+            }
+            if child.doubled_area_raw() < Self::MIN_CHILD_DOUBLED_AREA_RAW {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Child {} area {} is below the minimum subdivision area",
+                    i, child.area()
+                )));
+            }
+        }
+        Ok(())
+    }
+
     /// Performs a full validation of the transaction against the current blockchain state.
     pub fn validate(&self, state: &TriangleState) -> Result<(), ChainError> {
-        // First, perform a stateless signature check.
+        // First, perform the stateless checks.
+        self.validate_addresses()?;
         self.validate_signature()?;
+        self.validate_dust_rules()?;
 
         // Then, validate against the current state (UTXO set).
-        if !state.utxo_set.contains_key(&self.parent_hash) {
+        if !state.utxo_set().contains_key(&self.parent_hash) {
             return Err(ChainError::TriangleNotFound(format!(
                 "Parent triangle {} not found in UTXO set",
                 hex::encode(self.parent_hash)
             )));
         }
 
-        let parent = state.utxo_set.get(&self.parent_hash).unwrap();
+        let parent = state.get(&self.parent_hash).unwrap();
         let expected_children = parent.subdivide();
 
         if self.children.len() != 3 {
@@ -164,16 +334,36 @@ impl SubdivisionTx {
             ));
         }
 
+        // Fixed-point geometry is exact, so children must match bit for bit
         for (i, child) in self.children.iter().enumerate() {
             let expected = &expected_children[i];
-            if !child.a.equals(&expected.a) ||
-               !child.b.equals(&expected.b) ||
-               !child.c.equals(&expected.c) {
+            if child.a != expected.a || child.b != expected.b || child.c != expected.c ||
+               child.depth != expected.depth {
                 return Err(ChainError::InvalidTransaction(format!(
                     "Child {} geometry does not match expected subdivision",
                     i
                 )));
             }
+
+            // Child owners are not signed, so they must be the parent's owner
+            if child.owner != parent.owner {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Child {} must keep the parent's owner",
+                    i
+                )));
+            }
+
+            // Inscribed parents pass their metadata on unchanged; children of
+            // uninscribed parents may be inscribed freely
+            if parent.metadata.is_some() && child.metadata != parent.metadata {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Child {} must inherit the parent's metadata",
+                    i
+                )));
+            }
+            if let Some(ref metadata) = child.metadata {
+                metadata.validate()?;
+            }
         }
 
         Ok(())
@@ -183,6 +373,7 @@ impl SubdivisionTx {
 /// Coinbase transaction: miner reward
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct CoinbaseTx {
+    /// Reward in whole area units
     pub reward_area: u64,
     pub beneficiary_address: Address,
 }
@@ -191,6 +382,11 @@ impl CoinbaseTx {
     /// Maximum reward area that can be claimed in a coinbase transaction
     pub const MAX_REWARD_AREA: u64 = 1000;
 
+    /// The reward as an exact area; the coinbase triangle has exactly this area
+    pub fn reward(&self) -> Area {
+        Area::from_units(self.reward_area)
+    }
+
     pub fn validate(&self) -> Result<(), ChainError> {
         // Validate reward area is within acceptable bounds
         if self.reward_area == 0 {
@@ -213,6 +409,12 @@ impl CoinbaseTx {
             ));
         }
 
+        if !crate::address::is_canonical(&self.beneficiary_address) {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Malformed coinbase beneficiary address {}", self.beneficiary_address
+            )));
+        }
+
         Ok(())
     }
 }
@@ -227,8 +429,21 @@ pub struct TransferTx {
     pub nonce: u64,
     pub signature: Option<Vec<u8>>,
     pub public_key: Option<Vec<u8>>,
+    /// Scheme of `signature`; transactions from before Schnorr support are ECDSA
+    #[serde(default)]
+    pub signature_scheme: SignatureScheme,
     #[serde(default)]
     pub memo: Option<String>,
+    /// Script and witness, required when the input is locked to a script
+    #[serde(default)]
+    pub script_spend: Option<ScriptSpend>,
+    /// Replacement metadata for the transferred triangle, if any
+    #[serde(default)]
+    pub metadata: Option<TriangleMetadata>,
+    /// Allows a conflicting transaction paying a higher fee to replace this
+    /// one in mempools (opt-in replace-by-fee)
+    #[serde(default)]
+    pub replaceable: bool,
 }
 
 impl TransferTx {
@@ -244,7 +459,11 @@ impl TransferTx {
             nonce,
             signature: None,
             public_key: None,
+            signature_scheme: SignatureScheme::Ecdsa,
             memo: None,
+            script_spend: None,
+            metadata: None,
+            replaceable: false,
         }
     }
 
@@ -257,6 +476,25 @@ impl TransferTx {
         self.memo = Some(memo);
         Ok(self)
     }
+
+    /// Attach the script and witness that unlock a script-locked input
+    pub fn with_script_spend(mut self, spend: ScriptSpend) -> Self {
+        self.script_spend = Some(spend);
+        self
+    }
+
+    /// Signal that this transfer may be replaced by one paying a higher fee
+    pub fn signal_replaceable(mut self) -> Self {
+        self.replaceable = true;
+        self
+    }
+
+    /// Inscribe the transferred triangle, replacing any existing metadata
+    pub fn with_metadata(mut self, metadata: TriangleMetadata) -> Result<Self, ChainError> {
+        metadata.validate()?;
+        self.metadata = Some(metadata);
+        Ok(self)
+    }
     
     pub fn signable_message(&self) -> Vec<u8> {
         let mut message = Vec::new();
@@ -266,16 +504,29 @@ impl TransferTx {
         message.extend_from_slice(self.sender.as_bytes());
         message.extend_from_slice(&self.fee.to_le_bytes());
         message.extend_from_slice(&self.nonce.to_le_bytes());
+        if let Some(ref metadata) = self.metadata {
+            message.extend_from_slice("METADATA:".as_bytes());
+            message.extend_from_slice(&metadata.commitment());
+        }
+        if self.replaceable {
+            message.extend_from_slice(REPLACEABLE_TAG);
+        }
         message
     }
     
     pub fn sign(&mut self, signature: Vec<u8>, public_key: Vec<u8>) {
+        self.sign_with_scheme(SignatureScheme::Ecdsa, signature, public_key);
+    }
+
+    /// Attach a signature made with `scheme`
+    pub fn sign_with_scheme(&mut self, scheme: SignatureScheme, signature: Vec<u8>, public_key: Vec<u8>) {
         self.signature = Some(signature);
         self.public_key = Some(public_key);
+        self.signature_scheme = scheme;
     }
     
     pub fn validate(&self) -> Result<(), ChainError> {
-        if self.signature.is_none() || self.public_key.is_none() {
+        if self.script_spend.is_none() && (self.signature.is_none() || self.public_key.is_none()) {
             return Err(ChainError::InvalidTransaction("Transfer not signed".to_string()));
         }
 
@@ -288,6 +539,15 @@ impl TransferTx {
             return Err(ChainError::InvalidTransaction("New owner address cannot be empty".to_string()));
         }
 
+        // Owners are stored in canonical form; encoded addresses are decoded
+        // before a transfer is built
+        if !crate::address::is_canonical(&self.new_owner) {
+            return Err(ChainError::InvalidTransaction(format!("Malformed new owner address {}", self.new_owner)));
+        }
+        if !crate::address::is_canonical(&self.sender) {
+            return Err(ChainError::InvalidTransaction(format!("Malformed sender address {}", self.sender)));
+        }
+
         // Validate memo length to prevent DoS attacks
         if let Some(ref memo) = self.memo {
             if memo.len() > Self::MAX_MEMO_LENGTH {
@@ -297,12 +557,27 @@ impl TransferTx {
             }
         }
 
-        let message = self.signable_message();
-        let is_valid = crate::crypto::verify_signature(
-            self.public_key.as_ref().unwrap(),
-            &message,
-            self.signature.as_ref().unwrap(),
-        )?;
+        if let Some(ref metadata) = self.metadata {
+            metadata.validate()?;
+        }
+
+        self.validate_signature()
+    }
+
+    /// Validates just the signature of the transfer, without access to
+    /// blockchain state. Script spends are authorized by their witness
+    /// during block validation; here only the witness signatures and the
+    /// script cost limits are checked.
+    pub fn validate_signature(&self) -> Result<(), ChainError> {
+        if let Some(ref spend) = self.script_spend {
+            spend.check_limits()?;
+            return spend.verify_signatures(&self.signable_message()).map(|_| ());
+        }
+
+        let (Some(public_key), Some(signature)) = (&self.public_key, &self.signature) else {
+            return Err(ChainError::InvalidTransaction("Transfer not signed".to_string()));
+        };
+        let is_valid = crate::sigcache::verify(self.signature_scheme, public_key, &self.signable_message(), signature)?;
 
         if !is_valid {
             return Err(ChainError::InvalidTransaction("Invalid signature".to_string()));
@@ -323,14 +598,14 @@ mod tests {
     fn test_tx_validation_success() {
         let mut state = TriangleState::new();
         let parent = Triangle::new(
-            Point { x: 0.0, y: 0.0 },
-            Point { x: 1.0, y: 0.0 },
-            Point { x: 0.5, y: 0.866 },
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(1.0, 0.0),
+            Point::from_f64(0.5, 0.866),
             None,
             "test_owner".to_string(),
         );
         let parent_hash = parent.hash();
-        state.utxo_set.insert(parent_hash, parent.clone());
+        state.insert_triangle(parent_hash, parent.clone());
 
         let children = parent.subdivide();
         let keypair = KeyPair::generate().unwrap();
@@ -349,14 +624,14 @@ mod tests {
     fn test_unsigned_transaction_fails() {
         let mut state = TriangleState::new();
         let parent = Triangle::new(
-            Point { x: 0.0, y: 0.0 },
-            Point { x: 1.0, y: 0.0 },
-            Point { x: 0.5, y: 0.866 },
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(1.0, 0.0),
+            Point::from_f64(0.5, 0.866),
             None,
             "test_owner".to_string(),
         );
         let parent_hash = parent.hash();
-        state.utxo_set.insert(parent_hash, parent.clone());
+        state.insert_triangle(parent_hash, parent.clone());
 
         let children = parent.subdivide();
         let address = "test_address".to_string();
@@ -369,14 +644,14 @@ mod tests {
     fn test_invalid_signature_fails() {
         let mut state = TriangleState::new();
         let parent = Triangle::new(
-            Point { x: 0.0, y: 0.0 },
-            Point { x: 1.0, y: 0.0 },
-            Point { x: 0.5, y: 0.866 },
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(1.0, 0.0),
+            Point::from_f64(0.5, 0.866),
             None,
             "test_owner".to_string(),
         );
         let parent_hash = parent.hash();
-        state.utxo_set.insert(parent_hash, parent.clone());
+        state.insert_triangle(parent_hash, parent.clone());
 
         let children = parent.subdivide();
         let keypair = KeyPair::generate().unwrap();
@@ -394,19 +669,19 @@ mod tests {
     fn test_tx_validation_area_conservation_failure() {
         let mut state = TriangleState::new();
         let parent = Triangle::new(
-            Point { x: 0.0, y: 0.0 },
-            Point { x: 1.0, y: 0.0 },
-            Point { x: 0.5, y: 0.866 },
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(1.0, 0.0),
+            Point::from_f64(0.5, 0.866),
             None,
             "test_owner".to_string(),
         );
         let parent_hash = parent.hash();
-        state.utxo_set.insert(parent_hash, parent);
+        state.insert_triangle(parent_hash, parent);
 
         let bad_child = Triangle::new(
-            Point { x: 0.0, y: 0.0 },
-            Point { x: 2.0, y: 0.0 },
-            Point { x: 1.0, y: 1.732 },
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(2.0, 0.0),
+            Point::from_f64(1.0, 1.732),
             None,
             "test_owner".to_string(),
         );
@@ -424,9 +699,9 @@ mod tests {
         let state = TriangleState::new();
 
         let parent = Triangle::new(
-            Point { x: 0.0, y: 0.0 },
-            Point { x: 1.0, y: 0.0 },
-            Point { x: 0.5, y: 0.866 },
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(1.0, 0.0),
+            Point::from_f64(0.5, 0.866),
             None,
             "test_owner".to_string(),
         );
@@ -438,4 +713,174 @@ mod tests {
 
         assert!(tx.validate(&state).is_err());
     }
+
+    #[test]
+    fn test_subdivision_children_keep_parent_owner() {
+        let mut state = TriangleState::new();
+        let parent = Triangle::new(
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(1.0, 0.0),
+            Point::from_f64(0.5, 0.866),
+            None,
+            "test_owner".to_string(),
+        );
+        state.insert_triangle(parent.hash(), parent.clone());
+        assert!(signed_subdivision(&parent).validate(&state).is_ok());
+
+        // Child owners are outside the signed message, so a relayer could
+        // otherwise redirect them
+        let mut tx = signed_subdivision(&parent);
+        tx.children[1].owner = "ab".repeat(32);
+        assert!(tx.validate_signature().is_ok());
+        let result = tx.validate(&state);
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("owner")));
+    }
+
+    fn signed_subdivision(parent: &Triangle) -> SubdivisionTx {
+        let keypair = KeyPair::generate().unwrap();
+        let mut tx = SubdivisionTx::new(parent.hash(), parent.subdivide().to_vec(), keypair.address(), 0, 1);
+        let signature = keypair.sign(&tx.signable_message()).unwrap();
+        tx.sign(signature, keypair.public_key_bytes());
+        tx
+    }
+
+    #[test]
+    fn test_subdivision_below_minimum_area_fails() {
+        let mut state = TriangleState::new();
+        let side = 1.0 / 256.0;
+        let parent = Triangle::new(
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(side, 0.0),
+            Point::from_f64(0.0, side),
+            None,
+            "test_owner".to_string(),
+        );
+        state.insert_triangle(parent.hash(), parent.clone());
+
+        assert!(signed_subdivision(&parent).validate(&state).is_err());
+    }
+
+    #[test]
+    fn test_subdivision_beyond_max_depth_fails() {
+        let mut state = TriangleState::new();
+        let mut parent = Triangle::new(
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(100.0, 0.0),
+            Point::from_f64(0.0, 100.0),
+            None,
+            "test_owner".to_string(),
+        );
+        parent.depth = SubdivisionTx::MAX_DEPTH - 1;
+        state.insert_triangle(parent.hash(), parent.clone());
+        assert!(signed_subdivision(&parent).validate(&state).is_ok());
+
+        parent.depth = SubdivisionTx::MAX_DEPTH;
+        state.insert_triangle(parent.hash(), parent.clone());
+        assert!(signed_subdivision(&parent).validate(&state).is_err());
+    }
+
+    fn signed_subdivision_with(parent: &Triangle, children: Vec<Triangle>) -> SubdivisionTx {
+        let keypair = KeyPair::generate().unwrap();
+        let mut tx = SubdivisionTx::new(parent.hash(), children, keypair.address(), 0, 1);
+        let signature = keypair.sign(&tx.signable_message()).unwrap();
+        tx.sign(signature, keypair.public_key_bytes());
+        tx
+    }
+
+    #[test]
+    fn test_subdivision_metadata_inheritance() {
+        let mut state = TriangleState::new();
+        let mut parent = Triangle::new(
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(100.0, 0.0),
+            Point::from_f64(0.0, 100.0),
+            None,
+            "test_owner".to_string(),
+        );
+        state.insert_triangle(parent.hash(), parent.clone());
+
+        // Children of an uninscribed parent may be inscribed, and the inscription is committed
+        let plain = signed_subdivision(&parent);
+        let mut children = parent.subdivide().to_vec();
+        children[1].metadata = Some(TriangleMetadata::new().with_name("shard"));
+        let inscribed = signed_subdivision_with(&parent, children.clone());
+        assert!(inscribed.validate(&state).is_ok());
+        assert_ne!(
+            Transaction::Subdivision(plain).hash(),
+            Transaction::Subdivision(inscribed.clone()).hash()
+        );
+
+        let mut tampered = inscribed;
+        tampered.children[1].metadata = Some(TriangleMetadata::new().with_name("forged"));
+        assert!(tampered.validate(&state).is_err());
+
+        children[1].metadata = Some(TriangleMetadata::new().with_name("x".repeat(65)));
+        assert!(signed_subdivision_with(&parent, children).validate(&state).is_err());
+
+        // Children of an inscribed parent must carry the parent's metadata
+        parent.metadata = Some(TriangleMetadata::new().with_name("collectible"));
+        state.insert_triangle(parent.hash(), parent.clone());
+        assert!(signed_subdivision(&parent).validate(&state).is_ok());
+
+        let mut children = parent.subdivide().to_vec();
+        children[0].metadata = None;
+        assert!(signed_subdivision_with(&parent, children).validate(&state).is_err());
+    }
+
+    #[test]
+    fn test_transfer_metadata_is_signed() {
+        let keypair = KeyPair::generate().unwrap();
+        let metadata = TriangleMetadata::new().with_uri("ipfs://triangle");
+        let mut tx = TransferTx::new([1u8; 32], "ab".repeat(32), keypair.address(), 0, 1)
+            .with_metadata(metadata)
+            .unwrap();
+        let signature = keypair.sign(&tx.signable_message()).unwrap();
+        tx.sign(signature, keypair.public_key_bytes());
+        assert!(tx.validate().is_ok());
+
+        tx.metadata = Some(TriangleMetadata::new().with_uri("ipfs://other"));
+        assert!(tx.validate().is_err());
+
+        assert!(TransferTx::new([1u8; 32], "r".to_string(), "s".to_string(), 0, 1)
+            .with_metadata(TriangleMetadata::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_malformed_sender_and_owner_addresses_fail() {
+        let keypair = KeyPair::generate().unwrap();
+        let mut tx = TransferTx::new([1u8; 32], "ab".repeat(32), "alice".to_string(), 0, 1);
+        let signature = keypair.sign(&tx.signable_message()).unwrap();
+        tx.sign(signature, keypair.public_key_bytes());
+        assert!(tx.validate().is_err());
+
+        let parent = Triangle::new(
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(1.0, 0.0),
+            Point::from_f64(0.5, 0.866),
+            None,
+            "alice".to_string(),
+        );
+        let tx = SubdivisionTx::new(parent.hash(), parent.subdivide().to_vec(), "alice".to_string(), 0, 1);
+        assert!(tx.validate_addresses().is_err());
+    }
+
+    #[test]
+    fn test_schnorr_signed_transfer() {
+        let keypair = KeyPair::generate().unwrap();
+        let mut tx = TransferTx::new([1u8; 32], "ab".repeat(32), keypair.address(), 0, 1);
+        let signature = keypair.sign_schnorr(&tx.signable_message()).unwrap();
+        tx.sign_with_scheme(SignatureScheme::Schnorr, signature.clone(), keypair.public_key_bytes());
+        assert!(tx.validate().is_ok());
+
+        // The signature is only valid under the scheme it is tagged with
+        tx.sign(signature, keypair.public_key_bytes());
+        assert!(tx.validate().is_err());
+
+        // Transactions serialized before the tag existed are ECDSA
+        let mut legacy = serde_json::to_value(&tx).unwrap();
+        legacy.as_object_mut().unwrap().remove("signature_scheme");
+        let legacy: TransferTx = serde_json::from_value(legacy).unwrap();
+        assert_eq!(legacy.signature_scheme, SignatureScheme::Ecdsa);
+    }
 }
@@ -0,0 +1,339 @@
+//! Offline verification of chain state invariants
+//!
+//! These routines re-check properties that consensus relies on across the
+//! whole state, and back the `trinity-verify` tool.
+
+use crate::blockchain::{genesis_triangle, Blockchain, BlockHeight, Sha256Hash, TriangleState};
+use crate::geometry::{Area, Triangle};
+use crate::transaction::Transaction;
+
+/// Result of checking the geometric invariants of a `TriangleState`
+#[derive(Debug, Clone, Default)]
+pub struct GeometryReport {
+    pub triangles_checked: usize,
+    /// Triangles that are degenerate or out of bounds
+    pub invalid: Vec<Sha256Hash>,
+    /// UTXO entries stored under a key that does not match their geometry
+    pub mismatched_keys: Vec<Sha256Hash>,
+    /// Pairs of live triangles whose interiors overlap
+    pub overlaps: Vec<(Sha256Hash, Sha256Hash)>,
+}
+
+impl GeometryReport {
+    pub fn is_ok(&self) -> bool {
+        self.invalid.is_empty() && self.mismatched_keys.is_empty() && self.overlaps.is_empty()
+    }
+}
+
+/// Check every live triangle: valid geometry, correct UTXO key, and no
+/// interior overlap with any other live triangle.
+pub fn verify_geometry(state: &TriangleState) -> GeometryReport {
+    let mut report = GeometryReport {
+        triangles_checked: state.count(),
+        ..Default::default()
+    };
+
+    for (hash, triangle) in state.utxo_set() {
+        if !triangle.is_valid() {
+            report.invalid.push(*hash);
+        }
+        if triangle.hash() != *hash {
+            report.mismatched_keys.push(*hash);
+        }
+
+        for (other_hash, other) in state.triangles_in_region(&triangle.bounding_box()) {
+            // Report each pair once
+            if *hash < other_hash && triangle.overlaps(other) {
+                report.overlaps.push((*hash, other_hash));
+            }
+        }
+    }
+
+    report.invalid.sort_unstable();
+    report.mismatched_keys.sort_unstable();
+    report.overlaps.sort_unstable();
+    report
+}
+
+/// A supply invariant that failed while replaying the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SupplyIssue {
+    /// The block's coinbases minted more than the schedule plus fees allow
+    RewardMismatch { expected: Area, minted: Area },
+    /// The coinbase triangle's area differs from the claimed reward
+    CoinbaseAreaMismatch { reward: Area, triangle_area: Area },
+    /// A subdivision's children hold a different area than the parent's
+    /// consensus subdivision. Rounded midpoints mean that is not always
+    /// exactly 3/4 of the parent's area.
+    SubdivisionLoss { tx_hash: Sha256Hash, expected: Area, kept: Area },
+    /// UTXO area after the block differs from genesis + minted - burned
+    TotalMismatch { expected: Area, actual: Area },
+    /// The block could not be applied to the replayed state
+    ReplayFailed(String),
+}
+
+/// A supply issue located at a specific block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupplyDiscrepancy {
+    pub height: BlockHeight,
+    pub block_hash: Sha256Hash,
+    pub issue: SupplyIssue,
+}
+
+/// Result of auditing the area supply of a chain
+#[derive(Debug, Clone, Default)]
+pub struct SupplyReport {
+    pub blocks_checked: usize,
+    /// Area of the genesis triangle
+    pub genesis_area: Area,
+    /// Total area created by coinbase transactions
+    pub minted: Area,
+    /// Total area lost to subdivisions
+    pub burned: Area,
+    /// Reward blocks were entitled to but did not claim. Consensus allows
+    /// under-claiming, so this is informational rather than a discrepancy.
+    pub unclaimed: Area,
+    /// Supply the halving schedule allows up to the chain tip, excluding fees
+    pub scheduled: Area,
+    /// Area of the live UTXO set of the chain being audited
+    pub utxo_area: Area,
+    pub discrepancies: Vec<SupplyDiscrepancy>,
+}
+
+impl SupplyReport {
+    pub fn is_ok(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+
+    /// Area the UTXO set should hold: genesis + minted - burned
+    pub fn expected_area(&self) -> Area {
+        (self.genesis_area + self.minted).saturating_sub(self.burned)
+    }
+}
+
+/// Replay the main chain from genesis and check that UTXO area always equals
+/// the genesis area plus minted rewards minus subdivision burns, where each
+/// subdivision keeps the area of its parent's consensus subdivision and no
+/// block mints more than it is entitled to. Every failed check is reported
+/// against the block where it occurred.
+pub fn verify_supply(chain: &Blockchain) -> SupplyReport {
+    let genesis = genesis_triangle();
+    let mut report = SupplyReport {
+        genesis_area: genesis.area(),
+        ..Default::default()
+    };
+    let mut state = TriangleState::new();
+    state.insert_triangle(genesis.hash(), genesis);
+
+    for block in &chain.blocks {
+        report.blocks_checked += 1;
+        let height = block.header.height;
+        let mut issues = Vec::new();
+
+        if height > 0 {
+            report.scheduled += Area::from_units(Blockchain::calculate_block_reward(height));
+        }
+        let mut block_minted = Area::ZERO;
+
+        for tx in &block.transactions {
+            let result = match tx {
+                Transaction::Coinbase(cb_tx) => {
+                    let minted = cb_tx.reward();
+                    block_minted += minted;
+                    match TriangleState::coinbase_triangle(cb_tx, height) {
+                        Ok(triangle) if triangle.area() != minted => {
+                            issues.push(SupplyIssue::CoinbaseAreaMismatch { reward: minted, triangle_area: triangle.area() });
+                        }
+                        _ => {}
+                    }
+                    report.minted += minted;
+                    state.apply_coinbase(cb_tx, height)
+                }
+                Transaction::Subdivision(sub_tx) => {
+                    if let Some(parent) = state.get(&sub_tx.parent_hash) {
+                        let kept: Area = sub_tx.children.iter().map(Triangle::area).sum();
+                        let expected: Area = parent.subdivide().iter().map(Triangle::area).sum();
+                        if kept != expected {
+                            issues.push(SupplyIssue::SubdivisionLoss { tx_hash: tx.hash(), expected, kept });
+                        }
+                        report.burned += parent.area().saturating_sub(kept);
+                    }
+                    state.apply_subdivision(sub_tx)
+                }
+                Transaction::Transfer(transfer_tx) => state.apply_transfer(transfer_tx),
+            };
+
+            if let Err(e) = result {
+                issues.push(SupplyIssue::ReplayFailed(e.to_string()));
+                break;
+            }
+        }
+
+        if height > 0 {
+            let expected = Area::from_units(Blockchain::expected_coinbase_reward(height, &block.transactions));
+            if block_minted > expected {
+                issues.push(SupplyIssue::RewardMismatch { expected, minted: block_minted });
+            } else {
+                report.unclaimed += expected - block_minted;
+            }
+        }
+
+        let actual = state.total_area();
+        if actual != report.expected_area() {
+            issues.push(SupplyIssue::TotalMismatch { expected: report.expected_area(), actual });
+        }
+
+        let failed = issues.iter().any(|issue| matches!(issue, SupplyIssue::ReplayFailed(_)));
+        report.discrepancies.extend(issues.into_iter().map(|issue| SupplyDiscrepancy {
+            height,
+            block_hash: block.hash,
+            issue,
+        }));
+        if failed {
+            break;
+        }
+    }
+
+    // The live state must agree with the replay
+    report.utxo_area = chain.state.total_area();
+    if report.utxo_area != state.total_area() {
+        if let Some(tip) = chain.blocks.last() {
+            report.discrepancies.push(SupplyDiscrepancy {
+                height: tip.header.height,
+                block_hash: tip.hash,
+                issue: SupplyIssue::TotalMismatch { expected: state.total_area(), actual: report.utxo_area },
+            });
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Block;
+    use crate::geometry::Point;
+    use crate::transaction::{CoinbaseTx, SubdivisionTx};
+
+    #[test]
+    fn test_verify_geometry_clean_state() {
+        let chain = Blockchain::new();
+        let report = verify_geometry(&chain.state);
+        assert_eq!(report.triangles_checked, 1);
+        assert!(report.is_ok());
+    }
+
+    #[test]
+    fn test_verify_geometry_reports_overlap() {
+        let mut state = TriangleState::new();
+        let t1 = Triangle::new(
+            Point::from_f64(0.0, 0.0),
+            Point::from_f64(4.0, 0.0),
+            Point::from_f64(0.0, 4.0),
+            None,
+            "a".to_string(),
+        );
+        let t2 = Triangle::new(
+            Point::from_f64(1.0, 1.0),
+            Point::from_f64(5.0, 1.0),
+            Point::from_f64(1.0, 5.0),
+            None,
+            "b".to_string(),
+        );
+        state.insert_triangle(t1.hash(), t1.clone());
+        state.insert_triangle(t2.hash(), t2.clone());
+
+        let report = verify_geometry(&state);
+        assert!(!report.is_ok());
+        assert_eq!(report.overlaps.len(), 1);
+        assert!(report.invalid.is_empty());
+    }
+
+    /// Append a block without proof of work and apply it to the chain state
+    fn push_block(chain: &mut Blockchain, transactions: Vec<Transaction>) {
+        let last = chain.blocks.last().unwrap();
+        let mut block = Block::new(last.header.height + 1, last.hash, chain.difficulty, transactions);
+        block.hash = block.calculate_hash();
+        for tx in &block.transactions {
+            match tx {
+                Transaction::Coinbase(cb) => chain.state.apply_coinbase(cb, block.header.height).unwrap(),
+                Transaction::Subdivision(sub) => chain.state.apply_subdivision(sub).unwrap(),
+                Transaction::Transfer(transfer) => chain.state.apply_transfer(transfer).unwrap(),
+            }
+        }
+        chain.blocks.push(block);
+    }
+
+    fn coinbase(reward_area: u64) -> Transaction {
+        Transaction::Coinbase(CoinbaseTx { reward_area, beneficiary_address: "miner".to_string() })
+    }
+
+    #[test]
+    fn test_verify_supply_clean_chain() {
+        let mut chain = Blockchain::new();
+        push_block(&mut chain, vec![coinbase(1000)]);
+
+        let genesis = genesis_triangle();
+        let subdivision = SubdivisionTx::new(genesis.hash(), genesis.subdivide().to_vec(), "genesis_owner".to_string(), 0, 1);
+        push_block(&mut chain, vec![coinbase(1000), Transaction::Subdivision(subdivision)]);
+
+        let report = verify_supply(&chain);
+        assert!(report.is_ok(), "{:?}", report.discrepancies);
+        assert_eq!(report.blocks_checked, 3);
+        assert_eq!(report.minted, Area::from_units(2000));
+        assert_eq!(report.scheduled, Area::from_units(2000));
+        assert_eq!(report.burned.raw() * 4, genesis.area().raw());
+        assert_eq!(report.utxo_area, report.expected_area());
+    }
+
+    #[test]
+    fn test_verify_supply_reports_discrepancies_by_block() {
+        let mut chain = Blockchain::new();
+        push_block(&mut chain, vec![coinbase(1000)]);
+        // Under-claiming is allowed and only counted as unclaimed
+        push_block(&mut chain, vec![coinbase(100)]);
+        push_block(&mut chain, vec![coinbase(1000), coinbase(500)]);
+
+        // Drop a live triangle, as a corrupted state would
+        let genesis_hash = genesis_triangle().hash();
+        chain.state.remove_triangle(&genesis_hash);
+
+        let report = verify_supply(&chain);
+        assert_eq!(report.unclaimed, Area::from_units(900));
+        assert_eq!(report.discrepancies.len(), 2);
+        assert_eq!(report.discrepancies[0].height, 3);
+        assert_eq!(report.discrepancies[0].issue, SupplyIssue::RewardMismatch {
+            expected: Area::from_units(1000),
+            minted: Area::from_units(1500),
+        });
+        assert_eq!(report.discrepancies[1].block_hash, chain.blocks[3].hash);
+        assert!(matches!(report.discrepancies[1].issue, SupplyIssue::TotalMismatch { .. }));
+    }
+
+    #[test]
+    fn test_verify_supply_accepts_rounded_subdivisions() {
+        let mut chain = Blockchain::new();
+        let mut level = vec![genesis_triangle()];
+        let mut rounded = false;
+        for nonce in 1..=5 {
+            rounded |= level.iter().any(|parent| {
+                let kept: Area = parent.subdivide().iter().map(Triangle::area).sum();
+                kept.raw() * 4 != parent.area().raw() * 3
+            });
+            let subdivisions = level.iter().map(|parent| {
+                Transaction::Subdivision(SubdivisionTx::new(
+                    parent.hash(), parent.subdivide().to_vec(), "genesis_owner".to_string(), 0, nonce,
+                ))
+            });
+            push_block(&mut chain, std::iter::once(coinbase(1000)).chain(subdivisions).collect());
+            level = level.iter().flat_map(|parent| parent.subdivide()).collect();
+        }
+
+        // Midpoints round from depth 3 down, so children no longer keep exactly 3/4
+        assert!(rounded);
+        let report = verify_supply(&chain);
+        assert!(report.is_ok(), "{:?}", report.discrepancies);
+        assert_eq!(report.utxo_area, report.expected_area());
+    }
+}
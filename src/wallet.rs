@@ -8,9 +8,11 @@
 
 use crate::crypto::KeyPair;
 use crate::error::ChainError;
+use crate::keystore::{KdfParams, Keystore, UnlockedKeystore};
 use std::fs;
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
 
 /// Wallet data structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,21 +26,27 @@ pub struct Wallet {
     pub secret_key_hex: String,
     /// Creation timestamp
     pub created: String,
+    /// BIP-44 path of the key, for wallets derived from a mnemonic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivation_path: Option<String>,
 }
 
 impl Wallet {
     /// Create a new wallet with a generated keypair
     pub fn new(name: Option<String>) -> Result<Self, ChainError> {
         let keypair = KeyPair::generate()?;
-        let address = keypair.address();
-        let secret_key_hex = hex::encode(keypair.secret_key.secret_bytes());
+        Ok(Self::from_keypair(name, &keypair, None))
+    }
 
-        Ok(Wallet {
+    /// Create a wallet holding an existing keypair
+    pub fn from_keypair(name: Option<String>, keypair: &KeyPair, derivation_path: Option<String>) -> Self {
+        Wallet {
             name,
-            address,
-            secret_key_hex,
+            address: keypair.address(),
+            secret_key_hex: hex::encode(keypair.secret_key.secret_bytes()),
             created: chrono::Utc::now().to_rfc3339(),
-        })
+            derivation_path,
+        }
     }
 
     /// Load a wallet from a file
@@ -52,7 +60,8 @@ impl Wallet {
         Ok(wallet)
     }
 
-    /// Save the wallet to a file
+    /// Save the wallet to a file as plaintext JSON.
+    /// Files in the wallet directory are written encrypted by [`crate::keystore`].
     pub fn save(&self, path: &PathBuf) -> Result<(), ChainError> {
         let json = serde_json::to_string_pretty(self)
             .map_err(|e| ChainError::WalletError(format!("Failed to serialize wallet: {}", e)))?;
@@ -72,6 +81,12 @@ impl Wallet {
     }
 }
 
+impl Drop for Wallet {
+    fn drop(&mut self) {
+        self.secret_key_hex.zeroize();
+    }
+}
+
 /// Get the default wallet directory
 pub fn get_wallet_dir() -> PathBuf {
     let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
@@ -88,6 +103,15 @@ pub fn get_named_wallet_path(name: &str) -> PathBuf {
     get_wallet_dir().join(format!("wallet_{}.json", name))
 }
 
+/// Get the wallet selected by the WALLET_NAME environment variable,
+/// falling back to the default wallet
+pub fn get_active_wallet_path() -> PathBuf {
+    match std::env::var("WALLET_NAME") {
+        Ok(name) if !name.is_empty() => get_named_wallet_path(&name),
+        _ => get_default_wallet_path(),
+    }
+}
+
 /// Create the wallet directory if it doesn't exist
 pub fn ensure_wallet_dir() -> Result<(), ChainError> {
     let wallet_dir = get_wallet_dir();
@@ -96,8 +120,8 @@ pub fn ensure_wallet_dir() -> Result<(), ChainError> {
     Ok(())
 }
 
-/// Create a new wallet and save it to the default location
-pub fn create_default_wallet() -> Result<Wallet, ChainError> {
+/// Create a new encrypted wallet and save it to the default location
+pub fn create_default_wallet(password: &str) -> Result<UnlockedKeystore, ChainError> {
     ensure_wallet_dir()?;
 
     let path = get_default_wallet_path();
@@ -108,14 +132,11 @@ pub fn create_default_wallet() -> Result<Wallet, ChainError> {
         ));
     }
 
-    let wallet = Wallet::new(None)?;
-    wallet.save(&path)?;
-
-    Ok(wallet)
+    create_encrypted_wallet(&path, None, password)
 }
 
-/// Create a named wallet
-pub fn create_named_wallet(name: &str) -> Result<Wallet, ChainError> {
+/// Create a named encrypted wallet
+pub fn create_named_wallet(name: &str, password: &str) -> Result<UnlockedKeystore, ChainError> {
     ensure_wallet_dir()?;
 
     let path = get_named_wallet_path(name);
@@ -126,13 +147,16 @@ pub fn create_named_wallet(name: &str) -> Result<Wallet, ChainError> {
         ));
     }
 
-    let wallet = Wallet::new(Some(name.to_string()))?;
-    wallet.save(&path)?;
+    create_encrypted_wallet(&path, Some(name.to_string()), password)
+}
 
-    Ok(wallet)
+fn create_encrypted_wallet(path: &std::path::Path, name: Option<String>, password: &str) -> Result<UnlockedKeystore, ChainError> {
+    let mut keystore = Keystore::new(name);
+    keystore.new_receive_address(None)?;
+    UnlockedKeystore::create(path, keystore, password, KdfParams::standard())
 }
 
-/// Load the default wallet
+/// Load the default wallet from a plaintext single-key file
 pub fn load_default_wallet() -> Result<Wallet, ChainError> {
     let path = get_default_wallet_path();
 
@@ -145,7 +169,7 @@ pub fn load_default_wallet() -> Result<Wallet, ChainError> {
     Wallet::load(&path)
 }
 
-/// Load a named wallet
+/// Load a named wallet from a plaintext single-key file
 pub fn load_named_wallet(name: &str) -> Result<Wallet, ChainError> {
     let path = get_named_wallet_path(name);
 
@@ -178,7 +202,8 @@ pub fn list_wallets() -> Result<Vec<String>, ChainError> {
         let path = entry.path();
 
         if path.extension().and_then(|s| s.to_str()) == Some("json") {
-            if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
+            // Skip hidden files such as unlock sessions
+            if let Some(filename) = path.file_name().and_then(|s| s.to_str()).filter(|f| !f.starts_with('.')) {
                 wallets.push(filename.to_string());
             }
         }
@@ -208,6 +233,8 @@ pub struct EncryptedWallet {
     pub salt: String,  // Base64 encoded salt
     pub nonce: String, // Base64 encoded nonce
     pub created: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivation_path: Option<String>,
 }
 
 impl EncryptedWallet {
@@ -255,6 +282,7 @@ impl EncryptedWallet {
             salt: salt.to_string(),
             nonce: general_purpose::STANDARD.encode(&nonce_bytes),
             created: wallet.created.clone(),
+            derivation_path: wallet.derivation_path.clone(),
         })
     }
 
@@ -303,6 +331,7 @@ impl EncryptedWallet {
             address: self.address.clone(),
             secret_key_hex,
             created: self.created.clone(),
+            derivation_path: self.derivation_path.clone(),
         })
     }
 
@@ -0,0 +1,290 @@
+//! Watch-only wallets
+//!
+//! A watch-only wallet tracks balances and history without holding any secret
+//! key. It either follows an account extended public key (xpub), deriving
+//! receive and change addresses with gap-limit discovery, or an explicit list
+//! of addresses. It can build unsigned transactions for a signing wallet to
+//! complete with [`crate::keystore::Keystore::sign_transaction`].
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::{Sha256Hash, TriangleState};
+use crate::error::ChainError;
+use crate::geometry::{Area, Triangle};
+use crate::hdwallet::{HDWallet, DEFAULT_GAP_LIMIT, EXTERNAL_CHAIN, INTERNAL_CHAIN};
+use crate::transaction::{Address, Transaction, TransferTx};
+
+/// Current watch-only wallet file format version
+pub const WATCH_ONLY_VERSION: u32 = 1;
+
+/// One address followed by a watch-only wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchedAddress {
+    pub address: Address,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// BIP-44 path for addresses derived from the xpub
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivation_path: Option<String>,
+    #[serde(default)]
+    pub used: bool,
+    #[serde(default)]
+    pub change: bool,
+}
+
+impl WatchedAddress {
+    fn new(address: Address, label: Option<String>) -> Self {
+        WatchedAddress { address, label, derivation_path: None, used: false, change: false }
+    }
+}
+
+/// A wallet that follows addresses without being able to spend from them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchOnlyWallet {
+    pub version: u32,
+    /// Always true; distinguishes the file from signing wallets
+    pub watch_only: bool,
+    pub name: Option<String>,
+    /// Account extended public key the addresses are derived from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xpub: Option<String>,
+    #[serde(default = "default_gap_limit")]
+    pub gap_limit: u32,
+    #[serde(default)]
+    pub next_receive_index: u32,
+    #[serde(default)]
+    pub next_change_index: u32,
+    pub addresses: Vec<WatchedAddress>,
+    pub created: String,
+}
+
+fn default_gap_limit() -> u32 {
+    DEFAULT_GAP_LIMIT
+}
+
+impl WatchOnlyWallet {
+    fn empty(name: Option<String>) -> Self {
+        WatchOnlyWallet {
+            version: WATCH_ONLY_VERSION,
+            watch_only: true,
+            name,
+            xpub: None,
+            gap_limit: DEFAULT_GAP_LIMIT,
+            next_receive_index: 0,
+            next_change_index: 0,
+            addresses: Vec::new(),
+            created: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Follow an account extended public key, starting with its first receive address
+    pub fn from_xpub(name: Option<String>, xpub: &str, gap_limit: u32) -> Result<Self, ChainError> {
+        HDWallet::parse_xpub(xpub).map_err(ChainError::WalletError)?;
+        let mut wallet = Self::empty(name);
+        wallet.xpub = Some(xpub.trim().to_string());
+        wallet.gap_limit = gap_limit;
+        wallet.new_receive_address(None)?;
+        Ok(wallet)
+    }
+
+    /// Follow an explicit list of addresses
+    pub fn from_addresses(name: Option<String>, addresses: impl IntoIterator<Item = Address>) -> Result<Self, ChainError> {
+        let mut wallet = Self::empty(name);
+        for address in addresses {
+            let address = crate::address::parse_address(&address)?;
+            if !wallet.contains(&address) {
+                wallet.add_address(address, None)?;
+            }
+        }
+        Ok(wallet)
+    }
+
+    /// Start watching another address
+    pub fn add_address(&mut self, address: Address, label: Option<String>) -> Result<&WatchedAddress, ChainError> {
+        let address = crate::address::parse_address(&address)?;
+        if self.contains(&address) {
+            return Err(ChainError::WalletError(format!("Already watching {}", address)));
+        }
+        self.addresses.push(WatchedAddress::new(address, label));
+        Ok(self.addresses.last().expect("address was just pushed"))
+    }
+
+    /// Derive the next receive address from the xpub
+    pub fn new_receive_address(&mut self, label: Option<String>) -> Result<&WatchedAddress, ChainError> {
+        let xpub = self.xpub.clone()
+            .ok_or_else(|| ChainError::WalletError("Only xpub wallets can derive addresses".to_string()))?;
+        loop {
+            let index = self.next_receive_index;
+            self.next_receive_index += 1;
+            let derived = HDWallet::derive_address(&xpub, EXTERNAL_CHAIN, index)
+                .map_err(ChainError::WalletError)?;
+            if !self.contains(&derived.address) {
+                let mut watched = WatchedAddress::new(derived.address.clone(), label);
+                watched.derivation_path = Some(derived.path());
+                self.addresses.push(watched);
+                return Ok(self.addresses.last().expect("address was just pushed"));
+            }
+        }
+    }
+
+    /// Bring the wallet up to date with the chain: discover used xpub
+    /// addresses within the gap limit and mark every used address.
+    /// Returns how many addresses were added.
+    pub fn sync(&mut self, on_chain: &HashSet<Address>) -> Result<usize, ChainError> {
+        let before = self.addresses.len();
+
+        if let Some(xpub) = self.xpub.clone() {
+            let discovery = HDWallet::discover_xpub(&xpub, self.gap_limit, |a| on_chain.contains(a))
+                .map_err(ChainError::WalletError)?;
+            for derived in discovery.used {
+                if !self.contains(&derived.address) {
+                    let mut watched = WatchedAddress::new(derived.address.clone(), None);
+                    watched.derivation_path = Some(derived.path());
+                    watched.change = derived.change == INTERNAL_CHAIN;
+                    self.addresses.push(watched);
+                }
+            }
+            self.next_receive_index = self.next_receive_index.max(discovery.next_receive_index);
+            self.next_change_index = self.next_change_index.max(discovery.next_change_index);
+        }
+
+        for watched in &mut self.addresses {
+            watched.used |= on_chain.contains(&watched.address);
+        }
+
+        Ok(self.addresses.len() - before)
+    }
+
+    pub fn contains(&self, address: &str) -> bool {
+        self.addresses.iter().any(|a| a.address == address)
+    }
+
+    pub fn address_set(&self) -> HashSet<Address> {
+        self.addresses.iter().map(|a| a.address.clone()).collect()
+    }
+
+    /// Triangles owned by any watched address
+    pub fn owned_triangles<'a>(&self, state: &'a TriangleState) -> Vec<(Sha256Hash, &'a Triangle)> {
+        state.owned_by(&self.address_set())
+    }
+
+    /// Total area owned across all watched addresses
+    pub fn balance(&self, state: &TriangleState) -> Area {
+        self.owned_triangles(state).iter().map(|(_, t)| t.area()).sum()
+    }
+
+    /// Build an unsigned transfer of a watched triangle. The signing wallet
+    /// holding the owner's key completes it.
+    pub fn create_unsigned_transfer(
+        &self,
+        state: &TriangleState,
+        triangle_hash: &Sha256Hash,
+        new_owner: Address,
+        fee: u64,
+        nonce: u64,
+    ) -> Result<Transaction, ChainError> {
+        let triangle = state.get(triangle_hash)
+            .ok_or_else(|| ChainError::TriangleNotFound(hex::encode(triangle_hash)))?;
+        if !self.contains(&triangle.owner) {
+            return Err(ChainError::WalletError(format!(
+                "Triangle {} is not owned by a watched address", hex::encode(triangle_hash)
+            )));
+        }
+        Ok(Transaction::Transfer(TransferTx::new(*triangle_hash, new_owner, triangle.owner.clone(), fee, nonce)))
+    }
+
+    /// Whether a parsed wallet file is a watch-only wallet
+    pub fn is_watch_only(value: &serde_json::Value) -> bool {
+        value.get("watch_only").and_then(|v| v.as_bool()).unwrap_or(false)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ChainError> {
+        Self::load_if_watch_only(path)?
+            .ok_or_else(|| ChainError::WalletError(format!("{} is not a watch-only wallet", path.display())))
+    }
+
+    /// Load the wallet at `path` if it is watch-only
+    pub fn load_if_watch_only(path: &Path) -> Result<Option<Self>, ChainError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to read wallet: {}", e)))?;
+        let value: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse wallet: {}", e)))?;
+        if !Self::is_watch_only(&value) {
+            return Ok(None);
+        }
+        serde_json::from_value(value)
+            .map(Some)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse watch-only wallet: {}", e)))
+    }
+
+    /// Save the wallet. It holds no secrets, so it is stored as plain JSON.
+    pub fn save(&self, path: &Path) -> Result<(), ChainError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize wallet: {}", e)))?;
+        fs::write(path, json)
+            .map_err(|e| ChainError::WalletError(format!("Failed to write wallet: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Point;
+    use crate::keystore::Keystore;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_xpub_wallet_tracks_signing_wallet() {
+        let seed = HDWallet::seed_from_mnemonic(PHRASE, None).unwrap();
+        let mut signer = Keystore::from_seed(None, &seed).unwrap();
+        signer.new_receive_address(None).unwrap();
+        let change = signer.new_change_address().unwrap().address.clone();
+
+        let xpub = HDWallet::account_xpub(&seed, 0).unwrap();
+        let mut watcher = WatchOnlyWallet::from_xpub(Some("audit".to_string()), &xpub, 5).unwrap();
+        assert_eq!(watcher.addresses[0].address, signer.keys[0].address);
+
+        // Addresses used on chain are discovered, including change
+        let on_chain: HashSet<Address> = [signer.keys[1].address.clone(), change.clone()].into_iter().collect();
+        assert_eq!(watcher.sync(&on_chain).unwrap(), 2);
+        assert!(watcher.contains(&change));
+        assert_eq!(watcher.next_receive_index, 2);
+        assert_eq!(watcher.next_change_index, 1);
+
+        // The watcher builds an unsigned transfer that the signer completes
+        let mut state = TriangleState::new();
+        let triangle = Triangle::new(
+            Point::from_f64(0.0, 0.0), Point::from_f64(2.0, 0.0), Point::from_f64(0.0, 2.0), None, change.clone(),
+        );
+        let hash = triangle.hash();
+        state.insert_triangle(hash, triangle);
+        assert_eq!(watcher.balance(&state), Area::from_units(2));
+
+        let mut tx = watcher.create_unsigned_transfer(&state, &hash, "ab".repeat(32), 0, 1).unwrap();
+        assert!(tx.validate(&state).is_err());
+        signer.sign_transaction(&mut tx).unwrap();
+        assert!(tx.validate(&state).is_ok());
+    }
+
+    #[test]
+    fn test_address_list_wallet() {
+        let (a, b) = ("aa".repeat(32), "bb".repeat(32));
+        let mut wallet = WatchOnlyWallet::from_addresses(None, [a.clone(), b.to_uppercase(), a.clone()]).unwrap();
+        assert_eq!(wallet.addresses.len(), 2);
+        assert!(wallet.add_address(b.clone(), None).is_err());
+        assert!(wallet.add_address("c".to_string(), None).is_err());
+        assert!(wallet.new_receive_address(None).is_err());
+
+        let path = std::env::temp_dir().join("test_watch_only.json");
+        wallet.save(&path).unwrap();
+        assert!(Keystore::load(&path).is_err());
+        assert_eq!(crate::keystore::wallet_addresses(&path).unwrap(), vec![a, b]);
+        assert_eq!(WatchOnlyWallet::load(&path).unwrap().addresses.len(), 2);
+        fs::remove_file(&path).unwrap();
+    }
+}